@@ -277,13 +277,21 @@ impl<'lua> LuaToDomValue<'lua> for LuaAnyUserData<'lua> {
 				// LuaToDomValue for the LuaValue type instead, allowing for nil/default
                 DomType::OptionalCFrame => {
                     return match self.borrow::<CFrame>() {
-                        Err(_) => unreachable!("Invalid use of conversion method, should be using LuaValue"),
+                        Err(_) => Err(DomConversionError::ToDomValue {
+                            to: "dom::OptionalCFrame",
+                            from: "userdata",
+                            detail: Some("Type mismatch".to_string()),
+                        }),
                         Ok(value) => Ok(DomValue::OptionalCFrame(Some(dom::CFrame::from(*value)))),
                     }
                 }
                 DomType::PhysicalProperties => {
                     return match self.borrow::<PhysicalProperties>() {
-                        Err(_) => unreachable!("Invalid use of conversion method, should be using LuaValue"),
+                        Err(_) => Err(DomConversionError::ToDomValue {
+                            to: "dom::PhysicalProperties",
+                            from: "userdata",
+                            detail: Some("Type mismatch".to_string()),
+                        }),
                         Ok(value) => {
                             let props = dom::CustomPhysicalProperties::from(*value);
                             let custom = dom::PhysicalProperties::Custom(props);
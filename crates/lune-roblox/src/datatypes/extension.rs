@@ -2,6 +2,7 @@ use super::*;
 
 pub(crate) trait DomValueExt {
     fn variant_name(&self) -> Option<&'static str>;
+    fn luau_type_name(&self) -> Option<&'static str>;
 }
 
 impl DomValueExt for DomType {
@@ -50,10 +51,51 @@ impl DomValueExt for DomType {
             _ => return None,
         })
     }
+
+    /**
+        Gets the name of the type that values of this variant have in Luau,
+        meaning the name that `typeof` returns, or `None` if not supported.
+    */
+    fn luau_type_name(&self) -> Option<&'static str> {
+        #[allow(clippy::enum_glob_use)]
+        use DomType::*;
+        Some(match self {
+            Bool => "boolean",
+            Float32 | Float64 | Int32 | Int64 => "number",
+            BinaryString | Content | String => "string",
+            Color3 | Color3uint8 => "Color3",
+            CFrame | OptionalCFrame => "CFrame",
+            Enum => "EnumItem",
+            Ref => "Instance",
+            Axes => "Axes",
+            BrickColor => "BrickColor",
+            ColorSequence => "ColorSequence",
+            Faces => "Faces",
+            Font => "Font",
+            NumberRange => "NumberRange",
+            NumberSequence => "NumberSequence",
+            PhysicalProperties => "PhysicalProperties",
+            Ray => "Ray",
+            Rect => "Rect",
+            Region3 => "Region3",
+            Region3int16 => "Region3int16",
+            UDim => "UDim",
+            UDim2 => "UDim2",
+            Vector2 => "Vector2",
+            Vector2int16 => "Vector2int16",
+            Vector3 => "Vector3",
+            Vector3int16 => "Vector3int16",
+            _ => return None,
+        })
+    }
 }
 
 impl DomValueExt for DomValue {
     fn variant_name(&self) -> Option<&'static str> {
         self.ty().variant_name()
     }
+
+    fn luau_type_name(&self) -> Option<&'static str> {
+        self.ty().luau_type_name()
+    }
 }
@@ -30,16 +30,19 @@ impl LuaExportsTable<'_> for BrickColor {
     fn create_exports_table(lua: &Lua) -> LuaResult<LuaTable> {
         type ArgsNumber = u16;
         type ArgsName = String;
-        type ArgsRgb = (u8, u8, u8);
+        type ArgsRgb = (f32, f32, f32);
         type ArgsColor3<'lua> = LuaUserDataRef<'lua, Color3>;
 
         let brick_color_new = |lua, args: LuaMultiValue| {
-            if let Ok(number) = ArgsNumber::from_lua_multi(args.clone(), lua) {
+            // NOTE: Components must be checked first, since the
+            // first component would otherwise be read as a number
+            if args.len() == 3 {
+                let (r, g, b) = ArgsRgb::from_lua_multi(args, lua)?;
+                Ok(Self::from(Color3 { r, g, b }))
+            } else if let Ok(number) = ArgsNumber::from_lua_multi(args.clone(), lua) {
                 Ok(color_from_number(number))
             } else if let Ok(name) = ArgsName::from_lua_multi(args.clone(), lua) {
                 Ok(color_from_name(name))
-            } else if let Ok((r, g, b)) = ArgsRgb::from_lua_multi(args.clone(), lua) {
-                Ok(color_from_rgb(r, g, b))
             } else if let Ok(color) = ArgsColor3::from_lua_multi(args.clone(), lua) {
                 Ok(Self::from(*color))
             } else {
@@ -112,9 +115,15 @@ impl fmt::Display for BrickColor {
 
 impl From<Color3> for BrickColor {
     fn from(value: Color3) -> Self {
-        let r = value.r.clamp(u8::MIN as f32, u8::MAX as f32) as u8;
-        let g = value.g.clamp(u8::MIN as f32, u8::MAX as f32) as u8;
-        let b = value.b.clamp(u8::MIN as f32, u8::MAX as f32) as u8;
+        let r = (value.r * 255.0)
+            .round()
+            .clamp(u8::MIN as f32, u8::MAX as f32) as u8;
+        let g = (value.g * 255.0)
+            .round()
+            .clamp(u8::MIN as f32, u8::MAX as f32) as u8;
+        let b = (value.b * 255.0)
+            .round()
+            .clamp(u8::MIN as f32, u8::MAX as f32) as u8;
         color_from_rgb(r, g, b)
     }
 }
@@ -182,18 +191,20 @@ fn color_from_name(name: impl AsRef<str>) -> BrickColor {
 }
 
 fn color_from_rgb(r: u8, g: u8, b: u8) -> BrickColor {
-    let r = r as i16;
-    let g = g as i16;
-    let b = b as i16;
+    let r = i32::from(r);
+    let g = i32::from(g);
+    let b = i32::from(b);
     BRICK_COLOR_VALUES
         .iter()
         .fold(
-            (None, u16::MAX),
+            (None, u32::MAX),
             |(closest_color, closest_distance), color| {
-                let cr = color.2 .0 as i16;
-                let cg = color.2 .1 as i16;
-                let cb = color.2 .2 as i16;
-                let distance = ((r - cr) + (g - cg) + (b - cb)).unsigned_abs();
+                // NOTE: Each channel must be compared separately, using the squared
+                // distance between the colors, or very different colors may match
+                let dr = r - i32::from(color.2 .0);
+                let dg = g - i32::from(color.2 .1);
+                let db = b - i32::from(color.2 .2);
+                let distance = (dr * dr + dg * dg + db * db).unsigned_abs();
                 if distance < closest_distance {
                     (Some(color), distance)
                 } else {
@@ -116,6 +116,15 @@ impl LuaExportsTable<'_> for CFrame {
                 Err(err) => Err(err),
             },
 
+            2 => match ArgsLook::from_lua_multi(args, lua) {
+                Ok((from, to, up)) => Ok(CFrame(look_at(
+                    from.0,
+                    to.0,
+                    up.as_deref().unwrap_or(&Vector3(Vec3::Y)).0,
+                ))),
+                Err(err) => Err(err),
+            },
+
             3 => {
                 if let Ok((from, to, up)) = ArgsLook::from_lua_multi(args.clone(), lua) {
                     Ok(CFrame(look_at(
@@ -154,7 +163,7 @@ impl LuaExportsTable<'_> for CFrame {
             },
 
             _ => Err(LuaError::RuntimeError(format!(
-                "Invalid number of arguments: expected 0, 1, 3, 7, or 12, got {}",
+                "Invalid number of arguments: expected 0, 1, 2, 3, 7, or 12, got {}",
                 args.len()
             ))),
         };
@@ -206,8 +215,10 @@ impl LuaUserData for CFrame {
         methods.add_method(
             "Lerp",
             |_, this, (goal, alpha): (LuaUserDataRef<CFrame>, f32)| {
-                let quat_this = Quat::from_mat4(&this.0);
-                let quat_goal = Quat::from_mat4(&goal.0);
+                // NOTE: Slerp picks the shortest path between the rotations, same as
+                // in Roblox, and requires them to be normalized to produce a rotation
+                let quat_this = Quat::from_mat4(&this.0).normalize();
+                let quat_goal = Quat::from_mat4(&goal.0).normalize();
                 let translation = this
                     .0
                     .w_axis
@@ -430,14 +441,16 @@ impl From<CFrame> for DomCFrame {
     they all create view matrices for camera transforms which is not what we want here.
 */
 fn look_at(from: Vec3, to: Vec3, up: Vec3) -> Mat4 {
-    let dir = (to - from).normalize();
-    let xaxis = up.cross(dir).normalize();
-    let yaxis = dir.cross(xaxis).normalize();
+    let look = (to - from).normalize();
+    let right = look.cross(up).normalize();
+    let up = right.cross(look).normalize();
 
+    // NOTE: The columns of the rotation matrix are the right, up, and
+    // back vectors - the back vector being the opposite of the look vector
     Mat4::from_cols(
-        Vec3::new(xaxis.x, yaxis.x, dir.x).extend(0.0),
-        Vec3::new(xaxis.y, yaxis.y, dir.y).extend(0.0),
-        Vec3::new(xaxis.z, yaxis.z, dir.z).extend(0.0),
+        right.extend(0.0),
+        up.extend(0.0),
+        (-look).extend(0.0),
         from.extend(1.0),
     )
 }
@@ -7,7 +7,7 @@ use rbx_dom_weak::types::{
 
 use lune_utils::TableBuilder;
 
-use crate::exports::LuaExportsTable;
+use crate::{datatypes::util::validate_sequence_keypoint_times, exports::LuaExportsTable};
 
 use super::{super::*, Color3, ColorSequenceKeypoint};
 
@@ -57,6 +57,10 @@ impl LuaExportsTable<'_> for ColorSequence {
                     ],
                 })
             } else if let Ok(keypoints) = ArgsKeypoints::from_lua_multi(args, lua) {
+                validate_sequence_keypoint_times(
+                    Self::EXPORT_NAME,
+                    keypoints.iter().map(|k| k.time),
+                )?;
                 Ok(ColorSequence {
                     keypoints: keypoints.iter().map(|k| **k).collect(),
                 })
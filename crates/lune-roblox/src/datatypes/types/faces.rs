@@ -52,7 +52,7 @@ impl LuaExportsTable<'_> for Faces {
                 }
             };
 
-            for (index, arg) in args.into_iter().enumerate() {
+            for (index, arg) in (1..).zip(args) {
                 if let LuaValue::UserData(u) = arg {
                     if let Ok(e) = u.borrow::<EnumItem>() {
                         check(&e);
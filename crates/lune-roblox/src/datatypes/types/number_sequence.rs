@@ -7,7 +7,7 @@ use rbx_dom_weak::types::{
 
 use lune_utils::TableBuilder;
 
-use crate::exports::LuaExportsTable;
+use crate::{datatypes::util::validate_sequence_keypoint_times, exports::LuaExportsTable};
 
 use super::{super::*, NumberSequenceKeypoint};
 
@@ -61,6 +61,10 @@ impl LuaExportsTable<'_> for NumberSequence {
                     ],
                 })
             } else if let Ok(keypoints) = ArgsKeypoints::from_lua_multi(args, lua) {
+                validate_sequence_keypoint_times(
+                    Self::EXPORT_NAME,
+                    keypoints.iter().map(|k| k.time),
+                )?;
                 Ok(NumberSequence {
                     keypoints: keypoints.iter().map(|k| **k).collect(),
                 })
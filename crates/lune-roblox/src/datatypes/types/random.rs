@@ -0,0 +1,197 @@
+use core::fmt;
+use std::f64::consts::TAU;
+
+use glam::Vec3;
+use mlua::prelude::*;
+
+use lune_utils::TableBuilder;
+
+use crate::exports::LuaExportsTable;
+
+use super::{super::*, Vector3};
+
+const PCG32_MULTIPLIER: u64 = 6_364_136_223_846_793_005;
+const PCG32_INCREMENT: u64 = 105;
+
+// NOTE: Integers outside of this range can not be represented exactly as Luau numbers
+const MAX_SAFE_INTEGER: f64 = 9_007_199_254_740_991.0;
+
+/**
+    An implementation of the [Random](https://create.roblox.com/docs/reference/engine/datatypes/Random) Roblox datatype.
+
+    This implements all documented methods & constructors of the `Random` class as of March 2023.
+
+    Numbers are generated using [PCG32](https://www.pcg-random.org), seeded the same way as
+    `math.randomseed` in Luau, so that a given seed always produces the same sequence of
+    numbers on every platform. Note that these sequences do not necessarily match Roblox.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    /**
+        Creates a new generator from the given seed.
+    */
+    #[must_use]
+    pub fn new(seed: i64) -> Self {
+        let mut random = Self { state: 0 };
+        random.next_u32();
+        #[allow(clippy::cast_sign_loss)]
+        {
+            random.state = random.state.wrapping_add(seed as u64);
+        }
+        random.next_u32();
+        random
+    }
+
+    fn next_u32(&mut self) -> u32 {
+        let state = self.state;
+        self.state = state
+            .wrapping_mul(PCG32_MULTIPLIER)
+            .wrapping_add(PCG32_INCREMENT);
+        #[allow(clippy::cast_possible_truncation)]
+        let xorshifted = (((state >> 18) ^ state) >> 27) as u32;
+        #[allow(clippy::cast_possible_truncation)]
+        let rot = (state >> 59) as u32;
+        xorshifted.rotate_right(rot)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let low = u64::from(self.next_u32());
+        let high = u64::from(self.next_u32());
+        low | (high << 32)
+    }
+
+    /**
+        Returns a uniformly distributed number in the range `[0, 1)`.
+    */
+    pub fn next_number(&mut self) -> f64 {
+        // NOTE: Only the 53 highest bits fit in the mantissa of a double
+        #[allow(clippy::cast_precision_loss)]
+        let value = (self.next_u64() >> 11) as f64;
+        value * (1.0 / (1u64 << 53) as f64)
+    }
+
+    /**
+        Returns a uniformly distributed integer in the range `[0, range)`, without any bias.
+    */
+    fn next_below(&mut self, range: u64) -> u64 {
+        let zone = u64::MAX - (u64::MAX - range + 1) % range;
+        loop {
+            let value = self.next_u64();
+            if value <= zone {
+                return value % range;
+            }
+        }
+    }
+
+    /**
+        Returns a uniformly distributed integer in the range `[min, max]`.
+    */
+    pub fn next_integer(&mut self, min: i64, max: i64) -> i64 {
+        debug_assert!(min <= max, "min must not be greater than max");
+        #[allow(clippy::cast_sign_loss)]
+        let range = max.wrapping_sub(min) as u64 + 1;
+        #[allow(clippy::cast_possible_wrap)]
+        min.wrapping_add(self.next_below(range) as i64)
+    }
+
+    /**
+        Returns a uniformly distributed vector with a length of one.
+    */
+    pub fn next_unit_vector(&mut self) -> Vec3 {
+        let z = self.next_number() * 2.0 - 1.0;
+        let angle = self.next_number() * TAU;
+        let radius = (1.0 - z * z).sqrt();
+        #[allow(clippy::cast_possible_truncation)]
+        Vec3::new(
+            (radius * angle.cos()) as f32,
+            (radius * angle.sin()) as f32,
+            z as f32,
+        )
+    }
+}
+
+impl LuaExportsTable<'_> for Random {
+    const EXPORT_NAME: &'static str = "Random";
+
+    fn create_exports_table(lua: &Lua) -> LuaResult<LuaTable> {
+        let random_new = |_, seed: Option<f64>| {
+            Ok(match seed {
+                Some(seed) => Random::new(integer_from_number(seed, "seed")?),
+                None => Random::new(rand::random()),
+            })
+        };
+
+        TableBuilder::new(lua)?
+            .with_function("new", random_new)?
+            .build_readonly()
+    }
+}
+
+impl LuaUserData for Random {
+    fn add_methods<'lua, M: LuaUserDataMethods<'lua, Self>>(methods: &mut M) {
+        methods.add_meta_method(LuaMetaMethod::ToString, userdata_impl_to_string);
+        methods.add_method_mut(
+            "NextNumber",
+            |_, this, (min, max): (Option<f64>, Option<f64>)| {
+                let min = min.unwrap_or(0.0);
+                let max = max.unwrap_or(1.0);
+                Ok(min + (max - min) * this.next_number())
+            },
+        );
+        methods.add_method_mut("NextInteger", |_, this, (min, max): (f64, f64)| {
+            let min = integer_from_number(min, "min")?;
+            let max = integer_from_number(max, "max")?;
+            if min > max {
+                return Err(LuaError::RuntimeError(format!(
+                    "Invalid interval [{min}, {max}] for NextInteger - min must not be greater than max"
+                )));
+            }
+            Ok(this.next_integer(min, max))
+        });
+        methods.add_method_mut("NextUnitVector", |_, this, ()| {
+            Ok(Vector3(this.next_unit_vector()))
+        });
+        methods.add_method_mut("Shuffle", |_, this, tab: LuaTable| {
+            let len = tab.raw_len();
+            if tab.clone().pairs::<LuaValue, LuaValue>().count() != len {
+                return Err(LuaError::runtime(
+                    "Invalid table for Shuffle - expected an array without holes",
+                ));
+            }
+            // NOTE: This is a Fisher-Yates shuffle, swapping from the end of the array
+            for i in (2..=len).rev() {
+                #[allow(clippy::cast_possible_wrap)]
+                let j = this.next_integer(1, i as i64);
+                let a = tab.raw_get::<_, LuaValue>(i)?;
+                let b = tab.raw_get::<_, LuaValue>(j)?;
+                tab.raw_set(i, b)?;
+                tab.raw_set(j, a)?;
+            }
+            Ok(())
+        });
+        methods.add_method("Clone", |_, this, ()| Ok(*this));
+    }
+}
+
+impl fmt::Display for Random {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Random")
+    }
+}
+
+fn integer_from_number(value: f64, name: &'static str) -> LuaResult<i64> {
+    let value = value.floor();
+    if value.is_finite() && value.abs() <= MAX_SAFE_INTEGER {
+        #[allow(clippy::cast_possible_truncation)]
+        Ok(value as i64)
+    } else {
+        Err(LuaError::RuntimeError(format!(
+            "Invalid value for '{name}' - expected an integer between \
+            -{MAX_SAFE_INTEGER} and {MAX_SAFE_INTEGER}, got {value}"
+        )))
+    }
+}
@@ -24,7 +24,9 @@ pub struct Ray {
 
 impl Ray {
     fn closest_point(&self, point: Vec3) -> Vec3 {
-        let norm = self.direction.normalize();
+        // NOTE: Rays with a zero-length direction have no line to project
+        // onto, so the closest point for those rays is always the origin
+        let norm = self.direction.normalize_or_zero();
         let lhs = point - self.origin;
 
         let dot_product = lhs.dot(norm).max(0.0);
@@ -52,6 +52,17 @@ impl LuaUserData for Vector2 {
 
     fn add_methods<'lua, M: LuaUserDataMethods<'lua, Self>>(methods: &mut M) {
         // Methods
+        methods.add_method(
+            "Angle",
+            |_, this, (rhs, is_signed): (LuaUserDataRef<Vector2>, Option<bool>)| {
+                let angle = this.0.angle_between(rhs.0);
+                Ok(if is_signed.unwrap_or_default() {
+                    angle
+                } else {
+                    angle.abs()
+                })
+            },
+        );
         methods.add_method("Cross", |_, this, rhs: LuaUserDataRef<Vector2>| {
             let this_v3 = Vec3::new(this.0.x, this.0.y, 0f32);
             let rhs_v3 = Vec3::new(rhs.0.x, rhs.0.y, 0f32);
@@ -60,6 +71,16 @@ impl LuaUserData for Vector2 {
         methods.add_method("Dot", |_, this, rhs: LuaUserDataRef<Vector2>| {
             Ok(this.0.dot(rhs.0))
         });
+        methods.add_method(
+            "FuzzyEq",
+            |_, this, (rhs, epsilon): (LuaUserDataRef<Vector2>, Option<f32>)| {
+                // NOTE: This is the same default epsilon that Roblox uses
+                let epsilon = epsilon.unwrap_or(1e-5);
+                let eq_x = (rhs.0.x - this.0.x).abs() <= epsilon;
+                let eq_y = (rhs.0.y - this.0.y).abs() <= epsilon;
+                Ok(eq_x && eq_y)
+            },
+        );
         methods.add_method(
             "Lerp",
             |_, this, (rhs, alpha): (LuaUserDataRef<Vector2>, f32)| {
@@ -51,10 +51,10 @@ impl LuaExportsTable<'_> for Vector3 {
         let vector3_from_normal_id = |_, normal_id: LuaUserDataRef<EnumItem>| {
             if normal_id.parent.desc.name == "NormalId" {
                 Ok(match normal_id.name.as_str() {
-                    "Left" => Vector3(Vec3::X),
+                    "Right" => Vector3(Vec3::X),
                     "Top" => Vector3(Vec3::Y),
                     "Front" => Vector3(-Vec3::Z),
-                    "Right" => Vector3(-Vec3::X),
+                    "Left" => Vector3(-Vec3::X),
                     "Bottom" => Vector3(-Vec3::Y),
                     "Back" => Vector3(Vec3::Z),
                     name => {
@@ -87,6 +87,8 @@ impl LuaExportsTable<'_> for Vector3 {
             .with_value("one", Vector3(Vec3::ONE))?
             .with_function("fromAxis", vector3_from_axis)?
             .with_function("fromNormalId", vector3_from_normal_id)?
+            .with_function("FromAxis", vector3_from_axis)?
+            .with_function("FromNormalId", vector3_from_normal_id)?
             .with_function("new", vector3_new)?
             .build_readonly()
     }
@@ -114,7 +116,9 @@ impl LuaUserData for Vector3 {
         });
         methods.add_method(
             "FuzzyEq",
-            |_, this, (rhs, epsilon): (LuaUserDataRef<Vector3>, f32)| {
+            |_, this, (rhs, epsilon): (LuaUserDataRef<Vector3>, Option<f32>)| {
+                // NOTE: This is the same default epsilon that Roblox uses
+                let epsilon = epsilon.unwrap_or(1e-5);
                 let eq_x = (rhs.0.x - this.0.x).abs() <= epsilon;
                 let eq_y = (rhs.0.y - this.0.y).abs() <= epsilon;
                 let eq_z = (rhs.0.z - this.0.z).abs() <= epsilon;
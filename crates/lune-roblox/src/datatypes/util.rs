@@ -1,3 +1,5 @@
+use mlua::prelude::*;
+
 // HACK: We round to the nearest Very Small Decimal
 // to reduce writing out floating point accumulation
 // errors to files (mostly relevant for xml formats)
@@ -14,3 +16,34 @@ pub fn round_float_decimal(value: f32) -> f32 {
 
     whole + fract
 }
+
+/**
+    Validates the times of the keypoints in a sequence, such as
+    a `NumberSequence` or `ColorSequence`, similar to how Roblox does.
+
+    Sequences must have at least two keypoints, ordered by time,
+    where the first keypoint is at time 0 and the last at time 1.
+*/
+pub fn validate_sequence_keypoint_times(
+    type_name: &'static str,
+    times: impl IntoIterator<Item = f32>,
+) -> LuaResult<()> {
+    let times = times.into_iter().collect::<Vec<_>>();
+    if times.len() < 2 {
+        return Err(LuaError::RuntimeError(format!(
+            "{type_name} requires at least 2 keypoints, got {}",
+            times.len()
+        )));
+    }
+    if times.first() != Some(&0.0) || times.last() != Some(&1.0) {
+        return Err(LuaError::RuntimeError(format!(
+            "{type_name} keypoints must start at time 0 and end at time 1"
+        )));
+    }
+    if times.windows(2).any(|pair| pair[0] > pair[1]) {
+        return Err(LuaError::RuntimeError(format!(
+            "{type_name} keypoints must be ordered by time"
+        )));
+    }
+    Ok(())
+}
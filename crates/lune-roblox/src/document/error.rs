@@ -19,6 +19,8 @@ pub enum DocumentError {
     FromDataModelInvalidArgs,
     #[error("Failed to convert into a model - a given instance is a DataModel")]
     FromInstanceArrayInvalidArgs,
+    #[error("Failed to convert into a document - a given instance has been destroyed")]
+    FromDestroyedInstance,
 }
 
 impl From<DocumentError> for LuaError {
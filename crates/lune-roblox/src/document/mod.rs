@@ -271,6 +271,9 @@ impl Document {
         if i.get_class_name() != data_model::CLASS_NAME {
             return Err(DocumentError::FromDataModelInvalidArgs);
         }
+        if i.is_destroyed() {
+            return Err(DocumentError::FromDestroyedInstance);
+        }
 
         let mut dom = WeakDom::new(DomInstanceBuilder::new("ROOT"));
         let children: Vec<DomRef> = i
@@ -301,6 +304,9 @@ impl Document {
             if i.get_class_name() == data_model::CLASS_NAME {
                 return Err(DocumentError::FromInstanceArrayInvalidArgs);
             }
+            if i.is_destroyed() {
+                return Err(DocumentError::FromDestroyedInstance);
+            }
         }
 
         let mut dom = WeakDom::new(DomInstanceBuilder::new("ROOT"));
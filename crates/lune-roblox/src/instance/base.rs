@@ -1,6 +1,9 @@
 #![allow(clippy::items_after_statements)]
 
+use std::time::Duration;
+
 use mlua::prelude::*;
+use tokio::time::{sleep, Instant};
 
 use rbx_dom_weak::{
     types::{Variant as DomValue, VariantType as DomType},
@@ -11,14 +14,20 @@ use crate::{
     datatypes::{
         attributes::{ensure_valid_attribute_name, ensure_valid_attribute_value},
         conversion::{DomValueToLua, LuaToDomValue},
+        extension::DomValueExt,
+        result::DomConversionError,
         types::EnumItem,
         userdata_impl_eq, userdata_impl_to_string,
     },
-    shared::instance::{class_is_a, find_property_info},
+    shared::instance::{class_is_a, class_is_a_service, find_property_info},
 };
 
 use super::{data_model, registry::InstanceRegistry, Instance};
 
+// NOTE: Children are checked for once per frame while waiting,
+// same as how often threads using task.wait are resumed in Roblox
+const WAIT_FOR_CHILD_INTERVAL: Duration = Duration::from_micros(16_667);
+
 #[allow(clippy::too_many_lines)]
 pub fn add_methods<'lua, M: LuaUserDataMethods<'lua, Instance>>(m: &mut M) {
     m.add_meta_method(LuaMetaMethod::ToString, |lua, this, ()| {
@@ -37,6 +46,7 @@ pub fn add_methods<'lua, M: LuaUserDataMethods<'lua, Instance>>(m: &mut M) {
         Ok(())
     });
     m.add_method_mut("ClearAllChildren", |_, this, ()| {
+        ensure_not_destroyed(this)?;
         this.clear_all_children();
         Ok(())
     });
@@ -87,6 +97,17 @@ pub fn add_methods<'lua, M: LuaUserDataMethods<'lua, Instance>>(m: &mut M) {
             }
         },
     );
+    m.add_async_method(
+        "WaitForChild",
+        |_, this, (name, timeout): (String, Option<f64>)| async move {
+            wait_for_child(this, &name, timeout).await
+        },
+    );
+    m.add_method("FindFirstDescendant", |lua, this, name: String| {
+        ensure_not_destroyed(this)?;
+        this.find_descendant(|child| child.name == name)
+            .into_lua(lua)
+    });
     m.add_method(
         "FindFirstChildOfClass",
         |lua, this, (class_name, recursive): (String, Option<bool>)| {
@@ -120,6 +141,7 @@ pub fn add_methods<'lua, M: LuaUserDataMethods<'lua, Instance>>(m: &mut M) {
         "IsAncestorOf",
         |_, this, instance: LuaUserDataRef<Instance>| {
             ensure_not_destroyed(this)?;
+            ensure_not_destroyed(&instance)?;
             Ok(instance
                 .find_ancestor(|ancestor| ancestor.referent() == this.dom_ref)
                 .is_some())
@@ -129,6 +151,7 @@ pub fn add_methods<'lua, M: LuaUserDataMethods<'lua, Instance>>(m: &mut M) {
         "IsDescendantOf",
         |_, this, instance: LuaUserDataRef<Instance>| {
             ensure_not_destroyed(this)?;
+            ensure_not_destroyed(&instance)?;
             Ok(this
                 .find_ancestor(|ancestor| ancestor.referent() == instance.dom_ref)
                 .is_some())
@@ -185,7 +208,14 @@ pub fn add_methods<'lua, M: LuaUserDataMethods<'lua, Instance>>(m: &mut M) {
     });
 }
 
-fn ensure_not_destroyed(inst: &Instance) -> LuaResult<()> {
+/**
+    Ensures that the given instance has not been destroyed.
+
+    # Errors
+
+    If the instance has been destroyed.
+*/
+pub fn ensure_not_destroyed(inst: &Instance) -> LuaResult<()> {
     if inst.is_destroyed() {
         Err(LuaError::RuntimeError(
             "Instance has been destroyed".to_string(),
@@ -195,6 +225,32 @@ fn ensure_not_destroyed(inst: &Instance) -> LuaResult<()> {
     }
 }
 
+pub(crate) fn ensure_valid_parent(inst: &Instance, parent: Option<&Instance>) -> LuaResult<()> {
+    if inst.get_class_name() == data_model::CLASS_NAME {
+        return Err(LuaError::RuntimeError(
+            "Failed to set Parent - DataModel can not be reparented".to_string(),
+        ));
+    }
+    if let Some(parent) = parent {
+        ensure_not_destroyed(parent)?;
+        if parent.dom_ref == inst.dom_ref {
+            return Err(LuaError::RuntimeError(
+                "Failed to set Parent - instance can not be parented to itself".to_string(),
+            ));
+        }
+        if parent
+            .find_ancestor(|ancestor| ancestor.referent() == inst.dom_ref)
+            .is_some()
+        {
+            return Err(LuaError::RuntimeError(
+                "Failed to set Parent - instance can not be parented to its own descendant"
+                    .to_string(),
+            ));
+        }
+    }
+    Ok(())
+}
+
 /*
     Gets a property value for an instance.
 
@@ -222,6 +278,9 @@ fn instance_property_get<'lua>(
         "Parent" => {
             return this.get_parent().into_lua(lua);
         }
+        "Archivable" => {
+            return this.is_archivable().into_lua(lua);
+        }
         _ => {}
     }
 
@@ -273,6 +332,12 @@ fn instance_property_get<'lua>(
         getter.call(this.clone())
     } else if let Some(method) = InstanceRegistry::find_method(lua, this, &prop_name) {
         Ok(LuaValue::Function(method))
+    } else if this.class_name == data_model::CLASS_NAME
+        && class_is_a_service(&prop_name).unwrap_or(false)
+    {
+        // NOTE: Services always exist in the engine, and scripts commonly access them
+        // by indexing the DataModel, so we create them here the same way GetService does
+        data_model::get_or_create_service(this, &prop_name).into_lua(lua)
     } else {
         Err(LuaError::RuntimeError(format!(
             "{prop_name} is not a valid member of {this}",
@@ -289,6 +354,7 @@ fn instance_property_get<'lua>(
     2. Check if a property exists for the wanted name
         2a. Set a strict enum from a given EnumItem OR
         2b. Set a normal property from a given value
+    3. Throw an error naming the expected type if the value can not be used
 */
 fn instance_property_set<'lua>(
     lua: &'lua Lua,
@@ -309,13 +375,10 @@ fn instance_property_set<'lua>(
             return Ok(());
         }
         "Parent" => {
-            if this.get_class_name() == data_model::CLASS_NAME {
-                return Err(LuaError::RuntimeError(
-                    "Failed to set Parent - DataModel can not be reparented".to_string(),
-                ));
-            }
             type Parent<'lua> = Option<LuaUserDataRef<'lua, Instance>>;
-            let parent = Parent::from_lua(prop_value, lua)?;
+            let parent = Parent::from_lua(prop_value.clone(), lua)
+                .map_err(|_| property_type_error(this, "Parent", "Instance", &prop_value))?;
+            ensure_valid_parent(this, parent.as_deref())?;
             this.set_parent(parent.map(|p| p.clone()));
             return Ok(());
         }
@@ -324,16 +387,25 @@ fn instance_property_set<'lua>(
 
     if let Some(info) = find_property_info(&this.class_name, &prop_name) {
         if let Some(enum_name) = info.enum_name {
-            match LuaUserDataRef::<EnumItem>::from_lua(prop_value, lua) {
-                Ok(given_enum) if given_enum.parent.desc.name == enum_name => {
-                    this.set_property(prop_name, DomValue::Enum((*given_enum).clone().into()));
-                    Ok(())
+            match &prop_value {
+                LuaValue::UserData(ud) if ud.is::<EnumItem>() => {
+                    let given_enum = ud.borrow::<EnumItem>()?;
+                    if given_enum.parent.desc.name == enum_name {
+                        this.set_property(prop_name, DomValue::Enum((*given_enum).clone().into()));
+                        Ok(())
+                    } else {
+                        Err(LuaError::RuntimeError(format!(
+                            "Failed to set property '{}' of {} - expected Enum.{}, got Enum.{}",
+                            prop_name, this.class_name, enum_name, given_enum.parent.desc.name
+                        )))
+                    }
                 }
-                Ok(given_enum) => Err(LuaError::RuntimeError(format!(
-                    "Failed to set property '{}' - expected Enum.{}, got Enum.{}",
-                    prop_name, enum_name, given_enum.parent.desc.name
-                ))),
-                Err(e) => Err(e),
+                _ => Err(property_type_error(
+                    this,
+                    &prop_name,
+                    &format!("Enum.{enum_name}"),
+                    &prop_value,
+                )),
             }
         } else if let Some(dom_type) = info.value_type {
             match prop_value.lua_to_dom_value(lua, Some(dom_type)) {
@@ -341,7 +413,12 @@ fn instance_property_set<'lua>(
                     this.set_property(prop_name, dom_value);
                     Ok(())
                 }
-                Err(e) => Err(e.into()),
+                Err(e) => match (&e, dom_type.luau_type_name()) {
+                    (DomConversionError::ToDomValue { .. }, Some(expected)) => {
+                        Err(property_type_error(this, &prop_name, expected, &prop_value))
+                    }
+                    _ => Err(e.into()),
+                },
             }
         } else {
             Err(LuaError::RuntimeError(format!(
@@ -356,3 +433,68 @@ fn instance_property_set<'lua>(
         )))
     }
 }
+
+/**
+    Creates an error for when a value of the wrong type is assigned to a property.
+
+    The type of the given value is the same as what `typeof` would return in Luau.
+*/
+fn property_type_error(
+    this: &Instance,
+    prop_name: &str,
+    expected: &str,
+    value: &LuaValue,
+) -> LuaError {
+    let given = match value {
+        LuaValue::UserData(ud) => ud
+            .get_metatable()
+            .and_then(|meta| meta.get::<String>("__type"))
+            .unwrap_or_else(|_| "userdata".to_string()),
+        LuaValue::Integer(_) => "number".to_string(),
+        value => value.type_name().to_string(),
+    };
+    LuaError::RuntimeError(format!(
+        "Failed to set property '{prop_name}' of {} - expected {expected}, got {given}",
+        this.class_name
+    ))
+}
+
+/**
+    Waits for a child with the given name to exist, checking for it
+    until it does or until the given timeout, in seconds, has elapsed.
+
+    Without a timeout, nothing else could add the child in the same way that the engine
+    would, so an error is thrown right away if it does not exist, instead of waiting forever.
+
+    # Errors
+
+    If the instance was destroyed, or if the child does not exist and no timeout was given.
+*/
+async fn wait_for_child(
+    this: &Instance,
+    name: &str,
+    timeout: Option<f64>,
+) -> LuaResult<Option<Instance>> {
+    let Some(secs) = timeout else {
+        ensure_not_destroyed(this)?;
+        return match this.find_child(|child| child.name == name) {
+            Some(child) => Ok(Some(child)),
+            None => Err(LuaError::runtime(format!(
+                "Infinite yield detected - '{name}' is not a child of '{}'",
+                this.get_full_name()
+            ))),
+        };
+    };
+    let duration = Duration::try_from_secs_f64(secs.max(0.0)).unwrap_or(Duration::MAX);
+    let deadline = Instant::now().checked_add(duration);
+    loop {
+        ensure_not_destroyed(this)?;
+        if let Some(child) = this.find_child(|child| child.name == name) {
+            return Ok(Some(child));
+        }
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return Ok(None);
+        }
+        sleep(WAIT_FOR_CHILD_INTERVAL).await;
+    }
+}
@@ -44,12 +44,25 @@ fn data_model_get_service(_: &Lua, this: &Instance, service_name: String) -> Lua
         Err(LuaError::RuntimeError(format!(
             "'{service_name}' is not a valid service name",
         )))
-    } else if let Some(service) = this.find_child(|child| child.class == service_name) {
-        Ok(service)
+    } else {
+        Ok(get_or_create_service(this, service_name))
+    }
+}
+
+/**
+    Gets the service with the given class name parented under the given
+    `DataModel`, or creates it if it doesn't exist, same as `GetService`.
+
+    The class name must be a valid service class name.
+*/
+pub(crate) fn get_or_create_service(this: &Instance, service_name: impl AsRef<str>) -> Instance {
+    let service_name = service_name.as_ref();
+    if let Some(service) = this.find_child(|child| child.class == service_name) {
+        service
     } else {
         let service = Instance::new_orphaned(service_name);
         service.set_parent(Some(this.clone()));
-        Ok(service)
+        service
     }
 }
 
@@ -0,0 +1,293 @@
+use std::collections::{BTreeSet, HashMap, VecDeque};
+
+use mlua::prelude::*;
+use rbx_dom_weak::{
+    types::{Enum as DomEnum, Ref as DomRef, Variant as DomValue},
+    Instance as DomInstance, WeakDom,
+};
+
+use lune_utils::TableBuilder;
+
+use crate::shared::instance::find_property_info;
+
+use super::{Instance, INTERNAL_DOM, PROPERTY_NAME_ATTRIBUTES};
+
+/**
+    A single difference between two instance trees, as found by [`diff_instances`].
+*/
+#[derive(Debug, Clone)]
+pub enum InstanceChange {
+    /// An instance that only exists in the tree after the changes, along with all of its descendants.
+    Added(Instance),
+    /// An instance that only exists in the tree before the changes, along with all of its descendants.
+    Removed(Instance),
+    /// An instance that exists in both trees, but with different properties or attributes.
+    Changed {
+        before: Instance,
+        after: Instance,
+        properties: Vec<String>,
+        attributes: Vec<String>,
+    },
+}
+
+/**
+    Finds all of the differences between two instance trees.
+
+    Instances in the two trees are matched by their class name and name, and instances
+    with the same class name and name under the same parent are matched in order.
+    Renaming an instance, or changing its class, is therefore seen as removing it and
+    adding a new one, and added and removed instances do not include their descendants.
+
+    Properties are compared by value, and properties that are not set are compared
+    using their default values, so setting a property to its default value is not a
+    change. References to other instances are compared using the matched instances,
+    so references to the same instance within both trees are not a change either.
+
+    Changes are returned in the order that instances are found in, with
+    the changes to any parent always coming before those of its children.
+
+    # Errors
+
+    If either of the instances has been destroyed.
+*/
+pub fn diff_instances(before: &Instance, after: &Instance) -> LuaResult<Vec<InstanceChange>> {
+    let dom = INTERNAL_DOM.lock().expect("Failed to lock document");
+
+    let mut matched = HashMap::new();
+    let mut entries = Vec::new();
+    match_instances(
+        &dom,
+        before.dom_ref,
+        after.dom_ref,
+        &mut matched,
+        &mut entries,
+    )?;
+
+    // NOTE: All instances must be matched before comparing properties,
+    // since references may point to instances anywhere in the trees
+    let mut changes = Vec::new();
+    for entry in entries {
+        let change = match entry {
+            DiffEntry::Added(after) => Some(DiffChange::Added(after)),
+            DiffEntry::Removed(before) => Some(DiffChange::Removed(before)),
+            DiffEntry::Matched(before, after) => {
+                let before_inst = get_instance(&dom, before)?;
+                let after_inst = get_instance(&dom, after)?;
+                let properties = changed_properties(before_inst, after_inst, &matched);
+                let attributes = changed_attributes(before_inst, after_inst);
+                (!properties.is_empty() || !attributes.is_empty()).then_some(DiffChange::Changed {
+                    before,
+                    after,
+                    properties,
+                    attributes,
+                })
+            }
+        };
+        changes.extend(change);
+    }
+
+    drop(dom); // Instance::new needs mutex handle, drop it first
+    Ok(changes
+        .into_iter()
+        .map(|change| match change {
+            DiffChange::Added(after) => InstanceChange::Added(Instance::new(after)),
+            DiffChange::Removed(before) => InstanceChange::Removed(Instance::new(before)),
+            DiffChange::Changed {
+                before,
+                after,
+                properties,
+                attributes,
+            } => InstanceChange::Changed {
+                before: Instance::new(before),
+                after: Instance::new(after),
+                properties,
+                attributes,
+            },
+        })
+        .collect())
+}
+
+enum DiffEntry {
+    Added(DomRef),
+    Removed(DomRef),
+    Matched(DomRef, DomRef),
+}
+
+enum DiffChange {
+    Added(DomRef),
+    Removed(DomRef),
+    Changed {
+        before: DomRef,
+        after: DomRef,
+        properties: Vec<String>,
+        attributes: Vec<String>,
+    },
+}
+
+fn match_instances(
+    dom: &WeakDom,
+    before: DomRef,
+    after: DomRef,
+    matched: &mut HashMap<DomRef, DomRef>,
+    entries: &mut Vec<DiffEntry>,
+) -> LuaResult<()> {
+    let before_inst = get_instance(dom, before)?;
+    let after_inst = get_instance(dom, after)?;
+    if before_inst.class != after_inst.class {
+        entries.push(DiffEntry::Removed(before));
+        entries.push(DiffEntry::Added(after));
+        return Ok(());
+    }
+
+    matched.insert(before, after);
+    entries.push(DiffEntry::Matched(before, after));
+
+    let mut unmatched: HashMap<(&str, &str), VecDeque<DomRef>> = HashMap::new();
+    for child_ref in after_inst.children() {
+        let child = get_instance(dom, *child_ref)?;
+        unmatched
+            .entry((child.class.as_str(), child.name.as_str()))
+            .or_default()
+            .push_back(*child_ref);
+    }
+
+    let mut pairs = Vec::new();
+    for child_ref in before_inst.children() {
+        let child = get_instance(dom, *child_ref)?;
+        let pair = unmatched
+            .get_mut(&(child.class.as_str(), child.name.as_str()))
+            .and_then(VecDeque::pop_front);
+        match pair {
+            Some(pair) => pairs.push((*child_ref, pair)),
+            None => entries.push(DiffEntry::Removed(*child_ref)),
+        }
+    }
+    for child_ref in after_inst.children() {
+        let child = get_instance(dom, *child_ref)?;
+        let is_unmatched = unmatched
+            .get(&(child.class.as_str(), child.name.as_str()))
+            .is_some_and(|refs| refs.contains(child_ref));
+        if is_unmatched {
+            entries.push(DiffEntry::Added(*child_ref));
+        }
+    }
+
+    for (before_child, after_child) in pairs {
+        match_instances(dom, before_child, after_child, matched, entries)?;
+    }
+    Ok(())
+}
+
+fn get_instance(dom: &WeakDom, dom_ref: DomRef) -> LuaResult<&DomInstance> {
+    dom.get_by_ref(dom_ref)
+        .ok_or_else(|| LuaError::runtime("Instance has been destroyed"))
+}
+
+fn changed_properties(
+    before: &DomInstance,
+    after: &DomInstance,
+    matched: &HashMap<DomRef, DomRef>,
+) -> Vec<String> {
+    let mut changed = Vec::new();
+    if before.name != after.name {
+        changed.push("Name".to_string());
+    }
+
+    let names = before
+        .properties
+        .keys()
+        .chain(after.properties.keys())
+        .filter(|name| name.as_str() != PROPERTY_NAME_ATTRIBUTES)
+        .collect::<BTreeSet<_>>();
+    for name in names {
+        let before_value = property_or_default(before, name);
+        let after_value = property_or_default(after, name);
+        if !values_equal(before_value.as_ref(), after_value.as_ref(), matched) {
+            changed.push(name.clone());
+        }
+    }
+
+    changed
+}
+
+fn changed_attributes(before: &DomInstance, after: &DomInstance) -> Vec<String> {
+    let attributes = |inst: &DomInstance| match inst.properties.get(PROPERTY_NAME_ATTRIBUTES) {
+        Some(DomValue::Attributes(attributes)) => attributes
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect(),
+        _ => HashMap::new(),
+    };
+    let before_attributes = attributes(before);
+    let after_attributes = attributes(after);
+    before_attributes
+        .keys()
+        .chain(after_attributes.keys())
+        .filter(|name| before_attributes.get(*name) != after_attributes.get(*name))
+        .cloned()
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+fn property_or_default(inst: &DomInstance, name: &str) -> Option<DomValue> {
+    if let Some(value) = inst.properties.get(name) {
+        return Some(value.clone());
+    }
+    let info = find_property_info(&inst.class, name)?;
+    match (info.enum_default, info.value_default) {
+        (Some(enum_default), _) => Some(DomValue::Enum(DomEnum::from_u32(enum_default))),
+        (None, Some(value_default)) => Some(value_default.clone()),
+        (None, None) => None,
+    }
+}
+
+fn values_equal(
+    before: Option<&DomValue>,
+    after: Option<&DomValue>,
+    matched: &HashMap<DomRef, DomRef>,
+) -> bool {
+    // NOTE: References that are not set are the same as references to nothing
+    let as_ref = |value: Option<&DomValue>| match value {
+        None => Some(DomRef::none()),
+        Some(DomValue::Ref(r)) => Some(*r),
+        Some(_) => None,
+    };
+    match (as_ref(before), as_ref(after)) {
+        (Some(before), Some(after)) if before.is_none() || after.is_none() => {
+            before.is_none() && after.is_none()
+        }
+        (Some(before), Some(after)) => matched
+            .get(&before)
+            .map_or(before == after, |m| *m == after),
+        _ => before == after,
+    }
+}
+
+impl<'lua> IntoLua<'lua> for InstanceChange {
+    fn into_lua(self, lua: &'lua Lua) -> LuaResult<LuaValue<'lua>> {
+        let table = match self {
+            Self::Added(after) => TableBuilder::new(lua)?
+                .with_value("kind", "Added")?
+                .with_value("after", after)?
+                .build_readonly()?,
+            Self::Removed(before) => TableBuilder::new(lua)?
+                .with_value("kind", "Removed")?
+                .with_value("before", before)?
+                .build_readonly()?,
+            Self::Changed {
+                before,
+                after,
+                properties,
+                attributes,
+            } => TableBuilder::new(lua)?
+                .with_value("kind", "Changed")?
+                .with_value("before", before)?
+                .with_value("after", after)?
+                .with_value("properties", properties)?
+                .with_value("attributes", attributes)?
+                .build_readonly()?,
+        };
+        Ok(LuaValue::Table(table))
+    }
+}
@@ -1,7 +1,7 @@
 #![allow(clippy::missing_panics_doc)]
 
 use std::{
-    collections::{BTreeMap, VecDeque},
+    collections::{BTreeMap, HashMap, VecDeque},
     fmt,
     hash::{Hash, Hasher},
     sync::Mutex,
@@ -26,8 +26,12 @@ pub(crate) mod data_model;
 pub(crate) mod terrain;
 pub(crate) mod workspace;
 
+pub mod diff;
 pub mod registry;
 
+pub use self::base::ensure_not_destroyed;
+
+const PROPERTY_NAME_ARCHIVABLE: &str = "Archivable";
 const PROPERTY_NAME_ATTRIBUTES: &str = "Attributes";
 const PROPERTY_NAME_TAGS: &str = "Tags";
 
@@ -169,6 +173,11 @@ impl Instance {
     /**
         Clones the instance and all of its descendants, and orphans it.
 
+        Descendants that are not [archivable](Self::is_archivable) are not cloned,
+        and any references to them from within the clone will keep pointing
+        to the original descendants. Returns `None` if this instance
+        itself is not archivable.
+
         To then save the new instance it must be re-parented,
         which matches the exact behavior of Roblox's instances.
 
@@ -177,14 +186,73 @@ impl Instance {
         on the Roblox Developer Hub
     */
     #[must_use]
-    pub fn clone_instance(&self) -> Self {
+    pub fn clone_instance(&self) -> Option<Self> {
         let mut dom = INTERNAL_DOM.lock().expect("Failed to lock document");
+        let inst = dom
+            .get_by_ref(self.dom_ref)
+            .expect("Failed to find instance in document");
+        if !dom_instance_is_archivable(inst) {
+            return None;
+        }
+
         let new_ref = dom.clone_within(self.dom_ref);
+
+        // Children of clones are in the same order as the children of their originals,
+        // so we can walk both trees at once to find clones of non-archivable instances
+        let mut kept = Vec::new();
+        let mut removed = Vec::new();
+        let mut originals = HashMap::new();
+        let mut stack = vec![(self.dom_ref, new_ref, false)];
+        while let Some((original_ref, cloned_ref, is_removed)) = stack.pop() {
+            let original = dom
+                .get_by_ref(original_ref)
+                .expect("Failed to find instance in document");
+            let cloned = dom
+                .get_by_ref(cloned_ref)
+                .expect("Failed to find cloned instance in document");
+            for (original_child, cloned_child) in original.children().iter().zip(cloned.children())
+            {
+                let child_removed = is_removed
+                    || !dom_instance_is_archivable(
+                        dom.get_by_ref(*original_child)
+                            .expect("Failed to find instance in document"),
+                    );
+                if child_removed {
+                    originals.insert(*cloned_child, *original_child);
+                    if !is_removed {
+                        removed.push(*cloned_child);
+                    }
+                } else {
+                    kept.push(*cloned_child);
+                }
+                stack.push((*original_child, *cloned_child, child_removed));
+            }
+        }
+
+        for removed_ref in removed {
+            dom.destroy(removed_ref);
+        }
+
+        if !originals.is_empty() {
+            for kept_ref in std::iter::once(new_ref).chain(kept) {
+                let kept_inst = dom
+                    .get_by_ref_mut(kept_ref)
+                    .expect("Failed to find cloned instance in document");
+                for value in kept_inst.properties.values_mut() {
+                    if let DomValue::Ref(value_ref) = value {
+                        if let Some(original_ref) = originals.get(value_ref) {
+                            *value_ref = *original_ref;
+                        }
+                    }
+                }
+            }
+        }
+
         drop(dom); // Self::new needs mutex handle, drop it first
 
         let new_inst = Self::new(new_ref);
         new_inst.set_parent(None);
-        new_inst
+        Some(new_inst)
     }
 
     /**
@@ -211,7 +279,7 @@ impl Instance {
         }
     }
 
-    fn is_destroyed(&self) -> bool {
+    pub(crate) fn is_destroyed(&self) -> bool {
         // NOTE: This property can not be cached since instance references
         // other than this one may have destroyed this one, and we don't
         // keep track of all current instance reference structs
@@ -251,6 +319,25 @@ impl Instance {
         class_is_a(&self.class_name, class_name).unwrap_or(false)
     }
 
+    /**
+        Checks if the instance is archivable, meaning that it will be
+        included when cloning it or any of its ancestors.
+
+        Instances are archivable unless their `Archivable` property has been set to `false`.
+
+        ### See Also
+        * [`Archivable`](https://create.roblox.com/docs/reference/engine/classes/Instance#Archivable)
+        on the Roblox Developer Hub
+    */
+    #[must_use]
+    pub fn is_archivable(&self) -> bool {
+        let dom = INTERNAL_DOM.lock().expect("Failed to lock document");
+        let inst = dom
+            .get_by_ref(self.dom_ref)
+            .expect("Failed to find instance in document");
+        dom_instance_is_archivable(inst)
+    }
+
     /**
         Gets the class name of the instance.
 
@@ -550,7 +637,11 @@ impl Instance {
     }
 
     /**
-        Gets all of the current descendants of this `Instance` using a breadth-first search.
+        Gets all of the current descendants of this `Instance` using a depth-first search.
+
+        Descendants are returned in pre-order, meaning that each child is
+        followed by its own descendants before the next sibling, which
+        matches the order used by Roblox.
 
         Note that this is a somewhat expensive operation and that other
         operations using weak dom referents should be preferred if possible.
@@ -563,17 +654,19 @@ impl Instance {
         let dom = INTERNAL_DOM.lock().expect("Failed to lock document");
 
         let mut descendants = Vec::new();
-        let mut queue = VecDeque::from_iter(
-            dom.get_by_ref(self.dom_ref)
-                .expect("Failed to find instance in document")
-                .children(),
-        );
-
-        while let Some(queue_ref) = queue.pop_front() {
-            descendants.push(*queue_ref);
-            let queue_inst = dom.get_by_ref(*queue_ref).unwrap();
-            for queue_ref_inner in queue_inst.children().iter().rev() {
-                queue.push_back(queue_ref_inner);
+        let mut stack = dom
+            .get_by_ref(self.dom_ref)
+            .expect("Failed to find instance in document")
+            .children()
+            .iter()
+            .rev()
+            .collect::<Vec<_>>();
+
+        while let Some(stack_ref) = stack.pop() {
+            descendants.push(*stack_ref);
+            let stack_inst = dom.get_by_ref(*stack_ref).unwrap();
+            for stack_ref_inner in stack_inst.children().iter().rev() {
+                stack.push(stack_ref_inner);
             }
         }
 
@@ -600,8 +693,12 @@ impl Instance {
         let mut parts = Vec::new();
         let mut instance_ref = self.dom_ref;
 
+        // NOTE: Same as in Roblox, data models are only left out of the full name
+        // of their descendants, and the full name of a data model is its name
         while let Some(instance) = dom.get_by_ref(instance_ref) {
-            if instance_ref != dom_root && instance.class != data_model::CLASS_NAME {
+            let is_excluded_data_model =
+                instance.class == data_model::CLASS_NAME && instance_ref != self.dom_ref;
+            if instance_ref != dom_root && !is_excluded_data_model {
                 instance_ref = instance.parent();
                 parts.push(instance.name.clone());
             } else {
@@ -715,14 +812,22 @@ impl LuaExportsTable<'_> for Instance {
     const EXPORT_NAME: &'static str = "Instance";
 
     fn create_exports_table(lua: &Lua) -> LuaResult<LuaTable> {
-        let instance_new = |lua, class_name: String| {
-            if class_exists(&class_name) {
-                Instance::new_orphaned(class_name).into_lua(lua)
-            } else {
-                Err(LuaError::RuntimeError(format!(
+        type Parent<'lua> = Option<LuaUserDataRef<'lua, Instance>>;
+        let instance_new = |lua, (class_name, parent): (String, Parent)| {
+            if !class_exists(&class_name) {
+                return Err(LuaError::RuntimeError(format!(
                     "Failed to create Instance - '{class_name}' is not a valid class name",
-                )))
+                )));
+            }
+            let mut instance = Instance::new_orphaned(class_name);
+            if let Some(parent) = parent {
+                if let Err(e) = base::ensure_valid_parent(&instance, Some(&parent)) {
+                    instance.destroy();
+                    return Err(e);
+                }
+                instance.set_parent(Some(parent.clone()));
             }
+            instance.into_lua(lua)
         };
 
         TableBuilder::new(lua)?
@@ -787,3 +892,10 @@ impl From<Instance> for DomRef {
         value.dom_ref
     }
 }
+
+fn dom_instance_is_archivable(inst: &DomInstance) -> bool {
+    !matches!(
+        inst.properties.get(PROPERTY_NAME_ARCHIVABLE),
+        Some(DomValue::Bool(false))
+    )
+}
@@ -0,0 +1,63 @@
+use std::{
+    ffi::OsString,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use mlua::prelude::*;
+use tokio::{fs, io::AsyncWriteExt};
+
+fn temp_path_for(path: &Path) -> LuaResult<PathBuf> {
+    static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let count = TEMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+
+    let Some(file_name) = path.file_name() else {
+        return Err(LuaError::RuntimeError(format!(
+            "The given path '{}' is not a file path",
+            path.display()
+        )));
+    };
+
+    // NOTE: The temp file must be a sibling of the target file, since
+    // renaming is only guaranteed to be atomic within the same filesystem
+    let mut temp_name = OsString::from(".");
+    temp_name.push(file_name);
+    temp_name.push(format!(".{}.{count}.tmp", process::id()));
+
+    Ok(path.with_file_name(temp_name))
+}
+
+async fn write_temp(temp_path: &Path, target_path: &Path, contents: &[u8]) -> LuaResult<()> {
+    let mut file = fs::File::create(temp_path).await?;
+    file.write_all(contents).await?;
+    file.sync_all().await?;
+
+    // Preserve permissions of any file we are about to replace
+    match fs::metadata(target_path).await {
+        Ok(meta) => file.set_permissions(meta.permissions()).await?,
+        Err(e) if e.kind() == ErrorKind::NotFound => {}
+        Err(e) => return Err(e.into()),
+    }
+
+    Ok(())
+}
+
+pub async fn write_atomic(path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> LuaResult<()> {
+    let path = path.as_ref();
+    let temp_path = temp_path_for(path)?;
+
+    // NOTE: Renaming replaces the target file on all platforms, which means
+    // readers will only ever see either the old contents or the new contents
+    let result = match write_temp(&temp_path, path, contents.as_ref()).await {
+        Ok(()) => fs::rename(&temp_path, path).await.into_lua_err(),
+        Err(e) => Err(e),
+    };
+
+    if result.is_err() {
+        fs::remove_file(&temp_path).await.ok();
+    }
+
+    result
+}
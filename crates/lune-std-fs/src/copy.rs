@@ -1,11 +1,12 @@
 use std::collections::VecDeque;
-use std::io::ErrorKind;
+use std::future::Future;
+use std::io::{ErrorKind, Result as IoResult};
 use std::path::{Path, PathBuf};
 
 use mlua::prelude::*;
 use tokio::fs;
 
-use super::options::FsWriteOptions;
+use super::{options::FsWriteOptions, root::FsRoot};
 
 pub struct CopyContents {
     // Vec<(relative depth, path)>
@@ -13,7 +14,11 @@ pub struct CopyContents {
     pub files: Vec<(usize, PathBuf)>,
 }
 
-async fn get_contents_at(root: PathBuf, _: FsWriteOptions) -> LuaResult<CopyContents> {
+async fn get_contents_at(
+    root: PathBuf,
+    fs_root: Option<&FsRoot>,
+    _: FsWriteOptions,
+) -> LuaResult<CopyContents> {
     let mut dirs = Vec::new();
     let mut files = Vec::new();
 
@@ -33,6 +38,11 @@ async fn get_contents_at(root: PathBuf, _: FsWriteOptions) -> LuaResult<CopyCont
     // when we find any new descendant directories
     // FUTURE: Try to do async reading here concurrently to speed it up a bit
     while let Some((current_depth, current_path)) = queue.pop_front() {
+        // NOTE: Metadata follows symlinks, so when confined to a filesystem root
+        // we must make sure that they never point to anywhere outside of it
+        if let Some(fs_root) = fs_root {
+            fs_root.check(&current_path)?;
+        }
         let meta = fs::metadata(&current_path).await?;
         if meta.is_symlink() {
             return Err(LuaError::RuntimeError(format!(
@@ -92,6 +102,7 @@ async fn ensure_no_file_exists(path: impl AsRef<Path>) -> LuaResult<()> {
 }
 
 pub async fn copy(
+    lua: &Lua,
     source: impl AsRef<Path>,
     target: impl AsRef<Path>,
     options: FsWriteOptions,
@@ -135,7 +146,8 @@ pub async fn copy(
     if is_file {
         fs::copy(source, target).await?;
     } else if is_dir {
-        let contents = get_contents_at(source.to_path_buf(), options).await?;
+        let fs_root = lua.app_data_ref::<FsRoot>().map(|root| root.clone());
+        let contents = get_contents_at(source.to_path_buf(), fs_root.as_ref(), options).await?;
 
         if options.overwrite {
             let (is_dir, is_file) = match fs::metadata(&target).await {
@@ -164,3 +176,96 @@ pub async fn copy(
 
     Ok(())
 }
+
+/**
+    Moves the source path to the target path using the given rename, which
+    is expected to be a rename of the source path to the target path.
+
+    Renaming does not work across filesystems / mount points, so if the rename fails
+    because of that, we fall back to copying and then removing the original instead.
+*/
+pub async fn move_with_fallback(
+    lua: &Lua,
+    source: &Path,
+    target: &Path,
+    options: FsWriteOptions,
+    rename: impl Future<Output = IoResult<()>>,
+) -> LuaResult<()> {
+    match rename.await {
+        Err(e) if e.kind() == ErrorKind::CrossesDevices => {
+            let is_dir = fs::metadata(source).await?.is_dir();
+            copy(lua, source, target, options).await?;
+            if is_dir {
+                fs::remove_dir_all(source).await.into_lua_err()?;
+            } else {
+                fs::remove_file(source).await.into_lua_err()?;
+            }
+        }
+        res => res.into_lua_err()?,
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::{ready, Ready};
+    use std::io::Error as IoError;
+
+    use super::*;
+
+    fn crosses_devices() -> Ready<IoResult<()>> {
+        ready(Err(IoError::from(ErrorKind::CrossesDevices)))
+    }
+
+    #[tokio::test]
+    async fn moves_files_across_devices() -> LuaResult<()> {
+        let lua = Lua::new();
+        let dir = tempfile::tempdir()?;
+        let source = dir.path().join("source.txt");
+        let target = dir.path().join("target.txt");
+        fs::write(&source, "contents").await?;
+
+        let options = FsWriteOptions { overwrite: false };
+        move_with_fallback(&lua, &source, &target, options, crosses_devices()).await?;
+
+        assert!(!source.exists());
+        assert_eq!(fs::read_to_string(&target).await?, "contents");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn moves_dirs_across_devices() -> LuaResult<()> {
+        let lua = Lua::new();
+        let dir = tempfile::tempdir()?;
+        let source = dir.path().join("source");
+        let target = dir.path().join("target");
+        fs::create_dir_all(source.join("inner")).await?;
+        fs::write(source.join("inner").join("file.txt"), "contents").await?;
+
+        let options = FsWriteOptions { overwrite: false };
+        move_with_fallback(&lua, &source, &target, options, crosses_devices()).await?;
+
+        assert!(!source.exists());
+        let moved = fs::read_to_string(target.join("inner").join("file.txt")).await?;
+        assert_eq!(moved, "contents");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn keeps_source_when_copying_fails() -> LuaResult<()> {
+        let lua = Lua::new();
+        let dir = tempfile::tempdir()?;
+        let source = dir.path().join("source.txt");
+        let target = dir.path().join("target.txt");
+        fs::write(&source, "contents").await?;
+        fs::write(&target, "existing").await?;
+
+        let options = FsWriteOptions { overwrite: false };
+        let result = move_with_fallback(&lua, &source, &target, options, crosses_devices()).await;
+
+        assert!(result.is_err());
+        assert_eq!(fs::read_to_string(&source).await?, "contents");
+        assert_eq!(fs::read_to_string(&target).await?, "existing");
+        Ok(())
+    }
+}
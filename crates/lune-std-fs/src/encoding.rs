@@ -0,0 +1,64 @@
+use encoding_rs::{Encoding, UTF_8};
+use mlua::prelude::*;
+
+/**
+    A text encoding to decode the contents of a file with when reading it.
+*/
+#[derive(Debug, Clone, Copy)]
+pub enum FsReadEncoding {
+    /**
+        Detects the encoding from a byte order mark at the start of the
+        contents, if there is one, otherwise assumes that they are UTF-8.
+    */
+    Auto,
+    /**
+        Uses the given encoding, skipping a byte order mark for it if there is one.
+    */
+    Explicit(&'static Encoding),
+}
+
+impl<'lua> FromLua<'lua> for FsReadEncoding {
+    fn from_lua(value: LuaValue<'lua>, _: &'lua Lua) -> LuaResult<Self> {
+        let LuaValue::String(s) = &value else {
+            return Err(LuaError::RuntimeError(format!(
+                "Invalid option value for 'encoding' in read options - expected string, got {}",
+                value.type_name()
+            )));
+        };
+        let label = s.to_str()?.trim();
+        if label.eq_ignore_ascii_case("auto") {
+            return Ok(Self::Auto);
+        }
+        // NOTE: The replacement encoding is only meant to be used for
+        // encodings that are known to be unsafe, and it is not useful here
+        match Encoding::for_label_no_replacement(label.as_bytes()) {
+            Some(encoding) => Ok(Self::Explicit(encoding)),
+            None => Err(LuaError::RuntimeError(format!(
+                "Invalid option value for 'encoding' in read options - '{label}' is not \
+                a known encoding, expected 'auto' or an encoding such as 'utf-8' or 'utf-16le'"
+            ))),
+        }
+    }
+}
+
+/**
+    Decodes the given bytes into a UTF-8 string using the given encoding,
+    returning the string along with the name of the encoding that was used.
+
+    Byte order marks are removed, and any byte sequences that are not valid
+    for the encoding are replaced with the unicode replacement character.
+*/
+pub fn decode_text(bytes: &[u8], encoding: FsReadEncoding) -> (String, String) {
+    let (text, encoding) = match encoding {
+        FsReadEncoding::Auto => {
+            let (encoding, bom_len) = Encoding::for_bom(bytes).unwrap_or((UTF_8, 0));
+            let text = encoding.decode_without_bom_handling(&bytes[bom_len..]).0;
+            (text, encoding)
+        }
+        FsReadEncoding::Explicit(encoding) => {
+            let text = encoding.decode_with_bom_removal(bytes).0;
+            (text, encoding)
+        }
+    };
+    (text.into_owned(), encoding.name().to_ascii_lowercase())
+}
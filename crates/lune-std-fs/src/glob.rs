@@ -0,0 +1,64 @@
+use std::path::{Path, MAIN_SEPARATOR};
+
+use glob::{glob_with, MatchOptions, Pattern};
+use mlua::prelude::*;
+use mlua_luau_scheduler::LuaSpawnExt;
+
+use super::{options::FsGlobOptions, root::FsRoot};
+
+pub async fn glob(lua: &Lua, pattern: String, options: FsGlobOptions) -> LuaResult<Vec<String>> {
+    let match_options = MatchOptions {
+        case_sensitive: options.case_sensitive,
+        ..MatchOptions::new()
+    };
+
+    // NOTE: When confined to a filesystem root, relative patterns are relative to the
+    // root, and matched paths are made relative again before they are returned
+    let root = lua.app_data_ref::<FsRoot>().map(|root| root.clone());
+    let full_pattern = match &root {
+        Some(root) if !Path::new(&pattern).is_absolute() => {
+            let root_str = root.path().to_string_lossy();
+            format!("{}{MAIN_SEPARATOR}{pattern}", Pattern::escape(&root_str))
+        }
+        _ => pattern.clone(),
+    };
+    let is_relative = !Path::new(&pattern).is_absolute();
+
+    let paths = glob_with(&full_pattern, match_options)
+        .map_err(|e| LuaError::RuntimeError(format!("Invalid glob pattern '{pattern}'\n{e}")))?;
+
+    // NOTE: Globbing walks the filesystem synchronously, so we
+    // run it on a separate thread to not block the lua thread
+    lua.spawn_blocking(move || {
+        let mut path_strings = Vec::new();
+        for path in paths {
+            let path = path.map_err(|e| {
+                LuaError::RuntimeError(format!(
+                    "Failed to read '{}' while globbing - {}",
+                    e.path().display(),
+                    e.error()
+                ))
+            })?;
+            // NOTE: Matches may also be outside of the root, if the pattern contains
+            // '..' components or goes through symlinks, and those are skipped too
+            let path = match &root {
+                Some(root) if root.check(&path).is_err() => continue,
+                Some(root) if is_relative => match path.strip_prefix(root.path()) {
+                    Ok(relative) => relative.to_path_buf(),
+                    Err(_) => continue,
+                },
+                _ => path,
+            };
+            if let Some(path_str) = path.to_str() {
+                path_strings.push(path_str.to_owned());
+            } else {
+                return Err(LuaError::RuntimeError(format!(
+                    "File path could not be converted into a string: '{}'",
+                    path.display()
+                )));
+            }
+        }
+        Ok(path_strings)
+    })
+    .await
+}
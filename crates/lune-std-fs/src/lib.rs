@@ -1,21 +1,63 @@
 #![allow(clippy::cargo_common_metadata)]
 
+use std::env;
 use std::io::ErrorKind as IoErrorKind;
-use std::path::{PathBuf, MAIN_SEPARATOR};
+use std::path::Path;
 
 use bstr::{BString, ByteSlice};
 use mlua::prelude::*;
-use tokio::fs;
+use tokio::{
+    fs,
+    io::{AsyncWriteExt, BufReader},
+};
 
-use lune_utils::TableBuilder;
+use lune_std_serde::{
+    compress_to_writer, decompress_from_reader, CompressOptions, DecompressOptions,
+};
+use lune_utils::{buffer, path::clean_path, TableBuilder};
 
+mod atomic;
 mod copy;
+mod encoding;
+mod glob;
+mod lines;
+mod lock;
 mod metadata;
 mod options;
+mod path;
+mod permissions;
+mod range;
+mod root;
+mod size;
+mod symlink;
+mod temp;
+mod walk;
+mod watch;
+mod writer;
 
-use self::copy::copy;
+use self::atomic::write_atomic;
+use self::copy::{copy, move_with_fallback};
+use self::encoding::decode_text;
+use self::glob::glob;
+use self::lines::read_lines;
+use self::lock::{lock, try_lock, FsLock};
 use self::metadata::FsMetadata;
-use self::options::FsWriteOptions;
+use self::options::{
+    FsCreateDirOptions, FsGlobOptions, FsLockOptions, FsMetadataOptions, FsOpenWriteOptions,
+    FsReadOptions, FsTempOptions, FsWriteFileOptions, FsWriteOptions,
+};
+use self::path::{path_extension, path_file_name, path_join, path_parent, path_stem};
+use self::permissions::{set_permissions, FsSetPermissions};
+use self::range::read_range;
+use self::root::{resolve_path, resolve_path_no_follow};
+use self::size::{disk_usage, size};
+use self::symlink::create_symlink;
+use self::temp::create_temp;
+use self::walk::walk_dir;
+use self::watch::watch;
+use self::writer::{open_write, FsWriter};
+
+pub use self::root::FsRoot;
 
 /**
     Creates the `fs` standard library module.
@@ -27,113 +69,373 @@ use self::options::FsWriteOptions;
 pub fn module(lua: &Lua) -> LuaResult<LuaTable> {
     TableBuilder::new(lua)?
         .with_async_function("readFile", fs_read_file)?
+        .with_async_function("readLines", fs_read_lines)?
         .with_async_function("readDir", fs_read_dir)?
         .with_async_function("writeFile", fs_write_file)?
+        .with_async_function("writeFileAtomic", fs_write_file_atomic)?
+        .with_async_function("appendFile", fs_append_file)?
+        .with_async_function("openWrite", fs_open_write)?
         .with_async_function("writeDir", fs_write_dir)?
+        .with_async_function("createDir", fs_create_dir)?
         .with_async_function("removeFile", fs_remove_file)?
         .with_async_function("removeDir", fs_remove_dir)?
+        .with_async_function("remove", fs_remove)?
         .with_async_function("metadata", fs_metadata)?
+        .with_async_function("setPermissions", fs_set_permissions)?
         .with_async_function("isFile", fs_is_file)?
         .with_async_function("isDir", fs_is_dir)?
+        .with_async_function("size", fs_size)?
+        .with_async_function("diskUsage", fs_disk_usage)?
         .with_async_function("move", fs_move)?
+        .with_async_function("rename", fs_rename)?
         .with_async_function("copy", fs_copy)?
+        .with_async_function("glob", fs_glob)?
+        .with_async_function("lock", fs_lock)?
+        .with_function("tryLock", fs_try_lock)?
+        .with_function("createTempFile", fs_create_temp_file)?
+        .with_function("createTempDir", fs_create_temp_dir)?
+        .with_async_function("symlink", fs_symlink)?
+        .with_async_function("readLink", fs_read_link)?
+        .with_async_function("canonicalize", fs_canonicalize)?
+        .with_function("absolute", fs_absolute)?
+        .with_function("pathJoin", path_join)?
+        .with_function("pathParent", path_parent)?
+        .with_function("pathFileName", path_file_name)?
+        .with_function("pathExtension", path_extension)?
+        .with_function("pathStem", path_stem)?
+        .with_async_function("walkDir", fs_walk_dir)?
+        .with_function("watch", fs_watch)?
         .build_readonly()
 }
 
-async fn fs_read_file(lua: &Lua, path: String) -> LuaResult<LuaString> {
-    let bytes = fs::read(&path).await.into_lua_err()?;
+async fn fs_read_file(
+    lua: &Lua,
+    (path, options): (String, FsReadOptions),
+) -> LuaResult<LuaMultiValue> {
+    let resolved = resolve_path(lua, &path)?;
+    let bytes = match options.decompress {
+        None if options.offset.is_some() || options.length.is_some() => {
+            let offset = options.offset.unwrap_or_default();
+            read_range(&resolved, offset, options.length, options.exact).await?
+        }
+        None => fs::read(&resolved).await.into_lua_err()?,
+        Some(format) => {
+            let file = fs::File::open(&resolved).await.into_lua_err()?;
+            decompress_from_reader(BufReader::new(file), format, DecompressOptions::default())
+                .await
+                .map_err(|e| {
+                    LuaError::runtime(format!(
+                        "Failed to decompress file '{path}' as {}\n{e}",
+                        format.name()
+                    ))
+                })?
+        }
+    };
+
+    if let Some(encoding) = options.encoding {
+        let (text, encoding) = decode_text(&bytes, encoding);
+        (text, encoding).into_lua_multi(lua)
+    } else if options.binary {
+        buffer::to_lua(lua, bytes)?.into_lua_multi(lua)
+    } else {
+        lua.create_string(bytes)?.into_lua_multi(lua)
+    }
+}
 
-    lua.create_string(bytes)
+async fn fs_read_lines(lua: &Lua, path: String) -> LuaResult<LuaFunction> {
+    read_lines(lua, resolve_path(lua, path)?).await
 }
 
-async fn fs_read_dir(_: &Lua, path: String) -> LuaResult<Vec<String>> {
+async fn fs_read_dir(lua: &Lua, path: String) -> LuaResult<Vec<String>> {
     let mut dir_strings = Vec::new();
-    let mut dir = fs::read_dir(&path).await.into_lua_err()?;
+    let mut dir = fs::read_dir(resolve_path(lua, path)?)
+        .await
+        .into_lua_err()?;
     while let Some(dir_entry) = dir.next_entry().await.into_lua_err()? {
-        if let Some(dir_path_str) = dir_entry.path().to_str() {
-            dir_strings.push(dir_path_str.to_owned());
+        if let Some(dir_name_str) = dir_entry.file_name().to_str() {
+            dir_strings.push(dir_name_str.to_owned());
         } else {
             return Err(LuaError::RuntimeError(format!(
-                "File path could not be converted into a string: '{}'",
-                dir_entry.path().display()
+                "File name could not be converted into a string: '{}'",
+                dir_entry.file_name().to_string_lossy()
             )));
         }
     }
-    let mut dir_string_prefix = path;
-    if !dir_string_prefix.ends_with(MAIN_SEPARATOR) {
-        dir_string_prefix.push(MAIN_SEPARATOR);
-    }
-    let dir_strings_no_prefix = dir_strings
-        .iter()
-        .map(|inner_path| {
-            inner_path
-                .trim()
-                .trim_start_matches(&dir_string_prefix)
-                .to_owned()
-        })
-        .collect::<Vec<_>>();
-    Ok(dir_strings_no_prefix)
+    // NOTE: Entry order from the OS is platform-dependent,
+    // so we sort here to make sure it is always deterministic
+    dir_strings.sort_unstable();
+    Ok(dir_strings)
 }
 
-async fn fs_write_file(_: &Lua, (path, contents): (String, BString)) -> LuaResult<()> {
-    fs::write(&path, contents.as_bytes()).await.into_lua_err()
+async fn fs_write_file(
+    lua: &Lua,
+    (path, contents, options): (String, BString, FsWriteFileOptions),
+) -> LuaResult<()> {
+    let path = resolve_path(lua, path)?;
+    let Some(format) = options.compress else {
+        return fs::write(&path, contents.as_bytes()).await.into_lua_err();
+    };
+
+    // NOTE: The options are validated before creating the file, so
+    // that invalid options never leave behind an empty or truncated file
+    let options = CompressOptions {
+        level: options.level,
+        ..Default::default()
+    };
+    options.validate(format)?;
+
+    let mut file = fs::File::create(&path).await.into_lua_err()?;
+    compress_to_writer(contents.as_bytes(), &mut file, format, options).await
 }
 
-async fn fs_write_dir(_: &Lua, path: String) -> LuaResult<()> {
-    fs::create_dir_all(&path).await.into_lua_err()
+async fn fs_write_file_atomic(lua: &Lua, (path, contents): (String, BString)) -> LuaResult<()> {
+    write_atomic(resolve_path(lua, path)?, contents.as_bytes()).await
 }
 
-async fn fs_remove_file(_: &Lua, path: String) -> LuaResult<()> {
-    fs::remove_file(&path).await.into_lua_err()
+async fn fs_append_file(lua: &Lua, (path, contents): (String, BString)) -> LuaResult<()> {
+    let mut file = fs::OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(resolve_path(lua, path)?)
+        .await
+        .into_lua_err()?;
+    file.write_all(contents.as_bytes()).await.into_lua_err()?;
+    file.flush().await.into_lua_err()
 }
 
-async fn fs_remove_dir(_: &Lua, path: String) -> LuaResult<()> {
-    fs::remove_dir_all(&path).await.into_lua_err()
+async fn fs_open_write(
+    lua: &Lua,
+    (path, options): (String, FsOpenWriteOptions),
+) -> LuaResult<FsWriter> {
+    open_write(resolve_path(lua, path)?, options).await
 }
 
-async fn fs_metadata(_: &Lua, path: String) -> LuaResult<FsMetadata> {
-    match fs::metadata(path).await {
-        Err(e) if e.kind() == IoErrorKind::NotFound => Ok(FsMetadata::not_found()),
-        Ok(meta) => Ok(FsMetadata::from(meta)),
-        Err(e) => Err(e.into()),
+async fn fs_write_dir(lua: &Lua, path: String) -> LuaResult<()> {
+    fs::create_dir_all(resolve_path(lua, path)?)
+        .await
+        .into_lua_err()
+}
+
+async fn fs_create_dir(lua: &Lua, (path, options): (String, FsCreateDirOptions)) -> LuaResult<()> {
+    let path = resolve_path(lua, path)?;
+    if options.recursive {
+        fs::create_dir_all(&path).await.into_lua_err()
+    } else {
+        fs::create_dir(&path).await.into_lua_err()
     }
 }
 
-async fn fs_is_file(_: &Lua, path: String) -> LuaResult<bool> {
-    match fs::metadata(path).await {
-        Err(e) if e.kind() == IoErrorKind::NotFound => Ok(false),
-        Ok(meta) => Ok(meta.is_file()),
-        Err(e) => Err(e.into()),
+async fn fs_remove_file(lua: &Lua, path: String) -> LuaResult<()> {
+    fs::remove_file(resolve_path_no_follow(lua, path)?)
+        .await
+        .into_lua_err()
+}
+
+async fn fs_remove_dir(lua: &Lua, path: String) -> LuaResult<()> {
+    fs::remove_dir_all(resolve_path(lua, path)?)
+        .await
+        .into_lua_err()
+}
+
+async fn fs_remove(lua: &Lua, path: String) -> LuaResult<bool> {
+    let path = resolve_path_no_follow(lua, path)?;
+    // NOTE: We use symlink metadata here to make sure
+    // that we remove symlinks and not their targets
+    let meta = match fs::symlink_metadata(&path).await {
+        Err(e) if e.kind() == IoErrorKind::NotFound => return Ok(false),
+        Ok(meta) => meta,
+        Err(e) => return Err(e.into()),
+    };
+    if meta.is_dir() {
+        fs::remove_dir_all(&path).await.into_lua_err()?;
+    } else {
+        fs::remove_file(&path).await.into_lua_err()?;
     }
+    Ok(true)
 }
 
-async fn fs_is_dir(_: &Lua, path: String) -> LuaResult<bool> {
-    match fs::metadata(path).await {
-        Err(e) if e.kind() == IoErrorKind::NotFound => Ok(false),
-        Ok(meta) => Ok(meta.is_dir()),
+async fn fs_metadata(
+    lua: &Lua,
+    (path, options): (String, FsMetadataOptions),
+) -> LuaResult<Option<FsMetadata>> {
+    let result = if options.follow_symlinks {
+        fs::metadata(resolve_path(lua, path)?).await
+    } else {
+        fs::symlink_metadata(resolve_path_no_follow(lua, path)?).await
+    };
+    match result {
+        Err(e) if e.kind() == IoErrorKind::NotFound => Ok(None),
+        Ok(meta) => Ok(Some(FsMetadata::from(meta))),
         Err(e) => Err(e.into()),
     }
 }
 
-async fn fs_move(_: &Lua, (from, to, options): (String, String, FsWriteOptions)) -> LuaResult<()> {
-    let path_from = PathBuf::from(from);
+async fn fs_set_permissions(
+    lua: &Lua,
+    (path, permissions): (String, FsSetPermissions),
+) -> LuaResult<()> {
+    set_permissions(resolve_path(lua, path)?, permissions).await
+}
+
+async fn fs_is_file(lua: &Lua, path: String) -> LuaResult<bool> {
+    // NOTE: Similar to shell tests, we treat any error as "not a file"
+    let path = resolve_path(lua, path)?;
+    Ok(fs::metadata(path).await.is_ok_and(|meta| meta.is_file()))
+}
+
+async fn fs_is_dir(lua: &Lua, path: String) -> LuaResult<bool> {
+    // NOTE: Similar to shell tests, we treat any error as "not a dir"
+    let path = resolve_path(lua, path)?;
+    Ok(fs::metadata(path).await.is_ok_and(|meta| meta.is_dir()))
+}
+
+async fn fs_size(lua: &Lua, path: String) -> LuaResult<(u64, LuaTable)> {
+    size(lua, resolve_path(lua, path)?)
+        .await?
+        .into_lua_multi(lua)
+}
+
+async fn fs_disk_usage(lua: &Lua, path: String) -> LuaResult<LuaTable> {
+    disk_usage(lua, resolve_path(lua, path)?)
+        .await?
+        .into_lua_table(lua)
+}
+
+async fn fs_move(
+    lua: &Lua,
+    (from, to, options): (String, String, FsWriteOptions),
+) -> LuaResult<()> {
+    let path_from = resolve_path(lua, from)?;
     if !path_from.exists() {
         return Err(LuaError::RuntimeError(format!(
             "No file or directory exists at the path '{}'",
             path_from.display()
         )));
     }
-    let path_to = PathBuf::from(to);
+    let path_to = resolve_path(lua, to)?;
     if !options.overwrite && path_to.exists() {
         return Err(LuaError::RuntimeError(format!(
             "A file or directory already exists at the path '{}'",
             path_to.display()
         )));
     }
-    fs::rename(path_from, path_to).await.into_lua_err()?;
-    Ok(())
+    let rename = fs::rename(&path_from, &path_to);
+    move_with_fallback(lua, &path_from, &path_to, options, rename).await
+}
+
+async fn fs_rename(lua: &Lua, (from, to): (String, String)) -> LuaResult<bool> {
+    let path_from = resolve_path_no_follow(lua, &from)?;
+    let path_to = resolve_path_no_follow(lua, &to)?;
+    // NOTE: We use symlink metadata here so that symlinks are renamed
+    // instead of their targets, and so that broken symlinks count as existing
+    match fs::symlink_metadata(&path_from).await {
+        Err(e) if e.kind() == IoErrorKind::NotFound => {
+            return Err(LuaError::RuntimeError(format!(
+                "No file or directory exists at the path '{from}'"
+            )))
+        }
+        Err(e) => return Err(e.into()),
+        Ok(_) => {}
+    }
+    // NOTE: Renaming replaces the target in a single step on most platforms,
+    // so checking if it exists beforehand is the only way to know if it did
+    let replaced = match fs::symlink_metadata(&path_to).await {
+        Err(e) if e.kind() == IoErrorKind::NotFound => false,
+        Err(e) => return Err(e.into()),
+        Ok(_) => true,
+    };
+    fs::rename(&path_from, &path_to).await.map_err(|e| {
+        LuaError::RuntimeError(format!("Failed to rename '{from}' to '{to}' - {e}"))
+    })?;
+    Ok(replaced)
+}
+
+async fn fs_copy(
+    lua: &Lua,
+    (from, to, options): (String, String, FsWriteOptions),
+) -> LuaResult<()> {
+    copy(
+        lua,
+        resolve_path(lua, from)?,
+        resolve_path(lua, to)?,
+        options,
+    )
+    .await
+}
+
+async fn fs_glob(lua: &Lua, (pattern, options): (String, FsGlobOptions)) -> LuaResult<Vec<String>> {
+    glob(lua, pattern, options).await
+}
+
+async fn fs_lock(lua: &Lua, (path, options): (String, FsLockOptions)) -> LuaResult<FsLock> {
+    lock(lua, resolve_path(lua, path)?, options).await
+}
+
+fn fs_try_lock(lua: &Lua, (path, options): (String, FsLockOptions)) -> LuaResult<Option<FsLock>> {
+    try_lock(resolve_path(lua, path)?, options)
+}
+
+fn fs_create_temp_file(lua: &Lua, options: FsTempOptions) -> LuaResult<String> {
+    create_temp(lua, options, false)
+}
+
+fn fs_create_temp_dir(lua: &Lua, options: FsTempOptions) -> LuaResult<String> {
+    create_temp(lua, options, true)
 }
 
-async fn fs_copy(_: &Lua, (from, to, options): (String, String, FsWriteOptions)) -> LuaResult<()> {
-    copy(from, to, options).await
+async fn fs_symlink(lua: &Lua, (target, link): (String, String)) -> LuaResult<()> {
+    create_symlink(lua, target, link).await
+}
+
+async fn fs_read_link(lua: &Lua, path: String) -> LuaResult<String> {
+    let target = fs::read_link(resolve_path_no_follow(lua, &path)?)
+        .await
+        .map_err(|e| LuaError::RuntimeError(format!("Failed to read symlink at '{path}'\n{e}")))?;
+    path_to_string(&target)
+}
+
+async fn fs_canonicalize(lua: &Lua, path: String) -> LuaResult<String> {
+    let canonicalized = fs::canonicalize(resolve_path(lua, &path)?)
+        .await
+        .map_err(|e| {
+            LuaError::RuntimeError(format!("Failed to canonicalize path '{path}'\n{e}"))
+        })?;
+    // NOTE: Canonicalizing on Windows gives UNC paths, which are not
+    // very friendly, so we simplify them whenever that is possible
+    path_to_string(dunce::simplified(&canonicalized))
+}
+
+fn fs_absolute(lua: &Lua, path: String) -> LuaResult<String> {
+    // NOTE: Paths are relative to the filesystem root instead, if one has been set
+    if let Some(root) = lua.app_data_ref::<FsRoot>() {
+        return path_to_string(&root.resolve(path)?);
+    }
+    // NOTE: The working directory may be changed using process.setCwd,
+    // so we must get it here and not use the one from when Lune started
+    let cwd = env::current_dir().into_lua_err()?;
+    path_to_string(&clean_path(cwd.join(path)))
+}
+
+async fn fs_walk_dir<'lua>(
+    lua: &'lua Lua,
+    (path, callback): (String, LuaFunction<'lua>),
+) -> LuaResult<()> {
+    walk_dir(lua, resolve_path(lua, path)?, callback).await
+}
+
+fn fs_watch<'lua>(
+    lua: &'lua Lua,
+    (path, callback): (String, LuaFunction<'lua>),
+) -> LuaResult<LuaTable<'lua>> {
+    watch(lua, resolve_path(lua, path)?, callback)
+}
+
+fn path_to_string(path: &Path) -> LuaResult<String> {
+    match path.to_str() {
+        Some(s) => Ok(s.to_owned()),
+        None => Err(LuaError::RuntimeError(format!(
+            "File path could not be converted into a string: '{}'",
+            path.display()
+        ))),
+    }
 }
@@ -0,0 +1,76 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Result as IoResult},
+    path::Path,
+    sync::mpsc::sync_channel,
+};
+
+use mlua::prelude::*;
+use mlua_luau_scheduler::LuaSpawnExt;
+use tokio::fs;
+
+// NOTE: This limits how far ahead of the iterator lines are read, which
+// keeps memory usage low while still reading most lines ahead of time
+const READ_AHEAD_LINES: usize = 64;
+
+/**
+    Opens the file at `path` and returns an iterator function
+    that reads and returns one line of the file at a time.
+
+    Lines are returned without their `\n` or `\r\n` terminators,
+    and the file is closed once the end of it has been reached.
+*/
+pub async fn read_lines(lua: &Lua, path: impl AsRef<Path>) -> LuaResult<LuaFunction> {
+    let path = path.as_ref();
+    let file = fs::File::open(path).await.map_err(|e| {
+        LuaError::runtime(format!(
+            "Failed to open file '{}' for reading lines\n{e}",
+            path.display()
+        ))
+    })?;
+
+    // NOTE: Iterator functions in a generic for loop can not yield, so lines are read
+    // ahead of time on a separate thread, and the iterator only has to wait for that
+    // thread when reaching lines that have not been read yet - the thread stops once
+    // the end of the file was reached, or when the iterator gets garbage collected
+    let mut reader = BufReader::new(file.into_std().await);
+    let (tx, rx) = sync_channel(READ_AHEAD_LINES);
+    lua.spawn_blocking(move || loop {
+        let line = read_line(&mut reader);
+        let done = !matches!(line, Ok(Some(_)));
+        if tx.send(line).is_err() || done {
+            break;
+        }
+    })
+    .detach();
+
+    let path = path.to_path_buf();
+    lua.create_function(move |lua, _: LuaMultiValue| {
+        // NOTE: Once the reader thread stops, all remaining calls return nil
+        let Ok(line) = rx.recv() else {
+            return Ok(None);
+        };
+        match line {
+            Ok(Some(line)) => Ok(Some(lua.create_string(line)?)),
+            Ok(None) => Ok(None),
+            Err(e) => Err(LuaError::runtime(format!(
+                "Failed to read lines from file '{}'\n{e}",
+                path.display()
+            ))),
+        }
+    })
+}
+
+fn read_line(reader: &mut BufReader<File>) -> IoResult<Option<Vec<u8>>> {
+    let mut line = Vec::new();
+    if reader.read_until(b'\n', &mut line)? == 0 {
+        return Ok(None);
+    }
+    if line.ends_with(b"\n") {
+        line.pop();
+        if line.ends_with(b"\r") {
+            line.pop();
+        }
+    }
+    Ok(Some(line))
+}
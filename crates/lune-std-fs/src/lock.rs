@@ -0,0 +1,119 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::Error as IoError,
+    path::{Path, PathBuf},
+};
+
+use fs2::FileExt;
+use mlua::prelude::*;
+use mlua_luau_scheduler::LuaSpawnExt;
+
+use super::options::{FsLockMode, FsLockOptions};
+
+// NOTE: Newer versions of the standard library have locking methods on files
+// with the same names, so we call the methods through the fs2 trait explicitly
+
+/**
+    An advisory lock on a file, released when unlocked or garbage collected.
+*/
+#[derive(Debug)]
+pub struct FsLock {
+    path: PathBuf,
+    mode: FsLockMode,
+    file: Option<File>,
+}
+
+impl FsLock {
+    fn unlock(&mut self) -> LuaResult<()> {
+        // NOTE: Closing the file also releases the lock, but we unlock
+        // explicitly first to surface any errors from doing so
+        if let Some(file) = self.file.take() {
+            FileExt::unlock(&file).map_err(|e| {
+                LuaError::runtime(format!(
+                    "Failed to unlock file '{}'\n{e}",
+                    self.path.display()
+                ))
+            })?;
+        }
+        Ok(())
+    }
+}
+
+impl LuaUserData for FsLock {
+    fn add_fields<'lua, F: LuaUserDataFields<'lua, Self>>(fields: &mut F) {
+        fields.add_field_method_get("path", |_, this| Ok(this.path.display().to_string()));
+        fields.add_field_method_get("mode", |_, this| Ok(this.mode.name()));
+        fields.add_field_method_get("locked", |_, this| Ok(this.file.is_some()));
+    }
+
+    fn add_methods<'lua, M: LuaUserDataMethods<'lua, Self>>(methods: &mut M) {
+        methods.add_method_mut("unlock", |_, this, (): ()| this.unlock());
+    }
+}
+
+fn open_lock_file(path: &Path) -> LuaResult<File> {
+    // NOTE: Missing files are created so that lock files can be used
+    // for coordination, but existing contents are never truncated
+    OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)
+        .map_err(|e| {
+            LuaError::runtime(format!(
+                "Failed to open file '{}' for locking\n{e}",
+                path.display()
+            ))
+        })
+}
+
+fn lock_error(path: &Path, e: &IoError) -> LuaError {
+    LuaError::runtime(format!("Failed to lock file '{}'\n{e}", path.display()))
+}
+
+/**
+    Locks the file at `path`, waiting until any conflicting lock has been released.
+*/
+pub async fn lock(lua: &Lua, path: PathBuf, options: FsLockOptions) -> LuaResult<FsLock> {
+    // NOTE: Waiting for a lock blocks the thread it happens on,
+    // so we do it on a separate thread to not block the lua thread
+    let mode = options.mode;
+    lua.spawn_blocking(move || {
+        let file = open_lock_file(&path)?;
+        let result = match mode {
+            FsLockMode::Exclusive => FileExt::lock_exclusive(&file),
+            FsLockMode::Shared => FileExt::lock_shared(&file),
+        };
+        match result {
+            Ok(()) => Ok(FsLock {
+                path,
+                mode,
+                file: Some(file),
+            }),
+            Err(e) => Err(lock_error(&path, &e)),
+        }
+    })
+    .await
+}
+
+/**
+    Tries to lock the file at `path`, returning `None` if a conflicting lock is currently held.
+*/
+pub fn try_lock(path: PathBuf, options: FsLockOptions) -> LuaResult<Option<FsLock>> {
+    let mode = options.mode;
+    let file = open_lock_file(&path)?;
+    let result = match mode {
+        FsLockMode::Exclusive => FileExt::try_lock_exclusive(&file),
+        FsLockMode::Shared => FileExt::try_lock_shared(&file),
+    };
+    match result {
+        Ok(()) => Ok(Some(FsLock {
+            path,
+            mode,
+            file: Some(file),
+        })),
+        Err(e) if e.raw_os_error() == fs2::lock_contended_error().raw_os_error() => Ok(None),
+        Err(e) => Err(lock_error(&path, &e)),
+    }
+}
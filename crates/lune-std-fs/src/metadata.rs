@@ -74,20 +74,31 @@ impl<'lua> IntoLua<'lua> for FsMetadataKind {
 #[derive(Debug, Clone)]
 pub struct FsPermissions {
     pub(crate) read_only: bool,
+    pub(crate) mode: Option<u32>,
 }
 
 impl From<StdPermissions> for FsPermissions {
     fn from(value: StdPermissions) -> Self {
+        // NOTE: Only the permission bits of the mode are kept, the file type is in the metadata kind
+        #[cfg(unix)]
+        let mode = {
+            use std::os::unix::fs::PermissionsExt;
+            Some(value.mode() & 0o7777)
+        };
+        #[cfg(not(unix))]
+        let mode = None;
         Self {
             read_only: value.readonly(),
+            mode,
         }
     }
 }
 
 impl<'lua> IntoLua<'lua> for FsPermissions {
     fn into_lua(self, lua: &'lua Lua) -> LuaResult<LuaValue<'lua>> {
-        let tab = lua.create_table_with_capacity(0, 1)?;
+        let tab = lua.create_table_with_capacity(0, 2)?;
         tab.set("readOnly", self.read_only)?;
+        tab.set("mode", self.mode)?;
         tab.set_readonly(true);
         Ok(LuaValue::Table(tab))
     }
@@ -96,31 +107,19 @@ impl<'lua> IntoLua<'lua> for FsPermissions {
 #[derive(Debug, Clone)]
 pub struct FsMetadata {
     pub(crate) kind: FsMetadataKind,
-    pub(crate) exists: bool,
+    pub(crate) size: Option<u64>,
     pub(crate) created_at: Option<DateTime>,
     pub(crate) modified_at: Option<DateTime>,
     pub(crate) accessed_at: Option<DateTime>,
     pub(crate) permissions: Option<FsPermissions>,
 }
 
-impl FsMetadata {
-    pub fn not_found() -> Self {
-        Self {
-            kind: FsMetadataKind::None,
-            exists: false,
-            created_at: None,
-            modified_at: None,
-            accessed_at: None,
-            permissions: None,
-        }
-    }
-}
-
 impl<'lua> IntoLua<'lua> for FsMetadata {
     fn into_lua(self, lua: &'lua Lua) -> LuaResult<LuaValue<'lua>> {
-        let tab = lua.create_table_with_capacity(0, 6)?;
+        let tab = lua.create_table_with_capacity(0, 7)?;
         tab.set("kind", self.kind)?;
-        tab.set("exists", self.exists)?;
+        tab.set("exists", true)?;
+        tab.set("size", self.size)?;
         tab.set("createdAt", self.created_at)?;
         tab.set("modifiedAt", self.modified_at)?;
         tab.set("accessedAt", self.accessed_at)?;
@@ -134,7 +133,7 @@ impl From<StdMetadata> for FsMetadata {
     fn from(value: StdMetadata) -> Self {
         Self {
             kind: value.file_type().into(),
-            exists: true,
+            size: Some(value.len()),
             created_at: system_time_to_timestamp(value.created()),
             modified_at: system_time_to_timestamp(value.modified()),
             accessed_at: system_time_to_timestamp(value.accessed()),
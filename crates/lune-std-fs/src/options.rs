@@ -1,5 +1,131 @@
 use mlua::prelude::*;
 
+use lune_std_serde::CompressDecompressFormat;
+
+use super::encoding::FsReadEncoding;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FsReadOptions {
+    pub(crate) binary: bool,
+    pub(crate) decompress: Option<CompressDecompressFormat>,
+    pub(crate) offset: Option<u64>,
+    pub(crate) length: Option<u64>,
+    pub(crate) exact: bool,
+    pub(crate) encoding: Option<FsReadEncoding>,
+}
+
+impl<'lua> FromLua<'lua> for FsReadOptions {
+    fn from_lua(value: LuaValue<'lua>, _: &'lua Lua) -> LuaResult<Self> {
+        Ok(match value {
+            LuaValue::Nil => Self::default(),
+            LuaValue::Table(t) => {
+                let binary: Option<bool> = t.get("binary")?;
+                let decompress: Option<CompressDecompressFormat> = t.get("decompress")?;
+                let encoding: Option<FsReadEncoding> = t.get("encoding")?;
+                let offset = read_byte_count(&t, "offset")?;
+                let length = read_byte_count(&t, "length")?;
+                let exact = match t.get::<_, LuaValue>("exact")? {
+                    LuaValue::Nil => false,
+                    LuaValue::Boolean(exact) => exact,
+                    value => {
+                        return Err(LuaError::RuntimeError(format!(
+                            "Invalid option value for 'exact' in read options \
+                            - expected boolean, got {}",
+                            value.type_name()
+                        )))
+                    }
+                };
+                if decompress.is_some() && (offset.is_some() || length.is_some()) {
+                    return Err(LuaError::runtime(
+                        "Invalid read options - 'offset' and 'length' can not be used with 'decompress'",
+                    ));
+                }
+                if exact && length.is_none() {
+                    return Err(LuaError::runtime(
+                        "Invalid read options - 'exact' requires a 'length'",
+                    ));
+                }
+                if encoding.is_some() && binary == Some(true) {
+                    return Err(LuaError::runtime(
+                        "Invalid read options - 'encoding' can not be used with 'binary'",
+                    ));
+                }
+                Self {
+                    binary: binary.unwrap_or(false),
+                    decompress,
+                    offset,
+                    length,
+                    exact,
+                    encoding,
+                }
+            }
+            _ => {
+                return Err(LuaError::FromLuaConversionError {
+                    from: value.type_name(),
+                    to: "FsReadOptions",
+                    message: Some(format!(
+                        "Invalid read options - expected table, got {}",
+                        value.type_name()
+                    )),
+                })
+            }
+        })
+    }
+}
+
+fn read_byte_count(t: &LuaTable, key: &str) -> LuaResult<Option<u64>> {
+    match t.get::<_, LuaValue>(key)? {
+        LuaValue::Nil => Ok(None),
+        LuaValue::Integer(n) if n >= 0 => Ok(Some(n.unsigned_abs().into())),
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        LuaValue::Number(n) if n >= 0.0 && n.fract() == 0.0 && n <= u64::MAX as f64 => {
+            Ok(Some(n as u64))
+        }
+        value => Err(LuaError::RuntimeError(format!(
+            "Invalid option value for '{key}' in read options \
+            - expected a non-negative integer, got {}",
+            match value {
+                LuaValue::Integer(_) | LuaValue::Number(_) => value.to_string()?,
+                _ => value.type_name().to_string(),
+            }
+        ))),
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FsWriteFileOptions {
+    pub(crate) compress: Option<CompressDecompressFormat>,
+    pub(crate) level: Option<i32>,
+}
+
+impl<'lua> FromLua<'lua> for FsWriteFileOptions {
+    fn from_lua(value: LuaValue<'lua>, _: &'lua Lua) -> LuaResult<Self> {
+        Ok(match value {
+            LuaValue::Nil => Self::default(),
+            LuaValue::Table(t) => {
+                let compress: Option<CompressDecompressFormat> = t.get("compress")?;
+                let level: Option<i32> = t.get("level")?;
+                if compress.is_none() && level.is_some() {
+                    return Err(LuaError::runtime(
+                        "Invalid write options - 'level' requires a 'compress' format",
+                    ));
+                }
+                Self { compress, level }
+            }
+            _ => {
+                return Err(LuaError::FromLuaConversionError {
+                    from: value.type_name(),
+                    to: "FsWriteFileOptions",
+                    message: Some(format!(
+                        "Invalid write options - expected table, got {}",
+                        value.type_name()
+                    )),
+                })
+            }
+        })
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct FsWriteOptions {
     pub(crate) overwrite: bool,
@@ -29,3 +155,212 @@ impl<'lua> FromLua<'lua> for FsWriteOptions {
         })
     }
 }
+
+#[derive(Debug, Clone, Copy)]
+pub struct FsMetadataOptions {
+    pub(crate) follow_symlinks: bool,
+}
+
+impl<'lua> FromLua<'lua> for FsMetadataOptions {
+    fn from_lua(value: LuaValue<'lua>, _: &'lua Lua) -> LuaResult<Self> {
+        Ok(match value {
+            LuaValue::Nil => Self {
+                follow_symlinks: false,
+            },
+            LuaValue::Table(t) => {
+                let follow_symlinks: Option<bool> = t.get("followSymlinks")?;
+                Self {
+                    follow_symlinks: follow_symlinks.unwrap_or(false),
+                }
+            }
+            _ => {
+                return Err(LuaError::FromLuaConversionError {
+                    from: value.type_name(),
+                    to: "FsMetadataOptions",
+                    message: Some(format!(
+                        "Invalid metadata options - expected table, got {}",
+                        value.type_name()
+                    )),
+                })
+            }
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct FsGlobOptions {
+    pub(crate) case_sensitive: bool,
+}
+
+impl<'lua> FromLua<'lua> for FsGlobOptions {
+    fn from_lua(value: LuaValue<'lua>, _: &'lua Lua) -> LuaResult<Self> {
+        Ok(match value {
+            LuaValue::Nil => Self {
+                case_sensitive: true,
+            },
+            LuaValue::Table(t) => {
+                let case_sensitive: Option<bool> = t.get("caseSensitive")?;
+                Self {
+                    case_sensitive: case_sensitive.unwrap_or(true),
+                }
+            }
+            _ => {
+                return Err(LuaError::FromLuaConversionError {
+                    from: value.type_name(),
+                    to: "FsGlobOptions",
+                    message: Some(format!(
+                        "Invalid glob options - expected table, got {}",
+                        value.type_name()
+                    )),
+                })
+            }
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct FsCreateDirOptions {
+    pub(crate) recursive: bool,
+}
+
+impl<'lua> FromLua<'lua> for FsCreateDirOptions {
+    fn from_lua(value: LuaValue<'lua>, _: &'lua Lua) -> LuaResult<Self> {
+        Ok(match value {
+            LuaValue::Nil => Self { recursive: true },
+            LuaValue::Table(t) => {
+                let recursive: Option<bool> = t.get("recursive")?;
+                Self {
+                    recursive: recursive.unwrap_or(true),
+                }
+            }
+            _ => {
+                return Err(LuaError::FromLuaConversionError {
+                    from: value.type_name(),
+                    to: "FsCreateDirOptions",
+                    message: Some(format!(
+                        "Invalid create dir options - expected table, got {}",
+                        value.type_name()
+                    )),
+                })
+            }
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FsTempOptions {
+    pub(crate) prefix: Option<String>,
+    pub(crate) suffix: Option<String>,
+    pub(crate) dir: Option<String>,
+}
+
+impl<'lua> FromLua<'lua> for FsTempOptions {
+    fn from_lua(value: LuaValue<'lua>, _: &'lua Lua) -> LuaResult<Self> {
+        Ok(match value {
+            LuaValue::Nil => Self::default(),
+            LuaValue::Table(t) => Self {
+                prefix: t.get("prefix")?,
+                suffix: t.get("suffix")?,
+                dir: t.get("dir")?,
+            },
+            _ => {
+                return Err(LuaError::FromLuaConversionError {
+                    from: value.type_name(),
+                    to: "FsTempOptions",
+                    message: Some(format!(
+                        "Invalid temp options - expected table, got {}",
+                        value.type_name()
+                    )),
+                })
+            }
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FsLockMode {
+    #[default]
+    Exclusive,
+    Shared,
+}
+
+impl FsLockMode {
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Self::Exclusive => "exclusive",
+            Self::Shared => "shared",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FsLockOptions {
+    pub(crate) mode: FsLockMode,
+}
+
+impl<'lua> FromLua<'lua> for FsLockOptions {
+    fn from_lua(value: LuaValue<'lua>, _: &'lua Lua) -> LuaResult<Self> {
+        Ok(match value {
+            LuaValue::Nil => Self::default(),
+            LuaValue::Table(t) => {
+                let mode = match t.get::<_, Option<String>>("mode")?.as_deref() {
+                    None | Some("exclusive") => FsLockMode::Exclusive,
+                    Some("shared") => FsLockMode::Shared,
+                    Some(mode) => {
+                        return Err(LuaError::RuntimeError(format!(
+                            "Invalid lock mode '{mode}' - expected 'exclusive' or 'shared'"
+                        )))
+                    }
+                };
+                Self { mode }
+            }
+            _ => {
+                return Err(LuaError::FromLuaConversionError {
+                    from: value.type_name(),
+                    to: "FsLockOptions",
+                    message: Some(format!(
+                        "Invalid lock options - expected table, got {}",
+                        value.type_name()
+                    )),
+                })
+            }
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FsOpenWriteOptions {
+    pub(crate) append: bool,
+}
+
+impl<'lua> FromLua<'lua> for FsOpenWriteOptions {
+    fn from_lua(value: LuaValue<'lua>, _: &'lua Lua) -> LuaResult<Self> {
+        Ok(match value {
+            LuaValue::Nil => Self::default(),
+            LuaValue::Table(t) => {
+                let append = match t.get::<_, LuaValue>("append")? {
+                    LuaValue::Nil => false,
+                    LuaValue::Boolean(append) => append,
+                    value => {
+                        return Err(LuaError::RuntimeError(format!(
+                            "Invalid option value for 'append' in write options \
+                            - expected boolean, got {}",
+                            value.type_name()
+                        )))
+                    }
+                };
+                Self { append }
+            }
+            _ => {
+                return Err(LuaError::FromLuaConversionError {
+                    from: value.type_name(),
+                    to: "FsOpenWriteOptions",
+                    message: Some(format!(
+                        "Invalid write options - expected table, got {}",
+                        value.type_name()
+                    )),
+                })
+            }
+        })
+    }
+}
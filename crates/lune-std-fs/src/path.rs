@@ -0,0 +1,55 @@
+use std::path::{Path, PathBuf};
+
+use mlua::prelude::*;
+
+use super::path_to_string;
+
+/*
+    Path utilities - these are purely string operations using the
+    platform path separator, and never access the filesystem
+*/
+
+pub fn path_join(_: &Lua, components: LuaMultiValue) -> LuaResult<String> {
+    let mut path = PathBuf::new();
+    for (index, component) in components.into_iter().enumerate() {
+        match component {
+            LuaValue::String(s) => path.push(s.to_str()?),
+            value => {
+                return Err(LuaError::RuntimeError(format!(
+                    "Expected path component #{} to be a string, got {}",
+                    index + 1,
+                    value.type_name()
+                )))
+            }
+        }
+    }
+    path_to_string(&path)
+}
+
+pub fn path_parent(_: &Lua, path: String) -> LuaResult<Option<String>> {
+    // NOTE: Relative paths with a single component have an empty
+    // parent in Rust, we treat those as not having a parent at all
+    Path::new(&path)
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .map(path_to_string)
+        .transpose()
+}
+
+pub fn path_file_name(_: &Lua, path: String) -> LuaResult<Option<String>> {
+    Ok(Path::new(&path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string()))
+}
+
+pub fn path_extension(_: &Lua, path: String) -> LuaResult<Option<String>> {
+    Ok(Path::new(&path)
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_string()))
+}
+
+pub fn path_stem(_: &Lua, path: String) -> LuaResult<Option<String>> {
+    Ok(Path::new(&path)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string()))
+}
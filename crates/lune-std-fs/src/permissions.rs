@@ -0,0 +1,138 @@
+use std::path::Path;
+
+use mlua::prelude::*;
+use tokio::fs;
+
+const MAX_MODE: u32 = 0o7777;
+
+/**
+    Permissions to set for a file or directory, given either as a Unix mode, such
+    as `493` or the octal string `"755"`, or as a table with a `mode` and / or a
+    `readOnly` flag. Luau has no octal number literals, so modes may be given as strings.
+
+    When both are given, the mode is set first, and the read-only flag is then applied on top of it.
+*/
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FsSetPermissions {
+    pub(crate) mode: Option<u32>,
+    pub(crate) read_only: Option<bool>,
+}
+
+impl<'lua> FromLua<'lua> for FsSetPermissions {
+    fn from_lua(value: LuaValue<'lua>, _: &'lua Lua) -> LuaResult<Self> {
+        match value {
+            LuaValue::Integer(_) | LuaValue::Number(_) | LuaValue::String(_) => Ok(Self {
+                mode: Some(parse_mode(value)?),
+                read_only: None,
+            }),
+            LuaValue::Table(t) => {
+                let mode = match t.get::<_, LuaValue>("mode")? {
+                    LuaValue::Nil => None,
+                    value => Some(parse_mode(value)?),
+                };
+                let read_only = match t.get::<_, LuaValue>("readOnly")? {
+                    LuaValue::Nil => None,
+                    LuaValue::Boolean(read_only) => Some(read_only),
+                    value => {
+                        return Err(LuaError::RuntimeError(format!(
+                            "Invalid value for 'readOnly' in permissions - \
+                            expected boolean, got {}",
+                            value.type_name()
+                        )))
+                    }
+                };
+                if mode.is_none() && read_only.is_none() {
+                    return Err(LuaError::runtime(
+                        "Invalid permissions - expected a 'mode' or 'readOnly' value",
+                    ));
+                }
+                Ok(Self { mode, read_only })
+            }
+            _ => Err(LuaError::FromLuaConversionError {
+                from: value.type_name(),
+                to: "FsSetPermissions",
+                message: Some(format!(
+                    "Invalid permissions - expected number, string or table, got {}",
+                    value.type_name()
+                )),
+            }),
+        }
+    }
+}
+
+fn parse_mode(value: LuaValue) -> LuaResult<u32> {
+    let mode = match value {
+        LuaValue::Integer(i) => f64::from(i),
+        LuaValue::Number(n) => n,
+        LuaValue::String(s) => {
+            let s = s.to_str()?.trim();
+            let digits = s.strip_prefix("0o").unwrap_or(s);
+            return u32::from_str_radix(digits, 8)
+                .ok()
+                .filter(|mode| *mode <= MAX_MODE)
+                .ok_or_else(|| {
+                    LuaError::RuntimeError(format!(
+                        "Invalid mode '{s}' in permissions - expected an octal string between '0' and '7777'"
+                    ))
+                });
+        }
+        value => {
+            return Err(LuaError::RuntimeError(format!(
+                "Invalid value for 'mode' in permissions - expected number or string, got {}",
+                value.type_name()
+            )))
+        }
+    };
+    if mode.fract() == 0.0 && (0.0..=f64::from(MAX_MODE)).contains(&mode) {
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        Ok(mode as u32)
+    } else {
+        Err(LuaError::RuntimeError(format!(
+            "Invalid mode '{mode}' in permissions - expected an integer between 0 and 0o7777"
+        )))
+    }
+}
+
+/**
+    Sets the permissions of the file or directory at the given path.
+
+    On Unix, making a file read-only removes all of its write permissions, and making it
+    writable again gives write permission only to its owner. Other platforms only have a
+    read-only flag, which is set when given a mode that does not allow writing at all.
+*/
+pub async fn set_permissions(path: impl AsRef<Path>, set: FsSetPermissions) -> LuaResult<()> {
+    let path = path.as_ref();
+    let describe = |e: std::io::Error| {
+        LuaError::RuntimeError(format!(
+            "Failed to set permissions for '{}'\n{e}",
+            path.display()
+        ))
+    };
+
+    let mut permissions = fs::metadata(path).await.map_err(describe)?.permissions();
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mut mode = set.mode.unwrap_or(permissions.mode() & MAX_MODE);
+        match set.read_only {
+            Some(true) => mode &= !0o222,
+            Some(false) => mode |= 0o200,
+            None => {}
+        }
+        permissions.set_mode(mode);
+    }
+
+    #[cfg(not(unix))]
+    {
+        let read_only = set.read_only.or(set.mode.map(|mode| mode & 0o222 == 0));
+        if let Some(read_only) = read_only {
+            permissions.set_readonly(read_only);
+        }
+    }
+
+    fs::set_permissions(path, permissions)
+        .await
+        .map_err(describe)
+}
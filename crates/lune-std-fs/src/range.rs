@@ -0,0 +1,51 @@
+use std::{io::SeekFrom, path::Path};
+
+use mlua::prelude::*;
+use tokio::{
+    fs,
+    io::{AsyncReadExt, AsyncSeekExt},
+};
+
+/**
+    Reads up to `length` bytes, or all remaining bytes if no length is
+    given, from the file at `path`, starting at the given `offset`.
+
+    Reading past the end of the file returns only the bytes that are available,
+    unless `exact` is set, in which case an error is returned instead.
+
+    # Errors
+
+    Errors when the file could not be opened or read from, or if `exact`
+    is set and the file does not contain `length` bytes after `offset`.
+*/
+pub async fn read_range(
+    path: &Path,
+    offset: u64,
+    length: Option<u64>,
+    exact: bool,
+) -> LuaResult<Vec<u8>> {
+    let mut file = fs::File::open(path).await.into_lua_err()?;
+    file.seek(SeekFrom::Start(offset)).await.into_lua_err()?;
+
+    let mut bytes = Vec::new();
+    if let Some(length) = length {
+        // NOTE: The length may be much larger than the file, or even the available
+        // memory, so we only allocate as we read instead of reserving it up front
+        let read = file
+            .take(length)
+            .read_to_end(&mut bytes)
+            .await
+            .into_lua_err()?;
+        if exact && (read as u64) < length {
+            return Err(LuaError::runtime(format!(
+                "Failed to read {length} bytes at offset {offset} from file '{}' \
+                - only {read} bytes are available",
+                path.display()
+            )));
+        }
+    } else {
+        file.read_to_end(&mut bytes).await.into_lua_err()?;
+    }
+
+    Ok(bytes)
+}
@@ -0,0 +1,26 @@
+use std::path::{Path, PathBuf};
+
+use mlua::prelude::*;
+
+pub use lune_utils::FsRoot;
+
+/**
+    Resolves the given path inside of the filesystem root for the given Lua state, if
+    one has been set, otherwise the path is returned as-is and may point anywhere.
+*/
+pub(crate) fn resolve_path(lua: &Lua, path: impl AsRef<Path>) -> LuaResult<PathBuf> {
+    match lua.app_data_ref::<FsRoot>() {
+        Some(root) => root.resolve(path),
+        None => Ok(path.as_ref().to_path_buf()),
+    }
+}
+
+/**
+    Same as [`resolve_path`], but without following a symlink in the last component.
+*/
+pub(crate) fn resolve_path_no_follow(lua: &Lua, path: impl AsRef<Path>) -> LuaResult<PathBuf> {
+    match lua.app_data_ref::<FsRoot>() {
+        Some(root) => root.resolve_no_follow(path),
+        None => Ok(path.as_ref().to_path_buf()),
+    }
+}
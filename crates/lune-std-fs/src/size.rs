@@ -0,0 +1,157 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use mlua::prelude::*;
+use mlua_luau_scheduler::LuaSpawnExt;
+
+use lune_utils::TableBuilder;
+
+use super::path_to_string;
+
+/**
+    An entry that was skipped while calculating the size of a directory.
+*/
+#[derive(Debug, Clone)]
+pub struct FsSizeError {
+    path: PathBuf,
+    message: String,
+}
+
+impl FsSizeError {
+    fn into_lua_table(self, lua: &Lua) -> LuaResult<LuaTable> {
+        TableBuilder::new(lua)?
+            .with_value("path", path_to_string(&self.path)?)?
+            .with_value("message", self.message)?
+            .build_readonly()
+    }
+}
+
+/**
+    The total size of a file or directory, and any entries that had to be skipped.
+*/
+#[derive(Debug, Clone, Default)]
+pub struct FsSize {
+    bytes: u64,
+    errors: Vec<FsSizeError>,
+}
+
+impl FsSize {
+    pub fn into_lua_multi(self, lua: &Lua) -> LuaResult<(u64, LuaTable)> {
+        let errors = lua.create_table_with_capacity(self.errors.len(), 0)?;
+        for error in self.errors {
+            errors.raw_push(error.into_lua_table(lua)?)?;
+        }
+        Ok((self.bytes, errors))
+    }
+}
+
+/**
+    Calculates the size of the file at `path`, or the total size of
+    all of the files inside of the directory at `path`, recursively.
+
+    Symlinks are never followed, and any entries that can not be read, such as
+    due to missing permissions, are skipped and returned alongside the size.
+*/
+pub async fn size(lua: &Lua, path: PathBuf) -> LuaResult<FsSize> {
+    // NOTE: Walking large directories reads lots of metadata, so we
+    // do it on a separate thread to not block the lua thread meanwhile
+    lua.spawn_blocking(move || {
+        let meta = fs::symlink_metadata(&path).map_err(|e| {
+            LuaError::runtime(format!("Failed to get size of '{}'\n{e}", path.display()))
+        })?;
+        let mut size = FsSize::default();
+        if meta.is_dir() {
+            add_dir_size(&path, &mut size);
+        } else if !meta.is_symlink() {
+            size.bytes = meta.len();
+        }
+        Ok(size)
+    })
+    .await
+}
+
+fn add_dir_size(dir: &Path, size: &mut FsSize) {
+    let mut stack = vec![dir.to_path_buf()];
+    while let Some(dir) = stack.pop() {
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                size.errors.push(FsSizeError {
+                    path: dir,
+                    message: e.to_string(),
+                });
+                continue;
+            }
+        };
+        for entry in entries {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    size.errors.push(FsSizeError {
+                        path: dir.clone(),
+                        message: e.to_string(),
+                    });
+                    continue;
+                }
+            };
+            // NOTE: Metadata for dir entries does not follow symlinks
+            let path = entry.path();
+            match entry.metadata() {
+                Ok(meta) if meta.is_dir() => stack.push(path),
+                Ok(meta) if meta.is_symlink() => {}
+                Ok(meta) => size.bytes += meta.len(),
+                Err(e) => size.errors.push(FsSizeError {
+                    path,
+                    message: e.to_string(),
+                }),
+            }
+        }
+    }
+}
+
+/**
+    Space on the filesystem that contains a path, in bytes.
+*/
+#[derive(Debug, Clone, Copy)]
+pub struct FsDiskUsage {
+    total: u64,
+    free: u64,
+    available: u64,
+}
+
+impl FsDiskUsage {
+    pub fn into_lua_table(self, lua: &Lua) -> LuaResult<LuaTable> {
+        TableBuilder::new(lua)?
+            .with_value("total", self.total)?
+            .with_value("free", self.free)?
+            .with_value("available", self.available)?
+            .build_readonly()
+    }
+}
+
+/**
+    Gets the total, free, and available space on the filesystem that contains `path`.
+
+    Available space may be less than free space, since some of the free
+    space may be reserved, such as for the root user on unix systems.
+*/
+pub async fn disk_usage(lua: &Lua, path: PathBuf) -> LuaResult<FsDiskUsage> {
+    lua.spawn_blocking(move || {
+        let usage = fs2::total_space(&path).and_then(|total| {
+            Ok(FsDiskUsage {
+                total,
+                free: fs2::free_space(&path)?,
+                available: fs2::available_space(&path)?,
+            })
+        });
+        usage.map_err(|e| {
+            LuaError::runtime(format!(
+                "Failed to get disk usage for '{}'\n{e}",
+                path.display()
+            ))
+        })
+    })
+    .await
+}
@@ -0,0 +1,72 @@
+use std::path::Path;
+
+use mlua::prelude::*;
+use tokio::fs;
+
+use super::root::{resolve_path_no_follow, FsRoot};
+
+/**
+    Creates a symbolic link at `link` pointing to `target`.
+
+    Relative targets are relative to the directory containing the link, not the current working
+    directory, which matches how the link will be resolved by the operating system once created.
+
+    When confined to a filesystem root, both the link and its target must be inside of the root.
+*/
+pub async fn create_symlink(
+    lua: &Lua,
+    target: impl AsRef<Path>,
+    link: impl AsRef<Path>,
+) -> LuaResult<()> {
+    let target = target.as_ref();
+    let link = resolve_path_no_follow(lua, link)?;
+    if let Some(root) = lua.app_data_ref::<FsRoot>() {
+        root.resolve(link.parent().unwrap_or(&link).join(target))?;
+    }
+    let link = link.as_path();
+    create_symlink_inner(target, link).await.map_err(|e| {
+        LuaError::RuntimeError(format!(
+            "Failed to create symlink at '{}' pointing to '{}'\n{}",
+            link.display(),
+            target.display(),
+            describe_error(&e)
+        ))
+    })
+}
+
+#[cfg(unix)]
+async fn create_symlink_inner(target: &Path, link: &Path) -> std::io::Result<()> {
+    fs::symlink(target, link).await
+}
+
+#[cfg(windows)]
+async fn create_symlink_inner(target: &Path, link: &Path) -> std::io::Result<()> {
+    // NOTE: Windows has separate kinds of symlinks for files and directories,
+    // so we need to check what the target is, relative to the link itself
+    let resolved = match link.parent() {
+        Some(parent) => parent.join(target),
+        None => target.to_path_buf(),
+    };
+    if fs::metadata(&resolved)
+        .await
+        .is_ok_and(|meta| meta.is_dir())
+    {
+        fs::symlink_dir(target, link).await
+    } else {
+        fs::symlink_file(target, link).await
+    }
+}
+
+fn describe_error(e: &std::io::Error) -> String {
+    // NOTE: Creating symlinks on Windows requires either admin privileges
+    // or developer mode, and the error given by the OS is not very helpful
+    const ERROR_PRIVILEGE_NOT_HELD: i32 = 1314;
+    if cfg!(windows) && e.raw_os_error() == Some(ERROR_PRIVILEGE_NOT_HELD) {
+        format!(
+            "{e}\nCreating symlinks on Windows requires running as administrator, \
+            or enabling developer mode in the Windows settings"
+        )
+    } else {
+        e.to_string()
+    }
+}
@@ -0,0 +1,49 @@
+use std::{env, path::PathBuf};
+
+use mlua::prelude::*;
+use tempfile::Builder;
+
+use super::{options::FsTempOptions, path_to_string, root::FsRoot};
+
+const DEFAULT_PREFIX: &str = "lune-";
+
+/**
+    Creates a new, empty, temporary file or directory with a unique name.
+
+    The created file or directory is not removed automatically, and
+    the returned path is always absolute, unless `dir` is relative.
+
+    When confined to a filesystem root, the system temporary directory is outside of
+    it, so the root itself is used instead, unless `dir` is given and inside the root.
+*/
+pub fn create_temp(lua: &Lua, options: FsTempOptions, is_dir: bool) -> LuaResult<String> {
+    let dir = match lua.app_data_ref::<FsRoot>() {
+        Some(root) => root.resolve(options.dir.as_deref().unwrap_or_default())?,
+        None => options.dir.map_or_else(env::temp_dir, PathBuf::from),
+    };
+
+    let mut builder = Builder::new();
+    builder.prefix(options.prefix.as_deref().unwrap_or(DEFAULT_PREFIX));
+    if let Some(suffix) = options.suffix.as_deref() {
+        builder.suffix(suffix);
+    }
+
+    // NOTE: Temp files and directories created by the tempfile crate are removed
+    // once dropped, so we need to make sure to keep them around for the user
+    let path = if is_dir {
+        builder.tempdir_in(&dir).map(tempfile::TempDir::into_path)
+    } else {
+        builder
+            .tempfile_in(&dir)
+            .and_then(|file| file.into_temp_path().keep().map_err(|e| e.error))
+    }
+    .map_err(|e| {
+        LuaError::RuntimeError(format!(
+            "Failed to create temporary {} in '{}'\n{e}",
+            if is_dir { "directory" } else { "file" },
+            dir.display()
+        ))
+    })?;
+
+    path_to_string(&path)
+}
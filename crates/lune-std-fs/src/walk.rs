@@ -0,0 +1,96 @@
+use std::path::{Path, PathBuf};
+
+use mlua::prelude::*;
+use tokio::fs;
+
+use lune_utils::TableBuilder;
+
+use super::{metadata::FsMetadataKind, path_to_string};
+
+#[derive(Debug, Clone)]
+struct FsWalkEntry {
+    path: PathBuf,
+    kind: FsMetadataKind,
+    depth: usize,
+}
+
+impl FsWalkEntry {
+    fn to_lua_table<'lua>(&self, lua: &'lua Lua, root: &Path) -> LuaResult<LuaTable<'lua>> {
+        let relative = self
+            .path
+            .strip_prefix(root)
+            .expect("walked paths are always inside of the root");
+        TableBuilder::new(lua)?
+            .with_value("path", path_to_string(&self.path)?)?
+            .with_value("relativePath", path_to_string(relative)?)?
+            .with_value("kind", self.kind)?
+            .with_value("depth", self.depth)?
+            .build_readonly()
+    }
+}
+
+/**
+    Recursively walks the directory at `root`, calling `callback`
+    for each entry found, with directories visited before their children.
+
+    Entries are visited in sorted order, and if the callback returns `false`
+    for a directory, the entries inside of that directory are skipped.
+
+    Symlinks are never followed, to prevent walking in cycles.
+*/
+pub async fn walk_dir<'lua>(
+    lua: &'lua Lua,
+    root: impl AsRef<Path>,
+    callback: LuaFunction<'lua>,
+) -> LuaResult<()> {
+    let root = root.as_ref();
+    if !fs::metadata(root).await.into_lua_err()?.is_dir() {
+        return Err(LuaError::RuntimeError(format!(
+            "No directory exists at the path '{}'",
+            root.display()
+        )));
+    }
+
+    let mut stack = read_dir_entries(root, 1).await?;
+    stack.reverse();
+
+    while let Some(entry) = stack.pop() {
+        let result = callback
+            .call_async::<_, LuaValue>(entry.to_lua_table(lua, root)?)
+            .await?;
+        let prune = matches!(result, LuaValue::Boolean(false));
+        if entry.kind == FsMetadataKind::Dir && !prune {
+            let mut children = read_dir_entries(&entry.path, entry.depth + 1).await?;
+            children.reverse();
+            stack.extend(children);
+        }
+    }
+
+    Ok(())
+}
+
+async fn read_dir_entries(dir: &Path, depth: usize) -> LuaResult<Vec<FsWalkEntry>> {
+    let mut entries = Vec::new();
+    let mut read_dir = fs::read_dir(dir).await.into_lua_err()?;
+    while let Some(entry) = read_dir.next_entry().await.into_lua_err()? {
+        // NOTE: The file type of a dir entry does not follow symlinks, and any
+        // special files such as sockets or pipes are treated as normal files
+        let file_type = entry.file_type().await.into_lua_err()?;
+        let kind = if file_type.is_dir() {
+            FsMetadataKind::Dir
+        } else if file_type.is_symlink() {
+            FsMetadataKind::Symlink
+        } else {
+            FsMetadataKind::File
+        };
+        entries.push(FsWalkEntry {
+            path: entry.path(),
+            kind,
+            depth,
+        });
+    }
+    // NOTE: Entry order from the OS is platform-dependent,
+    // so we sort here to make sure it is always deterministic
+    entries.sort_unstable_by(|a, b| a.path.cmp(&b.path));
+    Ok(entries)
+}
@@ -0,0 +1,172 @@
+use std::{
+    path::{Path, PathBuf},
+    rc::{Rc, Weak},
+    time::Duration,
+};
+
+use mlua::prelude::*;
+use mlua_luau_scheduler::{LuaSchedulerExt, LuaSpawnExt};
+use notify::{
+    recommended_watcher, Event as NotifyEvent, EventKind as NotifyEventKind, RecursiveMode,
+    Result as NotifyResult, Watcher,
+};
+use tokio::{
+    sync::{mpsc::unbounded_channel, watch},
+    time::timeout,
+};
+
+use lune_utils::TableBuilder;
+
+/*
+    Editors will often emit several events for the same path when saving
+    a single file, so we collect events for a short duration and dedupe
+    them before passing them along to the lua callback.
+*/
+const DEBOUNCE_DURATION: Duration = Duration::from_millis(50);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FsWatchEventKind {
+    Created,
+    Modified,
+    Removed,
+}
+
+impl FsWatchEventKind {
+    fn from_notify(kind: NotifyEventKind) -> Option<Self> {
+        match kind {
+            NotifyEventKind::Create(_) => Some(Self::Created),
+            NotifyEventKind::Modify(_) => Some(Self::Modified),
+            NotifyEventKind::Remove(_) => Some(Self::Removed),
+            _ => None,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Created => "created",
+            Self::Modified => "modified",
+            Self::Removed => "removed",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FsWatchEvent {
+    path: PathBuf,
+    kind: FsWatchEventKind,
+}
+
+impl FsWatchEvent {
+    fn from_notify(event: NotifyEvent) -> Vec<Self> {
+        match FsWatchEventKind::from_notify(event.kind) {
+            None => Vec::new(),
+            Some(kind) => event
+                .paths
+                .into_iter()
+                .map(|path| Self { path, kind })
+                .collect(),
+        }
+    }
+
+    fn into_lua_table(self, lua: &Lua) -> LuaResult<LuaTable> {
+        TableBuilder::new(lua)?
+            .with_value("path", self.path.to_string_lossy().to_string())?
+            .with_value("kind", self.kind.as_str())?
+            .build_readonly()
+    }
+}
+
+pub fn watch<'lua>(
+    lua: &'lua Lua,
+    path: impl AsRef<Path>,
+    callback: LuaFunction<'lua>,
+) -> LuaResult<LuaTable<'lua>> {
+    let (event_tx, mut event_rx) = unbounded_channel::<NotifyResult<NotifyEvent>>();
+
+    // NOTE: The watcher calls our event handler on a separate thread,
+    // so we send events to the lua thread using an unbounded channel
+    let mut watcher = recommended_watcher(move |res| {
+        event_tx.send(res).ok();
+    })
+    .into_lua_err()?;
+    watcher
+        .watch(path.as_ref(), RecursiveMode::Recursive)
+        .into_lua_err()?;
+
+    let lua_inner = lua
+        .app_data_ref::<Weak<Lua>>()
+        .expect("Missing weak lua ref")
+        .upgrade()
+        .expect("Lua was dropped unexpectedly");
+    let callback_key = lua.create_registry_value(callback)?;
+
+    let (shutdown_tx, mut shutdown_rx) = watch::channel(false);
+    lua.spawn_local(async move {
+        // NOTE: The watcher stops watching once dropped, so we
+        // need to move it into this task and keep it alive here
+        let _watcher = watcher;
+        let mut stop_handle_alive = true;
+        loop {
+            let first = tokio::select! {
+                res = event_rx.recv() => match res {
+                    Some(res) => res,
+                    None => break,
+                },
+                res = shutdown_rx.changed(), if stop_handle_alive => {
+                    // NOTE: We will only get a RecvError here if the watch handle is dropped,
+                    // this means lua has garbage collected it and the user does not want
+                    // to manually stop the watcher using the watch handle. Run forever.
+                    if res.is_ok() {
+                        break;
+                    }
+                    stop_handle_alive = false;
+                    continue;
+                }
+            };
+
+            let mut events = Vec::new();
+            let mut push_events = |res: NotifyResult<NotifyEvent>| {
+                if let Ok(event) = res {
+                    for event in FsWatchEvent::from_notify(event) {
+                        if !events.contains(&event) {
+                            events.push(event);
+                        }
+                    }
+                }
+            };
+
+            push_events(first);
+            while let Ok(Some(res)) = timeout(DEBOUNCE_DURATION, event_rx.recv()).await {
+                push_events(res);
+            }
+
+            if *shutdown_rx.borrow() {
+                break;
+            }
+
+            if call_callback(&lua_inner, &callback_key, events).is_err() {
+                break;
+            }
+        }
+        lua_inner.remove_registry_value(callback_key).ok();
+    });
+
+    TableBuilder::new(lua)?
+        .with_function("stop", move |_, (): ()| match shutdown_tx.send(true) {
+            Ok(()) => Ok(()),
+            Err(_) => Err(LuaError::runtime("Watcher already stopped")),
+        })?
+        .build_readonly()
+}
+
+fn call_callback(
+    lua: &Rc<Lua>,
+    callback_key: &LuaRegistryKey,
+    events: Vec<FsWatchEvent>,
+) -> LuaResult<()> {
+    let callback: LuaFunction = lua.registry_value(callback_key)?;
+    for event in events {
+        lua.push_thread_back(callback.clone(), event.into_lua_table(lua)?)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,147 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Error as IoError, Result as IoResult, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use bstr::{BString, ByteSlice};
+use mlua::prelude::*;
+use mlua_luau_scheduler::LuaSpawnExt;
+use tokio::fs;
+
+use super::options::FsOpenWriteOptions;
+
+/**
+    A buffered writer for a file, created using `fs.openWrite`.
+
+    Data is written to the file in chunks as the buffer fills up, and any data
+    still in the buffer is written when the writer is flushed or closed.
+*/
+#[derive(Debug, Clone)]
+pub struct FsWriter {
+    path: PathBuf,
+    inner: Arc<Mutex<Option<BufWriter<File>>>>,
+}
+
+impl FsWriter {
+    async fn write(&self, lua: &Lua, bytes: &[u8]) -> LuaResult<()> {
+        // NOTE: Writes that fit in the buffer never touch the file, so we only need to use
+        // a separate thread for any other writes, or if the writer is currently being used
+        if let Ok(mut inner) = self.inner.try_lock() {
+            let writer = inner.as_mut().ok_or_else(|| self.closed_error())?;
+            if writer.capacity() - writer.buffer().len() >= bytes.len() {
+                let result = writer.write_all(bytes);
+                return result.map_err(|e| self.io_error("write to", &e));
+            }
+        }
+        let bytes = bytes.to_vec();
+        self.run_blocking(lua, "write to", move |writer| writer.write_all(&bytes))
+            .await
+    }
+
+    async fn flush(&self, lua: &Lua) -> LuaResult<()> {
+        self.run_blocking(lua, "flush", BufWriter::flush).await
+    }
+
+    async fn close(&self, lua: &Lua) -> LuaResult<()> {
+        // NOTE: The writer is taken out even if flushing fails, since
+        // the file is in an unknown state after a failed write anyway
+        let inner = Arc::clone(&self.inner);
+        let result = lua
+            .spawn_blocking(move || {
+                let writer = inner.lock().expect("Writer lock was poisoned").take();
+                writer.map(|mut writer| writer.flush())
+            })
+            .await;
+        match result {
+            Some(result) => result.map_err(|e| self.io_error("close", &e)),
+            None => Ok(()),
+        }
+    }
+
+    /**
+        Runs the given function with the writer on a separate
+        thread, since writing to files may block for a while.
+    */
+    async fn run_blocking<F>(&self, lua: &Lua, action: &str, f: F) -> LuaResult<()>
+    where
+        F: FnOnce(&mut BufWriter<File>) -> IoResult<()> + Send + 'static,
+    {
+        let inner = Arc::clone(&self.inner);
+        let result = lua
+            .spawn_blocking(move || {
+                let mut inner = inner.lock().expect("Writer lock was poisoned");
+                inner.as_mut().map(f)
+            })
+            .await;
+        match result {
+            Some(result) => result.map_err(|e| self.io_error(action, &e)),
+            None => Err(self.closed_error()),
+        }
+    }
+
+    fn closed_error(&self) -> LuaError {
+        LuaError::runtime(format!(
+            "Writer for file '{}' has already been closed",
+            self.path.display()
+        ))
+    }
+
+    fn io_error(&self, action: &str, e: &IoError) -> LuaError {
+        LuaError::runtime(format!(
+            "Failed to {action} file '{}'\n{e}",
+            self.path.display()
+        ))
+    }
+}
+
+impl LuaUserData for FsWriter {
+    fn add_fields<'lua, F: LuaUserDataFields<'lua, Self>>(fields: &mut F) {
+        fields.add_field_method_get("path", |_, this| Ok(this.path.display().to_string()));
+    }
+
+    fn add_methods<'lua, M: LuaUserDataMethods<'lua, Self>>(methods: &mut M) {
+        methods.add_async_method("write", |lua, this, bytes: BString| async move {
+            this.write(lua, bytes.as_bytes()).await
+        });
+        methods.add_async_method(
+            "flush",
+            |lua, this, (): ()| async move { this.flush(lua).await },
+        );
+        methods.add_async_method(
+            "close",
+            |lua, this, (): ()| async move { this.close(lua).await },
+        );
+    }
+}
+
+/**
+    Opens the file at `path` for buffered writing, creating it if it does not exist.
+
+    The file is truncated when opened, unless `append` is set in the options.
+*/
+pub async fn open_write(
+    path: impl AsRef<Path>,
+    options: FsOpenWriteOptions,
+) -> LuaResult<FsWriter> {
+    let path = path.as_ref();
+    let file = fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .append(options.append)
+        .truncate(!options.append)
+        .open(path)
+        .await
+        .map_err(|e| {
+            LuaError::runtime(format!(
+                "Failed to open file '{}' for writing\n{e}",
+                path.display()
+            ))
+        })?;
+
+    Ok(FsWriter {
+        path: path.to_path_buf(),
+        inner: Arc::new(Mutex::new(Some(BufWriter::new(file.into_std().await)))),
+    })
+}
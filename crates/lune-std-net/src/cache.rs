@@ -0,0 +1,423 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    path::PathBuf,
+    str::FromStr,
+    sync::{Mutex, PoisonError},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use reqwest::{
+    header::{
+        HeaderMap, HeaderName, HeaderValue, AGE, CACHE_CONTROL, DATE, ETAG, EXPIRES,
+        IF_MODIFIED_SINCE, IF_NONE_MATCH, IF_RANGE, IF_UNMODIFIED_SINCE, LAST_MODIFIED, RANGE,
+        VARY,
+    },
+    Method, Request,
+};
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+
+use lune_std_serde::{hash, HashAlgorithm};
+
+use super::config::ClientCache;
+
+const MEMORY_CACHE_MAX_ENTRIES: usize = 256;
+const MEMORY_CACHE_MAX_BYTES: usize = 32 * 1024 * 1024;
+
+/**
+    A cache for responses to requests made using a client, stored either in memory
+    or on disk, and kept up to date using the `Cache-Control`, `Expires`, `ETag`,
+    and `Last-Modified` headers that are sent by servers.
+
+    Only successful responses to `GET` and `HEAD` requests without a body are cached,
+    keyed by their method and full url. Responses that are no longer fresh are revalidated
+    by sending conditional requests, and a `304 Not Modified` response is a cache hit.
+
+    Caches stored in memory are limited in how many responses, and how many bytes of
+    response bodies, that they may contain, evicting the least recently used responses.
+*/
+#[derive(Debug)]
+pub struct NetClientCache {
+    storage: CacheStorage,
+}
+
+#[derive(Debug)]
+enum CacheStorage {
+    Memory(Mutex<MemoryCache>),
+    Disk(PathBuf),
+}
+
+impl NetClientCache {
+    /**
+        Creates a new cache.
+
+        The directory for a cache stored on disk is created once the first response is stored.
+    */
+    pub fn new(config: ClientCache) -> Self {
+        let storage = match config {
+            ClientCache::Memory => CacheStorage::Memory(Mutex::default()),
+            ClientCache::Disk(dir) => CacheStorage::Disk(dir),
+        };
+        Self { storage }
+    }
+
+    /**
+        Looks up the cached response for the given request, if the request
+        may use the cache at all, and if there is a matching cached response.
+    */
+    pub async fn lookup(&self, request: &Request, key: &str) -> Option<CachedResponse> {
+        let cached = match &self.storage {
+            CacheStorage::Memory(memory) => memory
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .get(key),
+            CacheStorage::Disk(dir) => {
+                let contents = fs::read(dir.join(file_name(key))).await.ok()?;
+                CachedResponse::from_file_contents(&contents)
+            }
+        }?;
+        (cached.key == key && cached.matches_vary(request.headers())).then_some(cached)
+    }
+
+    /**
+        Stores the given response in the cache, replacing any previous response for its request.
+
+        Caching is only an optimization, so failing to write the
+        response to disk is not an error, it is simply not cached.
+    */
+    pub async fn store(&self, cached: CachedResponse) {
+        match &self.storage {
+            CacheStorage::Memory(memory) => memory
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .insert(cached),
+            CacheStorage::Disk(dir) => {
+                let Some(contents) = cached.to_file_contents() else {
+                    return;
+                };
+                // NOTE: Writing to a temporary file first and then renaming it means
+                // that other requests never read a partially written response
+                let path = dir.join(file_name(&cached.key));
+                let temp = path.with_extension("tmp");
+                if fs::create_dir_all(dir).await.is_ok()
+                    && fs::write(&temp, contents).await.is_ok()
+                    && fs::rename(&temp, &path).await.is_err()
+                {
+                    fs::remove_file(&temp).await.ok();
+                }
+            }
+        }
+    }
+
+    /**
+        Returns the key to cache the response to the given request with, or `None` if
+        the response to the request should not be cached, or read from the cache.
+    */
+    pub fn key_for(request: &Request, decompress: bool) -> Option<String> {
+        let method = request.method();
+        if *method != Method::GET && *method != Method::HEAD {
+            return None;
+        }
+        if request
+            .body()
+            .is_some_and(|body| body.as_bytes() != Some(&[]))
+        {
+            return None;
+        }
+        // NOTE: Conditional and range requests are handled by whoever sent them,
+        // and a request may also explicitly ask for nothing to be stored at all
+        let headers = request.headers();
+        let conditional = [
+            IF_NONE_MATCH,
+            IF_MODIFIED_SINCE,
+            IF_UNMODIFIED_SINCE,
+            IF_RANGE,
+            RANGE,
+        ];
+        if conditional.iter().any(|name| headers.contains_key(name))
+            || cache_directives(headers).contains_key("no-store")
+        {
+            return None;
+        }
+        // NOTE: Bodies are cached as they are returned, so cached bodies that
+        // were decompressed are never returned for requests that asked not to
+        let suffix = if decompress { "" } else { " (raw)" };
+        Some(format!("{method} {}{suffix}", request.url()))
+    }
+}
+
+/**
+    Responses cached in memory, along with the order they were last used in.
+*/
+#[derive(Debug, Default)]
+struct MemoryCache {
+    entries: HashMap<String, CachedResponse>,
+    // NOTE: Keys are ordered from least to most recently used
+    order: VecDeque<String>,
+    size: usize,
+}
+
+impl MemoryCache {
+    fn get(&mut self, key: &str) -> Option<CachedResponse> {
+        let cached = self.entries.get(key)?.clone();
+        if let Some(index) = self.order.iter().position(|k| k == key) {
+            let key = self
+                .order
+                .remove(index)
+                .expect("index was just found in the order of keys");
+            self.order.push_back(key);
+        }
+        Some(cached)
+    }
+
+    fn insert(&mut self, cached: CachedResponse) {
+        self.remove(&cached.key);
+        // NOTE: Responses that would evict everything else are not worth caching
+        if cached.body.len() > MEMORY_CACHE_MAX_BYTES {
+            return;
+        }
+        self.size += cached.body.len();
+        self.order.push_back(cached.key.clone());
+        self.entries.insert(cached.key.clone(), cached);
+        while self.entries.len() > MEMORY_CACHE_MAX_ENTRIES || self.size > MEMORY_CACHE_MAX_BYTES {
+            let Some(oldest) = self.order.pop_front() else {
+                break;
+            };
+            if let Some(evicted) = self.entries.remove(&oldest) {
+                self.size -= evicted.body.len();
+            }
+        }
+    }
+
+    fn remove(&mut self, key: &str) {
+        if let Some(removed) = self.entries.remove(key) {
+            self.size -= removed.body.len();
+            self.order.retain(|k| k != key);
+        }
+    }
+}
+
+/**
+    A response that was stored in a cache, along with everything needed to
+    know if it is still fresh, and to revalidate it once it is no longer fresh.
+*/
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedResponse {
+    key: String,
+    pub status_code: u16,
+    pub status_message: String,
+    pub http_version: String,
+    headers: Vec<(String, String)>,
+    pub body_decompressed: bool,
+    vary: Vec<(String, Option<String>)>,
+    fresh_until: u64,
+    #[serde(skip)]
+    pub body: Vec<u8>,
+}
+
+impl CachedResponse {
+    /**
+        Creates a cached response from the given response to the given request,
+        or returns `None` if the response can not, or should not, be cached.
+    */
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        key: String,
+        request_headers: &HeaderMap,
+        status_code: u16,
+        status_message: String,
+        http_version: String,
+        headers: &HeaderMap,
+        body: Vec<u8>,
+        body_decompressed: bool,
+    ) -> Option<Self> {
+        if status_code != 200 || cache_directives(headers).contains_key("no-store") {
+            return None;
+        }
+
+        let mut vary = Vec::new();
+        for value in headers.get_all(VARY) {
+            for name in value.to_str().ok()?.split(',') {
+                let name = name.trim().to_ascii_lowercase();
+                if name == "*" {
+                    return None;
+                }
+                let value = request_headers
+                    .get(name.as_str())
+                    .and_then(|value| value.to_str().ok())
+                    .map(ToString::to_string);
+                vary.push((name, value));
+            }
+        }
+
+        let mut cached = Self {
+            key,
+            status_code,
+            status_message,
+            http_version,
+            headers: Vec::new(),
+            body_decompressed,
+            vary,
+            fresh_until: 0,
+            body,
+        };
+        cached.update(headers);
+
+        // NOTE: Responses that are never fresh and can not be revalidated are useless to cache
+        let has_validators = headers.contains_key(ETAG) || headers.contains_key(LAST_MODIFIED);
+        (cached.fresh_until > now() || has_validators).then_some(cached)
+    }
+
+    /**
+        Checks if the response is still fresh, meaning that it can be used
+        without revalidating it, unless the request asked for revalidation.
+    */
+    pub fn is_fresh(&self, request: &Request) -> bool {
+        let directives = cache_directives(request.headers());
+        self.fresh_until > now()
+            && !directives.contains_key("no-cache")
+            && !matches!(directives.get("max-age"), Some(Some(0)))
+    }
+
+    /**
+        Adds headers to the given request that make it a conditional
+        request, to revalidate this response with the server.
+    */
+    pub fn make_conditional(&self, request: &mut Request) {
+        let headers = request.headers_mut();
+        for (name, conditional) in [(ETAG, IF_NONE_MATCH), (LAST_MODIFIED, IF_MODIFIED_SINCE)] {
+            let value = self
+                .headers
+                .iter()
+                .find(|(n, _)| *n == name.as_str())
+                .and_then(|(_, value)| HeaderValue::from_str(value).ok());
+            if let Some(value) = value {
+                headers.insert(conditional, value);
+            }
+        }
+    }
+
+    /**
+        Updates the stored headers and freshness of the response
+        using the headers of a response that revalidated it.
+    */
+    pub fn update(&mut self, headers: &HeaderMap) {
+        // NOTE: Headers from revalidation replace stored headers with the same
+        // name, and any other stored headers are kept, such as the content type
+        for name in headers.keys() {
+            self.headers.retain(|(n, _)| n != name.as_str());
+        }
+        for (name, value) in headers {
+            if let Ok(value) = value.to_str() {
+                self.headers
+                    .push((name.as_str().to_string(), value.to_string()));
+            }
+        }
+        self.fresh_until = now().saturating_add(freshness_lifetime(headers));
+    }
+
+    pub fn headers(&self) -> HeaderMap {
+        let mut map = HeaderMap::new();
+        for (name, value) in &self.headers {
+            if let (Ok(name), Ok(value)) =
+                (HeaderName::from_str(name), HeaderValue::from_str(value))
+            {
+                map.append(name, value);
+            }
+        }
+        map
+    }
+
+    fn matches_vary(&self, request_headers: &HeaderMap) -> bool {
+        self.vary.iter().all(|(name, value)| {
+            let current = request_headers
+                .get(name.as_str())
+                .and_then(|value| value.to_str().ok());
+            current == value.as_deref()
+        })
+    }
+
+    // NOTE: Cached responses are stored on disk as a single line of JSON
+    // with everything but the body, and then the body itself as-is
+
+    fn to_file_contents(&self) -> Option<Vec<u8>> {
+        let mut contents = serde_json::to_vec(self).ok()?;
+        contents.push(b'\n');
+        contents.extend_from_slice(&self.body);
+        Some(contents)
+    }
+
+    fn from_file_contents(contents: &[u8]) -> Option<Self> {
+        let newline = contents.iter().position(|b| *b == b'\n')?;
+        let mut cached: Self = serde_json::from_slice(&contents[..newline]).ok()?;
+        cached.body = contents[newline + 1..].to_vec();
+        Some(cached)
+    }
+}
+
+fn file_name(key: &str) -> String {
+    hash(HashAlgorithm::Sha256, key)
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/**
+    Parses the directives in the `Cache-Control` headers, such
+    as `max-age=60`, into their names and optional number values.
+*/
+fn cache_directives(headers: &HeaderMap) -> HashMap<String, Option<u64>> {
+    headers
+        .get_all(CACHE_CONTROL)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|directive| match directive.split_once('=') {
+            Some((name, value)) => (
+                name.trim().to_ascii_lowercase(),
+                value.trim().trim_matches('"').parse().ok(),
+            ),
+            None => (directive.trim().to_ascii_lowercase(), None),
+        })
+        .collect()
+}
+
+/**
+    Returns how many seconds a response with the given headers is fresh for, using the
+    `max-age` directive, or the `Expires` header, minus the age given by the `Age` header.
+
+    Responses without an explicit lifetime are never considered fresh.
+*/
+fn freshness_lifetime(headers: &HeaderMap) -> u64 {
+    let directives = cache_directives(headers);
+    if directives.contains_key("no-cache") {
+        return 0;
+    }
+    let date = |name| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| httpdate::parse_http_date(value).ok())
+    };
+    let lifetime = match directives.get("max-age") {
+        Some(max_age) => max_age.unwrap_or_default(),
+        // NOTE: Invalid dates in the Expires header, such as "0", mean already expired
+        None => date(EXPIRES).map_or(0, |expires| {
+            let date = date(DATE).unwrap_or_else(SystemTime::now);
+            expires
+                .duration_since(date)
+                .unwrap_or(Duration::ZERO)
+                .as_secs()
+        }),
+    };
+    let age = headers
+        .get(AGE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .unwrap_or_default();
+    lifetime.saturating_sub(age)
+}
@@ -1,24 +1,101 @@
-use std::str::FromStr;
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{Arc, OnceLock},
+    time::Duration,
+};
 
+use encoding_rs::{Encoding, UTF_8};
 use mlua::prelude::*;
 
-use reqwest::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_ENCODING};
+use mlua_luau_scheduler::LuaSpawnExt;
+use reqwest::{
+    cookie::Jar,
+    header::{
+        HeaderMap, HeaderName, HeaderValue, ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_LENGTH,
+        CONTENT_TYPE, RETRY_AFTER,
+    },
+    multipart::{Form, Part},
+    redirect::Policy as RedirectPolicy,
+    Body, Certificate, Identity, Method, NoProxy, Proxy, StatusCode, Url, Version,
+};
+use tokio::{
+    fs::{self, File},
+    io::AsyncWriteExt,
+    time::{sleep, timeout, Instant},
+};
 
-use lune_std_serde::{decompress, CompressDecompressFormat};
-use lune_utils::TableBuilder;
+use lune_std_serde::{
+    decode, decompress, CompressDecompressFormat, DecompressOptions, EncodeDecodeConfig,
+    EncodeDecodeFormat,
+};
+use lune_utils::{buffer, TableBuilder};
 
-use super::{config::RequestConfig, util::header_map_to_table};
+use super::{
+    cache::{CachedResponse, NetClientCache},
+    config::{
+        ClientCache, ClientProxy, ClientTlsConfig, RequestConfig, RequestConfigBody,
+        RequestHttpVersion, RequestMultipartField, RequestRedirects,
+    },
+    error::{into_net_result, wrap_net_errors, NetError, NetErrorKind},
+    policy::{NetPolicy, NetPolicyResolver},
+    stream::NetClientBodyStream,
+    util::{create_user_agent_header, header_map_to_table},
+};
 
 const REGISTRY_KEY: &str = "NetClient";
+const REQUEST_KEY: &str = "NetClientRequest";
+
+tokio::task_local! {
+    // NOTE: Redirect policies are set per client in reqwest, but our redirect
+    // options are set per request, so the policy reads them from here instead
+    static REQUEST_REDIRECTS: RequestRedirects;
+}
+
+fn redirect_policy(policy: Option<Arc<NetPolicy>>) -> RedirectPolicy {
+    RedirectPolicy::custom(move |attempt| {
+        let redirects = REQUEST_REDIRECTS.try_with(|r| *r).unwrap_or_default();
+        let denied = policy
+            .as_ref()
+            .and_then(|policy| policy.check_url(attempt.url()).err());
+        if redirects == RequestRedirects::Manual {
+            attempt.stop()
+        } else if let Some(err) = denied {
+            attempt.error(err)
+        } else if attempt.previous().len() > redirects.max_redirects() {
+            attempt.error(format!(
+                "Too many redirects - followed the maximum of {}",
+                redirects.max_redirects()
+            ))
+        } else {
+            attempt.follow()
+        }
+    })
+}
+
+/**
+    Parses the url of a proxy, which may leave out the scheme, same as reqwest does.
+*/
+fn parse_proxy_url(url: &str) -> LuaResult<Url> {
+    Url::parse(url)
+        .ok()
+        .filter(Url::has_host)
+        .map_or_else(|| Url::parse(&format!("http://{url}")), Ok)
+        .map_err(|e| LuaError::runtime(format!("Invalid proxy url '{url}' - {e}")))
+}
 
 pub struct NetClientBuilder {
-    builder: reqwest::ClientBuilder,
+    settings: NetClientSettings,
+    timeout: Option<Duration>,
+    cache: Option<NetClientCache>,
 }
 
 impl NetClientBuilder {
     pub fn new() -> NetClientBuilder {
         Self {
-            builder: reqwest::ClientBuilder::new(),
+            settings: NetClientSettings::default(),
+            timeout: None,
+            cache: None,
         }
     }
 
@@ -33,91 +110,639 @@ impl NetClientBuilder {
             let hval = HeaderValue::from_bytes(val.as_ref()).into_lua_err()?;
             map.insert(hkey, hval);
         }
-        self.builder = self.builder.default_headers(map);
+        self.settings.headers = map;
+        Ok(self)
+    }
+
+    pub fn cookies(mut self, enabled: bool) -> Self {
+        self.settings.cookies = enabled.then(|| Arc::new(Jar::default()));
+        self
+    }
+
+    pub fn proxy(mut self, proxy: ClientProxy) -> LuaResult<Self> {
+        if let ClientProxy::Url(url) = &proxy {
+            Proxy::all(url).into_lua_err()?;
+        }
+        self.settings.proxy = proxy;
         Ok(self)
     }
 
+    pub fn tls(mut self, tls: ClientTlsConfig) -> Self {
+        self.settings.tls = tls;
+        self
+    }
+
+    /**
+        Sets the default timeout for requests made using the client,
+        used for any request that does not set a timeout of its own.
+    */
+    pub fn timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /**
+        Sets the network policy that requests made using the client must follow, if any.
+    */
+    pub fn policy(mut self, policy: Option<NetPolicy>) -> Self {
+        self.settings.policy = policy.map(Arc::new);
+        self
+    }
+
+    /**
+        Sets where responses to requests made using the client are cached, if anywhere.
+    */
+    pub fn cache(mut self, cache: Option<ClientCache>) -> Self {
+        self.cache = cache.map(NetClientCache::new);
+        self
+    }
+
     pub fn build(self) -> LuaResult<NetClient> {
-        let client = self.builder.build().into_lua_err()?;
-        Ok(NetClient { inner: client })
+        let client = self.settings.build_inner(RequestHttpVersion::Auto)?;
+        Ok(NetClient {
+            inner: client,
+            versioned: Arc::new(NetClientVersioned::default()),
+            settings: Arc::new(self.settings),
+            timeout: self.timeout,
+            cache: self.cache.map(Arc::new),
+        })
+    }
+}
+
+/**
+    Settings for creating the inner clients of a `NetClient`.
+
+    Reqwest sets the HTTP version to use per client, so these are kept around to
+    create clients for specific HTTP versions, only once they are first needed.
+*/
+#[derive(Debug, Default)]
+struct NetClientSettings {
+    headers: HeaderMap,
+    cookies: Option<Arc<Jar>>,
+    proxy: ClientProxy,
+    tls: ClientTlsConfig,
+    policy: Option<Arc<NetPolicy>>,
+}
+
+impl NetClientSettings {
+    fn build_inner(&self, version: RequestHttpVersion) -> LuaResult<reqwest::Client> {
+        let mut builder = reqwest::ClientBuilder::new()
+            .redirect(redirect_policy(self.policy.clone()))
+            .default_headers(self.headers.clone());
+
+        // NOTE: Resolved addresses must be checked right before connecting to them,
+        // checking them any earlier would let the host resolve to something else later
+        if let Some(policy) = &self.policy {
+            builder = builder.dns_resolver(Arc::new(NetPolicyResolver(Arc::clone(policy))));
+        }
+
+        // NOTE: Clients for specific versions share the same cookie jar,
+        // so that cookies set by any request are sent with all other requests
+        if let Some(jar) = &self.cookies {
+            builder = builder.cookie_provider(Arc::clone(jar));
+        }
+
+        builder = match (&self.proxy, &self.policy) {
+            // NOTE: Reqwest reads proxies from the environment by default, but scripts may set
+            // those themselves, and requests sent through a proxy would connect to the proxy
+            // instead of to hosts that the policy checks, so they are never used with a policy
+            (ClientProxy::Environment, None) => builder,
+            (ClientProxy::Environment, Some(_)) | (ClientProxy::Disabled, _) => builder.no_proxy(),
+            (ClientProxy::Url(url), policy) => {
+                if let Some(policy) = policy {
+                    policy.check_url(&parse_proxy_url(url)?)?;
+                }
+                let proxy = Proxy::all(url).into_lua_err()?;
+                builder.proxy(proxy.no_proxy(NoProxy::from_env()))
+            }
+        };
+
+        let tls = &self.tls;
+        if tls.accept_invalid_certs {
+            builder = builder.danger_accept_invalid_certs(true);
+        }
+        if let Some(pem) = &tls.ca_cert {
+            let certs = Certificate::from_pem_bundle(pem).into_lua_err()?;
+            if certs.is_empty() {
+                return Err(LuaError::runtime(
+                    "Invalid option value for 'caCert' in client config - no certificates found",
+                ));
+            }
+            for cert in certs {
+                builder = builder.add_root_certificate(cert);
+            }
+        }
+        if let Some(pem) = &tls.identity {
+            let identity = Identity::from_pem(pem).into_lua_err()?;
+            builder = builder.identity(identity);
+        }
+
+        builder = match version {
+            RequestHttpVersion::Auto => builder,
+            RequestHttpVersion::Http1 => builder.http1_only(),
+            RequestHttpVersion::Http2 => builder.http2_prior_knowledge(),
+        };
+
+        builder.build().into_lua_err()
     }
 }
 
+#[derive(Debug, Default)]
+struct NetClientVersioned {
+    http1: OnceLock<reqwest::Client>,
+    http2: OnceLock<reqwest::Client>,
+}
+
 #[derive(Debug, Clone)]
 pub struct NetClient {
     inner: reqwest::Client,
+    versioned: Arc<NetClientVersioned>,
+    settings: Arc<NetClientSettings>,
+    timeout: Option<Duration>,
+    cache: Option<Arc<NetClientCache>>,
 }
 
 impl NetClient {
-    pub fn from_registry(lua: &Lua) -> Self {
-        lua.named_registry_value(REGISTRY_KEY)
-            .expect("Failed to get NetClient from lua registry")
+    /**
+        Gets the client shared by all requests made using `net.request` and
+        `net.download`, creating it the first time that it is needed.
+
+        Sharing a single client lets connections be kept alive and
+        reused, instead of connecting again for every request.
+    */
+    pub fn shared(lua: &Lua) -> LuaResult<Self> {
+        if let Some(client) = lua.named_registry_value::<Option<Self>>(REGISTRY_KEY)? {
+            return Ok(client);
+        }
+        let client = NetClientBuilder::new()
+            .headers(&[("User-Agent", create_user_agent_header(lua)?)])?
+            .policy(lua.app_data_ref::<NetPolicy>().map(|policy| policy.clone()))
+            .build()?;
+        lua.set_named_registry_value(REGISTRY_KEY, client.clone())?;
+        Ok(client)
     }
 
-    pub fn into_registry(self, lua: &Lua) {
-        lua.set_named_registry_value(REGISTRY_KEY, self)
-            .expect("Failed to store NetClient in lua registry");
+    /**
+        Creates the function used for the `request` method of clients and stores
+        it in the registry, this must be done before any client is used in Lua.
+    */
+    pub fn request_method_into_registry(lua: &Lua) -> LuaResult<()> {
+        let request = lua.create_async_function(
+            |lua, (client, config): (LuaUserDataRef<NetClient>, RequestConfig)| {
+                let client = client.clone();
+                async move {
+                    // NOTE: We spawn the request as a background task to free up resources in lua
+                    let res = lua.spawn(async move { client.request(config).await }).await;
+                    into_net_result(lua, res.and_then(|res| res.into_lua_table(lua)))
+                }
+            },
+        )?;
+        lua.set_named_registry_value(REQUEST_KEY, wrap_net_errors(lua, request)?)
+    }
+
+    /**
+        Gets the inner client to use for requests with the given HTTP version,
+        creating it the first time that a specific version is requested.
+    */
+    fn inner_for_version(&self, version: RequestHttpVersion) -> LuaResult<reqwest::Client> {
+        let cell = match version {
+            RequestHttpVersion::Auto => return Ok(self.inner.clone()),
+            RequestHttpVersion::Http1 => &self.versioned.http1,
+            RequestHttpVersion::Http2 => &self.versioned.http2,
+        };
+        if let Some(client) = cell.get() {
+            return Ok(client.clone());
+        }
+        let client = self.settings.build_inner(version)?;
+        Ok(cell.get_or_init(|| client).clone())
     }
 
     pub async fn request(&self, config: RequestConfig) -> LuaResult<NetClientResponse> {
-        // Create and send the request
-        let mut request = self.inner.request(config.method, config.url);
-        for (query, values) in config.query {
-            request = request.query(
-                &values
-                    .iter()
-                    .map(|v| (query.as_str(), v))
-                    .collect::<Vec<_>>(),
-            );
+        let Some(retry) = config.options.retry.clone() else {
+            return self.request_with_timeout(config).await;
+        };
+
+        let mut attempt = 1;
+        loop {
+            let res = self.request_with_timeout(config.clone()).await;
+            let delay = match &res {
+                Ok(response) if retry.status_codes.contains(&response.status_code) => response
+                    .retry_after()
+                    .unwrap_or_else(|| retry.delay_after_attempt(attempt)),
+                Ok(_) => return res,
+                Err(_) => retry.delay_after_attempt(attempt),
+            };
+            if attempt >= retry.attempts {
+                return res.map_err(|e| {
+                    let context = format!(
+                        "Request failed after {attempt} attempt{}",
+                        if attempt == 1 { "" } else { "s" }
+                    );
+                    match NetError::find(&e) {
+                        Some(net_err) => net_err.clone().with_context(context).into(),
+                        None => LuaError::runtime(format!("{context}\n{e}")),
+                    }
+                });
+            }
+            sleep(delay).await;
+            attempt += 1;
         }
-        for (header, values) in config.headers {
-            for value in values {
-                request = request.header(header.as_str(), value);
+    }
+
+    async fn request_with_timeout(&self, config: RequestConfig) -> LuaResult<NetClientResponse> {
+        // NOTE: Dropping the inner request future when the timeout elapses
+        // also drops the underlying connection, so nothing is leaked here
+        match config.options.timeout.or(self.timeout) {
+            None => self.request_inner(config).await,
+            Some(duration) => match timeout(duration, self.request_inner(config)).await {
+                Ok(res) => res,
+                Err(_) => Err(NetError::new(
+                    NetErrorKind::Timeout,
+                    format!("Request timed out after {} seconds", duration.as_secs_f64()),
+                )
+                .into()),
+            },
+        }
+    }
+
+    async fn request_inner(&self, config: RequestConfig) -> LuaResult<NetClientResponse> {
+        // NOTE: Timing is only measured when asked for, and reqwest does not expose
+        // its connection phases, so what we can measure is around the whole request
+        let options = config.options.clone();
+        let started = options.timing.then(Instant::now);
+
+        // Create the request
+        let is_head = config.method == Method::HEAD;
+        let client = self.inner_for_version(options.http_version)?;
+        // NOTE: We only advertise encodings that we know how to decompress, and only
+        // if the user has not explicitly asked for a specific one or a streamed body
+        let should_decompress = options.decompress && !options.stream;
+        let mut request = build_request(&client, config, should_decompress).await?;
+        if let Some(policy) = &self.settings.policy {
+            policy.check_url(request.url())?;
+        }
+
+        // Check the cache, if any, for a fresh response or one that can be revalidated
+        let max_body_size = options.max_body_size;
+        let cache_key = self
+            .cache
+            .as_ref()
+            .filter(|_| !options.stream)
+            .and_then(|_| NetClientCache::key_for(&request, should_decompress));
+        let mut cached = None;
+        if let (Some(cache), Some(key)) = (&self.cache, &cache_key) {
+            let found = cache
+                .lookup(&request, key)
+                .await
+                .filter(|c| max_body_size.map_or(true, |max| c.body.len() as u64 <= max));
+            match found {
+                Some(c) if c.is_fresh(&request) => {
+                    return Ok(NetClientResponse::from_cached(c, started));
+                }
+                Some(c) => {
+                    c.make_conditional(&mut request);
+                    cached = Some(c);
+                }
+                None => {}
             }
         }
-        let res = request
-            .body(config.body.unwrap_or_default())
-            .send()
+        let request_headers = request.headers().clone();
+
+        // Send the request
+        let mut res = REQUEST_REDIRECTS
+            .scope(options.redirects, client.execute(request))
             .await
-            .into_lua_err()?;
+            .map_err(NetError::from)?;
+        let ttfb = started.map(|started| started.elapsed());
+
+        // A response that was not modified means that the cached response is still valid
+        if let (Some(cache), Some(mut cached)) = (&self.cache, cached) {
+            if res.status() == StatusCode::NOT_MODIFIED {
+                cached.update(res.headers());
+                cache.store(cached.clone()).await;
+                return Ok(NetClientResponse::from_cached(cached, started));
+            }
+        }
 
         // Extract status, headers
         let res_status = res.status().as_u16();
         let res_status_text = res.status().canonical_reason();
+        let res_version = res.version();
         let res_headers = res.headers().clone();
 
-        // Read response bytes
-        let mut res_bytes = res.bytes().await.into_lua_err()?.to_vec();
+        // Read response bytes, HEAD responses never have a body, even if they
+        // contain a content length header, and streamed bodies are read later on
+        let mut res_bytes = Vec::new();
         let mut res_decompressed = false;
+        let mut res_stream = None;
+        if options.stream {
+            res_stream = Some(NetClientBodyStream::new(res, max_body_size));
+        } else if !is_head {
+            res_bytes = read_body_limited(&mut res, max_body_size).await?;
+        }
 
         // Check for extra options, decompression
-        if config.options.decompress {
-            let decompress_format = res_headers
-                .iter()
-                .find(|(name, _)| {
-                    name.as_str()
-                        .eq_ignore_ascii_case(CONTENT_ENCODING.as_str())
-                })
-                .and_then(|(_, value)| value.to_str().ok())
-                .and_then(CompressDecompressFormat::detect_from_header_str);
-            if let Some(format) = decompress_format {
-                res_bytes = decompress(res_bytes, format).await?;
-                res_decompressed = true;
+        if should_decompress {
+            (res_bytes, res_decompressed) =
+                decompress_body(&res_headers, res_bytes, max_body_size).await?;
+        }
+
+        // Store the response in the cache, if it can be cached
+        if let (Some(cache), Some(key)) = (&self.cache, cache_key) {
+            let cacheable = CachedResponse::new(
+                key,
+                &request_headers,
+                res_status,
+                res_status_text.unwrap_or_default().to_string(),
+                http_version_name(res_version).to_string(),
+                &res_headers,
+                res_bytes.clone(),
+                res_decompressed,
+            );
+            if let Some(cacheable) = cacheable {
+                cache.store(cacheable).await;
             }
         }
 
+        let timing = started.zip(ttfb).map(|(started, ttfb)| NetClientTiming {
+            ttfb,
+            total: started.elapsed(),
+        });
+
         Ok(NetClientResponse {
             ok: (200..300).contains(&res_status),
             status_code: res_status,
             status_message: res_status_text.unwrap_or_default().to_string(),
+            http_version: res_version,
             headers: res_headers,
             body: res_bytes,
             body_decompressed: res_decompressed,
+            body_stream: res_stream,
+            cached: false,
+            timing,
+        })
+    }
+
+    pub async fn download(
+        &self,
+        url: String,
+        path: PathBuf,
+        mut on_progress: impl FnMut(u64, Option<u64>) -> LuaResult<()>,
+    ) -> LuaResult<()> {
+        // NOTE: Invalid urls are rejected by reqwest when sending the request below
+        if let (Some(policy), Ok(url)) = (&self.settings.policy, Url::parse(&url)) {
+            policy.check_url(&url)?;
+        }
+        let mut res = self.inner.get(url).send().await.map_err(NetError::from)?;
+        if !res.status().is_success() {
+            return Err(NetError::status(
+                res.status().as_u16(),
+                format!("Download failed with status {}", res.status()),
+            )
+            .into());
+        }
+
+        let total = res.content_length();
+        let mut file = File::create(&path).await.map_err(|e| {
+            LuaError::runtime(format!(
+                "Failed to create file '{}' for download\n{e}",
+                path.display()
+            ))
+        })?;
+
+        // NOTE: We write chunks to the file as they arrive so that the
+        // full response body never has to be held in memory at once
+        let result = async {
+            let mut so_far = 0;
+            while let Some(chunk) = res.chunk().await.map_err(NetError::from)? {
+                file.write_all(&chunk).await.into_lua_err()?;
+                so_far += chunk.len() as u64;
+                on_progress(so_far, total)?;
+            }
+            file.flush().await.into_lua_err()
+        }
+        .await;
+
+        if result.is_err() {
+            drop(file);
+            fs::remove_file(&path).await.ok();
+        }
+        result
+    }
+}
+
+/**
+    Creates the request to send for the given request config.
+*/
+async fn build_request(
+    client: &reqwest::Client,
+    config: RequestConfig,
+    should_decompress: bool,
+) -> LuaResult<reqwest::Request> {
+    let mut request = client.request(config.method, config.url);
+    for (query, values) in config.query {
+        request = request.query(
+            &values
+                .iter()
+                .map(|v| (query.as_str(), v))
+                .collect::<Vec<_>>(),
+        );
+    }
+    let has_accept_encoding = config
+        .headers
+        .keys()
+        .any(|name| name.eq_ignore_ascii_case(ACCEPT_ENCODING.as_str()));
+    let has_content_type = config
+        .headers
+        .keys()
+        .any(|name| name.eq_ignore_ascii_case(CONTENT_TYPE.as_str()));
+    if should_decompress && !has_accept_encoding {
+        request = request.header(ACCEPT_ENCODING, "gzip, deflate, br");
+    }
+    for (header, values) in config.headers {
+        for value in values {
+            request = request.header(header.as_str(), value);
+        }
+    }
+    request = match config.body {
+        None => request.body(Vec::new()),
+        Some(RequestConfigBody::Bytes(bytes)) => request.body(bytes),
+        Some(RequestConfigBody::Json(bytes)) => {
+            if !has_content_type {
+                request = request.header(CONTENT_TYPE, "application/json");
+            }
+            request.body(bytes)
+        }
+        // NOTE: This also sets the content type header, including the boundary
+        Some(RequestConfigBody::Multipart(fields)) => {
+            request.multipart(create_multipart_form(fields).await?)
+        }
+        Some(RequestConfigBody::Form(fields)) => {
+            if !has_content_type {
+                request = request.header(CONTENT_TYPE, "application/x-www-form-urlencoded");
+            }
+            request.body(encode_form(&fields))
+        }
+        Some(RequestConfigBody::File(path)) => {
+            let (body, body_len) = open_file_body(&path).await?;
+            request.header(CONTENT_LENGTH, body_len).body(body)
+        }
+    };
+    request.build().map_err(|e| NetError::from(e).into())
+}
+
+/**
+    Reads the full body of a response, erroring as soon as it is known
+    that the body exceeds the given maximum size, if there is one.
+*/
+async fn read_body_limited(
+    res: &mut reqwest::Response,
+    max_body_size: Option<u64>,
+) -> LuaResult<Vec<u8>> {
+    let max_body_size = max_body_size.unwrap_or(u64::MAX);
+    let exceeded = || {
+        NetError::new(
+            NetErrorKind::Request,
+            format!("Response body exceeds the maximum size of {max_body_size} bytes"),
+        )
+    };
+
+    // NOTE: The content length header may be missing or wrong, so it can only
+    // be used to fail fast, the size must still be checked while reading
+    if res.content_length().is_some_and(|len| len > max_body_size) {
+        return Err(exceeded().into());
+    }
+
+    let mut bytes = Vec::new();
+    while let Some(chunk) = res.chunk().await.map_err(NetError::from)? {
+        if bytes.len() as u64 + chunk.len() as u64 > max_body_size {
+            return Err(exceeded().into());
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(bytes)
+}
+
+/**
+    Decompresses a response body using the encoding given in the `Content-Encoding`
+    header, returning the body as-is if there is no supported encoding.
+
+    Also returns whether the body was decompressed or not.
+*/
+async fn decompress_body(
+    headers: &HeaderMap,
+    bytes: Vec<u8>,
+    max_body_size: Option<u64>,
+) -> LuaResult<(Vec<u8>, bool)> {
+    let decompress_format = headers
+        .iter()
+        .find(|(name, _)| {
+            name.as_str()
+                .eq_ignore_ascii_case(CONTENT_ENCODING.as_str())
         })
+        .and_then(|(_, value)| value.to_str().ok())
+        .and_then(CompressDecompressFormat::detect_from_header_str);
+    let Some(format) = decompress_format else {
+        return Ok((bytes, false));
+    };
+    // NOTE: The limit also applies to the decompressed body, since
+    // a tiny compressed body may otherwise decompress to gigabytes
+    let options = DecompressOptions {
+        max_size: max_body_size.map(|size| usize::try_from(size).unwrap_or(usize::MAX)),
+        ..Default::default()
+    };
+    let bytes = decompress(bytes, format, options)
+        .await
+        .map_err(|e| NetError::new(NetErrorKind::Decode, e.to_string()))?;
+    Ok((bytes, true))
+}
+
+fn encode_form(fields: &[(String, String)]) -> String {
+    fields
+        .iter()
+        .map(|(name, value)| {
+            format!(
+                "{}={}",
+                urlencoding::encode(name),
+                urlencoding::encode(value)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+async fn open_file_body(path: &Path) -> LuaResult<(Body, u64)> {
+    let file = File::open(path).await.map_err(|e| {
+        LuaError::runtime(format!(
+            "Failed to open file '{}' for request body\n{e}",
+            path.display()
+        ))
+    })?;
+    let file_len = file.metadata().await.into_lua_err()?.len();
+    // NOTE: Streaming the file means we never hold all of its contents in memory,
+    // but reqwest can not know its length, so we also need to return that here
+    Ok((Body::from(file), file_len))
+}
+
+async fn create_multipart_form(fields: Vec<(String, RequestMultipartField)>) -> LuaResult<Form> {
+    let mut form = Form::new();
+    for (name, field) in fields {
+        form = match field {
+            RequestMultipartField::Text(text) => form.text(name, text),
+            RequestMultipartField::File {
+                path,
+                file_name,
+                content_type,
+            } => {
+                let (body, body_len) = open_file_body(&path).await?;
+                let mut part = Part::stream_with_length(body, body_len);
+                let file_name = file_name.or_else(|| {
+                    path.file_name()
+                        .map(|name| name.to_string_lossy().to_string())
+                });
+                if let Some(file_name) = file_name {
+                    part = part.file_name(file_name);
+                }
+                if let Some(content_type) = content_type {
+                    part = part.mime_str(&content_type).into_lua_err()?;
+                }
+                form.part(name, part)
+            }
+        };
     }
+    Ok(form)
 }
 
-impl LuaUserData for NetClient {}
+fn http_version_from_name(name: &str) -> Version {
+    match name {
+        "0.9" => Version::HTTP_09,
+        "1.0" => Version::HTTP_10,
+        "2" => Version::HTTP_2,
+        "3" => Version::HTTP_3,
+        _ => Version::HTTP_11,
+    }
+}
+
+fn http_version_name(version: Version) -> &'static str {
+    match version {
+        Version::HTTP_09 => "0.9",
+        Version::HTTP_10 => "1.0",
+        Version::HTTP_11 => "1.1",
+        Version::HTTP_2 => "2",
+        Version::HTTP_3 => "3",
+        _ => "unknown",
+    }
+}
+
+impl LuaUserData for NetClient {
+    fn add_fields<'lua, F: LuaUserDataFields<'lua, Self>>(fields: &mut F) {
+        // NOTE: Methods can not throw structured errors, so this is a field
+        // containing a wrapped function that is then called as a method
+        fields.add_field_function_get("request", |lua, _| {
+            lua.named_registry_value::<LuaFunction>(REQUEST_KEY)
+        });
+    }
+}
 
 impl FromLua<'_> for NetClient {
     fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
@@ -130,11 +755,23 @@ impl FromLua<'_> for NetClient {
     }
 }
 
-impl From<&Lua> for NetClient {
-    fn from(value: &Lua) -> Self {
-        value
-            .named_registry_value(REGISTRY_KEY)
-            .expect("Missing require context in lua registry")
+/**
+    Timing measurements for a single request, if those were asked for.
+*/
+#[derive(Debug, Clone, Copy)]
+pub struct NetClientTiming {
+    /// Time from sending the request until the response headers were received
+    ttfb: Duration,
+    /// Time from sending the request until the full response body was read
+    total: Duration,
+}
+
+impl NetClientTiming {
+    fn into_lua_table(self, lua: &Lua) -> LuaResult<LuaTable> {
+        TableBuilder::new(lua)?
+            .with_value("ttfb", self.ttfb.as_secs_f64() * 1000.0)?
+            .with_value("total", self.total.as_secs_f64() * 1000.0)?
+            .build_readonly()
     }
 }
 
@@ -142,22 +779,122 @@ pub struct NetClientResponse {
     ok: bool,
     status_code: u16,
     status_message: String,
+    http_version: Version,
     headers: HeaderMap,
     body: Vec<u8>,
     body_decompressed: bool,
+    body_stream: Option<NetClientBodyStream>,
+    cached: bool,
+    timing: Option<NetClientTiming>,
 }
 
 impl NetClientResponse {
+    /**
+        Creates a response from one that was stored in the cache of a client.
+    */
+    fn from_cached(cached: CachedResponse, started: Option<Instant>) -> Self {
+        let timing = started.map(|started| NetClientTiming {
+            ttfb: started.elapsed(),
+            total: started.elapsed(),
+        });
+        Self {
+            ok: (200..300).contains(&cached.status_code),
+            status_code: cached.status_code,
+            status_message: cached.status_message.clone(),
+            http_version: http_version_from_name(&cached.http_version),
+            headers: cached.headers(),
+            body: cached.body,
+            body_decompressed: cached.body_decompressed,
+            body_stream: None,
+            cached: true,
+            timing,
+        }
+    }
+
+    /**
+        Returns the delay given by the `Retry-After` header, if any.
+
+        Only the delay-seconds form of the header is supported,
+        HTTP dates are ignored and treated as a missing header.
+    */
+    fn retry_after(&self) -> Option<Duration> {
+        self.headers
+            .get(RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse::<u64>().ok())
+            .map(Duration::from_secs)
+    }
+
+    /**
+        Returns the encoding given by the charset in the `Content-Type` header, if any.
+    */
+    fn charset(&self) -> Option<&'static Encoding> {
+        let content_type = self.headers.get(CONTENT_TYPE)?.to_str().ok()?;
+        content_type.split(';').skip(1).find_map(|param| {
+            let (name, value) = param.split_once('=')?;
+            if name.trim().eq_ignore_ascii_case("charset") {
+                Encoding::for_label(value.trim().trim_matches('"').as_bytes())
+            } else {
+                None
+            }
+        })
+    }
+
     pub fn into_lua_table(self, lua: &Lua) -> LuaResult<LuaTable> {
+        let body = self.body.clone();
+        let json = lua.create_function(move |lua, _: LuaValue| {
+            let config = EncodeDecodeConfig::from(EncodeDecodeFormat::Json);
+            let res = decode(&body, lua, config).map_err(|e| {
+                NetError::new(
+                    NetErrorKind::Decode,
+                    format!("Failed to decode response body as JSON\n{e}"),
+                )
+                .into()
+            });
+            into_net_result(lua, res)
+        })?;
+        let json = wrap_net_errors(lua, json)?;
+        let body = self.body.clone();
+        let charset = self.charset();
+        let text = lua.create_function(move |lua, (_, encoding): (LuaValue, Option<String>)| {
+            let encoding = match encoding {
+                Some(label) => Encoding::for_label(label.trim().as_bytes())
+                    .ok_or_else(|| LuaError::runtime(format!("Unknown text encoding '{label}'")))?,
+                None => charset.unwrap_or(UTF_8),
+            };
+            // NOTE: Invalid sequences are replaced instead of erroring, same as browsers do
+            let (text, _, _) = encoding.decode(&body);
+            lua.create_string(text.as_ref())
+        })?;
+        let (read_chunk, read_line, close) = match self.body_stream {
+            Some(stream) => {
+                let (read_chunk, read_line, close) = stream.into_lua_methods(lua)?;
+                (Some(read_chunk), Some(read_line), Some(close))
+            }
+            None => (None, None, None),
+        };
         TableBuilder::new(lua)?
             .with_value("ok", self.ok)?
             .with_value("statusCode", self.status_code)?
             .with_value("statusMessage", self.status_message)?
+            .with_value("httpVersion", http_version_name(self.http_version))?
             .with_value(
                 "headers",
                 header_map_to_table(lua, self.headers, self.body_decompressed)?,
             )?
-            .with_value("body", lua.create_string(&self.body)?)?
+            .with_value("body", buffer::to_lua(lua, &self.body)?)?
+            .with_value("json", json)?
+            .with_value("text", text)?
+            .with_value("readChunk", read_chunk)?
+            .with_value("readLine", read_line)?
+            .with_value("close", close)?
+            .with_value("cached", self.cached)?
+            .with_value(
+                "timing",
+                self.timing
+                    .map(|timing| timing.into_lua_table(lua))
+                    .transpose()?,
+            )?
             .build_readonly()
     }
 }
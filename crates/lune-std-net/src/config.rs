@@ -1,12 +1,20 @@
 use std::{
     collections::HashMap,
+    fs,
     net::{IpAddr, Ipv4Addr},
+    path::PathBuf,
+    time::Duration,
 };
 
-use bstr::{BString, ByteSlice};
 use mlua::prelude::*;
 
-use reqwest::Method;
+use reqwest::{
+    header::{HeaderName, HeaderValue, CONTENT_LENGTH, TRANSFER_ENCODING},
+    Method,
+};
+
+use lune_std_serde::{encode, EncodeDecodeConfig, EncodeDecodeFormat};
+use lune_utils::buffer;
 
 use super::util::table_to_hash_map;
 
@@ -22,21 +30,420 @@ return {
 }
 "#;
 
+// Net client config
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum ClientProxy {
+    #[default]
+    Environment,
+    Disabled,
+    Url(String),
+}
+
+impl<'lua> FromLua<'lua> for ClientProxy {
+    fn from_lua(value: LuaValue<'lua>, _: &'lua Lua) -> LuaResult<Self> {
+        match value {
+            LuaValue::Nil | LuaValue::Boolean(true) => Ok(Self::Environment),
+            LuaValue::Boolean(false) => Ok(Self::Disabled),
+            LuaValue::String(s) => Ok(Self::Url(s.to_str()?.to_string())),
+            value => Err(LuaError::RuntimeError(format!(
+                "Invalid option value for 'proxy' in client config \
+                - expected string or boolean, got {}",
+                value.type_name()
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClientTlsConfig {
+    pub accept_invalid_certs: bool,
+    pub ca_cert: Option<Vec<u8>>,
+    pub identity: Option<Vec<u8>>,
+}
+
+impl<'lua> FromLua<'lua> for ClientTlsConfig {
+    fn from_lua(value: LuaValue<'lua>, _: &'lua Lua) -> LuaResult<Self> {
+        let LuaValue::Table(tab) = value else {
+            return Ok(Self::default());
+        };
+        let accept_invalid_certs = match tab.get::<_, LuaValue>("tlsVerify")? {
+            LuaValue::Nil => Ok(false),
+            LuaValue::Boolean(verify) => Ok(!verify),
+            value => Err(LuaError::RuntimeError(format!(
+                "Invalid option value for 'tlsVerify' in client config \
+                - expected boolean, got {}",
+                value.type_name()
+            ))),
+        }?;
+        let ca_cert = read_pem_option(&tab, "caCert")?;
+        let identity =
+            match (
+                read_pem_option(&tab, "clientCert")?,
+                read_pem_option(&tab, "clientKey")?,
+            ) {
+                (None, None) => None,
+                (Some(mut cert), Some(key)) => {
+                    // NOTE: Reqwest expects a single pem containing both the
+                    // certificate and private key, so we join them together
+                    cert.push(b'\n');
+                    cert.extend(key);
+                    Some(cert)
+                }
+                _ => return Err(LuaError::RuntimeError(
+                    "Invalid client config - 'clientCert' and 'clientKey' must be given together"
+                        .to_string(),
+                )),
+            };
+        Ok(Self {
+            accept_invalid_certs,
+            ca_cert,
+            identity,
+        })
+    }
+}
+
+/**
+    Reads an option that is either the contents of a pem file, or a path to a pem file.
+*/
+fn read_pem_option(tab: &LuaTable, key: &'static str) -> LuaResult<Option<Vec<u8>>> {
+    match tab.get::<_, LuaValue>(key)? {
+        LuaValue::Nil => Ok(None),
+        LuaValue::String(s) if s.as_bytes().starts_with(b"-----BEGIN ") => {
+            Ok(Some(s.as_bytes().to_vec()))
+        }
+        LuaValue::String(s) => {
+            let path = s.to_str()?;
+            match fs::read(path) {
+                Ok(contents) => Ok(Some(contents)),
+                Err(e) => Err(LuaError::RuntimeError(format!(
+                    "Invalid option value for '{key}' in client config \
+                    - failed to read file at '{path}': {e}"
+                ))),
+            }
+        }
+        value => Err(LuaError::RuntimeError(format!(
+            "Invalid option value for '{key}' in client config \
+            - expected string, got {}",
+            value.type_name()
+        ))),
+    }
+}
+
+/**
+    Where responses cached by a client are stored, if caching is enabled.
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClientCache {
+    Memory,
+    Disk(PathBuf),
+}
+
+impl ClientCache {
+    fn from_lua_option(value: LuaValue) -> LuaResult<Option<Self>> {
+        match value {
+            LuaValue::Nil | LuaValue::Boolean(false) => Ok(None),
+            LuaValue::Boolean(true) => Ok(Some(Self::Memory)),
+            LuaValue::Table(tab) => match tab.get::<_, LuaValue>("dir")? {
+                LuaValue::Nil => Ok(Some(Self::Memory)),
+                LuaValue::String(dir) => Ok(Some(Self::Disk(PathBuf::from(dir.to_str()?)))),
+                value => Err(LuaError::RuntimeError(format!(
+                    "Invalid option value for 'cache.dir' in client config \
+                    - expected string, got {}",
+                    value.type_name()
+                ))),
+            },
+            value => Err(LuaError::RuntimeError(format!(
+                "Invalid option value for 'cache' in client config \
+                - expected boolean or table, got {}",
+                value.type_name()
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ClientConfig {
+    pub cookies: bool,
+    pub proxy: ClientProxy,
+    pub tls: ClientTlsConfig,
+    pub timeout: Option<Duration>,
+    pub cache: Option<ClientCache>,
+}
+
+impl<'lua> FromLua<'lua> for ClientConfig {
+    fn from_lua(value: LuaValue<'lua>, lua: &'lua Lua) -> LuaResult<Self> {
+        if let LuaValue::Nil = value {
+            // Nil means default options
+            Ok(Self::default())
+        } else if let LuaValue::Table(tab) = value {
+            // Table means custom options
+            let cookies = match tab.get::<_, LuaValue>("cookies")? {
+                LuaValue::Nil => Ok(false),
+                LuaValue::Boolean(cookies) => Ok(cookies),
+                value => Err(LuaError::RuntimeError(format!(
+                    "Invalid option value for 'cookies' in client config \
+                    - expected boolean, got {}",
+                    value.type_name()
+                ))),
+            }?;
+            let proxy = ClientProxy::from_lua(tab.get("proxy")?, lua)?;
+            let timeout = match tab.get::<_, Option<f64>>("timeout") {
+                Ok(None) => Ok(None),
+                Ok(Some(secs)) => Duration::try_from_secs_f64(secs).map(Some).map_err(|_| {
+                    LuaError::RuntimeError(format!(
+                        "Invalid option value for 'timeout' in client config \
+                        - expected a positive number of seconds, got {secs}"
+                    ))
+                }),
+                Err(_) => Err(LuaError::RuntimeError(
+                    "Invalid option value for 'timeout' in client config".to_string(),
+                )),
+            }?;
+            let cache = ClientCache::from_lua_option(tab.get("cache")?)?;
+            let tls = ClientTlsConfig::from_lua(LuaValue::Table(tab), lua)?;
+            Ok(Self {
+                cookies,
+                proxy,
+                tls,
+                timeout,
+                cache,
+            })
+        } else {
+            // Anything else is invalid
+            Err(LuaError::FromLuaConversionError {
+                from: value.type_name(),
+                to: "ClientConfig",
+                message: Some(format!(
+                    "Invalid client config - expected table, got {}",
+                    value.type_name()
+                )),
+            })
+        }
+    }
+}
+
 // Net request config
 
+const DEFAULT_RETRY_ATTEMPTS: u32 = 3;
+const DEFAULT_RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+const DEFAULT_RETRY_STATUS_CODES: &[u16] = &[429, 502, 503, 504];
+const DEFAULT_MAX_REDIRECTS: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestRetryBackoff {
+    Constant,
+    Linear,
+    Exponential,
+}
+
+#[derive(Debug, Clone)]
+pub struct RequestRetryOptions {
+    pub attempts: u32,
+    pub backoff: RequestRetryBackoff,
+    pub base_delay: Duration,
+    pub status_codes: Vec<u16>,
+}
+
+impl RequestRetryOptions {
+    /**
+        Returns the delay to wait for after the given
+        failed attempt, before making the next attempt.
+
+        Attempts start counting at `1`.
+    */
+    pub fn delay_after_attempt(&self, attempt: u32) -> Duration {
+        match self.backoff {
+            RequestRetryBackoff::Constant => self.base_delay,
+            RequestRetryBackoff::Linear => self.base_delay.saturating_mul(attempt),
+            RequestRetryBackoff::Exponential => self
+                .base_delay
+                .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1))),
+        }
+    }
+}
+
+impl Default for RequestRetryOptions {
+    fn default() -> Self {
+        Self {
+            attempts: DEFAULT_RETRY_ATTEMPTS,
+            backoff: RequestRetryBackoff::Exponential,
+            base_delay: DEFAULT_RETRY_BASE_DELAY,
+            status_codes: DEFAULT_RETRY_STATUS_CODES.to_vec(),
+        }
+    }
+}
+
+impl<'lua> FromLua<'lua> for RequestRetryOptions {
+    fn from_lua(value: LuaValue<'lua>, _: &'lua Lua) -> LuaResult<Self> {
+        let LuaValue::Table(tab) = value else {
+            return Err(LuaError::FromLuaConversionError {
+                from: value.type_name(),
+                to: "RequestRetryOptions",
+                message: Some(format!(
+                    "Invalid request retry options - expected table, got {}",
+                    value.type_name()
+                )),
+            });
+        };
+        let invalid = |key: &str| {
+            LuaError::RuntimeError(format!(
+                "Invalid option value for '{key}' in request retry options"
+            ))
+        };
+
+        let mut options = Self::default();
+        if let Some(attempts) = tab
+            .get::<_, Option<u32>>("attempts")
+            .map_err(|_| invalid("attempts"))?
+        {
+            if attempts == 0 {
+                return Err(invalid("attempts"));
+            }
+            options.attempts = attempts;
+        }
+        if let Some(backoff) = tab
+            .get::<_, Option<String>>("backoff")
+            .map_err(|_| invalid("backoff"))?
+        {
+            options.backoff = match backoff.trim().to_ascii_lowercase().as_str() {
+                "constant" => RequestRetryBackoff::Constant,
+                "linear" => RequestRetryBackoff::Linear,
+                "exponential" => RequestRetryBackoff::Exponential,
+                _ => {
+                    return Err(LuaError::RuntimeError(format!(
+                        "Invalid option value for 'backoff' in request retry options \
+                        - expected 'constant', 'linear' or 'exponential', got '{backoff}'"
+                    )))
+                }
+            };
+        }
+        if let Some(base_delay) = tab
+            .get::<_, Option<f64>>("baseDelay")
+            .map_err(|_| invalid("baseDelay"))?
+        {
+            options.base_delay =
+                Duration::try_from_secs_f64(base_delay).map_err(|_| invalid("baseDelay"))?;
+        }
+        if let Some(status_codes) = tab
+            .get::<_, Option<Vec<u16>>>("statusCodes")
+            .map_err(|_| invalid("statusCodes"))?
+        {
+            options.status_codes = status_codes;
+        }
+
+        Ok(options)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RequestRedirects {
+    #[default]
+    Follow,
+    Manual,
+    Limited(usize),
+}
+
+impl RequestRedirects {
+    /**
+        Returns the maximum number of redirects that may be followed.
+    */
+    pub fn max_redirects(self) -> usize {
+        match self {
+            Self::Follow => DEFAULT_MAX_REDIRECTS,
+            Self::Manual => 0,
+            Self::Limited(max) => max,
+        }
+    }
+}
+
+impl<'lua> FromLua<'lua> for RequestRedirects {
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn from_lua(value: LuaValue<'lua>, _: &'lua Lua) -> LuaResult<Self> {
+        match &value {
+            LuaValue::Nil => return Ok(Self::default()),
+            LuaValue::String(s) => match s.to_str()? {
+                "follow" => return Ok(Self::Follow),
+                "manual" => return Ok(Self::Manual),
+                _ => {}
+            },
+            LuaValue::Integer(i) if *i >= 0 => return Ok(Self::Limited(*i as usize)),
+            LuaValue::Number(n) if n.fract() == 0.0 && *n >= 0.0 => {
+                return Ok(Self::Limited(*n as usize))
+            }
+            _ => {}
+        }
+        Err(LuaError::RuntimeError(format!(
+            "Invalid option value for 'redirects' in request config options \
+            - expected 'follow', 'manual' or a non-negative integer, got '{}'",
+            value.to_string()?
+        )))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RequestHttpVersion {
+    #[default]
+    Auto,
+    Http1,
+    Http2,
+}
+
+impl<'lua> FromLua<'lua> for RequestHttpVersion {
+    fn from_lua(value: LuaValue<'lua>, _: &'lua Lua) -> LuaResult<Self> {
+        match &value {
+            LuaValue::Nil => return Ok(Self::default()),
+            LuaValue::String(s) => match s.to_str()? {
+                "auto" => return Ok(Self::Auto),
+                "1.1" => return Ok(Self::Http1),
+                "2" => return Ok(Self::Http2),
+                _ => {}
+            },
+            _ => {}
+        }
+        Err(LuaError::RuntimeError(format!(
+            "Invalid option value for 'httpVersion' in request config options \
+            - expected 'auto', '1.1' or '2', got '{}'",
+            value.to_string()?
+        )))
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct RequestConfigOptions {
     pub decompress: bool,
+    pub timeout: Option<Duration>,
+    pub retry: Option<RequestRetryOptions>,
+    pub redirects: RequestRedirects,
+    pub max_body_size: Option<u64>,
+    pub timing: bool,
+    pub stream: bool,
+    pub http_version: RequestHttpVersion,
+}
+
+impl RequestConfigOptions {
+    // NOTE: This should be large enough to never get in the way of normal use,
+    // while still stopping a misbehaving server from using up all memory
+    pub const DEFAULT_MAX_BODY_SIZE: u64 = 512 * 1024 * 1024;
 }
 
 impl Default for RequestConfigOptions {
     fn default() -> Self {
-        Self { decompress: true }
+        Self {
+            decompress: true,
+            timeout: None,
+            retry: None,
+            redirects: RequestRedirects::default(),
+            max_body_size: Some(Self::DEFAULT_MAX_BODY_SIZE),
+            timing: false,
+            stream: false,
+            http_version: RequestHttpVersion::default(),
+        }
     }
 }
 
 impl<'lua> FromLua<'lua> for RequestConfigOptions {
-    fn from_lua(value: LuaValue<'lua>, _: &'lua Lua) -> LuaResult<Self> {
+    fn from_lua(value: LuaValue<'lua>, lua: &'lua Lua) -> LuaResult<Self> {
         if let LuaValue::Nil = value {
             // Nil means default options
             Ok(Self::default())
@@ -48,7 +455,65 @@ impl<'lua> FromLua<'lua> for RequestConfigOptions {
                     "Invalid option value for 'decompress' in request config options".to_string(),
                 )),
             }?;
-            Ok(Self { decompress })
+            let timeout = match tab.get::<_, Option<f64>>("timeout") {
+                Ok(None) => Ok(None),
+                Ok(Some(secs)) => Duration::try_from_secs_f64(secs).map(Some).map_err(|_| {
+                    LuaError::RuntimeError(format!(
+                        "Invalid option value for 'timeout' in request config options \
+                        - expected a positive number of seconds, got {secs}"
+                    ))
+                }),
+                Err(_) => Err(LuaError::RuntimeError(
+                    "Invalid option value for 'timeout' in request config options".to_string(),
+                )),
+            }?;
+            let retry = match tab.get::<_, LuaValue>("retry")? {
+                LuaValue::Nil => None,
+                value => Some(RequestRetryOptions::from_lua(value, lua)?),
+            };
+            let redirects = RequestRedirects::from_lua(tab.get("redirects")?, lua)?;
+            let max_body_size = match tab.get::<_, Option<f64>>("maxBodySize") {
+                Ok(None) => Ok(Some(Self::DEFAULT_MAX_BODY_SIZE)),
+                Ok(Some(size)) if size == f64::INFINITY => Ok(None),
+                #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+                Ok(Some(size)) if size >= 0.0 && size.fract() == 0.0 => Ok(Some(size as u64)),
+                Ok(Some(size)) => Err(LuaError::RuntimeError(format!(
+                    "Invalid option value for 'maxBodySize' in request config options \
+                    - expected a positive integer number of bytes or math.huge, got {size}"
+                ))),
+                Err(_) => Err(LuaError::RuntimeError(
+                    "Invalid option value for 'maxBodySize' in request config options".to_string(),
+                )),
+            }?;
+            let timing = match tab.get::<_, LuaValue>("timing")? {
+                LuaValue::Nil => Ok(false),
+                LuaValue::Boolean(timing) => Ok(timing),
+                value => Err(LuaError::RuntimeError(format!(
+                    "Invalid option value for 'timing' in request config options \
+                    - expected boolean, got {}",
+                    value.type_name()
+                ))),
+            }?;
+            let stream = match tab.get::<_, LuaValue>("stream")? {
+                LuaValue::Nil => Ok(false),
+                LuaValue::Boolean(stream) => Ok(stream),
+                value => Err(LuaError::RuntimeError(format!(
+                    "Invalid option value for 'stream' in request config options \
+                    - expected boolean, got {}",
+                    value.type_name()
+                ))),
+            }?;
+            let http_version = RequestHttpVersion::from_lua(tab.get("httpVersion")?, lua)?;
+            Ok(Self {
+                decompress,
+                timeout,
+                retry,
+                redirects,
+                max_body_size,
+                timing,
+                stream,
+                http_version,
+            })
         } else {
             // Anything else is invalid
             Err(LuaError::FromLuaConversionError {
@@ -63,13 +528,107 @@ impl<'lua> FromLua<'lua> for RequestConfigOptions {
     }
 }
 
+#[derive(Debug, Clone)]
+pub enum RequestMultipartField {
+    Text(String),
+    File {
+        path: PathBuf,
+        file_name: Option<String>,
+        content_type: Option<String>,
+    },
+}
+
+impl<'lua> FromLua<'lua> for RequestMultipartField {
+    fn from_lua(value: LuaValue<'lua>, _: &'lua Lua) -> LuaResult<Self> {
+        match value {
+            LuaValue::String(s) => Ok(Self::Text(s.to_str()?.to_string())),
+            LuaValue::Table(tab) => {
+                let path = match tab.get::<_, Option<String>>("path") {
+                    Ok(Some(path)) => Ok(PathBuf::from(path)),
+                    _ => Err(LuaError::runtime(
+                        "Missing or invalid 'path' for file in multipart body",
+                    )),
+                }?;
+                let file_name = tab.get::<_, Option<String>>("filename").map_err(|_| {
+                    LuaError::runtime("Invalid 'filename' for file in multipart body")
+                })?;
+                let content_type = tab.get::<_, Option<String>>("contentType").map_err(|_| {
+                    LuaError::runtime("Invalid 'contentType' for file in multipart body")
+                })?;
+                Ok(Self::File {
+                    path,
+                    file_name,
+                    content_type,
+                })
+            }
+            _ => Err(LuaError::FromLuaConversionError {
+                from: value.type_name(),
+                to: "RequestMultipartField",
+                message: Some(format!(
+                    "Invalid multipart body field - expected string or table, got {}",
+                    value.type_name()
+                )),
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum RequestConfigBody {
+    Bytes(Vec<u8>),
+    Json(Vec<u8>),
+    Multipart(Vec<(String, RequestMultipartField)>),
+    Form(Vec<(String, String)>),
+    File(PathBuf),
+}
+
+impl<'lua> FromLua<'lua> for RequestConfigBody {
+    fn from_lua(value: LuaValue<'lua>, lua: &'lua Lua) -> LuaResult<Self> {
+        if let LuaValue::Table(tab) = &value {
+            if let Some(fields) = tab.get::<_, Option<LuaTable>>("multipart").ok().flatten() {
+                let mut multipart = Vec::new();
+                for pair in fields.pairs::<String, RequestMultipartField>() {
+                    multipart.push(pair?);
+                }
+                // NOTE: Table iteration order is not stable, sort
+                // by field name to always send the same request body
+                multipart.sort_by(|(a, _), (b, _)| a.cmp(b));
+                Ok(Self::Multipart(multipart))
+            } else if let Some(fields) = tab.get::<_, Option<LuaTable>>("form").ok().flatten() {
+                // NOTE: Same as for multipart bodies, fields are sorted by name, and
+                // the order of repeated values for the same field name is kept as-is
+                let mut form = table_to_hash_map(fields, "form")?
+                    .into_iter()
+                    .collect::<Vec<_>>();
+                form.sort_by(|(a, _), (b, _)| a.cmp(b));
+                Ok(Self::Form(
+                    form.into_iter()
+                        .flat_map(|(name, values)| {
+                            values.into_iter().map(move |value| (name.clone(), value))
+                        })
+                        .collect(),
+                ))
+            } else if let Some(path) = tab.get::<_, Option<String>>("file").ok().flatten() {
+                Ok(Self::File(PathBuf::from(path)))
+            } else {
+                Err(LuaError::runtime(
+                    "Invalid request body - expected a table with a 'multipart' or 'form' table, \
+                    or a 'file' path",
+                ))
+            }
+        } else {
+            Ok(Self::Bytes(buffer::from_lua(lua, value)?))
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct RequestConfig {
     pub url: String,
     pub method: Method,
     pub query: HashMap<String, Vec<String>>,
     pub headers: HashMap<String, Vec<String>>,
-    pub body: Option<Vec<u8>>,
+    pub body: Option<RequestConfigBody>,
     pub options: RequestConfigOptions,
 }
 
@@ -103,31 +662,49 @@ impl FromLua<'_> for RequestConfig {
                 Err(_) => HashMap::new(),
             };
             // Extract headers
-            let headers = match tab.get::<_, LuaTable>("headers") {
+            let mut headers = match tab.get::<_, LuaTable>("headers") {
                 Ok(tab) => table_to_hash_map(tab, "headers")?,
                 Err(_) => HashMap::new(),
             };
+            // Extract content type, which is sent as a header, replacing any default one
+            extract_content_type(&tab, &mut headers)?;
+            validate_headers(&headers)?;
             // Extract body
-            let body = match tab.get::<_, BString>("body") {
-                Ok(config_body) => Some(config_body.as_bytes().to_owned()),
-                Err(_) => None,
+            let body = match tab.get::<_, LuaValue>("body")? {
+                LuaValue::Nil => None,
+                value => Some(RequestConfigBody::from_lua(value, lua)?),
+            };
+            // Extract json body, which is encoded right away so
+            // that any errors are reported before sending anything
+            let body = match (tab.get::<_, LuaValue>("json")?, body) {
+                (LuaValue::Nil, body) => body,
+                (_, Some(_)) => {
+                    return Err(LuaError::runtime(
+                        "Invalid request config - 'body' and 'json' can not both be given",
+                    ))
+                }
+                (value, None) => {
+                    let config = EncodeDecodeConfig::from(EncodeDecodeFormat::Json);
+                    let encoded = encode(value, lua, config).map_err(|e| {
+                        LuaError::runtime(format!("Failed to encode request json body\n{e}"))
+                    })?;
+                    Some(RequestConfigBody::Json(encoded.as_bytes().to_vec()))
+                }
             };
 
-            // Convert method string into proper enum
-            let method = method.trim().to_ascii_uppercase();
-            let method = match method.as_ref() {
-                "GET" => Ok(Method::GET),
-                "POST" => Ok(Method::POST),
-                "PUT" => Ok(Method::PUT),
-                "DELETE" => Ok(Method::DELETE),
-                "HEAD" => Ok(Method::HEAD),
-                "OPTIONS" => Ok(Method::OPTIONS),
-                "PATCH" => Ok(Method::PATCH),
-                _ => Err(LuaError::RuntimeError(format!(
-                    "Invalid request config method '{}'",
-                    &method
-                ))),
-            }?;
+            // NOTE: Multipart bodies must use the content type that includes their boundary
+            if matches!(body, Some(RequestConfigBody::Multipart(_)))
+                && tab.get::<_, LuaValue>("contentType")? != LuaValue::Nil
+            {
+                return Err(LuaError::runtime(
+                    "Invalid request config - 'contentType' can not be given for multipart bodies",
+                ));
+            }
+            // Convert method string into proper enum, any valid method
+            // token is accepted, to support less common verbs like WebDAV ones
+            let method = Method::from_bytes(method.as_bytes()).map_err(|_| {
+                LuaError::RuntimeError(format!("Invalid request config method '{method}'"))
+            })?;
             // Parse any extra options given
             let options = match tab.get::<_, LuaValue>("options") {
                 Ok(opts) => RequestConfigOptions::from_lua(opts, lua)?,
@@ -156,6 +733,62 @@ impl FromLua<'_> for RequestConfig {
     }
 }
 
+fn extract_content_type(
+    tab: &LuaTable,
+    headers: &mut HashMap<String, Vec<String>>,
+) -> LuaResult<()> {
+    match tab.get::<_, LuaValue>("contentType")? {
+        LuaValue::Nil => Ok(()),
+        LuaValue::String(s) => {
+            if headers
+                .keys()
+                .any(|name| name.eq_ignore_ascii_case("content-type"))
+            {
+                return Err(LuaError::runtime(
+                    "Invalid request config - 'contentType' can not be given \
+                    together with a 'Content-Type' header",
+                ));
+            }
+            let content_type = s.to_str()?.trim().to_string();
+            headers.insert("content-type".to_string(), vec![content_type]);
+            Ok(())
+        }
+        value => Err(LuaError::RuntimeError(format!(
+            "Invalid value for 'contentType' in request config - \
+            expected string, got {}",
+            value.type_name()
+        ))),
+    }
+}
+
+fn validate_headers(headers: &HashMap<String, Vec<String>>) -> LuaResult<()> {
+    // NOTE: We validate headers here so that any invalid ones
+    // are reported clearly before the request is ever sent
+    for (name, values) in headers {
+        let Ok(header) = HeaderName::from_bytes(name.as_bytes()) else {
+            return Err(LuaError::RuntimeError(format!(
+                "Invalid request config header name '{name}'"
+            )));
+        };
+        // NOTE: These are always set from the body of the request, and giving them
+        // as well would send a second, possibly conflicting, value for each of them
+        if header == CONTENT_LENGTH || header == TRANSFER_ENCODING {
+            return Err(LuaError::RuntimeError(format!(
+                "Invalid request config header '{name}' - \
+                it is set automatically from the body and can not be given"
+            )));
+        }
+        for value in values {
+            if HeaderValue::from_str(value).is_err() {
+                return Err(LuaError::RuntimeError(format!(
+                    "Invalid request config header value for '{name}'"
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
 // Net serve config
 
 #[derive(Debug)]
@@ -163,6 +796,7 @@ pub struct ServeConfig<'a> {
     pub address: IpAddr,
     pub handle_request: LuaFunction<'a>,
     pub handle_web_socket: Option<LuaFunction<'a>>,
+    pub max_concurrent: Option<usize>,
 }
 
 impl<'lua> FromLua<'lua> for ServeConfig<'lua> {
@@ -173,12 +807,31 @@ impl<'lua> FromLua<'lua> for ServeConfig<'lua> {
                 handle_request: f.clone(),
                 handle_web_socket: None,
                 address: DEFAULT_IP_ADDRESS,
+                max_concurrent: None,
             })
         } else if let LuaValue::Table(t) = &value {
             // Table means custom options
             let address: Option<LuaString> = t.get("address")?;
             let handle_request: Option<LuaFunction> = t.get("handleRequest")?;
             let handle_web_socket: Option<LuaFunction> = t.get("handleWebSocket")?;
+            let max_concurrent = match t.get::<_, LuaValue>("maxConcurrent")? {
+                LuaValue::Nil => None,
+                LuaValue::Integer(n) if n >= 1 => Some(n.unsigned_abs() as usize),
+                #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+                LuaValue::Number(n) if n >= 1.0 && n.fract() == 0.0 && n <= f64::from(u32::MAX) => {
+                    Some(n as usize)
+                }
+                value => {
+                    return Err(LuaError::RuntimeError(format!(
+                        "Invalid option value for 'maxConcurrent' in serve config \
+                        - expected a positive integer, got {}",
+                        match value {
+                            LuaValue::Integer(_) | LuaValue::Number(_) => value.to_string()?,
+                            _ => value.type_name().to_string(),
+                        }
+                    )))
+                }
+            };
             if handle_request.is_some() || handle_web_socket.is_some() {
                 let address: IpAddr = match &address {
                     Some(addr) => {
@@ -209,6 +862,7 @@ impl<'lua> FromLua<'lua> for ServeConfig<'lua> {
                             .expect("Failed to create default http responder function")
                     }),
                     handle_web_socket,
+                    max_concurrent,
                 })
             } else {
                 Err(LuaError::FromLuaConversionError {
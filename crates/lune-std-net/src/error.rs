@@ -0,0 +1,229 @@
+use std::{error::Error, fmt, io::Error as IoError};
+
+use mlua::prelude::*;
+
+use lune_utils::TableBuilder;
+
+const WRAP_KEY: &str = "NetErrorWrap";
+
+// NOTE: Errors thrown from Rust can not be any other value than the error
+// itself, so our functions return their errors instead, and we throw them here
+const WRAP_IMPL_LUA: &str = r"
+local func = ...
+return function(...)
+    local success, result = func(...)
+    if not success then
+        error(result, 0)
+    end
+    return result
+end
+";
+
+/**
+    The kind of failure for a [`NetError`].
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NetErrorKind {
+    Timeout,
+    Connect,
+    Dns,
+    Tls,
+    Status,
+    Decode,
+    Request,
+    Policy,
+}
+
+impl NetErrorKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Timeout => "timeout",
+            Self::Connect => "connect",
+            Self::Dns => "dns",
+            Self::Tls => "tls",
+            Self::Status => "status",
+            Self::Decode => "decode",
+            Self::Request => "request",
+            Self::Policy => "policy",
+        }
+    }
+}
+
+/**
+    A structured error for a failed network operation.
+
+    Thrown to Lua as a userdata, so that scripts can inspect
+    the kind of failure instead of having to parse messages.
+*/
+#[derive(Debug, Clone)]
+pub struct NetError {
+    kind: NetErrorKind,
+    message: String,
+    status: Option<u16>,
+}
+
+impl NetError {
+    pub fn new(kind: NetErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            status: None,
+        }
+    }
+
+    pub fn status(status: u16, message: impl Into<String>) -> Self {
+        Self {
+            kind: NetErrorKind::Status,
+            message: message.into(),
+            status: Some(status),
+        }
+    }
+
+    /**
+        Returns the same error, with the given context prepended to its message.
+    */
+    #[must_use]
+    pub fn with_context(mut self, context: impl fmt::Display) -> Self {
+        self.message = format!("{context}\n{}", self.message);
+        self
+    }
+
+    /**
+        Finds the net error that caused the given Lua error, if any.
+    */
+    pub fn find(err: &LuaError) -> Option<&Self> {
+        match err {
+            LuaError::ExternalError(inner) => inner.downcast_ref::<Self>(),
+            LuaError::CallbackError { cause, .. } | LuaError::WithContext { cause, .. } => {
+                Self::find(cause)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for NetError {
+    fn from(err: reqwest::Error) -> Self {
+        // NOTE: Errors from our own resolver, such as for a network policy, are
+        // wrapped by reqwest, and should be passed through to Lua as they are
+        let mut source = err.source();
+        while let Some(inner) = source {
+            if let Some(net_err) = inner.downcast_ref::<NetError>() {
+                return net_err.clone();
+            }
+            source = inner.source();
+        }
+        let kind = if err.is_timeout() {
+            NetErrorKind::Timeout
+        } else if err.is_connect() {
+            connect_error_kind(&err)
+        } else if err.is_decode() {
+            NetErrorKind::Decode
+        } else {
+            NetErrorKind::Request
+        };
+        Self::new(kind, err.to_string())
+    }
+}
+
+/**
+    Finds out why connecting failed, using the errors that caused it.
+
+    Reqwest does not expose this directly, but TLS errors can be found
+    in the chain of errors, and failed lookups are described as such.
+*/
+fn connect_error_kind(err: &reqwest::Error) -> NetErrorKind {
+    let mut source = err.source();
+    while let Some(inner) = source {
+        if is_tls_error(inner) {
+            return NetErrorKind::Tls;
+        } else if inner.to_string().starts_with("dns error") {
+            return NetErrorKind::Dns;
+        }
+        source = inner.source();
+    }
+    NetErrorKind::Connect
+}
+
+fn is_tls_error(err: &(dyn Error + 'static)) -> bool {
+    // NOTE: IO errors do not return the error they wrap as their
+    // source, so we need to check for TLS errors inside them too
+    err.is::<rustls::Error>()
+        || err
+            .downcast_ref::<IoError>()
+            .and_then(IoError::get_ref)
+            .is_some_and(|inner| is_tls_error(inner))
+}
+
+impl From<NetError> for LuaError {
+    fn from(err: NetError) -> Self {
+        LuaError::external(err)
+    }
+}
+
+impl fmt::Display for NetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Error for NetError {}
+
+impl LuaUserData for NetError {
+    fn add_fields<'lua, F: LuaUserDataFields<'lua, Self>>(fields: &mut F) {
+        fields.add_field_method_get("kind", |_, this| Ok(this.kind.name()));
+        fields.add_field_method_get("message", |_, this| Ok(this.message.clone()));
+        fields.add_field_method_get("status", |_, this| Ok(this.status));
+    }
+
+    fn add_methods<'lua, M: LuaUserDataMethods<'lua, Self>>(methods: &mut M) {
+        methods.add_meta_method(LuaMetaMethod::ToString, |_, this, (): ()| {
+            Ok(this.message.clone())
+        });
+    }
+}
+
+/**
+    Converts the result of a network operation into a success flag and either the
+    value or the structured error, to be thrown by a function from [`wrap_net_errors`].
+
+    Errors that were not caused by a [`NetError`] are passed through and thrown as usual.
+*/
+pub fn into_net_result<'lua, T: IntoLua<'lua>>(
+    lua: &'lua Lua,
+    result: LuaResult<T>,
+) -> LuaResult<(bool, LuaValue<'lua>)> {
+    match result {
+        Ok(value) => Ok((true, value.into_lua(lua)?)),
+        Err(err) => match NetError::find(&err) {
+            Some(net_err) => Ok((
+                false,
+                LuaValue::UserData(lua.create_userdata(net_err.clone())?),
+            )),
+            None => Err(err),
+        },
+    }
+}
+
+/**
+    Wraps a function returning the result of [`into_net_result`] into
+    one that returns the value, or throws the structured error.
+*/
+pub fn wrap_net_errors<'lua>(
+    lua: &'lua Lua,
+    func: LuaFunction<'lua>,
+) -> LuaResult<LuaFunction<'lua>> {
+    if let Some(wrap) = lua.named_registry_value::<Option<LuaFunction>>(WRAP_KEY)? {
+        return wrap.call(func);
+    }
+    let env = TableBuilder::new(lua)?
+        .with_value("error", lua.globals().get::<_, LuaFunction>("error")?)?
+        .build_readonly()?;
+    let wrap = lua
+        .load(WRAP_IMPL_LUA)
+        .set_name("net")
+        .set_environment(env)
+        .into_function()?;
+    lua.set_named_registry_value(WRAP_KEY, wrap.clone())?;
+    wrap.call(func)
+}
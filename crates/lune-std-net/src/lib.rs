@@ -1,12 +1,19 @@
 #![allow(clippy::cargo_common_metadata)]
 
+use std::path::PathBuf;
+
 use bstr::BString;
 use mlua::prelude::*;
 use mlua_luau_scheduler::LuaSpawnExt;
 
+mod cache;
 mod client;
 mod config;
+mod error;
+mod policy;
 mod server;
+mod stream;
+mod url;
 mod util;
 mod websocket;
 
@@ -14,14 +21,18 @@ use lune_utils::TableBuilder;
 
 use self::{
     client::{NetClient, NetClientBuilder},
-    config::{RequestConfig, ServeConfig},
+    config::{ClientConfig, RequestConfig, ServeConfig},
+    error::{into_net_result, wrap_net_errors, NetError, NetErrorKind},
     server::serve,
+    url::UrlComponents,
     util::create_user_agent_header,
     websocket::NetWebSocket,
 };
 
 use lune_std_serde::{decode, encode, EncodeDecodeConfig, EncodeDecodeFormat};
 
+pub use self::policy::NetPolicy;
+
 /**
     Creates the `net` standard library module.
 
@@ -30,18 +41,32 @@ use lune_std_serde::{decode, encode, EncodeDecodeConfig, EncodeDecodeFormat};
     Errors when out of memory.
 */
 pub fn module(lua: &Lua) -> LuaResult<LuaTable> {
-    NetClientBuilder::new()
-        .headers(&[("User-Agent", create_user_agent_header(lua)?)])?
-        .build()?
-        .into_registry(lua);
+    NetClient::request_method_into_registry(lua)?;
+
+    // NOTE: Functions that can fail with a structured error return it instead
+    // of throwing it, and are then wrapped to throw it, see the error module
+    let request = lua.create_async_function(|lua, config| async move {
+        into_net_result(lua, net_request(lua, config).await)
+    })?;
+    let download = lua.create_async_function(|lua, args| async move {
+        into_net_result(lua, net_download(lua, args).await.map(|()| LuaValue::Nil))
+    })?;
+    let socket = lua.create_async_function(|lua, url| async move {
+        into_net_result(lua, net_socket(lua, url).await)
+    })?;
+
     TableBuilder::new(lua)?
         .with_function("jsonEncode", net_json_encode)?
         .with_function("jsonDecode", net_json_decode)?
-        .with_async_function("request", net_request)?
-        .with_async_function("socket", net_socket)?
+        .with_function("createClient", net_create_client)?
+        .with_value("request", wrap_net_errors(lua, request)?)?
+        .with_value("download", wrap_net_errors(lua, download)?)?
+        .with_value("socket", wrap_net_errors(lua, socket)?)?
         .with_async_function("serve", net_serve)?
         .with_function("urlEncode", net_url_encode)?
         .with_function("urlDecode", net_url_decode)?
+        .with_function("parseUrl", net_parse_url)?
+        .with_function("buildUrl", net_build_url)?
         .build_readonly()
 }
 
@@ -58,18 +83,69 @@ fn net_json_decode(lua: &Lua, json: BString) -> LuaResult<LuaValue> {
     decode(json, lua, config)
 }
 
+fn net_create_client(lua: &Lua, config: ClientConfig) -> LuaResult<NetClient> {
+    NetClientBuilder::new()
+        .headers(&[("User-Agent", create_user_agent_header(lua)?)])?
+        .cookies(config.cookies)
+        .proxy(config.proxy)?
+        .tls(config.tls)
+        .timeout(config.timeout)
+        .cache(config.cache)
+        .policy(lua.app_data_ref::<NetPolicy>().map(|policy| policy.clone()))
+        .build()
+}
+
 async fn net_request(lua: &Lua, config: RequestConfig) -> LuaResult<LuaTable> {
-    let client = NetClient::from_registry(lua);
+    let client = NetClient::shared(lua)?;
     // NOTE: We spawn the request as a background task to free up resources in lua
     let res = lua.spawn(async move { client.request(config).await });
     res.await?.into_lua_table(lua)
 }
 
+async fn net_download<'lua>(
+    lua: &'lua Lua,
+    (url, path, on_progress): (String, String, Option<LuaFunction<'lua>>),
+) -> LuaResult<()> {
+    let client = NetClient::shared(lua)?;
+    client
+        .download(
+            url,
+            PathBuf::from(path),
+            |so_far, total| match &on_progress {
+                Some(callback) => callback.call((so_far, total)),
+                None => Ok(()),
+            },
+        )
+        .await
+}
+
 async fn net_socket(lua: &Lua, url: String) -> LuaResult<LuaTable> {
-    let (ws, _) = tokio_tungstenite::connect_async(url).await.into_lua_err()?;
+    let policy = lua.app_data_ref::<NetPolicy>().map(|policy| policy.clone());
+    let (ws, _) = match policy {
+        None => tokio_tungstenite::connect_async(url).await,
+        // NOTE: With a network policy we must resolve and connect to the host
+        // ourselves, so that the policy is checked right before connecting
+        Some(policy) => {
+            let url = reqwest::Url::parse(&url)
+                .map_err(|e| NetError::new(NetErrorKind::Request, format!("Invalid url - {e}")))?;
+            let stream = policy.connect(&url).await?;
+            tokio_tungstenite::client_async_tls(url.as_str(), stream).await
+        }
+    }
+    .map_err(socket_connect_error)?;
     NetWebSocket::new(ws).into_lua_table(lua)
 }
 
+fn socket_connect_error(err: tokio_tungstenite::tungstenite::Error) -> NetError {
+    use tokio_tungstenite::tungstenite::Error as WsError;
+    match &err {
+        WsError::Http(response) => NetError::status(response.status().as_u16(), err.to_string()),
+        WsError::Tls(_) => NetError::new(NetErrorKind::Tls, err.to_string()),
+        WsError::Url(_) => NetError::new(NetErrorKind::Request, err.to_string()),
+        _ => NetError::new(NetErrorKind::Connect, err.to_string()),
+    }
+}
+
 async fn net_serve<'lua>(
     lua: &'lua Lua,
     (port, config): (u16, ServeConfig<'lua>),
@@ -100,3 +176,11 @@ fn net_url_decode<'lua>(
             .into_lua(lua)
     }
 }
+
+fn net_parse_url(lua: &Lua, url: String) -> LuaResult<LuaTable> {
+    UrlComponents::parse(&url)?.into_lua_table(lua)
+}
+
+fn net_build_url(_: &Lua, components: UrlComponents) -> LuaResult<String> {
+    components.build()
+}
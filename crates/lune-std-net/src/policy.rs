@@ -0,0 +1,323 @@
+use std::{
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    str::FromStr,
+    sync::Arc,
+};
+
+use hyper_legacy::client::connect::dns::Name;
+use reqwest::{
+    dns::{Addrs, Resolve, Resolving},
+    Url,
+};
+use tokio::net::{lookup_host, TcpStream};
+use url::Host;
+
+use super::error::{NetError, NetErrorKind};
+
+/**
+    A policy for which hosts network requests may be sent to.
+
+    Hosts are matched against patterns, which may be one of:
+
+    - `*`, matching any host
+    - A host name such as `example.com`, matching only that exact host
+    - A wildcard such as `*.example.com`, matching any subdomain of `example.com`
+    - An IP address such as `10.0.0.1`, or a network such as `10.0.0.0/8`
+
+    Hosts matching any of the denied patterns are always rejected, and if any allowed patterns
+    are given, hosts must also match one of those. Host names are checked before any request is
+    sent, and the addresses they resolve to are checked right before connecting to them, so that
+    a host name can not be used to reach an address that was denied, or a private address.
+*/
+#[derive(Debug, Clone, Default)]
+pub struct NetPolicy {
+    allow: Option<Vec<HostPattern>>,
+    deny: Vec<HostPattern>,
+    block_private: bool,
+}
+
+impl NetPolicy {
+    /**
+        Creates a new policy that allows all hosts, until patterns are added to it.
+    */
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /**
+        Allows hosts matching the given pattern - once any pattern has
+        been allowed, hosts not matching any allowed pattern are rejected.
+    */
+    #[must_use]
+    pub fn allow(mut self, pattern: impl AsRef<str>) -> Self {
+        let pattern = HostPattern::new(pattern.as_ref());
+        self.allow.get_or_insert_with(Vec::new).push(pattern);
+        self
+    }
+
+    /**
+        Denies hosts matching the given pattern, even if they are also allowed.
+    */
+    #[must_use]
+    pub fn deny(mut self, pattern: impl AsRef<str>) -> Self {
+        self.deny.push(HostPattern::new(pattern.as_ref()));
+        self
+    }
+
+    /**
+        Sets if private, loopback, and link-local addresses should be rejected, even if
+        they are allowed by a pattern, to prevent reaching services on a private network.
+    */
+    #[must_use]
+    pub fn block_private_ips(mut self, block: bool) -> Self {
+        self.block_private = block;
+        self
+    }
+
+    /**
+        Checks if requests may be sent to the host of the given url.
+    */
+    pub(crate) fn check_url(&self, url: &Url) -> Result<(), NetError> {
+        match url.host_str() {
+            Some(host) => self.check_host(host),
+            None => Err(policy_error(format!("Url '{url}' has no host"))),
+        }
+    }
+
+    /**
+        Checks if requests may be sent to the given host, which may also be an IP address.
+    */
+    pub(crate) fn check_host(&self, host: &str) -> Result<(), NetError> {
+        // NOTE: Host names are case insensitive, and IPv6 addresses in urls are in brackets
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+        if let Ok(addr) = IpAddr::from_str(&host) {
+            return self.check_addr(&host, addr);
+        }
+        if self.deny.iter().any(|pattern| pattern.matches_name(&host)) {
+            return Err(denied_error(&host));
+        }
+        match &self.allow {
+            Some(allow) if !allow.iter().any(|pattern| pattern.matches_name(&host)) => {
+                Err(policy_error(format!(
+                    "Host '{host}' is not allowed by the network policy"
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /**
+        Checks if connections may be made to the given address, which the given host resolved to.
+
+        Resolved addresses only need to pass the denied patterns and the private address check,
+        since the host name that they were resolved from has already been allowed by name.
+    */
+    pub(crate) fn check_resolved(&self, host: &str, addr: IpAddr) -> Result<(), NetError> {
+        if self.deny.iter().any(|pattern| pattern.matches_addr(addr)) {
+            return Err(policy_error(format!(
+                "Host '{host}' resolved to the address {addr}, which is denied by the network policy"
+            )));
+        }
+        if self.block_private && is_private(addr) {
+            return Err(policy_error(format!(
+                "Host '{host}' resolved to the private address {addr}, \
+                which is not allowed by the network policy"
+            )));
+        }
+        Ok(())
+    }
+
+    fn check_addr(&self, host: &str, addr: IpAddr) -> Result<(), NetError> {
+        if self.deny.iter().any(|pattern| pattern.matches_addr(addr)) {
+            return Err(denied_error(host));
+        }
+        if self.block_private && is_private(addr) {
+            return Err(policy_error(format!(
+                "Private address {addr} is not allowed by the network policy"
+            )));
+        }
+        match &self.allow {
+            Some(allow) if !allow.iter().any(|pattern| pattern.matches_addr(addr)) => {
+                Err(policy_error(format!(
+                    "Host '{host}' is not allowed by the network policy"
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /**
+        Resolves the given host, and returns only the addresses that connections may be made to.
+
+        # Errors
+
+        Errors if the host could not be resolved, or if none of its addresses are allowed.
+    */
+    pub(crate) async fn resolve(&self, host: &str, port: u16) -> Result<Vec<SocketAddr>, NetError> {
+        let resolved = lookup_host((host, port))
+            .await
+            .map_err(|e| NetError::new(NetErrorKind::Dns, format!("dns error: {e}")))?;
+        let mut allowed = Vec::new();
+        let mut last_err = None;
+        for addr in resolved {
+            match self.check_resolved(host, addr.ip()) {
+                Ok(()) => allowed.push(addr),
+                Err(e) => last_err = Some(e),
+            }
+        }
+        match last_err {
+            Some(err) if allowed.is_empty() => Err(err),
+            _ => Ok(allowed),
+        }
+    }
+
+    /**
+        Checks the host of the given url, resolves it, and connects to the first address
+        that the policy allows, for connections that are not made using a client.
+
+        # Errors
+
+        Errors if the host is not allowed, could not be resolved, or if connecting failed.
+    */
+    pub(crate) async fn connect(&self, url: &Url) -> Result<TcpStream, NetError> {
+        self.check_url(url)?;
+        let host = match url.host() {
+            Some(Host::Domain(domain)) => domain.to_string(),
+            Some(Host::Ipv4(addr)) => addr.to_string(),
+            Some(Host::Ipv6(addr)) => addr.to_string(),
+            None => return Err(policy_error(format!("Url '{url}' has no host"))),
+        };
+        let port = url.port_or_known_default().unwrap_or(80);
+        let addrs = self.resolve(&host, port).await?;
+        TcpStream::connect(addrs.as_slice())
+            .await
+            .map_err(|e| NetError::new(NetErrorKind::Connect, e.to_string()))
+    }
+}
+
+/**
+    A DNS resolver for clients, which only gives back addresses that the policy allows.
+*/
+#[derive(Debug)]
+pub(crate) struct NetPolicyResolver(pub(crate) Arc<NetPolicy>);
+
+impl Resolve for NetPolicyResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let policy = Arc::clone(&self.0);
+        Box::pin(async move {
+            // NOTE: The port given here does not matter, it is replaced when connecting
+            let addrs = policy.resolve(name.as_str(), 0).await?;
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+enum HostPattern {
+    Any,
+    Name(String),
+    Subdomains(String),
+    Network(IpAddr, u8),
+}
+
+impl HostPattern {
+    fn new(pattern: &str) -> Self {
+        let pattern = pattern.trim().trim_end_matches('.').to_ascii_lowercase();
+        if pattern == "*" {
+            return Self::Any;
+        }
+        if let Some(suffix) = pattern.strip_prefix("*.") {
+            return Self::Subdomains(suffix.to_string());
+        }
+        let (addr, prefix) = match pattern.split_once('/') {
+            Some((addr, prefix)) => (addr, prefix.parse().ok()),
+            None => (pattern.as_str(), None),
+        };
+        let addr = addr.trim_start_matches('[').trim_end_matches(']');
+        match IpAddr::from_str(addr) {
+            Ok(addr) => {
+                let max = if addr.is_ipv4() { 32 } else { 128 };
+                Self::Network(addr, prefix.unwrap_or(max).min(max))
+            }
+            Err(_) => Self::Name(pattern),
+        }
+    }
+
+    fn matches_name(&self, host: &str) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Name(name) => host == name,
+            Self::Subdomains(suffix) => host
+                .strip_suffix(suffix.as_str())
+                .is_some_and(|sub| sub.len() > 1 && sub.ends_with('.')),
+            Self::Network(..) => false,
+        }
+    }
+
+    fn matches_addr(&self, addr: IpAddr) -> bool {
+        match (self, normalize(addr)) {
+            (Self::Any, _) => true,
+            (Self::Network(IpAddr::V4(net), prefix), IpAddr::V4(addr)) => {
+                let mask = u32::MAX.checked_shl(32 - u32::from(*prefix)).unwrap_or(0);
+                u32::from(*net) & mask == u32::from(addr) & mask
+            }
+            (Self::Network(IpAddr::V6(net), prefix), IpAddr::V6(addr)) => {
+                let mask = u128::MAX.checked_shl(128 - u32::from(*prefix)).unwrap_or(0);
+                u128::from(*net) & mask == u128::from(addr) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/**
+    Converts IPv4 addresses that are mapped to IPv6 back into plain
+    IPv4 addresses, so that they can not be used to get around checks.
+*/
+fn normalize(addr: IpAddr) -> IpAddr {
+    match addr {
+        IpAddr::V6(v6) => match v6.to_ipv4_mapped() {
+            Some(v4) => IpAddr::V4(v4),
+            None => IpAddr::V6(v6),
+        },
+        IpAddr::V4(_) => addr,
+    }
+}
+
+fn is_private(addr: IpAddr) -> bool {
+    match normalize(addr) {
+        IpAddr::V4(v4) => is_private_v4(v4),
+        IpAddr::V6(v6) => is_private_v6(v6),
+    }
+}
+
+fn is_private_v4(addr: Ipv4Addr) -> bool {
+    let [a, b, ..] = addr.octets();
+    addr.is_private()
+        || addr.is_loopback()
+        || addr.is_link_local()
+        || addr.is_unspecified()
+        || addr.is_broadcast()
+        // Shared address space for carrier-grade NAT, 100.64.0.0/10
+        || (a == 100 && (b & 0b1100_0000) == 64)
+}
+
+fn is_private_v6(addr: Ipv6Addr) -> bool {
+    let first = addr.segments()[0];
+    addr.is_loopback()
+        || addr.is_unspecified()
+        // Unique local addresses, fc00::/7
+        || (first & 0xfe00) == 0xfc00
+        // Link-local addresses, fe80::/10
+        || (first & 0xffc0) == 0xfe80
+}
+
+fn denied_error(host: &str) -> NetError {
+    policy_error(format!("Host '{host}' is denied by the network policy"))
+}
+
+fn policy_error(message: String) -> NetError {
+    NetError::new(NetErrorKind::Policy, message)
+}
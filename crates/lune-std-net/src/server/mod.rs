@@ -18,9 +18,11 @@ mod keys;
 mod request;
 mod response;
 mod service;
+mod state;
 
 use keys::SvcKeys;
 use service::Svc;
+use state::ServeState;
 
 pub async fn serve<'lua>(
     lua: &'lua Lua,
@@ -40,15 +42,18 @@ pub async fn serve<'lua>(
     };
 
     let keys = SvcKeys::new(lua, config.handle_request, config.handle_web_socket)?;
+    let state = Rc::new(ServeState::new(config.max_concurrent));
     let svc = Svc {
         lua: lua_svc,
         addr,
         keys,
+        state: Rc::clone(&state),
     };
 
     let (shutdown_tx, shutdown_rx) = tokio::sync::watch::channel(false);
     lua.spawn_local(async move {
         let mut shutdown_rx_outer = shutdown_rx.clone();
+        let mut serve_handle_alive = true;
         loop {
             // Create futures for accepting new connections and shutting down
             let fut_shutdown = shutdown_rx_outer.changed();
@@ -61,8 +66,10 @@ pub async fn serve<'lua>(
                 let io = TokioIo::new(stream);
                 let svc = svc.clone();
                 let mut shutdown_rx_inner = shutdown_rx.clone();
+                let connection = svc.state.open_connection();
 
                 lua_inner.spawn_local(async move {
+                    let _connection = connection;
                     let conn = http1::Builder::new()
                         .keep_alive(true) // Web sockets need this
                         .serve_connection(io, svc)
@@ -72,8 +79,15 @@ pub async fn serve<'lua>(
                     pin!(conn);
                     tokio::select! {
                         _ = conn.as_mut() => {}
-                        _ = shutdown_rx_inner.changed() => {
-                            conn.as_mut().graceful_shutdown();
+                        res = shutdown_rx_inner.changed() => {
+                            // NOTE: Graceful shutdown lets any in-flight request finish, but only
+                            // if we keep driving the connection until it does - the serve handle
+                            // being dropped does not stop the server, so we only shut down if
+                            // the shutdown signal was actually sent
+                            if res.is_ok() {
+                                conn.as_mut().graceful_shutdown();
+                            }
+                            conn.as_mut().await.ok();
                         }
                     }
                 });
@@ -82,13 +96,16 @@ pub async fn serve<'lua>(
             // Wait for either a new connection or a shutdown signal
             tokio::select! {
                 () = fut_accept => {}
-                res = fut_shutdown => {
+                res = fut_shutdown, if serve_handle_alive => {
                     // NOTE: We will only get a RecvError here if the serve handle is dropped,
                     // this means lua has garbage collected it and the user does not want
                     // to manually stop the server using the serve handle. Run forever.
+                    // We also stop listening for the shutdown signal, since it would
+                    // otherwise resolve instantly and we would never accept connections.
                     if res.is_ok() {
                         break;
                     }
+                    serve_handle_alive = false;
                 }
             }
         }
@@ -97,9 +114,21 @@ pub async fn serve<'lua>(
     TableBuilder::new(lua)?
         .with_value("ip", addr.ip().to_string())?
         .with_value("port", addr.port())?
-        .with_function("stop", move |_, (): ()| match shutdown_tx.send(true) {
-            Ok(()) => Ok(()),
-            Err(_) => Err(LuaError::runtime("Server already stopped")),
+        .with_async_function("stop", move |lua, (): ()| {
+            let sent = shutdown_tx.send(true);
+            // NOTE: A request handler that stops the server can not wait
+            // for its own request to finish, so we only wait outside of them
+            let wait = !state.is_handler_thread(&lua.current_thread());
+            let state = Rc::clone(&state);
+            async move {
+                if sent.is_err() {
+                    return Err(LuaError::runtime("Server already stopped"));
+                }
+                if wait {
+                    state.drained().await;
+                }
+                Ok(())
+            }
         })?
         .build_readonly()
 }
@@ -26,20 +26,29 @@ impl LuaRequest {
             .split('&')
             .filter_map(|q| q.split_once('='))
             .map(|(k, v)| {
-                let k = lua.create_string(k)?;
-                let v = lua.create_string(v)?;
+                let k = lua.create_string(decode_query_component(k))?;
+                let v = lua.create_string(decode_query_component(v))?;
                 Ok((k, v))
             })
             .collect::<LuaResult<_>>()?;
 
+        // NOTE: Repeated headers are combined into a single comma-separated
+        // value, which is equivalent for all headers except for Set-Cookie,
+        // and that header should never be sent in requests anyway
         let headers: HashMap<LuaString, LuaString> = self
             .head
             .headers
-            .iter()
-            .map(|(k, v)| {
-                let k = lua.create_string(k.as_str())?;
-                let v = lua.create_string(v.as_bytes())?;
-                Ok((k, v))
+            .keys()
+            .map(|k| {
+                let v = self
+                    .head
+                    .headers
+                    .get_all(k)
+                    .iter()
+                    .map(http::HeaderValue::as_bytes)
+                    .collect::<Vec<_>>()
+                    .join(b", ".as_slice());
+                Ok((lua.create_string(k.as_str())?, lua.create_string(v)?))
             })
             .collect::<LuaResult<_>>()?;
 
@@ -52,3 +61,9 @@ impl LuaRequest {
             .build()
     }
 }
+
+fn decode_query_component(component: &str) -> Vec<u8> {
+    // NOTE: Query strings use '+' for spaces, which percent-decoding alone does not handle
+    let component = component.replace('+', " ");
+    urlencoding::decode_binary(component.as_bytes()).into_owned()
+}
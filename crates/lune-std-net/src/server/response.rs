@@ -14,6 +14,7 @@ use mlua::prelude::*;
 pub(super) enum LuaResponseKind {
     PlainText,
     Table,
+    Upgrade,
 }
 
 pub(super) struct LuaResponse {
@@ -21,6 +22,7 @@ pub(super) struct LuaResponse {
     pub(super) status: u16,
     pub(super) headers: HeaderMap,
     pub(super) body: Option<Vec<u8>>,
+    pub(super) on_socket: Option<LuaRegistryKey>,
 }
 
 impl LuaResponse {
@@ -31,6 +33,13 @@ impl LuaResponse {
                 .header("Content-Type", "text/plain")
                 .body(Full::new(Bytes::from(self.body.unwrap())))
                 .into_lua_err()?,
+            // NOTE: Upgrades are responded to by the service, and
+            // only end up here when the request was not an upgrade
+            LuaResponseKind::Upgrade => Response::builder()
+                .status(426)
+                .header("Upgrade", "websocket")
+                .body(Full::new(Bytes::from("Upgrade Required")))
+                .into_lua_err()?,
             LuaResponseKind::Table => {
                 let mut response = Response::builder()
                     .status(self.status)
@@ -44,7 +53,7 @@ impl LuaResponse {
 }
 
 impl FromLua<'_> for LuaResponse {
-    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+    fn from_lua(value: LuaValue, lua: &Lua) -> LuaResult<Self> {
         match value {
             // Plain strings from the handler are plaintext responses
             LuaValue::String(s) => Ok(Self {
@@ -52,12 +61,21 @@ impl FromLua<'_> for LuaResponse {
                 status: 200,
                 headers: HeaderMap::new(),
                 body: Some(s.as_bytes().to_vec()),
+                on_socket: None,
             }),
             // Tables are more detailed responses with potential status, headers, body
             LuaValue::Table(t) => {
                 let status: Option<u16> = t.get("status")?;
                 let headers: Option<LuaTable> = t.get("headers")?;
                 let body: Option<BString> = t.get("body")?;
+                let on_socket: Option<LuaFunction> = t.get("onSocket")?;
+
+                if on_socket.is_some() && (status.is_some_and(|s| s != 101) || body.is_some()) {
+                    return Err(LuaError::runtime(
+                        "Invalid response - responses with 'onSocket' upgrade the request \
+                        to a web socket, and can not also have a 'status' or 'body'",
+                    ));
+                }
 
                 let mut headers_map = HeaderMap::new();
                 if let Some(headers) = headers {
@@ -72,10 +90,17 @@ impl FromLua<'_> for LuaResponse {
                 let body_bytes = body.map(|s| s.as_bytes().to_vec());
 
                 Ok(Self {
-                    kind: LuaResponseKind::Table,
+                    kind: if on_socket.is_some() {
+                        LuaResponseKind::Upgrade
+                    } else {
+                        LuaResponseKind::Table
+                    },
                     status: status.unwrap_or(200),
                     headers: headers_map,
                     body: body_bytes,
+                    on_socket: on_socket
+                        .map(|f| lua.create_registry_value(f))
+                        .transpose()?,
                 })
             }
             // Anything else is an error
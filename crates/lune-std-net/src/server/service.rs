@@ -4,15 +4,17 @@ use http_body_util::{BodyExt, Full};
 use hyper::{
     body::{Bytes, Incoming},
     service::Service,
+    upgrade::OnUpgrade,
     Request, Response,
 };
-use hyper_tungstenite::{is_upgrade_request, upgrade};
+use hyper_tungstenite::{is_upgrade_request, upgrade, HyperWebsocket};
 
 use mlua::prelude::*;
 use mlua_luau_scheduler::{LuaSchedulerExt, LuaSpawnExt};
 
 use super::{
     super::websocket::NetWebSocket, keys::SvcKeys, request::LuaRequest, response::LuaResponse,
+    state::ServeState,
 };
 
 #[derive(Debug, Clone)]
@@ -20,6 +22,7 @@ pub(super) struct Svc {
     pub(super) lua: Rc<Lua>,
     pub(super) addr: SocketAddr,
     pub(super) keys: SvcKeys,
+    pub(super) state: Rc<ServeState>,
 }
 
 impl Service<Request<Incoming>> for Svc {
@@ -31,28 +34,25 @@ impl Service<Request<Incoming>> for Svc {
         let lua = self.lua.clone();
         let addr = self.addr;
         let keys = self.keys;
+        let state = Rc::clone(&self.state);
 
         if keys.has_websocket_handler() && is_upgrade_request(&req) {
             Box::pin(async move {
                 let (res, sock) = upgrade(req, None).into_lua_err()?;
 
-                let lua_inner = lua.clone();
-                lua.spawn_local(async move {
-                    let sock = sock.await.unwrap();
-                    let lua_sock = NetWebSocket::new(sock);
-                    let lua_tab = lua_sock.into_lua_table(&lua_inner).unwrap();
-
-                    let handler_websocket: LuaFunction =
-                        keys.websocket_handler(&lua_inner).unwrap().unwrap();
-
-                    lua_inner
-                        .push_thread_back(handler_websocket, lua_tab)
-                        .unwrap();
-                });
+                let handler_websocket: LuaFunction = keys
+                    .websocket_handler(&lua)?
+                    .expect("Missing web socket handler");
+                spawn_socket_handler(&lua, sock, handler_websocket)?;
 
                 Ok(res)
             })
         } else {
+            // NOTE: The request handler may choose to upgrade the request to a web socket
+            // by returning a response with an 'onSocket' callback, so we keep the parts
+            // of the request that the upgrade needs, before the request is consumed
+            let mut req = req;
+            let upgrade_req = is_upgrade_request(&req).then(|| take_upgrade_request(&mut req));
             let (head, body) = req.into_parts();
 
             Box::pin(async move {
@@ -68,15 +68,90 @@ impl Service<Request<Incoming>> for Svc {
                 };
                 let lua_req_table = lua_req.into_lua_table(&lua)?;
 
-                let thread_id = lua.push_thread_back(handler_request, lua_req_table)?;
+                // NOTE: The permit is held until the handler has finished, and
+                // we keep track of the handler thread so that stopping the
+                // server from inside of a handler does not wait for itself
+                let _permit = state.acquire_handler_permit().await;
+                let thread = lua.create_thread(handler_request)?;
+                state.add_handler_thread(&thread);
+                let thread_id = lua.push_thread_back(thread.clone(), lua_req_table)?;
                 lua.track_thread(thread_id);
                 lua.wait_for_thread(thread_id).await;
+                state.remove_handler_thread(&thread);
                 let thread_res = lua
                     .get_thread_result(thread_id)
                     .expect("Missing handler thread result")?;
 
-                LuaResponse::from_lua_multi(thread_res, &lua)?.into_response()
+                let mut lua_res = LuaResponse::from_lua_multi(thread_res, &lua)?;
+                let (Some(key), Some(upgrade_req)) = (lua_res.on_socket.take(), upgrade_req) else {
+                    return lua_res.into_response();
+                };
+
+                let (mut res, sock) = match upgrade(upgrade_req, None) {
+                    Ok(upgraded) => upgraded,
+                    Err(e) => {
+                        return Response::builder()
+                            .status(400)
+                            .body(Full::new(Bytes::from(e.to_string())))
+                            .into_lua_err()
+                    }
+                };
+                res.headers_mut().extend(lua_res.headers);
+
+                let handler_socket: LuaFunction = lua.registry_value(&key)?;
+                spawn_socket_handler(&lua, sock, handler_socket)?;
+
+                Ok(res)
             })
         }
     }
 }
+
+/**
+    Takes everything needed to upgrade the given request to a
+    web socket out of it, which is its headers and upgrade handle.
+*/
+fn take_upgrade_request(req: &mut Request<Incoming>) -> Request<()> {
+    let mut upgrade_req = Request::new(());
+    upgrade_req.headers_mut().clone_from(req.headers());
+    if let Some(on_upgrade) = req.extensions_mut().remove::<OnUpgrade>() {
+        upgrade_req.extensions_mut().insert(on_upgrade);
+    }
+    upgrade_req
+}
+
+/**
+    Spawns a task on the scheduler that waits for the upgraded
+    web socket connection, and then calls the given handler with it.
+
+    Connections where the upgrade fails, such as when the client
+    disconnects before it completes, are dropped without calling the handler.
+*/
+fn spawn_socket_handler(
+    lua: &Rc<Lua>,
+    sock: HyperWebsocket,
+    handler: LuaFunction,
+) -> LuaResult<()> {
+    let lua_inner = Rc::clone(lua);
+    let handler_key = lua.create_registry_value(handler)?;
+    lua.spawn_local(async move {
+        let Ok(sock) = sock.await else {
+            return;
+        };
+        let res = NetWebSocket::new(sock)
+            .into_lua_table(&lua_inner)
+            .and_then(|lua_tab| {
+                let handler: LuaFunction = lua_inner.registry_value(&handler_key)?;
+                lua_inner.push_thread_back(handler, lua_tab)
+            });
+        // NOTE: Errors are reported through the scheduler, the same way as errors in any
+        // other thread, by pushing a thread that does nothing other than throwing the error
+        if let Err(e) = res.context("Failed to handle web socket connection") {
+            let report = lua_inner.create_function(move |_, ()| Err::<(), _>(e.clone()));
+            if let Ok(report) = report {
+                lua_inner.push_thread_back(report, ()).ok();
+            }
+        }
+    });
+    Ok(())
+}
@@ -0,0 +1,101 @@
+use std::{
+    cell::{Cell, RefCell},
+    ffi::c_void,
+    rc::Rc,
+};
+
+use tokio::sync::{Notify, Semaphore, SemaphorePermit};
+
+use mlua::prelude::*;
+
+/**
+    State shared between a running server, its connections, and its handle.
+
+    Keeps track of open connections, so that stopping the server can wait for
+    any in-flight requests to finish, and limits concurrent request handlers.
+*/
+#[derive(Debug, Default)]
+pub(super) struct ServeState {
+    connections: Cell<usize>,
+    drained: Notify,
+    handler_threads: RefCell<Vec<*const c_void>>,
+    handler_permits: Option<Semaphore>,
+}
+
+impl ServeState {
+    pub(super) fn new(max_concurrent: Option<usize>) -> Self {
+        Self {
+            handler_permits: max_concurrent.map(Semaphore::new),
+            ..Default::default()
+        }
+    }
+
+    /**
+        Marks a connection as open until the returned guard is dropped.
+    */
+    pub(super) fn open_connection(self: &Rc<Self>) -> ConnectionGuard {
+        self.connections.set(self.connections.get() + 1);
+        ConnectionGuard {
+            state: Rc::clone(self),
+        }
+    }
+
+    /**
+        Waits until all open connections have been closed.
+    */
+    pub(super) async fn drained(&self) {
+        loop {
+            // NOTE: The notified future must be created before checking the count,
+            // otherwise the last connection could close in between and never notify us
+            let notified = self.drained.notified();
+            if self.connections.get() == 0 {
+                return;
+            }
+            notified.await;
+        }
+    }
+
+    /**
+        Waits for a permit to run a request handler, if concurrent handlers are limited.
+    */
+    pub(super) async fn acquire_handler_permit(&self) -> Option<SemaphorePermit<'_>> {
+        match &self.handler_permits {
+            // NOTE: The semaphore is never closed, so acquiring can never fail
+            Some(permits) => permits.acquire().await.ok(),
+            None => None,
+        }
+    }
+
+    /**
+        Marks the given thread as running a request handler until it is removed again.
+    */
+    pub(super) fn add_handler_thread(&self, thread: &LuaThread) {
+        self.handler_threads.borrow_mut().push(thread.to_pointer());
+    }
+
+    pub(super) fn remove_handler_thread(&self, thread: &LuaThread) {
+        let ptr = thread.to_pointer();
+        self.handler_threads.borrow_mut().retain(|p| *p != ptr);
+    }
+
+    /**
+        Checks if the given thread is currently running a request handler.
+    */
+    pub(super) fn is_handler_thread(&self, thread: &LuaThread) -> bool {
+        self.handler_threads.borrow().contains(&thread.to_pointer())
+    }
+}
+
+pub(super) struct ConnectionGuard {
+    state: Rc<ServeState>,
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        let state = &self.state;
+        state.connections.set(state.connections.get() - 1);
+        if state.connections.get() == 0 {
+            state.drained.notify_waiters();
+        }
+    }
+}
@@ -0,0 +1,149 @@
+use std::rc::Rc;
+
+use mlua::prelude::*;
+use tokio::sync::Mutex as AsyncMutex;
+
+use lune_utils::buffer;
+
+use super::error::{into_net_result, wrap_net_errors, NetError, NetErrorKind};
+
+/**
+    The body of a response that is read in chunks as it arrives,
+    for requests made with the `stream` option.
+
+    The underlying connection is dropped as soon as the stream is
+    closed, or when the stream itself is dropped / garbage collected.
+*/
+#[derive(Debug)]
+pub struct NetClientBodyStream {
+    res: Option<reqwest::Response>,
+    max_body_size: Option<u64>,
+    bytes_read: u64,
+    // NOTE: Bytes that were read from the response but not yet
+    // returned, which only happens when reading the body by lines
+    pending: Vec<u8>,
+}
+
+impl NetClientBodyStream {
+    pub fn new(res: reqwest::Response, max_body_size: Option<u64>) -> Self {
+        Self {
+            res: Some(res),
+            max_body_size,
+            bytes_read: 0,
+            pending: Vec::new(),
+        }
+    }
+
+    async fn next_chunk(&mut self) -> LuaResult<Option<Vec<u8>>> {
+        let Some(res) = self.res.as_mut() else {
+            return Ok(None);
+        };
+        let Some(chunk) = res.chunk().await.map_err(NetError::from)? else {
+            self.res = None;
+            return Ok(None);
+        };
+        self.bytes_read += chunk.len() as u64;
+        if let Some(max_body_size) = self.max_body_size.filter(|max| self.bytes_read > *max) {
+            self.close();
+            return Err(NetError::new(
+                NetErrorKind::Request,
+                format!("Response body exceeds the maximum size of {max_body_size} bytes"),
+            )
+            .into());
+        }
+        Ok(Some(chunk.to_vec()))
+    }
+
+    /**
+        Reads the next chunk of the body, as it was received, or
+        `None` if the full body has been read or the stream was closed.
+    */
+    pub async fn read_chunk(&mut self) -> LuaResult<Option<Vec<u8>>> {
+        if !self.pending.is_empty() {
+            return Ok(Some(std::mem::take(&mut self.pending)));
+        }
+        self.next_chunk().await
+    }
+
+    /**
+        Reads the next line of the body, without its trailing `\n` or `\r\n`, or
+        `None` if the full body has been read or the stream was closed.
+
+        The last line of the body is returned even if it does not end with a newline.
+    */
+    pub async fn read_line(&mut self) -> LuaResult<Option<Vec<u8>>> {
+        loop {
+            if let Some(index) = self.pending.iter().position(|b| *b == b'\n') {
+                let mut line = self.pending.drain(..=index).collect::<Vec<_>>();
+                line.pop();
+                if line.last() == Some(&b'\r') {
+                    line.pop();
+                }
+                return Ok(Some(line));
+            }
+            match self.next_chunk().await? {
+                Some(chunk) => self.pending.extend_from_slice(&chunk),
+                None if self.pending.is_empty() => return Ok(None),
+                None => return Ok(Some(std::mem::take(&mut self.pending))),
+            }
+        }
+    }
+
+    /**
+        Closes the stream, dropping the underlying connection if the
+        full body has not yet been read. Closing twice does nothing.
+    */
+    pub fn close(&mut self) {
+        self.res = None;
+        self.pending.clear();
+    }
+
+    /**
+        Creates the `readChunk`, `readLine` and `close` methods for a response table.
+    */
+    pub fn into_lua_methods(self, lua: &Lua) -> LuaResult<(LuaFunction, LuaFunction, LuaFunction)> {
+        let stream = Rc::new(AsyncMutex::new(self));
+
+        let inner = Rc::clone(&stream);
+        let read_chunk = lua.create_async_function(move |lua, _: LuaValue| {
+            let stream = Rc::clone(&inner);
+            async move {
+                let chunk = stream.lock().await.read_chunk().await;
+                let chunk = chunk.and_then(|chunk| match chunk {
+                    Some(chunk) => buffer::to_lua(lua, chunk),
+                    None => Ok(LuaValue::Nil),
+                });
+                into_net_result(lua, chunk)
+            }
+        })?;
+
+        let inner = Rc::clone(&stream);
+        let read_line = lua.create_async_function(move |lua, _: LuaValue| {
+            let stream = Rc::clone(&inner);
+            async move {
+                let line = stream.lock().await.read_line().await;
+                let line = line.and_then(|line| match line {
+                    Some(line) => lua.create_string(line).map(LuaValue::String),
+                    None => Ok(LuaValue::Nil),
+                });
+                into_net_result(lua, line)
+            }
+        })?;
+
+        // NOTE: Closing waits for any read that is currently in progress, since the
+        // stream is locked during reads, so the read that is waiting will finish first
+        let close = lua.create_async_function(move |_, _: LuaValue| {
+            let stream = Rc::clone(&stream);
+            async move {
+                stream.lock().await.close();
+                Ok(())
+            }
+        })?;
+
+        Ok((
+            wrap_net_errors(lua, read_chunk)?,
+            wrap_net_errors(lua, read_line)?,
+            close,
+        ))
+    }
+}
@@ -0,0 +1,229 @@
+use std::collections::HashMap;
+
+use mlua::prelude::*;
+use url::Url;
+
+use lune_utils::TableBuilder;
+
+use super::util::table_to_hash_map;
+
+/**
+    The components of a url, as returned by `net.parseUrl` and accepted by `net.buildUrl`.
+
+    The path and fragment are kept percent-encoded, while the
+    query is decoded into a table of key-value pairs.
+*/
+#[derive(Debug, Clone, Default)]
+pub struct UrlComponents {
+    pub scheme: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub host: Option<String>,
+    pub port: Option<u16>,
+    pub path: Option<String>,
+    pub query: HashMap<String, Vec<String>>,
+    pub fragment: Option<String>,
+}
+
+impl UrlComponents {
+    /**
+        Parses the given url into its components.
+
+        # Errors
+
+        Errors when the url is malformed.
+    */
+    pub fn parse(url: &str) -> LuaResult<Self> {
+        let url = Url::parse(url)
+            .map_err(|e| LuaError::runtime(format!("Failed to parse url '{url}' - {e}")))?;
+
+        let mut query: HashMap<String, Vec<String>> = HashMap::new();
+        for (key, value) in url.query_pairs() {
+            query
+                .entry(key.into_owned())
+                .or_default()
+                .push(value.into_owned());
+        }
+
+        Ok(Self {
+            scheme: url.scheme().to_string(),
+            username: Some(url.username())
+                .filter(|username| !username.is_empty())
+                .map(ToString::to_string),
+            password: url.password().map(ToString::to_string),
+            host: url.host_str().map(ToString::to_string),
+            port: url.port_or_known_default(),
+            path: Some(url.path().to_string()),
+            query,
+            fragment: url.fragment().map(ToString::to_string),
+        })
+    }
+
+    /**
+        Builds a url from the components.
+
+        # Errors
+
+        Errors when the components do not form a valid url,
+        such as when a scheme that requires a host has none.
+    */
+    pub fn build(self) -> LuaResult<String> {
+        let base = match &self.host {
+            Some(host) => format!("{}://{host}", self.scheme),
+            None => format!("{}:", self.scheme),
+        };
+        let mut url = Url::parse(&base)
+            .map_err(|e| LuaError::runtime(format!("Failed to build url - {e}")))?;
+
+        if self.username.is_some() || self.password.is_some() || self.port.is_some() {
+            let invalid = |component: &str| {
+                LuaError::runtime(format!(
+                    "Failed to build url - '{}' urls can not have a {component}",
+                    self.scheme
+                ))
+            };
+            if let Some(username) = &self.username {
+                url.set_username(username)
+                    .map_err(|()| invalid("username"))?;
+            }
+            if let Some(password) = &self.password {
+                url.set_password(Some(password))
+                    .map_err(|()| invalid("password"))?;
+            }
+            if let Some(port) = self.port {
+                url.set_port(Some(port)).map_err(|()| invalid("port"))?;
+            }
+        }
+
+        if let Some(path) = &self.path {
+            url.set_path(path);
+        }
+
+        if !self.query.is_empty() {
+            // NOTE: Same as for request queries, keys are sorted by name, and
+            // the order of repeated values for the same key is kept as-is
+            let mut query = self.query.into_iter().collect::<Vec<_>>();
+            query.sort_by(|(a, _), (b, _)| a.cmp(b));
+            let mut pairs = url.query_pairs_mut();
+            for (key, values) in query {
+                for value in values {
+                    pairs.append_pair(&key, &value);
+                }
+            }
+        }
+
+        url.set_fragment(self.fragment.as_deref());
+
+        Ok(url.into())
+    }
+
+    pub fn into_lua_table(self, lua: &Lua) -> LuaResult<LuaTable> {
+        let mut query = TableBuilder::new(lua)?;
+        for (key, mut values) in self.query {
+            if values.len() == 1 {
+                query = query.with_value(key, values.pop())?;
+            } else {
+                let values = TableBuilder::new(lua)?
+                    .with_sequential_values(values)?
+                    .build_readonly()?;
+                query = query.with_value(key, values)?;
+            }
+        }
+
+        TableBuilder::new(lua)?
+            .with_value("scheme", self.scheme)?
+            .with_value("username", self.username)?
+            .with_value("password", self.password)?
+            .with_value("host", self.host)?
+            .with_value("port", self.port)?
+            .with_value("path", self.path)?
+            .with_value("query", query.build_readonly()?)?
+            .with_value("fragment", self.fragment)?
+            .build_readonly()
+    }
+}
+
+impl<'lua> FromLua<'lua> for UrlComponents {
+    fn from_lua(value: LuaValue<'lua>, _: &'lua Lua) -> LuaResult<Self> {
+        let LuaValue::Table(tab) = value else {
+            return Err(LuaError::FromLuaConversionError {
+                from: value.type_name(),
+                to: "UrlComponents",
+                message: Some(format!(
+                    "Invalid url components - expected table, got {}",
+                    value.type_name()
+                )),
+            });
+        };
+
+        let string = |key: &str| -> LuaResult<Option<String>> {
+            match tab.get::<_, LuaValue>(key)? {
+                LuaValue::Nil => Ok(None),
+                LuaValue::String(s) => Ok(Some(s.to_str()?.to_string())),
+                value => Err(LuaError::runtime(format!(
+                    "Invalid option value for '{key}' in url components \
+                    - expected string, got {}",
+                    value.type_name()
+                ))),
+            }
+        };
+
+        let Some(scheme) = string("scheme")? else {
+            return Err(LuaError::runtime(
+                "Missing 'scheme' in url components - expected string",
+            ));
+        };
+
+        let port = match tab.get::<_, LuaValue>("port")? {
+            LuaValue::Nil => None,
+            value @ (LuaValue::Integer(_) | LuaValue::Number(_)) => Some(port_from_lua(&value)?),
+            value => {
+                return Err(LuaError::runtime(format!(
+                    "Invalid option value for 'port' in url components \
+                    - expected number, got {}",
+                    value.type_name()
+                )))
+            }
+        };
+
+        let query = match tab.get::<_, LuaValue>("query")? {
+            LuaValue::Nil => HashMap::new(),
+            LuaValue::Table(query) => table_to_hash_map(query, "query")?,
+            value => {
+                return Err(LuaError::runtime(format!(
+                    "Invalid option value for 'query' in url components \
+                    - expected table, got {}",
+                    value.type_name()
+                )))
+            }
+        };
+
+        Ok(Self {
+            scheme,
+            username: string("username")?,
+            password: string("password")?,
+            host: string("host")?,
+            port,
+            path: string("path")?,
+            query,
+            fragment: string("fragment")?,
+        })
+    }
+}
+
+fn port_from_lua(value: &LuaValue) -> LuaResult<u16> {
+    #[allow(clippy::cast_possible_truncation)]
+    let port = match *value {
+        LuaValue::Integer(i) => Some(i64::from(i)),
+        LuaValue::Number(n) if n.fract() == 0.0 && n.abs() <= f64::from(u32::MAX) => Some(n as i64),
+        _ => None,
+    };
+    port.and_then(|port| u16::try_from(port).ok())
+        .ok_or_else(|| {
+            LuaError::runtime(format!(
+                "Invalid option value for 'port' in url components \
+            - expected an integer between 0 and {}",
+                u16::MAX
+            ))
+        })
+}
@@ -71,24 +71,29 @@ pub fn table_to_hash_map(
 
     for pair in tab.pairs::<String, LuaValue>() {
         let (key, value) = pair?;
-        match value {
-            LuaValue::String(s) => {
-                map.insert(key, vec![s.to_str()?.to_owned()]);
-            }
-            LuaValue::Table(t) => {
-                let mut values = Vec::new();
-                for value in t.sequence_values::<LuaString>() {
-                    values.push(value?.to_str()?.to_owned());
-                }
-                map.insert(key, values);
-            }
-            _ => {
-                return Err(LuaError::runtime(format!(
-                    "Value for '{tab_origin_key}' must be a string or array of strings",
-                )))
+        if let LuaValue::Table(t) = value {
+            let mut values = Vec::new();
+            for value in t.sequence_values::<LuaValue>() {
+                values.push(value_to_string(value?, tab_origin_key)?);
             }
+            map.insert(key, values);
+        } else {
+            map.insert(key, vec![value_to_string(value, tab_origin_key)?]);
         }
     }
 
     Ok(map)
 }
+
+fn value_to_string(value: LuaValue, tab_origin_key: &'static str) -> LuaResult<String> {
+    match value {
+        LuaValue::String(s) => Ok(s.to_str()?.to_owned()),
+        LuaValue::Integer(i) => Ok(i.to_string()),
+        LuaValue::Number(n) => Ok(n.to_string()),
+        LuaValue::Boolean(b) => Ok(b.to_string()),
+        _ => Err(LuaError::runtime(format!(
+            "Value for '{tab_origin_key}' must be a string, number, \
+            boolean, or an array of those",
+        ))),
+    }
+}
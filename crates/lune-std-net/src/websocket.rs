@@ -23,7 +23,7 @@ use hyper_tungstenite::{
     WebSocketStream,
 };
 
-use lune_utils::TableBuilder;
+use lune_utils::{buffer, TableBuilder};
 
 // Wrapper implementation for compatibility and changing colon syntax to dot syntax
 const WEB_SOCKET_IMPL_LUA: &str = r#"
@@ -100,7 +100,17 @@ where
 
     pub async fn next(&self) -> LuaResult<Option<WsMessage>> {
         let mut ws = self.read_stream.lock().await;
-        ws.next().await.transpose().into_lua_err()
+        loop {
+            let msg = ws.next().await.transpose().into_lua_err()?;
+            // Ping & pong messages are responded to automatically by tungstenite,
+            // and raw frames are never yielded when reading, so we skip them here
+            if !matches!(
+                msg,
+                Some(WsMessage::Ping(_) | WsMessage::Pong(_) | WsMessage::Frame(_))
+            ) {
+                break Ok(msg);
+            }
+        }
     }
 
     pub async fn close(&self, code: Option<u16>) -> LuaResult<()> {
@@ -180,10 +190,10 @@ where
             }
 
             Ok(match msg {
-                Some(WsMessage::Binary(bin)) => LuaValue::String(lua.create_string(bin)?),
+                Some(WsMessage::Binary(bin)) => buffer::to_lua(lua, bin)?,
                 Some(WsMessage::Text(txt)) => LuaValue::String(lua.create_string(txt)?),
                 Some(WsMessage::Close(_)) | None => LuaValue::Nil,
-                // Ignore ping/pong/frame messages, they are handled by tungstenite
+                // Ping/pong/frame messages are skipped when reading, see above
                 msg => unreachable!("Unhandled message: {:?}", msg),
             })
         });
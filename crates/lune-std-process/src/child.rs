@@ -0,0 +1,241 @@
+use std::{io, sync::Arc};
+
+use bstr::{BString, ByteSlice};
+use mlua::prelude::*;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, AsyncWriteExt, BufReader},
+    process::{Child, ChildStdin},
+    sync::{mpsc::UnboundedSender, oneshot, watch, Mutex as AsyncMutex},
+};
+
+use super::wait_for_child::WaitForChildResult;
+
+type BoxedReader = BufReader<Box<dyn AsyncRead + Send + Unpin>>;
+
+/**
+    A readable stream of output from a child process.
+
+    Reads are line-based, and line endings are stripped from the returned lines.
+*/
+#[derive(Clone)]
+pub(super) struct ChildProcessReader {
+    inner: Arc<AsyncMutex<BoxedReader>>,
+}
+
+impl ChildProcessReader {
+    pub fn new(reader: impl AsyncRead + Send + Unpin + 'static) -> Self {
+        let reader: Box<dyn AsyncRead + Send + Unpin> = Box::new(reader);
+        Self {
+            inner: Arc::new(AsyncMutex::new(BufReader::new(reader))),
+        }
+    }
+
+    async fn read_line(&self) -> LuaResult<Option<Vec<u8>>> {
+        let mut reader = self.inner.lock().await;
+        let mut line = Vec::new();
+        if reader.read_until(b'\n', &mut line).await.into_lua_err()? == 0 {
+            return Ok(None);
+        }
+        if line.ends_with(b"\n") {
+            line.pop();
+            if line.ends_with(b"\r") {
+                line.pop();
+            }
+        }
+        Ok(Some(line))
+    }
+}
+
+impl LuaUserData for ChildProcessReader {
+    fn add_methods<'lua, M: LuaUserDataMethods<'lua, Self>>(methods: &mut M) {
+        methods.add_async_method("read", |lua, this, (): ()| async move {
+            match this.read_line().await? {
+                Some(line) => Ok(LuaValue::String(lua.create_string(line)?)),
+                None => Ok(LuaValue::Nil),
+            }
+        });
+    }
+}
+
+/**
+    A writable stream of input to a child process.
+
+    Closing the writer closes stdin for the child process, which will then read EOF.
+*/
+#[derive(Clone)]
+pub(super) struct ChildProcessWriter {
+    inner: Arc<AsyncMutex<Option<ChildStdin>>>,
+}
+
+impl ChildProcessWriter {
+    pub fn new(stdin: ChildStdin) -> Self {
+        Self {
+            inner: Arc::new(AsyncMutex::new(Some(stdin))),
+        }
+    }
+
+    async fn write(&self, bytes: &[u8]) -> LuaResult<()> {
+        let mut stdin = self.inner.lock().await;
+        let stdin = stdin
+            .as_mut()
+            .ok_or_else(|| LuaError::runtime("Stdin has already been closed"))?;
+        stdin.write_all(bytes).await.into_lua_err()?;
+        stdin.flush().await.into_lua_err()
+    }
+
+    async fn close(&self) -> LuaResult<()> {
+        let mut stdin = self.inner.lock().await;
+        match stdin.take() {
+            Some(mut stdin) => stdin.shutdown().await.into_lua_err(),
+            None => Err(LuaError::runtime("Stdin has already been closed")),
+        }
+    }
+}
+
+impl LuaUserData for ChildProcessWriter {
+    fn add_methods<'lua, M: LuaUserDataMethods<'lua, Self>>(methods: &mut M) {
+        methods.add_async_method("write", |_, this, bytes: BString| async move {
+            this.write(bytes.as_bytes()).await
+        });
+        methods.add_async_method("close", |_, this, (): ()| async move { this.close().await });
+    }
+}
+
+/**
+    A signal to send to a child process when killing it.
+
+    No signal means that the child process is forcefully killed, which is the only
+    supported way of killing child processes on platforms that do not have signals.
+*/
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(super) struct ChildProcessSignal(Option<i32>);
+
+impl<'lua> FromLua<'lua> for ChildProcessSignal {
+    fn from_lua(value: LuaValue<'lua>, lua: &'lua Lua) -> LuaResult<Self> {
+        match value {
+            LuaValue::Nil => Ok(Self(None)),
+            LuaValue::String(name) => Self::from_name(name.to_str()?),
+            LuaValue::Integer(_) | LuaValue::Number(_) => {
+                let signal = f64::from_lua(value, lua)?;
+                if signal.fract() == 0.0 && signal >= 0.0 && signal <= f64::from(i32::MAX) {
+                    #[allow(clippy::cast_possible_truncation)]
+                    Ok(Self(Some(signal as i32)))
+                } else {
+                    Err(LuaError::runtime(format!("Invalid signal '{signal}'")))
+                }
+            }
+            _ => Err(LuaError::FromLuaConversionError {
+                from: value.type_name(),
+                to: "ChildProcessSignal",
+                message: Some(format!(
+                    "Invalid signal - expected string or number, got {}",
+                    value.type_name()
+                )),
+            }),
+        }
+    }
+}
+
+impl ChildProcessSignal {
+    /**
+        Parses a signal name, such as `SIGTERM`, with or without the `SIG` prefix.
+    */
+    #[cfg(unix)]
+    fn from_name(name: &str) -> LuaResult<Self> {
+        use std::str::FromStr;
+
+        let upper = name.to_ascii_uppercase();
+        let full = if upper.starts_with("SIG") {
+            upper
+        } else {
+            format!("SIG{upper}")
+        };
+        nix::sys::signal::Signal::from_str(&full)
+            .map(|signal| Self(Some(signal as i32)))
+            .map_err(|_| LuaError::runtime(format!("Unknown signal '{name}'")))
+    }
+
+    #[cfg(not(unix))]
+    #[allow(clippy::unnecessary_wraps)]
+    fn from_name(_: &str) -> LuaResult<Self> {
+        Ok(Self(None))
+    }
+}
+
+/**
+    A request to kill a child process, sent to the task that is waiting for it.
+*/
+pub(super) struct ChildProcessKillRequest {
+    pub signal: ChildProcessSignal,
+    pub result: oneshot::Sender<io::Result<()>>,
+}
+
+/**
+    Kills the given child process, using the given signal if supported on the current platform.
+*/
+pub(super) fn kill_child(child: &mut Child, signal: ChildProcessSignal) -> io::Result<()> {
+    #[cfg(unix)]
+    if let (Some(signal), Some(pid)) = (signal.0, child.id()) {
+        use nix::{sys::signal, unistd::Pid};
+
+        let signal = signal::Signal::try_from(signal).map_err(io::Error::from)?;
+        let pid = Pid::from_raw(i32::try_from(pid).map_err(io::Error::other)?);
+        return signal::kill(pid, signal).map_err(io::Error::from);
+    }
+    child.start_kill()
+}
+
+/**
+    A handle to a child process that is still running.
+
+    Piped input can be written using the `stdin` writer, streamed output can be read using
+    the `stdout` and `stderr` readers, and the final result can be waited for using `wait`.
+*/
+pub(super) struct ChildProcessHandle {
+    pub pid: Option<u32>,
+    pub stdin: Option<ChildProcessWriter>,
+    pub stdout: Option<ChildProcessReader>,
+    pub stderr: Option<ChildProcessReader>,
+    pub kill: UnboundedSender<ChildProcessKillRequest>,
+    pub result: watch::Receiver<Option<LuaResult<WaitForChildResult>>>,
+}
+
+impl LuaUserData for ChildProcessHandle {
+    fn add_fields<'lua, F: LuaUserDataFields<'lua, Self>>(fields: &mut F) {
+        fields.add_field_method_get("stdin", |_, this| Ok(this.stdin.clone()));
+        fields.add_field_method_get("stdout", |_, this| Ok(this.stdout.clone()));
+        fields.add_field_method_get("stderr", |_, this| Ok(this.stderr.clone()));
+    }
+
+    fn add_methods<'lua, M: LuaUserDataMethods<'lua, Self>>(methods: &mut M) {
+        methods.add_method("pid", |_, this, (): ()| Ok(this.pid));
+        methods.add_async_method("kill", |_, this, signal: ChildProcessSignal| {
+            let kill = this.kill.clone();
+            async move {
+                // NOTE: The waiting task is gone once the child process has exited,
+                // in which case there is nothing left to kill and we return false
+                let (tx, rx) = oneshot::channel();
+                let request = ChildProcessKillRequest { signal, result: tx };
+                if kill.send(request).is_err() {
+                    return Ok(false);
+                }
+                match rx.await {
+                    Ok(res) => res.into_lua_err().map(|()| true),
+                    Err(_) => Ok(false),
+                }
+            }
+        });
+        methods.add_async_method("wait", |lua, this, (): ()| {
+            let mut result = this.result.clone();
+            async move {
+                let res = result
+                    .wait_for(Option::is_some)
+                    .await
+                    .map_err(|_| LuaError::runtime("Child process was dropped unexpectedly"))?
+                    .clone()
+                    .expect("result was checked to exist");
+                res?.into_lua_table(lua)
+            }
+        });
+    }
+}
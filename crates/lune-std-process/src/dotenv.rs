@@ -0,0 +1,202 @@
+use std::{env, fs};
+
+use mlua::prelude::*;
+
+/**
+    Options for loading environment variables using `process.loadEnv`.
+*/
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProcessLoadEnvOptions {
+    pub(crate) override_existing: bool,
+}
+
+impl<'lua> FromLua<'lua> for ProcessLoadEnvOptions {
+    fn from_lua(value: LuaValue<'lua>, _: &'lua Lua) -> LuaResult<Self> {
+        Ok(match value {
+            LuaValue::Nil => Self::default(),
+            LuaValue::Table(t) => {
+                let override_existing = match t.get::<_, LuaValue>("override")? {
+                    LuaValue::Nil => false,
+                    LuaValue::Boolean(b) => b,
+                    value => {
+                        return Err(LuaError::RuntimeError(format!(
+                            "Invalid option value for 'override' in load env options \
+                            - expected boolean, got {}",
+                            value.type_name()
+                        )))
+                    }
+                };
+                Self { override_existing }
+            }
+            _ => {
+                return Err(LuaError::FromLuaConversionError {
+                    from: value.type_name(),
+                    to: "ProcessLoadEnvOptions",
+                    message: Some(format!(
+                        "Invalid load env options - expected table, got {}",
+                        value.type_name()
+                    )),
+                })
+            }
+        })
+    }
+}
+
+/**
+    Reads and parses the dotenv file at the given path, and sets all of the variables
+    in it that are not already set - or all of them, if overriding existing variables.
+
+    Returns all of the variables in the file, in the order they appear in.
+
+    # Errors
+
+    Errors when the file could not be read, or is not a valid dotenv file.
+*/
+pub fn load_env(path: &str, options: ProcessLoadEnvOptions) -> LuaResult<Vec<(String, String)>> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| LuaError::runtime(format!("Failed to read env file '{path}'\n{e}")))?;
+    let vars = parse_dotenv(&contents)
+        .map_err(|e| LuaError::runtime(format!("Failed to parse env file '{path}'\n{e}")))?;
+
+    for (key, value) in &vars {
+        if value.contains('\0') {
+            return Err(LuaError::runtime(format!(
+                "Failed to load env file '{path}'\nValue of '{key}' must not contain the NUL character"
+            )));
+        }
+    }
+    for (key, value) in &vars {
+        if options.override_existing || env::var_os(key).is_none() {
+            env::set_var(key, value);
+        }
+    }
+
+    Ok(vars)
+}
+
+/**
+    Parses the contents of a dotenv file into a list of variables.
+
+    Supports comments, `export` prefixes, single quoted values that are read
+    literally, and double quoted values that may contain escape sequences.
+    Quoted values may also span multiple lines.
+
+    Variables that reference other variables, such as `${HOME}`, are not expanded.
+*/
+fn parse_dotenv(contents: &str) -> Result<Vec<(String, String)>, String> {
+    let mut vars = Vec::new();
+    let mut lines = contents.lines().enumerate();
+
+    while let Some((index, line)) = lines.next() {
+        let line_number = index + 1;
+        let line = line.trim_start();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").map_or(line, str::trim_start);
+
+        let Some((key, rest)) = line.split_once('=') else {
+            return Err(format!("Expected 'KEY=VALUE' on line {line_number}"));
+        };
+        let key = key.trim();
+        if !is_valid_key(key) {
+            return Err(format!(
+                "Invalid variable name '{key}' on line {line_number}"
+            ));
+        }
+
+        let rest = rest.trim_start();
+        let value = match rest.chars().next() {
+            Some(quote @ ('"' | '\'')) => {
+                let mut raw = rest[1..].to_string();
+                loop {
+                    if let Some(end) = find_closing_quote(&raw, quote) {
+                        let trailing = raw[end + 1..].trim_start();
+                        if !trailing.is_empty() && !trailing.starts_with('#') {
+                            return Err(format!(
+                                "Unexpected characters after quoted value of '{key}' on line {line_number}"
+                            ));
+                        }
+                        raw.truncate(end);
+                        break;
+                    }
+                    let Some((_, next)) = lines.next() else {
+                        return Err(format!(
+                            "Unterminated quoted value of '{key}' starting on line {line_number}"
+                        ));
+                    };
+                    raw.push('\n');
+                    raw.push_str(next);
+                }
+                if quote == '"' {
+                    unescape(&raw)
+                } else {
+                    raw
+                }
+            }
+            _ => strip_inline_comment(rest).trim_end().to_string(),
+        };
+
+        vars.push((key.to_string(), value));
+    }
+
+    Ok(vars)
+}
+
+fn is_valid_key(key: &str) -> bool {
+    let mut chars = key.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'))
+}
+
+fn find_closing_quote(s: &str, quote: char) -> Option<usize> {
+    let mut escaped = false;
+    for (index, c) in s.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' && quote == '"' {
+            escaped = true;
+        } else if c == quote {
+            return Some(index);
+        }
+    }
+    None
+}
+
+fn strip_inline_comment(s: &str) -> &str {
+    // NOTE: Comments in unquoted values must be preceded by whitespace,
+    // so that values such as urls with fragments are kept intact
+    let mut previous = ' ';
+    for (index, c) in s.char_indices() {
+        if c == '#' && previous.is_whitespace() {
+            return &s[..index];
+        }
+        previous = c;
+    }
+    s
+}
+
+fn unescape(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => result.push('\n'),
+            Some('r') => result.push('\r'),
+            Some('t') => result.push('\t'),
+            Some(c @ ('\\' | '"' | '$')) => result.push(c),
+            Some(c) => {
+                result.push('\\');
+                result.push(c);
+            }
+            None => result.push('\\'),
+        }
+    }
+    result
+}
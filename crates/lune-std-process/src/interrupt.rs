@@ -0,0 +1,334 @@
+use std::{
+    cell::RefCell,
+    process::ExitCode,
+    rc::{Rc, Weak},
+};
+
+use mlua::prelude::*;
+
+use lune_utils::ScriptExitCode;
+use mlua_luau_scheduler::{LuaSchedulerExt, LuaSpawnExt};
+use tokio::{
+    select,
+    sync::{mpsc::unbounded_channel, Notify},
+};
+
+// NOTE: Handlers may yield, so we can not call them directly from Rust, and errors
+// are rethrown so that they still get reported by the scheduler as usual
+const HANDLER_WRAPPER: &str = r"
+local handler, completed = ...
+local success, err = pcall(handler)
+completed()
+if not success then
+    error(err, 0)
+end
+";
+
+/**
+    A signal that asks the process to shut down.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum InterruptSignal {
+    Interrupt,
+    #[cfg_attr(windows, allow(dead_code))]
+    Terminate,
+}
+
+impl InterruptSignal {
+    /**
+        The conventional exit code for a process that was
+        stopped by this signal, `128` + the signal number.
+    */
+    fn exit_code(self) -> u8 {
+        match self {
+            Self::Interrupt => 130,
+            Self::Terminate => 143,
+        }
+    }
+}
+
+/**
+    Listens for signals that ask the process to shut down.
+
+    Signals are listened for from the moment this is created, so
+    that no signals are missed in between calls to [`Signals::recv`].
+*/
+struct Signals {
+    #[cfg(unix)]
+    interrupt: tokio::signal::unix::Signal,
+    #[cfg(unix)]
+    terminate: tokio::signal::unix::Signal,
+    #[cfg(windows)]
+    ctrl_c: tokio::signal::windows::CtrlC,
+}
+
+impl Signals {
+    #[cfg(unix)]
+    fn new() -> LuaResult<Self> {
+        use tokio::signal::unix::{signal, SignalKind};
+        actions::save_original();
+        let signals = Self {
+            interrupt: signal(SignalKind::interrupt())?,
+            terminate: signal(SignalKind::terminate())?,
+        };
+        actions::restore_handlers();
+        Ok(signals)
+    }
+
+    #[cfg(windows)]
+    fn new() -> LuaResult<Self> {
+        Ok(Self {
+            ctrl_c: tokio::signal::windows::ctrl_c()?,
+        })
+    }
+
+    #[cfg(unix)]
+    async fn recv(&mut self) -> InterruptSignal {
+        select! {
+            _ = self.interrupt.recv() => InterruptSignal::Interrupt,
+            _ = self.terminate.recv() => InterruptSignal::Terminate,
+        }
+    }
+
+    #[cfg(windows)]
+    async fn recv(&mut self) -> InterruptSignal {
+        self.ctrl_c.recv().await;
+        InterruptSignal::Interrupt
+    }
+}
+
+/*
+    NOTE: Signal handlers are installed once by tokio, and are then never removed,
+    even after all listeners have been dropped, which would make the process ignore
+    any following signals instead of shutting down - we restore the actions that were
+    used before tokio installed its handlers whenever we stop listening, which are the
+    default actions unless Lune is embedded in something with handlers of its own, and
+    then restore the handlers from tokio if we start listening again.
+
+    On Windows, tokio lets the default handler run when there are no listeners left.
+*/
+#[cfg(unix)]
+mod actions {
+    use std::{mem::MaybeUninit, ptr, sync::Mutex};
+
+    use nix::libc::{self, c_int};
+
+    const SIGNALS: [c_int; 2] = [libc::SIGINT, libc::SIGTERM];
+
+    struct SavedActions {
+        original: Option<Vec<(c_int, libc::sigaction)>>,
+        handlers: Vec<(c_int, libc::sigaction)>,
+    }
+
+    static SAVED_ACTIONS: Mutex<SavedActions> = Mutex::new(SavedActions {
+        original: None,
+        handlers: Vec::new(),
+    });
+
+    /**
+        Sets the action for the given signal, or only gets it if no action is given,
+        and returns the previous action, or `None` if the signal could not be changed.
+    */
+    fn swap_action(signal: c_int, action: Option<&libc::sigaction>) -> Option<libc::sigaction> {
+        let mut previous = MaybeUninit::<libc::sigaction>::uninit();
+        let action = action.map_or(ptr::null(), ptr::from_ref);
+        // SAFETY: Actions are either read from the OS, or were previously read from it,
+        // so any handlers in them are still valid, and the previous action is written
+        // by the OS whenever the call succeeds
+        unsafe {
+            (libc::sigaction(signal, action, previous.as_mut_ptr()) == 0)
+                .then(|| previous.assume_init())
+        }
+    }
+
+    /**
+        Saves the current actions, before tokio installs its handlers for the first time.
+    */
+    pub fn save_original() {
+        let mut saved = SAVED_ACTIONS
+            .lock()
+            .expect("Signal actions lock was poisoned");
+        if saved.original.is_none() {
+            let original = SIGNALS
+                .iter()
+                .filter_map(|signal| Some((*signal, swap_action(*signal, None)?)))
+                .collect();
+            saved.original = Some(original);
+        }
+    }
+
+    pub fn restore_handlers() {
+        let mut saved = SAVED_ACTIONS
+            .lock()
+            .expect("Signal actions lock was poisoned");
+        for (signal, action) in saved.handlers.drain(..) {
+            swap_action(signal, Some(&action));
+        }
+    }
+
+    pub fn restore_original() {
+        let mut saved = SAVED_ACTIONS
+            .lock()
+            .expect("Signal actions lock was poisoned");
+        if !saved.handlers.is_empty() {
+            return;
+        }
+        let original = saved.original.clone().unwrap_or_default();
+        for (signal, action) in original {
+            if let Some(previous) = swap_action(signal, Some(&action)) {
+                saved.handlers.push((signal, previous));
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Signals {
+    fn drop(&mut self) {
+        actions::restore_original();
+    }
+}
+
+#[derive(Default)]
+struct InterruptHandlers {
+    next_id: usize,
+    handlers: Vec<(usize, LuaRegistryKey)>,
+    listening: bool,
+    changed: Rc<Notify>,
+}
+
+type SharedInterruptHandlers = Rc<RefCell<InterruptHandlers>>;
+
+/**
+    Resets the interrupt handlers when the listener is dropped, which happens
+    either when the listener stops by itself or when the scheduler stops.
+
+    Handlers belong to the script that registered them, and
+    must not be called when a different script gets interrupted.
+*/
+struct ListenerGuard(SharedInterruptHandlers);
+
+impl Drop for ListenerGuard {
+    fn drop(&mut self) {
+        let mut state = self.0.borrow_mut();
+        state.handlers.clear();
+        state.listening = false;
+    }
+}
+
+fn shared_handlers(lua: &Lua) -> SharedInterruptHandlers {
+    if let Some(state) = lua.app_data_ref::<SharedInterruptHandlers>() {
+        return Rc::clone(&state);
+    }
+    let state = SharedInterruptHandlers::default();
+    lua.set_app_data(Rc::clone(&state));
+    state
+}
+
+/**
+    Registers a handler that will be called when the process
+    receives an interrupt signal, such as when pressing Ctrl-C.
+
+    Returns a function that removes the handler when called.
+*/
+pub fn on_interrupt<'lua>(
+    lua: &'lua Lua,
+    handler: LuaFunction<'lua>,
+) -> LuaResult<LuaFunction<'lua>> {
+    let state = shared_handlers(lua);
+    let key = lua.create_registry_value(handler)?;
+
+    // NOTE: Signals must be listened for right away, and not once the listener
+    // first runs, since any signals received in between would be missed
+    let signals = if state.borrow().listening {
+        None
+    } else {
+        Some(Signals::new()?)
+    };
+
+    let id = {
+        let mut state = state.borrow_mut();
+        let id = state.next_id;
+        state.next_id += 1;
+        state.handlers.push((id, key));
+        state.listening = true;
+        id
+    };
+
+    if let Some(signals) = signals {
+        let lua_inner = lua
+            .app_data_ref::<Weak<Lua>>()
+            .expect("Missing weak lua ref")
+            .upgrade()
+            .expect("Lua was dropped unexpectedly");
+        let guard = ListenerGuard(Rc::clone(&state));
+        lua.spawn_local(async move {
+            // NOTE: Any errors here can only happen when out of memory, ignoring them will
+            // make the script behave the same as if no interrupt handlers were registered
+            let _ = listen(&lua_inner, &guard.0, signals).await;
+            drop(guard);
+        });
+    }
+
+    let state = Rc::downgrade(&state);
+    lua.create_function(move |_, (): ()| {
+        if let Some(state) = state.upgrade() {
+            let mut state = state.borrow_mut();
+            state.handlers.retain(|(handler_id, _)| *handler_id != id);
+            state.changed.notify_one();
+        }
+        Ok(())
+    })
+}
+
+async fn listen(lua: &Lua, state: &SharedInterruptHandlers, mut signals: Signals) -> LuaResult<()> {
+    let changed = Rc::clone(&state.borrow().changed);
+
+    // Wait for a signal, and stop listening once all of the handlers have been removed,
+    // since listening forever would otherwise prevent the script from ever completing
+    let signal = loop {
+        select! {
+            signal = signals.recv() => break signal,
+            () = changed.notified() => {
+                if state.borrow().handlers.is_empty() {
+                    return Ok(());
+                }
+            }
+        }
+    };
+
+    // Call all of the handlers on the scheduler, and wait for them to
+    // complete, unless the process is interrupted again in the meantime
+    let handlers = state.borrow_mut().handlers.drain(..).collect::<Vec<_>>();
+    let handler_count = handlers.len();
+    let (completed_tx, mut completed_rx) = unbounded_channel();
+    let completed = lua.create_function(move |_, (): ()| {
+        let _ = completed_tx.send(());
+        Ok(())
+    })?;
+    let wrapper = lua
+        .load(HANDLER_WRAPPER)
+        .set_name("=__process_interrupt_handler")
+        .into_function()?;
+    for (_, key) in handlers {
+        let handler = lua.registry_value::<LuaFunction>(&key)?;
+        lua.push_thread_back(wrapper.clone(), (handler, completed.clone()))?;
+    }
+    let handlers_completed = async {
+        for _ in 0..handler_count {
+            completed_rx.recv().await;
+        }
+    };
+    select! {
+        () = handlers_completed => {}
+        _ = signals.recv() => {}
+    }
+
+    // Once handlers have completed, the script should shut down, same as it
+    // would have done if no handlers were registered for the signal at all
+    let code = signal.exit_code();
+    lua.set_app_data(ScriptExitCode(code));
+    lua.set_exit_code(ExitCode::from(code));
+
+    Ok(())
+}
@@ -7,24 +7,43 @@ use std::{
     },
     path::MAIN_SEPARATOR,
     process::Stdio,
+    time::Duration,
 };
 
 use mlua::prelude::*;
 
-use lune_utils::TableBuilder;
+use lune_utils::{ScriptExitCode, TableBuilder};
 use mlua_luau_scheduler::{Functions, LuaSpawnExt};
 use os_str_bytes::RawOsString;
-use tokio::io::AsyncWriteExt;
+use tokio::{
+    io::AsyncWriteExt,
+    process::Child,
+    sync::{mpsc::unbounded_channel, watch},
+};
 
+mod child;
+mod dotenv;
+mod interrupt;
 mod options;
+mod process_group;
 mod tee_writer;
 mod wait_for_child;
 
-use self::options::ProcessSpawnOptions;
+use self::child::{ChildProcessHandle, ChildProcessReader, ChildProcessWriter};
+use self::dotenv::{load_env, ProcessLoadEnvOptions};
+use self::interrupt::on_interrupt;
+use self::options::{ProcessSpawnOptions, ProcessSpawnOptionsStdin, ProcessSpawnOptionsStdioKind};
+use self::process_group::ChildProcessGroup;
 use self::wait_for_child::{wait_for_child, WaitForChildResult};
 
 use lune_utils::path::get_current_dir;
 
+const EXIT_IMPL_LUA: &str = r"
+local code = ...
+record(code)
+return exit(code)
+";
+
 /**
     Creates the `process` standard library module.
 
@@ -62,9 +81,21 @@ pub fn module(lua: &Lua) -> LuaResult<LuaTable> {
                 .build_readonly()?,
         )?
         .build_readonly()?;
-    // Create our process exit function, the scheduler crate provides this
+    // Create our process exit function, the scheduler crate provides this,
+    // but we also record the exit code so that the runtime can report it
     let fns = Functions::new(lua)?;
-    let process_exit = fns.exit;
+    let exit_env = TableBuilder::new(lua)?
+        .with_function("record", |lua, code: Option<u8>| {
+            lua.set_app_data(ScriptExitCode(code.unwrap_or_default()));
+            Ok(())
+        })?
+        .with_value("exit", fns.exit)?
+        .build_readonly()?;
+    let process_exit = lua
+        .load(EXIT_IMPL_LUA)
+        .set_name("process.exit")
+        .set_environment(exit_env)
+        .into_function()?;
     // Create the full process table
     TableBuilder::new(lua)?
         .with_value("os", os)?
@@ -73,10 +104,47 @@ pub fn module(lua: &Lua) -> LuaResult<LuaTable> {
         .with_value("cwd", cwd_str)?
         .with_value("env", env_tab)?
         .with_value("exit", process_exit)?
+        .with_function("getCwd", process_get_cwd)?
+        .with_function("setCwd", process_set_cwd)?
+        .with_function("loadEnv", process_load_env)?
+        .with_function("getEnv", process_get_env)?
+        .with_function("getEnvNumber", process_get_env_number)?
+        .with_function("getEnvBool", process_get_env_bool)?
+        .with_function("onInterrupt", on_interrupt)?
         .with_async_function("spawn", process_spawn)?
+        .with_function("spawnAsync", process_spawn_async)?
         .build_readonly()
 }
 
+fn process_get_cwd(_: &Lua, (): ()) -> LuaResult<String> {
+    let cwd = env::current_dir().into_lua_err()?;
+    let cwd = dunce::canonicalize(cwd).into_lua_err()?;
+    let mut cwd_str = cwd
+        .to_str()
+        .ok_or_else(|| LuaError::runtime("Current working directory is not valid UTF-8"))?
+        .to_string();
+    if !cwd_str.ends_with(MAIN_SEPARATOR) {
+        cwd_str.push(MAIN_SEPARATOR);
+    }
+    Ok(cwd_str)
+}
+
+fn process_set_cwd(_: &Lua, path: String) -> LuaResult<()> {
+    env::set_current_dir(&path).map_err(|e| {
+        LuaError::runtime(format!(
+            "Failed to change current working directory to '{path}'\n{e}"
+        ))
+    })
+}
+
+fn process_load_env(
+    lua: &Lua,
+    (path, options): (Option<String>, ProcessLoadEnvOptions),
+) -> LuaResult<LuaTable> {
+    let vars = load_env(path.as_deref().unwrap_or(".env"), options)?;
+    TableBuilder::new(lua)?.with_values(vars)?.build()
+}
+
 fn process_env_get<'lua>(
     lua: &'lua Lua,
     (_, key): (LuaValue<'lua>, String),
@@ -123,6 +191,64 @@ fn process_env_set<'lua>(
     }
 }
 
+fn process_get_env<'lua>(
+    lua: &'lua Lua,
+    (key, default): (String, Option<LuaString<'lua>>),
+) -> LuaResult<Option<LuaString<'lua>>> {
+    match env::var_os(key) {
+        Some(value) => {
+            let raw_value = RawOsString::new(value);
+            Ok(Some(lua.create_string(raw_value.to_raw_bytes())?))
+        }
+        None => Ok(default),
+    }
+}
+
+/**
+    Reads the environment variable with the given name for typed getters,
+    treating variables that are empty or only whitespace the same as unset ones.
+*/
+fn read_env_var_trimmed(key: &str) -> LuaResult<Option<String>> {
+    match env::var(key) {
+        Ok(value) if value.trim().is_empty() => Ok(None),
+        Ok(value) => Ok(Some(value.trim().to_string())),
+        Err(env::VarError::NotPresent) => Ok(None),
+        Err(env::VarError::NotUnicode(_)) => Err(LuaError::runtime(format!(
+            "Environment variable '{key}' is not valid UTF-8"
+        ))),
+    }
+}
+
+fn process_get_env_number(
+    _: &Lua,
+    (key, default): (String, Option<f64>),
+) -> LuaResult<Option<f64>> {
+    match read_env_var_trimmed(&key)? {
+        None => Ok(default),
+        Some(value) => value.parse::<f64>().map(Some).map_err(|_| {
+            LuaError::runtime(format!(
+                "Environment variable '{key}' is not a valid number, got '{value}'"
+            ))
+        }),
+    }
+}
+
+fn process_get_env_bool(
+    _: &Lua,
+    (key, default): (String, Option<bool>),
+) -> LuaResult<Option<bool>> {
+    match read_env_var_trimmed(&key)? {
+        None => Ok(default),
+        Some(value) => match value.to_ascii_lowercase().as_str() {
+            "1" | "true" | "yes" | "y" | "on" => Ok(Some(true)),
+            "0" | "false" | "no" | "n" | "off" => Ok(Some(false)),
+            _ => Err(LuaError::runtime(format!(
+                "Environment variable '{key}' is not a valid boolean, got '{value}'"
+            ))),
+        },
+    }
+}
+
 fn process_env_iter<'lua>(
     lua: &'lua Lua,
     (_, ()): (LuaValue<'lua>, ()),
@@ -141,47 +267,127 @@ fn process_env_iter<'lua>(
     })
 }
 
-async fn process_spawn(
-    lua: &Lua,
-    (program, args, options): (String, Option<Vec<String>>, ProcessSpawnOptions),
-) -> LuaResult<LuaTable> {
+async fn process_spawn<'lua>(
+    lua: &'lua Lua,
+    (program, params, options): (String, LuaValue<'lua>, LuaValue<'lua>),
+) -> LuaResult<LuaTable<'lua>> {
+    let (args, options) = ProcessSpawnOptions::from_params(lua, params, options)?;
+    if options.stdio.needs_handle() {
+        return Err(LuaError::RuntimeError(
+            "Streamed stdio and piped stdin are only available using process.spawnAsync"
+                .to_string(),
+        ));
+    }
+
     let res = lua
         .spawn(spawn_command(program, args, options))
         .await
         .expect("Failed to receive result of spawned process");
 
-    /*
-        NOTE: If an exit code was not given by the child process,
-        we default to 1 if it yielded any error output, otherwise 0
-
-        An exit code may be missing if the process was terminated by
-        some external signal, which is the only time we use this default
-    */
-    let code = res
-        .status
-        .code()
-        .unwrap_or(i32::from(!res.stderr.is_empty()));
+    res.into_lua_table(lua)
+}
 
-    // Construct and return a readonly lua table with results
-    TableBuilder::new(lua)?
-        .with_value("ok", code == 0)?
-        .with_value("code", code)?
-        .with_value("stdout", lua.create_string(&res.stdout)?)?
-        .with_value("stderr", lua.create_string(&res.stderr)?)?
-        .build_readonly()
+fn process_spawn_async<'lua>(
+    lua: &'lua Lua,
+    (program, params, options): (String, LuaValue<'lua>, LuaValue<'lua>),
+) -> LuaResult<ChildProcessHandle> {
+    let (args, options) = ProcessSpawnOptions::from_params(lua, params, options)?;
+    spawn_command_with_handle(lua, program, args, options)
 }
 
 async fn spawn_command(
     program: String,
     args: Option<Vec<String>>,
-    mut options: ProcessSpawnOptions,
+    options: ProcessSpawnOptions,
 ) -> LuaResult<WaitForChildResult> {
+    let stdout = options.stdio.stdout;
+    let stderr = options.stdio.stderr;
+    let stderr_to_stdout = options.stderr_to_stdout;
+    let (mut child, stdin, timeout) = create_child(program, args, options)?;
+
+    if let Some(ProcessSpawnOptionsStdin::Bytes(stdin)) = stdin {
+        write_stdin(&mut child, &stdin).await?;
+    }
+
+    wait_for_child(child, stdout, stderr, stderr_to_stdout, None, timeout).await
+}
+
+fn spawn_command_with_handle(
+    lua: &Lua,
+    program: String,
+    args: Option<Vec<String>>,
+    options: ProcessSpawnOptions,
+) -> LuaResult<ChildProcessHandle> {
+    let stdout = options.stdio.stdout;
+    let stderr = options.stdio.stderr;
+    let stderr_to_stdout = options.stderr_to_stdout;
+    let (mut child, stdin, timeout) = create_child(program, args, options)?;
+
+    // Streamed pipes are taken here and given to the user, any other
+    // pipes will be read to completion when waiting for the child
+    let stdout_reader = (stdout == ProcessSpawnOptionsStdioKind::Stream)
+        .then(|| child.stdout.take().map(ChildProcessReader::new))
+        .flatten();
+    let stderr_reader = (stderr == ProcessSpawnOptionsStdioKind::Stream)
+        .then(|| child.stderr.take().map(ChildProcessReader::new))
+        .flatten();
+    let stdin_writer = (stdin == Some(ProcessSpawnOptionsStdin::Pipe))
+        .then(|| child.stdin.take().map(ChildProcessWriter::new))
+        .flatten();
+
+    let pid = child.id();
+    let (kill_tx, kill_rx) = unbounded_channel();
+    let (result_tx, result_rx) = watch::channel(None);
+    lua.spawn(async move {
+        if let Some(ProcessSpawnOptionsStdin::Bytes(stdin)) = stdin {
+            if let Err(e) = write_stdin(&mut child, &stdin).await {
+                result_tx.send(Some(Err(e))).ok();
+                return;
+            }
+        }
+        let res = wait_for_child(
+            child,
+            stdout,
+            stderr,
+            stderr_to_stdout,
+            Some(kill_rx),
+            timeout,
+        )
+        .await;
+        result_tx.send(Some(res)).ok();
+    })
+    .detach();
+
+    Ok(ChildProcessHandle {
+        pid,
+        stdin: stdin_writer,
+        stdout: stdout_reader,
+        stderr: stderr_reader,
+        kill: kill_tx,
+        result: result_rx,
+    })
+}
+
+type ChildTimeout = Option<(Duration, ChildProcessGroup)>;
+
+fn create_child(
+    program: String,
+    args: Option<Vec<String>>,
+    mut options: ProcessSpawnOptions,
+) -> LuaResult<(Child, Option<ProcessSpawnOptionsStdin>, ChildTimeout)> {
     let stdout = options.stdio.stdout;
     let stderr = options.stdio.stderr;
     let stdin = options.stdio.stdin.take();
+    let timeout = options.timeout;
 
-    let mut child = options
-        .into_command(program, args)
+    // NOTE: Child processes are only put into their own group when using a
+    // timeout, so that they otherwise still receive signals such as ctrl+c
+    let mut cmd = options.into_command(program, args);
+    if timeout.is_some() {
+        ChildProcessGroup::configure(&mut cmd);
+    }
+
+    let child = cmd
         .stdin(if stdin.is_some() {
             Stdio::piped()
         } else {
@@ -191,10 +397,13 @@ async fn spawn_command(
         .stderr(stderr.as_stdio())
         .spawn()?;
 
-    if let Some(stdin) = stdin {
-        let mut child_stdin = child.stdin.take().unwrap();
-        child_stdin.write_all(&stdin).await.into_lua_err()?;
-    }
+    let timeout = timeout.map(|timeout| (timeout, ChildProcessGroup::new(&child)));
+    Ok((child, stdin, timeout))
+}
 
-    wait_for_child(child, stdout, stderr).await
+async fn write_stdin(child: &mut Child, stdin: &[u8]) -> LuaResult<()> {
+    // NOTE: The child stdin handle is dropped after writing,
+    // which closes it and lets the child process read until EOF
+    let mut child_stdin = child.stdin.take().unwrap();
+    child_stdin.write_all(stdin).await.into_lua_err()
 }
@@ -11,11 +11,18 @@ pub enum ProcessSpawnOptionsStdioKind {
     Forward,
     Inherit,
     None,
+    Stream,
 }
 
 impl ProcessSpawnOptionsStdioKind {
     pub fn all() -> &'static [Self] {
-        &[Self::Default, Self::Forward, Self::Inherit, Self::None]
+        &[
+            Self::Default,
+            Self::Forward,
+            Self::Inherit,
+            Self::None,
+            Self::Stream,
+        ]
     }
 
     pub fn as_stdio(self) -> Stdio {
@@ -34,6 +41,7 @@ impl fmt::Display for ProcessSpawnOptionsStdioKind {
             Self::Forward => "forward",
             Self::Inherit => "inherit",
             Self::None => "none",
+            Self::Stream => "stream",
         };
         f.write_str(s)
     }
@@ -47,6 +55,7 @@ impl FromStr for ProcessSpawnOptionsStdioKind {
             "forward" => Self::Forward,
             "inherit" => Self::Inherit,
             "none" => Self::None,
+            "stream" => Self::Stream,
             _ => {
                 return Err(LuaError::RuntimeError(format!(
                     "Invalid spawn options stdio kind - got '{}', expected one of {}",
@@ -1,7 +1,8 @@
 use std::{
     collections::HashMap,
     env::{self},
-    path::PathBuf,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
 };
 
 use directories::UserDirs;
@@ -18,8 +19,11 @@ pub(super) use stdio::*;
 pub(super) struct ProcessSpawnOptions {
     pub cwd: Option<PathBuf>,
     pub envs: HashMap<String, String>,
+    pub clear_env: bool,
     pub shell: Option<String>,
     pub stdio: ProcessSpawnOptionsStdio,
+    pub stderr_to_stdout: bool,
+    pub timeout: Option<Duration>,
 }
 
 impl<'lua> FromLua<'lua> for ProcessSpawnOptions {
@@ -92,6 +96,20 @@ impl<'lua> FromLua<'lua> for ProcessSpawnOptions {
             }
         }
 
+        /*
+            If we want to clear the environment, make sure it is a boolean
+        */
+        match value.get("clearEnv")? {
+            LuaValue::Nil => {}
+            LuaValue::Boolean(b) => this.clear_env = b,
+            value => {
+                return Err(LuaError::RuntimeError(format!(
+                    "Invalid type for option 'clearEnv' - expected 'boolean', got '{}'",
+                    value.type_name()
+                )))
+            }
+        }
+
         /*
             If we got a shell to use:
 
@@ -104,7 +122,7 @@ impl<'lua> FromLua<'lua> for ProcessSpawnOptions {
             LuaValue::Boolean(true) => {
                 this.shell = match env::consts::FAMILY {
                     "unix" => Some("/bin/sh".to_string()),
-                    "windows" => Some("powershell".to_string()),
+                    "windows" => Some("cmd".to_string()),
                     _ => None,
                 };
             }
@@ -117,27 +135,60 @@ impl<'lua> FromLua<'lua> for ProcessSpawnOptions {
         }
 
         /*
-            If we got options for stdio handling, parse those as well - note that
-            we accept a separate "stdin" value here for compatibility with older
-            scripts, but the user should preferrably pass it in the stdio table
+            If we want stderr to be merged into stdout, make sure it is a boolean
         */
-        this.stdio = value.get("stdio")?;
-        match value.get("stdin")? {
+        match value.get("stderrToStdout")? {
             LuaValue::Nil => {}
-            LuaValue::String(s) => this.stdio.stdin = Some(s.as_bytes().to_vec()),
+            LuaValue::Boolean(b) => this.stderr_to_stdout = b,
             value => {
                 return Err(LuaError::RuntimeError(format!(
-                    "Invalid type for option 'stdin' - expected 'string', got '{}'",
+                    "Invalid type for option 'stderrToStdout' - expected 'boolean', got '{}'",
                     value.type_name()
                 )))
             }
         }
 
+        /*
+            If we got a timeout, make sure it is a positive number of seconds
+        */
+        this.timeout = parse_timeout(value.get("timeout")?)?;
+
+        /*
+            If we got options for stdio handling, parse those as well - note that
+            we accept a separate "stdin" value here for compatibility with older
+            scripts, but the user should preferrably pass it in the stdio table
+        */
+        this.stdio = value.get("stdio")?;
+        if let Some(stdin) = value.get("stdin")? {
+            this.stdio.stdin = Some(stdin);
+        }
+
         Ok(this)
     }
 }
 
 impl ProcessSpawnOptions {
+    /**
+        Parses the parameters and options given to a spawn function.
+
+        Options may also be given in place of the parameters, such as for
+        `process.spawn("ls | wc -l", { shell = true })`, which is detected
+        by the table not being an array and no options being given after it.
+    */
+    pub fn from_params<'lua>(
+        lua: &'lua Lua,
+        params: LuaValue<'lua>,
+        options: LuaValue<'lua>,
+    ) -> LuaResult<(Option<Vec<String>>, Self)> {
+        if let (LuaValue::Table(t), LuaValue::Nil) = (&params, &options) {
+            if t.raw_len() == 0 && t.clone().pairs::<LuaValue, LuaValue>().next().is_some() {
+                return Ok((None, Self::from_lua(params, lua)?));
+            }
+        }
+        let params = Option::<Vec<String>>::from_lua(params, lua)?;
+        Ok((params, Self::from_lua(options, lua)?))
+    }
+
     pub fn into_command(self, program: impl Into<String>, args: Option<Vec<String>>) -> Command {
         let mut program = program.into();
 
@@ -145,9 +196,10 @@ impl ProcessSpawnOptions {
         let pargs = match self.shell {
             None => args,
             Some(shell) => {
+                let flag = shell_command_flag(&shell).to_string();
                 let shell_args = match args {
-                    Some(args) => vec!["-c".to_string(), format!("{} {}", program, args.join(" "))],
-                    None => vec!["-c".to_string(), program.to_string()],
+                    Some(args) => vec![flag, format!("{} {}", program, args.join(" "))],
+                    None => vec![flag, program.to_string()],
                 };
                 program = shell.to_string();
                 Some(shell_args)
@@ -168,6 +220,9 @@ impl ProcessSpawnOptions {
         if let Some(cwd) = self.cwd {
             cmd.current_dir(cwd);
         }
+        if self.clear_env {
+            cmd.env_clear();
+        }
         if !self.envs.is_empty() {
             cmd.envs(self.envs);
         }
@@ -175,3 +230,46 @@ impl ProcessSpawnOptions {
         cmd
     }
 }
+
+fn parse_timeout(value: LuaValue) -> LuaResult<Option<Duration>> {
+    let seconds = match value {
+        LuaValue::Nil => return Ok(None),
+        LuaValue::Integer(i) => f64::from(i),
+        LuaValue::Number(n) => n,
+        value => {
+            return Err(LuaError::RuntimeError(format!(
+                "Invalid type for option 'timeout' - expected 'number', got '{}'",
+                value.type_name()
+            )))
+        }
+    };
+    // NOTE: Timeouts must also be representable as deadlines, which have a
+    // smaller maximum than durations do, and that maximum depends on the OS
+    Duration::try_from_secs_f64(seconds)
+        .ok()
+        .filter(|duration| !duration.is_zero())
+        .filter(|duration| Instant::now().checked_add(*duration).is_some())
+        .map(Some)
+        .ok_or_else(|| {
+            LuaError::runtime(format!(
+                "Invalid value for option 'timeout' - expected a positive number of seconds, got '{seconds}'"
+            ))
+        })
+}
+
+/**
+    Returns the flag used to pass a command line to the given shell.
+
+    Most shells, including powershell, use `-c`, but `cmd` uses `/C` instead.
+*/
+fn shell_command_flag(shell: &str) -> &'static str {
+    let is_cmd = Path::new(shell)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .is_some_and(|stem| stem.eq_ignore_ascii_case("cmd"));
+    if is_cmd {
+        "/C"
+    } else {
+        "-c"
+    }
+}
@@ -1,12 +1,50 @@
 use mlua::prelude::*;
 
+use lune_utils::buffer;
+
 use super::kind::ProcessSpawnOptionsStdioKind;
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProcessSpawnOptionsStdin {
+    Bytes(Vec<u8>),
+    Pipe,
+}
+
+impl<'lua> FromLua<'lua> for ProcessSpawnOptionsStdin {
+    fn from_lua(value: LuaValue<'lua>, lua: &'lua Lua) -> LuaResult<Self> {
+        if let LuaValue::String(s) = &value {
+            if s.as_bytes() == b"pipe" {
+                return Ok(Self::Pipe);
+            }
+        }
+        match buffer::from_lua(lua, value.clone()) {
+            Ok(bytes) => Ok(Self::Bytes(bytes)),
+            Err(_) => Err(LuaError::RuntimeError(format!(
+                "Invalid type for option 'stdin' - expected 'string' or 'buffer', got '{}'",
+                value.type_name()
+            ))),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct ProcessSpawnOptionsStdio {
     pub stdout: ProcessSpawnOptionsStdioKind,
     pub stderr: ProcessSpawnOptionsStdioKind,
-    pub stdin: Option<Vec<u8>>,
+    pub stdin: Option<ProcessSpawnOptionsStdin>,
+}
+
+impl ProcessSpawnOptionsStdio {
+    /**
+        Returns `true` if any of the stdio streams must be given to the
+        user while the child process is running, meaning that spawning
+        should return a handle to the child process without waiting.
+    */
+    pub fn needs_handle(&self) -> bool {
+        self.stdout == ProcessSpawnOptionsStdioKind::Stream
+            || self.stderr == ProcessSpawnOptionsStdioKind::Stream
+            || self.stdin == Some(ProcessSpawnOptionsStdin::Pipe)
+    }
 }
 
 impl From<ProcessSpawnOptionsStdioKind> for ProcessSpawnOptionsStdio {
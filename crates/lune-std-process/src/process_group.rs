@@ -0,0 +1,148 @@
+use std::io;
+
+use tokio::process::{Child, Command};
+
+/**
+    A group containing a child process and any processes that it spawns in turn,
+    so that all of them can be killed at once, such as when a timeout is reached.
+
+    On Unix, the child process is put into a new process group, which its own child processes
+    are also part of unless they create a new group themselves. On Windows, the child process
+    is assigned to a job object, which any processes that it spawns are also assigned to.
+*/
+#[derive(Debug)]
+pub(super) struct ChildProcessGroup {
+    #[cfg(unix)]
+    pgid: Option<i32>,
+    #[cfg(windows)]
+    job: Option<windows::JobObject>,
+}
+
+impl ChildProcessGroup {
+    /**
+        Configures the given command to run in a new group, which
+        must be done before spawning it to create a group using `new`.
+    */
+    pub fn configure(cmd: &mut Command) {
+        #[cfg(unix)]
+        {
+            use nix::unistd::{setpgid, Pid};
+
+            // NOTE: We only create a new process group here, and not a new session
+            // using setsid, so that the child stays attached to the same terminal
+            // SAFETY: setpgid is async-signal-safe, and this closure does not allocate
+            unsafe {
+                cmd.pre_exec(|| {
+                    setpgid(Pid::from_raw(0), Pid::from_raw(0)).map_err(io::Error::from)
+                });
+            }
+        }
+        #[cfg(not(unix))]
+        {
+            let _ = cmd;
+        }
+    }
+
+    /**
+        Creates a group for the given child process, which
+        was spawned using a command given to `configure`.
+    */
+    pub fn new(child: &Child) -> Self {
+        Self {
+            #[cfg(unix)]
+            pgid: child.id().and_then(|pid| i32::try_from(pid).ok()),
+            #[cfg(windows)]
+            job: windows::JobObject::new(child),
+        }
+    }
+
+    /**
+        Forcefully kills the child process, along with all other processes in its group.
+
+        Falls back to only killing the child process if the group could not be created,
+        or on platforms where killing groups of processes is not supported.
+    */
+    pub fn kill(&self, child: &mut Child) -> io::Result<()> {
+        #[cfg(unix)]
+        if let Some(pgid) = self.pgid {
+            use nix::{
+                sys::signal::{killpg, Signal},
+                unistd::Pid,
+            };
+
+            if killpg(Pid::from_raw(pgid), Signal::SIGKILL).is_ok() {
+                return Ok(());
+            }
+        }
+        #[cfg(windows)]
+        if let Some(job) = &self.job {
+            if job.terminate().is_ok() {
+                return Ok(());
+            }
+        }
+        child.start_kill()
+    }
+}
+
+#[cfg(windows)]
+mod windows {
+    use std::{io, ptr};
+
+    use tokio::process::Child;
+    use windows_sys::Win32::{
+        Foundation::{CloseHandle, HANDLE},
+        System::JobObjects::{AssignProcessToJobObject, CreateJobObjectW, TerminateJobObject},
+    };
+
+    /**
+        An owned handle to a job object, which is closed when dropped.
+    */
+    #[derive(Debug)]
+    pub(super) struct JobObject(HANDLE);
+
+    impl JobObject {
+        /**
+            Creates a new job object, and assigns the given child process to it.
+
+            NOTE: Any processes that the child process spawns before it has been
+            assigned to the job object are not part of it, and will not be killed.
+        */
+        pub fn new(child: &Child) -> Option<Self> {
+            let process = child.raw_handle()? as HANDLE;
+            // SAFETY: Both of these calls only use handles that are valid,
+            // and the job handle is owned by the returned job object
+            unsafe {
+                let job = CreateJobObjectW(ptr::null(), ptr::null());
+                if job == 0 {
+                    return None;
+                }
+                let job = Self(job);
+                if AssignProcessToJobObject(job.0, process) == 0 {
+                    return None;
+                }
+                Some(job)
+            }
+        }
+
+        /**
+            Terminates all processes in the job object, using the exit code 124.
+        */
+        pub fn terminate(&self) -> io::Result<()> {
+            // SAFETY: The job handle is valid for as long as this job object exists
+            if unsafe { TerminateJobObject(self.0, 124) } == 0 {
+                Err(io::Error::last_os_error())
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    impl Drop for JobObject {
+        fn drop(&mut self) {
+            // SAFETY: The job handle is valid and owned by this job object
+            unsafe {
+                CloseHandle(self.0);
+            }
+        }
+    }
+}
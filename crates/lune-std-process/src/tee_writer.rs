@@ -1,6 +1,6 @@
 use std::{
-    io::Write,
     pin::Pin,
+    sync::{Arc, Mutex},
     task::{Context, Poll},
 };
 
@@ -14,22 +14,22 @@ where
 {
     #[pin]
     writer: &'a mut W,
-    buffer: Vec<u8>,
+    buffer: Arc<Mutex<Vec<u8>>>,
 }
 
 impl<'a, W> AsyncTeeWriter<'a, W>
 where
     W: AsyncWrite + Unpin,
 {
-    pub fn new(writer: &'a mut W) -> Self {
-        Self {
-            writer,
-            buffer: Vec::new(),
-        }
-    }
+    /**
+        Creates a new tee writer that writes to the given writer,
+        and also appends all of the written bytes to the given buffer.
 
-    pub fn into_vec(self) -> Vec<u8> {
-        self.buffer
+        The buffer may be shared, such as for writing the
+        contents of several streams into the same buffer.
+    */
+    pub fn new(writer: &'a mut W, buffer: Arc<Mutex<Vec<u8>>>) -> Self {
+        Self { writer, buffer }
     }
 }
 
@@ -45,9 +45,13 @@ where
         let mut this = self.project();
         match this.writer.as_mut().poll_write(cx, buf) {
             Poll::Ready(res) => {
-                this.buffer
-                    .write_all(buf)
-                    .expect("Failed to write to internal tee buffer");
+                // NOTE: Only the bytes that were actually written should be added to the buffer
+                if let Ok(written) = &res {
+                    this.buffer
+                        .lock()
+                        .expect("Failed to lock internal tee buffer")
+                        .extend_from_slice(&buf[..*written]);
+                }
                 Poll::Ready(res)
             }
             Poll::Pending => Poll::Pending,
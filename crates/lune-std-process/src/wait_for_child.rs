@@ -1,73 +1,194 @@
-use std::process::ExitStatus;
+use std::{
+    future::pending,
+    process::ExitStatus,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
 use mlua::prelude::*;
 use tokio::{
-    io::{self, AsyncRead, AsyncReadExt},
+    io::{self, AsyncRead, AsyncWrite},
     process::Child,
+    select,
+    sync::mpsc::UnboundedReceiver,
     task,
+    time::{sleep_until, Instant},
 };
 
-use super::{options::ProcessSpawnOptionsStdioKind, tee_writer::AsyncTeeWriter};
+use lune_utils::TableBuilder;
+
+use super::{
+    child::{kill_child, ChildProcessKillRequest},
+    options::ProcessSpawnOptionsStdioKind,
+    process_group::ChildProcessGroup,
+    tee_writer::AsyncTeeWriter,
+};
 
 #[derive(Debug, Clone)]
 pub(super) struct WaitForChildResult {
     pub status: ExitStatus,
     pub stdout: Vec<u8>,
     pub stderr: Vec<u8>,
+    pub timed_out: bool,
 }
 
-async fn read_with_stdio_kind<R>(
+impl WaitForChildResult {
+    pub fn into_lua_table(self, lua: &Lua) -> LuaResult<LuaTable> {
+        /*
+            NOTE: If an exit code was not given by the child process,
+            we default to 1 if it yielded any error output, otherwise 0
+
+            An exit code may be missing if the process was terminated by
+            some external signal, which is the only time we use this default
+
+            Child processes that were killed due to a timeout always get the
+            exit code 124, same as when using the `timeout` command on Unix
+        */
+        let code = if self.timed_out {
+            124
+        } else {
+            self.status
+                .code()
+                .unwrap_or(i32::from(!self.stderr.is_empty()))
+        };
+
+        // Construct and return a readonly lua table with results
+        TableBuilder::new(lua)?
+            .with_value("ok", code == 0)?
+            .with_value("code", code)?
+            .with_value("timedOut", self.timed_out)?
+            .with_value("stdout", lua.create_string(&self.stdout)?)?
+            .with_value("stderr", lua.create_string(&self.stderr)?)?
+            .build_readonly()
+    }
+}
+
+type SharedBuffer = Arc<Mutex<Vec<u8>>>;
+
+async fn read_with_stdio_kind<R, W>(
     read_from: Option<R>,
     kind: ProcessSpawnOptionsStdioKind,
-) -> LuaResult<Vec<u8>>
+    mut forward_to: W,
+    buffer: SharedBuffer,
+) -> LuaResult<()>
 where
     R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
 {
-    Ok(match kind {
-        ProcessSpawnOptionsStdioKind::None | ProcessSpawnOptionsStdioKind::Forward => Vec::new(),
+    match kind {
+        // NOTE: Streamed output is read directly by the user, and never collected here
+        ProcessSpawnOptionsStdioKind::None
+        | ProcessSpawnOptionsStdioKind::Forward
+        | ProcessSpawnOptionsStdioKind::Stream => {}
         ProcessSpawnOptionsStdioKind::Default => {
             let mut read_from =
                 read_from.expect("read_from must be Some when stdio kind is Default");
 
-            let mut buffer = Vec::new();
+            let mut sink = io::sink();
+            let mut tee = AsyncTeeWriter::new(&mut sink, buffer);
 
-            read_from.read_to_end(&mut buffer).await.into_lua_err()?;
-
-            buffer
+            io::copy(&mut read_from, &mut tee).await.into_lua_err()?;
         }
         ProcessSpawnOptionsStdioKind::Inherit => {
             let mut read_from =
                 read_from.expect("read_from must be Some when stdio kind is Inherit");
 
-            let mut stdout = io::stdout();
-            let mut tee = AsyncTeeWriter::new(&mut stdout);
+            let mut tee = AsyncTeeWriter::new(&mut forward_to, buffer);
 
             io::copy(&mut read_from, &mut tee).await.into_lua_err()?;
-
-            tee.into_vec()
         }
-    })
+    }
+    Ok(())
 }
 
 pub(super) async fn wait_for_child(
     mut child: Child,
     stdout_kind: ProcessSpawnOptionsStdioKind,
     stderr_kind: ProcessSpawnOptionsStdioKind,
+    stderr_to_stdout: bool,
+    mut kill_requests: Option<UnboundedReceiver<ChildProcessKillRequest>>,
+    timeout: Option<(Duration, ChildProcessGroup)>,
 ) -> LuaResult<WaitForChildResult> {
     let stdout_opt = child.stdout.take();
     let stderr_opt = child.stderr.take();
 
-    let stdout_task = task::spawn(read_with_stdio_kind(stdout_opt, stdout_kind));
-    let stderr_task = task::spawn(read_with_stdio_kind(stderr_opt, stderr_kind));
+    // NOTE: When merging stderr into stdout, both streams write to the same
+    // buffer, in the order that their output is read from the child process
+    let stdout_buffer = SharedBuffer::default();
+    let stderr_buffer = if stderr_to_stdout {
+        Arc::clone(&stdout_buffer)
+    } else {
+        SharedBuffer::default()
+    };
+
+    let stdout_task = task::spawn(read_with_stdio_kind(
+        stdout_opt,
+        stdout_kind,
+        io::stdout(),
+        Arc::clone(&stdout_buffer),
+    ));
+    let stderr_task = task::spawn(read_with_stdio_kind(
+        stderr_opt,
+        stderr_kind,
+        io::stderr(),
+        Arc::clone(&stderr_buffer),
+    ));
+
+    // NOTE: The child can only be killed while we are still waiting for it, since
+    // its process id may be reused by some other process once it has been reaped
+    let deadline = timeout
+        .as_ref()
+        .and_then(|(duration, _)| Instant::now().checked_add(*duration));
+    let mut timed_out = false;
+    let status = loop {
+        let next_kill_request = async {
+            match kill_requests.as_mut() {
+                Some(kill_requests) => kill_requests.recv().await,
+                None => pending().await,
+            }
+        };
+        let timeout_reached = async {
+            match deadline {
+                Some(deadline) => sleep_until(deadline).await,
+                None => pending().await,
+            }
+        };
+        select! {
+            status = child.wait() => break status,
+            Some(request) = next_kill_request => {
+                let res = kill_child(&mut child, request.signal);
+                request.result.send(res).ok();
+            }
+            () = timeout_reached, if !timed_out => {
+                // NOTE: We keep waiting for the child after killing it, which
+                // also lets any output written before being killed be read
+                timed_out = true;
+                if let Some((_, group)) = &timeout {
+                    group.kill(&mut child).ok();
+                }
+            }
+        }
+    }
+    .expect("Child process failed to start");
+
+    stdout_task.await.into_lua_err()??;
+    stderr_task.await.into_lua_err()??;
 
-    let status = child.wait().await.expect("Child process failed to start");
+    let take_buffer = |buffer: SharedBuffer| {
+        std::mem::take(&mut *buffer.lock().expect("Failed to lock output buffer"))
+    };
 
-    let stdout_buffer = stdout_task.await.into_lua_err()??;
-    let stderr_buffer = stderr_task.await.into_lua_err()??;
+    let stdout = take_buffer(stdout_buffer);
+    let stderr = if stderr_to_stdout {
+        Vec::new()
+    } else {
+        take_buffer(stderr_buffer)
+    };
 
     Ok(WaitForChildResult {
         status,
-        stdout: stdout_buffer,
-        stderr: stderr_buffer,
+        stdout,
+        stderr,
+        timed_out,
     })
 }
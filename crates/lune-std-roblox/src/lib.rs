@@ -6,7 +6,12 @@ use once_cell::sync::OnceCell;
 
 use lune_roblox::{
     document::{Document, DocumentError, DocumentFormat, DocumentKind},
-    instance::{registry::InstanceRegistry, Instance},
+    instance::{
+        diff::{diff_instances, InstanceChange},
+        ensure_not_destroyed,
+        registry::InstanceRegistry,
+        Instance,
+    },
     reflection::Database as ReflectionDatabase,
 };
 
@@ -35,6 +40,7 @@ pub fn module(lua: &Lua) -> LuaResult<LuaTable> {
         .with_async_function("deserializeModel", deserialize_model)?
         .with_async_function("serializePlace", serialize_place)?
         .with_async_function("serializeModel", serialize_model)?
+        .with_function("diff", diff)?
         .with_function("getAuthCookie", get_auth_cookie)?
         .with_function("getReflectionDatabase", get_reflection_database)?
         .with_function("implementProperty", implement_property)?
@@ -102,6 +108,15 @@ async fn serialize_model<'lua>(
     lua.create_string(bytes)
 }
 
+fn diff(
+    _: &Lua,
+    (before, after): (LuaUserDataRef<Instance>, LuaUserDataRef<Instance>),
+) -> LuaResult<Vec<InstanceChange>> {
+    ensure_not_destroyed(&before)?;
+    ensure_not_destroyed(&after)?;
+    diff_instances(&before, &after)
+}
+
 fn get_auth_cookie(_: &Lua, raw: Option<bool>) -> LuaResult<Option<String>> {
     if matches!(raw, Some(true)) {
         Ok(rbx_cookie::get_value())
@@ -0,0 +1,105 @@
+use mlua::prelude::*;
+
+use base64::{
+    alphabet::{STANDARD, URL_SAFE},
+    engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig},
+    Engine as _,
+};
+
+/**
+    Options for encoding and decoding base64.
+*/
+#[derive(Debug, Clone, Copy)]
+pub struct Base64Options {
+    pub url_safe: bool,
+    pub padding: bool,
+}
+
+impl Base64Options {
+    fn engine(self) -> GeneralPurpose {
+        // NOTE: Padding is only ever added when encoding, decoding
+        // should accept both padded and unpadded strings alike
+        let config = GeneralPurposeConfig::new()
+            .with_encode_padding(self.padding)
+            .with_decode_padding_mode(DecodePaddingMode::Indifferent);
+        let alphabet = if self.url_safe { &URL_SAFE } else { &STANDARD };
+        GeneralPurpose::new(alphabet, config)
+    }
+}
+
+impl Default for Base64Options {
+    fn default() -> Self {
+        Self {
+            url_safe: false,
+            padding: true,
+        }
+    }
+}
+
+impl<'lua> FromLua<'lua> for Base64Options {
+    fn from_lua(value: LuaValue<'lua>, _: &'lua Lua) -> LuaResult<Self> {
+        match value {
+            LuaValue::Nil => Ok(Self::default()),
+            LuaValue::Table(t) => {
+                let defaults = Self::default();
+                Ok(Self {
+                    url_safe: t
+                        .get::<_, Option<bool>>("urlSafe")?
+                        .unwrap_or(defaults.url_safe),
+                    padding: t
+                        .get::<_, Option<bool>>("padding")?
+                        .unwrap_or(defaults.padding),
+                })
+            }
+            _ => Err(LuaError::FromLuaConversionError {
+                from: value.type_name(),
+                to: "Base64Options",
+                message: Some(format!(
+                    "Invalid base64 options - expected table, got {}",
+                    value.type_name()
+                )),
+            }),
+        }
+    }
+}
+
+/**
+    Encodes the given bytes as a base64 string, using the specified options.
+*/
+#[must_use]
+pub fn base64_encode(bytes: impl AsRef<[u8]>, options: Base64Options) -> String {
+    options.engine().encode(bytes)
+}
+
+/**
+    Decodes the given base64 string into bytes, using the specified options.
+
+    # Errors
+
+    Errors when the given string is not valid base64.
+*/
+pub fn base64_decode(encoded: impl AsRef<[u8]>, options: Base64Options) -> LuaResult<Vec<u8>> {
+    options
+        .engine()
+        .decode(encoded)
+        .map_err(|e| LuaError::runtime(format!("Failed to decode base64 - {e}")))
+}
+
+/**
+    Encodes the given bytes as a lowercase hex string.
+*/
+#[must_use]
+pub fn hex_encode(bytes: impl AsRef<[u8]>) -> String {
+    hex::encode(bytes)
+}
+
+/**
+    Decodes the given hex string into bytes. Both uppercase and lowercase hex are accepted.
+
+    # Errors
+
+    Errors when the given string is not valid hex.
+*/
+pub fn hex_decode(encoded: impl AsRef<[u8]>) -> LuaResult<Vec<u8>> {
+    hex::decode(encoded).map_err(|e| LuaError::runtime(format!("Failed to decode hex - {e}")))
+}
@@ -0,0 +1,220 @@
+use bstr::BString;
+use mlua::prelude::*;
+
+use lune_utils::buffer;
+
+/**
+    Reads numbers, bytes, and strings from binary data, keeping track of its position.
+
+    Numbers larger than one byte are read as little-endian by default,
+    same as the Luau `buffer` library, or as big-endian when asked to.
+*/
+#[derive(Debug, Clone)]
+pub struct BufferReader {
+    data: Vec<u8>,
+    position: usize,
+}
+
+impl BufferReader {
+    #[must_use]
+    pub fn new(data: impl Into<Vec<u8>>) -> Self {
+        Self {
+            data: data.into(),
+            position: 0,
+        }
+    }
+
+    /**
+        Reads the given number of bytes, and moves past them.
+
+        # Errors
+
+        Errors if there are not enough bytes remaining, without moving.
+    */
+    pub fn read_bytes(&mut self, len: usize) -> LuaResult<&[u8]> {
+        let remaining = self.remaining();
+        if len > remaining {
+            return Err(LuaError::runtime(format!(
+                "Failed to read {len} bytes at position {} - only {remaining} bytes remaining",
+                self.position
+            )));
+        }
+        let start = self.position;
+        self.position += len;
+        Ok(&self.data[start..self.position])
+    }
+
+    fn read_array<const N: usize>(&mut self) -> LuaResult<[u8; N]> {
+        let bytes = self.read_bytes(N)?;
+        Ok(bytes.try_into().expect("read exactly N bytes"))
+    }
+
+    #[must_use]
+    pub fn remaining(&self) -> usize {
+        self.data.len() - self.position
+    }
+}
+
+/**
+    Writes numbers, bytes, and strings to a growing binary buffer.
+
+    Numbers larger than one byte are written as little-endian by default,
+    same as the Luau `buffer` library, or as big-endian when asked to.
+*/
+#[derive(Debug, Clone, Default)]
+pub struct BufferWriter {
+    data: Vec<u8>,
+}
+
+impl BufferWriter {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn write_bytes(&mut self, bytes: impl AsRef<[u8]>) {
+        self.data.extend_from_slice(bytes.as_ref());
+    }
+
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+macro_rules! add_read_methods {
+    ($methods:ident, $($name:literal => $ty:ty),+ $(,)?) => {
+        $(
+            $methods.add_method_mut($name, |_, this, little_endian: Option<bool>| {
+                let bytes = this.read_array()?;
+                let value = if little_endian.unwrap_or(true) {
+                    <$ty>::from_le_bytes(bytes)
+                } else {
+                    <$ty>::from_be_bytes(bytes)
+                };
+                Ok(f64::from(value))
+            });
+        )+
+    };
+}
+
+macro_rules! add_write_int_methods {
+    ($methods:ident, $($name:literal => $ty:ty),+ $(,)?) => {
+        $(
+            $methods.add_method_mut($name, |_, this, (n, little_endian): (f64, Option<bool>)| {
+                // NOTE: Casting saturates, so numbers that are too large for
+                // an i64 are still rejected when converting to the wanted type
+                #[allow(clippy::cast_possible_truncation)]
+                let value = (n.is_finite() && n.fract() == 0.0)
+                    .then(|| <$ty>::try_from(n as i64).ok())
+                    .flatten()
+                    .ok_or_else(|| {
+                        LuaError::runtime(format!(
+                            "Invalid value '{n}' for {} - must be an integer between {} and {}",
+                            stringify!($ty),
+                            <$ty>::MIN,
+                            <$ty>::MAX,
+                        ))
+                    })?;
+                if little_endian.unwrap_or(true) {
+                    this.write_bytes(value.to_le_bytes());
+                } else {
+                    this.write_bytes(value.to_be_bytes());
+                }
+                Ok(())
+            });
+        )+
+    };
+}
+
+impl LuaUserData for BufferReader {
+    fn add_fields<'lua, F: LuaUserDataFields<'lua, Self>>(fields: &mut F) {
+        fields.add_field_method_get("position", |_, this| Ok(this.position));
+        fields.add_field_method_set("position", |_, this, position: usize| {
+            if position > this.data.len() {
+                return Err(LuaError::runtime(format!(
+                    "Invalid position {position} - must be between 0 and {}",
+                    this.data.len()
+                )));
+            }
+            this.position = position;
+            Ok(())
+        });
+        fields.add_field_method_get("len", |_, this| Ok(this.data.len()));
+        fields.add_field_method_get("remaining", |_, this| Ok(this.remaining()));
+    }
+
+    fn add_methods<'lua, M: LuaUserDataMethods<'lua, Self>>(methods: &mut M) {
+        add_read_methods!(methods,
+            "readU8" => u8,
+            "readI8" => i8,
+            "readU16" => u16,
+            "readI16" => i16,
+            "readU32" => u32,
+            "readI32" => i32,
+            "readF32" => f32,
+            "readF64" => f64,
+        );
+        methods.add_method_mut("readBytes", |lua, this, len: usize| {
+            buffer::to_lua(lua, this.read_bytes(len)?)
+        });
+        methods.add_method_mut("readString", |lua, this, len: usize| {
+            lua.create_string(this.read_bytes(len)?)
+        });
+        methods.add_method_mut("skip", |_, this, len: usize| {
+            this.read_bytes(len)?;
+            Ok(())
+        });
+    }
+}
+
+impl LuaUserData for BufferWriter {
+    fn add_fields<'lua, F: LuaUserDataFields<'lua, Self>>(fields: &mut F) {
+        fields.add_field_method_get("len", |_, this| Ok(this.data.len()));
+    }
+
+    fn add_methods<'lua, M: LuaUserDataMethods<'lua, Self>>(methods: &mut M) {
+        add_write_int_methods!(methods,
+            "writeU8" => u8,
+            "writeI8" => i8,
+            "writeU16" => u16,
+            "writeI16" => i16,
+            "writeU32" => u32,
+            "writeI32" => i32,
+        );
+        #[allow(clippy::cast_possible_truncation)]
+        methods.add_method_mut(
+            "writeF32",
+            |_, this, (n, little_endian): (f64, Option<bool>)| {
+                let n = n as f32;
+                if little_endian.unwrap_or(true) {
+                    this.write_bytes(n.to_le_bytes());
+                } else {
+                    this.write_bytes(n.to_be_bytes());
+                }
+                Ok(())
+            },
+        );
+        methods.add_method_mut(
+            "writeF64",
+            |_, this, (n, little_endian): (f64, Option<bool>)| {
+                if little_endian.unwrap_or(true) {
+                    this.write_bytes(n.to_le_bytes());
+                } else {
+                    this.write_bytes(n.to_be_bytes());
+                }
+                Ok(())
+            },
+        );
+        methods.add_method_mut("writeBytes", |_, this, bytes: BString| {
+            this.write_bytes(bytes);
+            Ok(())
+        });
+        methods.add_method_mut("writeString", |_, this, s: LuaString| {
+            this.write_bytes(s.as_bytes());
+            Ok(())
+        });
+        methods.add_method("toBuffer", |lua, this, ()| buffer::to_lua(lua, &this.data));
+        methods.add_method("toString", |lua, this, ()| lua.create_string(&this.data));
+    }
+}
@@ -1,33 +1,111 @@
-use std::io::{copy as copy_std, Cursor, Read as _, Write as _};
+use std::{
+    io::{copy as copy_std, BufReader as BufReaderStd, Cursor, Read, Write as _},
+    ops::RangeInclusive,
+};
 
+use bstr::BString;
 use mlua::prelude::*;
 
 use lz4::{Decoder, EncoderBuilder};
 use tokio::{
-    io::{copy, BufReader},
+    io::{copy, AsyncBufRead, AsyncRead, AsyncReadExt as _, AsyncWrite, BufReader},
     task::spawn_blocking,
 };
+use zstd::{
+    bulk::Compressor as ZstdCompressor, stream::read::Decoder as ZstdDecoder,
+    zstd_safe::get_frame_content_size,
+};
 
 use async_compression::{
     tokio::bufread::{
-        BrotliDecoder, BrotliEncoder, GzipDecoder, GzipEncoder, ZlibDecoder, ZlibEncoder,
+        BrotliDecoder, BrotliEncoder, DeflateDecoder, DeflateEncoder, GzipDecoder, GzipEncoder,
+        ZlibDecoder, ZlibEncoder, ZstdDecoder as AsyncZstdDecoder, ZstdEncoder as AsyncZstdEncoder,
     },
-    Level::Best as CompressionQuality,
+    Level::{self, Best as CompressionQuality},
 };
 
+type AsyncCodecReader<'a> = Box<dyn AsyncRead + Send + Unpin + 'a>;
+
 /**
     A compression and decompression format supported by Lune.
 */
 #[derive(Debug, Clone, Copy)]
 pub enum CompressDecompressFormat {
     Brotli,
+    Deflate,
     GZip,
     LZ4,
     ZLib,
+    Zstd,
 }
 
 #[allow(dead_code)]
 impl CompressDecompressFormat {
+    /**
+        Returns the human-friendly name of this format.
+    */
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Brotli => "brotli",
+            Self::Deflate => "deflate",
+            Self::GZip => "gzip",
+            Self::LZ4 => "lz4",
+            Self::ZLib => "zlib",
+            Self::Zstd => "zstd",
+        }
+    }
+
+    /**
+        Returns the range of compression levels supported by this format, if any.
+    */
+    #[must_use]
+    pub fn level_range(self) -> Option<RangeInclusive<i32>> {
+        match self {
+            Self::Brotli => Some(0..=11),
+            Self::Deflate | Self::GZip | Self::ZLib => Some(0..=9),
+            Self::Zstd => Some(1..=22),
+            Self::LZ4 => None,
+        }
+    }
+
+    /**
+        Creates a streaming encoder for this format, reading from the given reader.
+
+        Returns `None` for formats that are not streamed, and must be compressed all at once.
+    */
+    fn async_encoder<'a>(
+        self,
+        reader: impl AsyncBufRead + Send + Unpin + 'a,
+        level: Level,
+    ) -> Option<AsyncCodecReader<'a>> {
+        Some(match self {
+            Self::Brotli => Box::new(BrotliEncoder::with_quality(reader, level)),
+            Self::Deflate => Box::new(DeflateEncoder::with_quality(reader, level)),
+            Self::GZip => Box::new(GzipEncoder::with_quality(reader, level)),
+            Self::ZLib => Box::new(ZlibEncoder::with_quality(reader, level)),
+            Self::LZ4 | Self::Zstd => return None,
+        })
+    }
+
+    /**
+        Creates a streaming decoder for this format, reading from the given reader.
+
+        Returns `None` for formats that are not streamed, and must be decompressed all at once.
+    */
+    fn async_decoder<'a>(
+        self,
+        reader: impl AsyncBufRead + Send + Unpin + 'a,
+    ) -> Option<AsyncCodecReader<'a>> {
+        Some(match self {
+            Self::Brotli => Box::new(BrotliDecoder::new(reader)),
+            Self::Deflate => Box::new(DeflateDecoder::new(reader)),
+            Self::GZip => Box::new(GzipDecoder::new(reader)),
+            Self::ZLib => Box::new(ZlibDecoder::new(reader)),
+            Self::LZ4 | Self::Zstd => return None,
+        })
+    }
+
     /**
         Detects a supported compression format from the given bytes.
     */
@@ -43,6 +121,8 @@ impl CompressDecompressFormat {
             {
                 Some(Self::LZ4)
             }
+            // https://datatracker.ietf.org/doc/html/rfc8878#section-3.1.1
+            b if b.len() >= 4 && matches!(b[0..4], [0x28, 0xB5, 0x2F, 0xFD]) => Some(Self::Zstd),
             // https://github.com/dropbox/rust-brotli/blob/master/src/enc/brotli_bit_stream.rs#L2805
             b if b.len() >= 4
                 && matches!(
@@ -77,7 +157,8 @@ impl CompressDecompressFormat {
         match header.as_ref().to_ascii_lowercase().trim() {
             "br" | "brotli" => Some(Self::Brotli),
             "deflate" => Some(Self::ZLib),
-            "gz" | "gzip" => Some(Self::GZip),
+            "gz" | "gzip" | "x-gzip" => Some(Self::GZip),
+            "zstd" => Some(Self::Zstd),
             _ => None,
         }
     }
@@ -88,14 +169,16 @@ impl<'lua> FromLua<'lua> for CompressDecompressFormat {
         if let LuaValue::String(s) = &value {
             match s.to_string_lossy().to_ascii_lowercase().trim() {
                 "brotli" => Ok(Self::Brotli),
+                "deflate" => Ok(Self::Deflate),
                 "gzip" => Ok(Self::GZip),
                 "lz4" => Ok(Self::LZ4),
                 "zlib" => Ok(Self::ZLib),
+                "zstd" => Ok(Self::Zstd),
                 kind => Err(LuaError::FromLuaConversionError {
                     from: value.type_name(),
                     to: "CompressDecompressFormat",
                     message: Some(format!(
-                        "Invalid format '{kind}', valid formats are:  brotli, gzip, lz4, zlib"
+                        "Invalid format '{kind}', valid formats are:  brotli, deflate, gzip, lz4, zlib, zstd"
                     )),
                 }),
             }
@@ -109,45 +192,181 @@ impl<'lua> FromLua<'lua> for CompressDecompressFormat {
     }
 }
 
+/**
+    Options for compressing bytes.
+*/
+#[derive(Debug, Clone, Default)]
+pub struct CompressOptions {
+    pub level: Option<i32>,
+    pub dictionary: Option<Vec<u8>>,
+}
+
+impl CompressOptions {
+    /**
+        Checks that the compression level and dictionary, if they
+        were given, are supported by the given format.
+
+        # Errors
+
+        Errors when the compression level is not valid for the
+        format, or when the format does not support dictionaries.
+    */
+    pub fn validate(&self, format: CompressDecompressFormat) -> LuaResult<()> {
+        check_dictionary(self.dictionary.as_ref(), format)?;
+        self.level_for(format).map(|_| ())
+    }
+
+    fn level_for(&self, format: CompressDecompressFormat) -> LuaResult<Option<i32>> {
+        let Some(level) = self.level else {
+            return Ok(None);
+        };
+        match format.level_range() {
+            Some(range) if range.contains(&level) => Ok(Some(level)),
+            Some(range) => Err(LuaError::runtime(format!(
+                "Invalid compression level {level} for format '{}', expected a level between {} and {}",
+                format.name(),
+                range.start(),
+                range.end()
+            ))),
+            None => Err(LuaError::runtime(format!(
+                "Format '{}' does not support compression levels",
+                format.name()
+            ))),
+        }
+    }
+}
+
+impl<'lua> FromLua<'lua> for CompressOptions {
+    fn from_lua(value: LuaValue<'lua>, lua: &'lua Lua) -> LuaResult<Self> {
+        match value {
+            LuaValue::Nil => Ok(Self::default()),
+            LuaValue::Table(t) => Ok(Self {
+                level: t.get("level")?,
+                dictionary: dictionary_from_table(lua, &t)?,
+            }),
+            _ => Err(LuaError::FromLuaConversionError {
+                from: value.type_name(),
+                to: "CompressOptions",
+                message: Some(format!(
+                    "Invalid compress options - expected table, got {}",
+                    value.type_name()
+                )),
+            }),
+        }
+    }
+}
+
+/**
+    Options for decompressing bytes.
+*/
+#[derive(Debug, Clone, Default)]
+pub struct DecompressOptions {
+    pub max_size: Option<usize>,
+    pub dictionary: Option<Vec<u8>>,
+}
+
+impl DecompressOptions {
+    /**
+        Checks that the dictionary, if one was given, is supported by the given format.
+
+        # Errors
+
+        Errors when the format does not support dictionaries.
+    */
+    pub fn validate(&self, format: CompressDecompressFormat) -> LuaResult<()> {
+        check_dictionary(self.dictionary.as_ref(), format)
+    }
+
+    fn check_size(&self, size: u64) -> LuaResult<()> {
+        match self.max_size {
+            Some(max_size) if size > max_size as u64 => Err(LuaError::runtime(format!(
+                "Decompressed size exceeds the maximum size of {max_size} bytes"
+            ))),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl<'lua> FromLua<'lua> for DecompressOptions {
+    fn from_lua(value: LuaValue<'lua>, lua: &'lua Lua) -> LuaResult<Self> {
+        match value {
+            LuaValue::Nil => Ok(Self::default()),
+            LuaValue::Table(t) => Ok(Self {
+                max_size: t.get("maxSize")?,
+                dictionary: dictionary_from_table(lua, &t)?,
+            }),
+            _ => Err(LuaError::FromLuaConversionError {
+                from: value.type_name(),
+                to: "DecompressOptions",
+                message: Some(format!(
+                    "Invalid decompress options - expected table, got {}",
+                    value.type_name()
+                )),
+            }),
+        }
+    }
+}
+
+fn dictionary_from_table(lua: &Lua, t: &LuaTable) -> LuaResult<Option<Vec<u8>>> {
+    match t.get::<_, LuaValue>("dictionary")? {
+        LuaValue::Nil => Ok(None),
+        value @ (LuaValue::String(_) | LuaValue::UserData(_)) => {
+            Ok(Some(BString::from_lua(value, lua)?.into()))
+        }
+        value => Err(LuaError::runtime(format!(
+            "Invalid option value for 'dictionary' - expected string or buffer, got {}",
+            value.type_name()
+        ))),
+    }
+}
+
+fn check_dictionary(
+    dictionary: Option<&Vec<u8>>,
+    format: CompressDecompressFormat,
+) -> LuaResult<()> {
+    match (dictionary, format) {
+        (None, _) | (Some(_), CompressDecompressFormat::Zstd) => Ok(()),
+        (Some(_), _) => Err(LuaError::runtime(format!(
+            "Format '{}' does not support dictionaries",
+            format.name()
+        ))),
+    }
+}
+
 /**
     Compresses the given bytes using the specified format.
 
     # Errors
 
-    Errors when the compression fails.
+    Errors when the compression fails, when the compression level is
+    not valid for the format, or when the format does not support dictionaries.
 */
 pub async fn compress<'lua>(
     source: impl AsRef<[u8]>,
     format: CompressDecompressFormat,
+    options: CompressOptions,
 ) -> LuaResult<Vec<u8>> {
-    if let CompressDecompressFormat::LZ4 = format {
-        let source = source.as_ref().to_vec();
-        return spawn_blocking(move || compress_lz4(source))
-            .await
-            .into_lua_err()?
-            .into_lua_err();
-    }
+    options.validate(format)?;
+    let level = options.level_for(format)?;
 
-    let mut bytes = Vec::new();
-    let reader = BufReader::new(source.as_ref());
-
-    match format {
-        CompressDecompressFormat::Brotli => {
-            let mut encoder = BrotliEncoder::with_quality(reader, CompressionQuality);
-            copy(&mut encoder, &mut bytes).await?;
-        }
-        CompressDecompressFormat::GZip => {
-            let mut encoder = GzipEncoder::with_quality(reader, CompressionQuality);
-            copy(&mut encoder, &mut bytes).await?;
-        }
-        CompressDecompressFormat::ZLib => {
-            let mut encoder = ZlibEncoder::with_quality(reader, CompressionQuality);
-            copy(&mut encoder, &mut bytes).await?;
-        }
-        CompressDecompressFormat::LZ4 => unreachable!(),
+    let source = source.as_ref();
+    let quality = level.map_or(CompressionQuality, Level::Precise);
+    if let Some(mut encoder) = format.async_encoder(BufReader::new(source), quality) {
+        let mut bytes = Vec::new();
+        copy(&mut encoder, &mut bytes).await?;
+        return Ok(bytes);
     }
 
-    Ok(bytes)
+    let source = source.to_vec();
+    spawn_blocking(move || match format {
+        CompressDecompressFormat::LZ4 => compress_lz4(source),
+        CompressDecompressFormat::Zstd => {
+            compress_zstd(&source, level, options.dictionary.as_deref())
+        }
+        _ => unreachable!("format should have an async encoder"),
+    })
+    .await
+    .into_lua_err()?
 }
 
 /**
@@ -155,42 +374,226 @@ pub async fn compress<'lua>(
 
     # Errors
 
-    Errors when the decompression fails.
+    Errors when the decompression fails, when the decompressed bytes exceed
+    the maximum size given, or when the format does not support dictionaries.
 */
 pub async fn decompress<'lua>(
     source: impl AsRef<[u8]>,
     format: CompressDecompressFormat,
+    options: DecompressOptions,
 ) -> LuaResult<Vec<u8>> {
-    if let CompressDecompressFormat::LZ4 = format {
-        let source = source.as_ref().to_vec();
-        return spawn_blocking(move || decompress_lz4(source))
-            .await
-            .into_lua_err()?
-            .into_lua_err();
+    options.validate(format)?;
+    if let Some(size) = decompressed_size(source.as_ref(), format) {
+        options.check_size(size)?;
     }
 
-    let mut bytes = Vec::new();
-    let reader = BufReader::new(source.as_ref());
+    let source = source.as_ref();
+    if let Some(decoder) = format.async_decoder(BufReader::new(source)) {
+        return read_limited(decoder, &options).await;
+    }
+
+    let source = source.to_vec();
+    spawn_blocking(move || match format {
+        CompressDecompressFormat::LZ4 => decompress_lz4(source, &options),
+        CompressDecompressFormat::Zstd => decompress_zstd(source, &options),
+        _ => unreachable!("format should have an async decoder"),
+    })
+    .await
+    .into_lua_err()?
+}
+
+/**
+    Compresses the given bytes using the specified format, and writes
+    the compressed bytes to the given writer as they are produced.
+
+    The compressed bytes are never fully held in memory, except for the `LZ4`
+    format and `Zstd` with a dictionary, which must be compressed all at once.
+
+    # Errors
+
+    Errors when the compression fails, when writing to the writer fails, when the
+    compression level is not valid for the format, or when the format does not
+    support dictionaries.
+*/
+#[allow(clippy::missing_panics_doc)]
+pub async fn compress_to_writer(
+    source: impl AsRef<[u8]>,
+    writer: &mut (impl AsyncWrite + Unpin),
+    format: CompressDecompressFormat,
+    options: CompressOptions,
+) -> LuaResult<()> {
+    options.validate(format)?;
+    let level = options.level_for(format)?;
+
+    let source = source.as_ref();
+    if matches!(format, CompressDecompressFormat::LZ4) || options.dictionary.is_some() {
+        let bytes = compress(source, format, options).await?;
+        copy(&mut bytes.as_slice(), writer).await?;
+        return Ok(());
+    }
 
+    let mut encoder: AsyncCodecReader = match format {
+        // NOTE: The streaming zstd encoder does not store the decompressed size
+        // in the frame header, unlike compressing all at once, which is fine
+        // since it is only used to fail fast when decompressing
+        CompressDecompressFormat::Zstd => Box::new(AsyncZstdEncoder::with_quality(
+            BufReader::new(source),
+            level.map_or(Level::Default, Level::Precise),
+        )),
+        _ => format
+            .async_encoder(
+                BufReader::new(source),
+                level.map_or(CompressionQuality, Level::Precise),
+            )
+            .expect("format should have an async encoder"),
+    };
+
+    copy(&mut encoder, writer).await?;
+    Ok(())
+}
+
+/**
+    Decompresses the bytes read from the given reader using the specified format.
+
+    The compressed bytes are streamed through the decoder instead of being fully
+    held in memory, except for the `LZ4` format and `Zstd` with a dictionary,
+    which must be decompressed all at once.
+
+    # Errors
+
+    Errors when reading from the reader fails, when the decompression fails, when the
+    decompressed bytes exceed the maximum size given, or when the format does not
+    support dictionaries.
+*/
+#[allow(clippy::missing_panics_doc)]
+pub async fn decompress_from_reader(
+    mut reader: impl AsyncBufRead + Send + Unpin,
+    format: CompressDecompressFormat,
+    options: DecompressOptions,
+) -> LuaResult<Vec<u8>> {
+    options.validate(format)?;
+
+    if matches!(format, CompressDecompressFormat::LZ4) || options.dictionary.is_some() {
+        let mut source = Vec::new();
+        reader.read_to_end(&mut source).await?;
+        return decompress(source, format, options).await;
+    }
+
+    let decoder: AsyncCodecReader = match format {
+        CompressDecompressFormat::Zstd => Box::new(AsyncZstdDecoder::new(reader)),
+        _ => format
+            .async_decoder(reader)
+            .expect("format should have an async decoder"),
+    };
+
+    read_limited(decoder, &options).await
+}
+
+/**
+    Trains a zstd dictionary of at most the given size, in bytes, from the given samples.
+
+    The dictionary can then be used to compress and decompress bytes that
+    are similar to the samples, such as many small records of the same kind.
+
+    # Errors
+
+    Errors when no samples were given, or when the dictionary could not be trained,
+    which usually means that there were too few samples, or that they were too small.
+*/
+pub async fn train_zstd_dictionary(samples: Vec<Vec<u8>>, size: usize) -> LuaResult<Vec<u8>> {
+    if samples.is_empty() {
+        return Err(LuaError::runtime(
+            "Failed to train zstd dictionary - no samples were given",
+        ));
+    }
+    if size == 0 {
+        return Err(LuaError::runtime(
+            "Failed to train zstd dictionary - size must be greater than zero",
+        ));
+    }
+
+    spawn_blocking(move || {
+        zstd::dict::from_samples(&samples, size)
+            .map_err(|e| LuaError::runtime(format!("Failed to train zstd dictionary - {e}")))
+    })
+    .await
+    .into_lua_err()?
+}
+
+/**
+    Returns the decompressed size of the given bytes, if it is stored in
+    the compressed bytes. Currently, only the `Zstd` format stores this.
+*/
+#[must_use]
+pub fn decompressed_size(
+    source: impl AsRef<[u8]>,
+    format: CompressDecompressFormat,
+) -> Option<u64> {
     match format {
-        CompressDecompressFormat::Brotli => {
-            let mut decoder = BrotliDecoder::new(reader);
-            copy(&mut decoder, &mut bytes).await?;
-        }
-        CompressDecompressFormat::GZip => {
-            let mut decoder = GzipDecoder::new(reader);
-            copy(&mut decoder, &mut bytes).await?;
-        }
-        CompressDecompressFormat::ZLib => {
-            let mut decoder = ZlibDecoder::new(reader);
-            copy(&mut decoder, &mut bytes).await?;
-        }
-        CompressDecompressFormat::LZ4 => unreachable!(),
+        CompressDecompressFormat::Zstd => get_frame_content_size(source.as_ref()).ok().flatten(),
+        _ => None,
     }
+}
 
+// NOTE: We read at most one more byte than the maximum size,
+// to know if the decompressed bytes would exceed the maximum
+fn read_limit(options: &DecompressOptions) -> u64 {
+    options
+        .max_size
+        .map_or(u64::MAX, |max_size| (max_size as u64).saturating_add(1))
+}
+
+async fn read_limited(
+    reader: impl AsyncRead + Unpin,
+    options: &DecompressOptions,
+) -> LuaResult<Vec<u8>> {
+    let mut bytes = Vec::new();
+    reader
+        .take(read_limit(options))
+        .read_to_end(&mut bytes)
+        .await?;
+    options.check_size(bytes.len() as u64)?;
     Ok(bytes)
 }
 
+fn read_limited_std(
+    reader: impl Read,
+    options: &DecompressOptions,
+    capacity: usize,
+) -> LuaResult<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(capacity);
+    reader.take(read_limit(options)).read_to_end(&mut bytes)?;
+    options.check_size(bytes.len() as u64)?;
+    Ok(bytes)
+}
+
+fn compress_zstd(
+    input: &[u8],
+    level: Option<i32>,
+    dictionary: Option<&[u8]>,
+) -> LuaResult<Vec<u8>> {
+    // NOTE: Compressing all of the bytes at once, instead of using a stream,
+    // means that the decompressed size is stored in the frame header as well
+    let level = level.unwrap_or(zstd::DEFAULT_COMPRESSION_LEVEL);
+    match dictionary {
+        Some(dictionary) => ZstdCompressor::with_dictionary(level, dictionary)
+            .and_then(|mut compressor| compressor.compress(input))
+            .into_lua_err(),
+        None => zstd::bulk::compress(input, level).into_lua_err(),
+    }
+}
+
+fn decompress_zstd(input: Vec<u8>, options: &DecompressOptions) -> LuaResult<Vec<u8>> {
+    let input = Cursor::new(input);
+    match options.dictionary.as_deref() {
+        Some(dictionary) => {
+            let decoder = ZstdDecoder::with_dictionary(BufReaderStd::new(input), dictionary)?;
+            read_limited_std(decoder, options, 0)
+        }
+        None => read_limited_std(ZstdDecoder::new(input)?, options, 0),
+    }
+}
+
 // TODO: Remove the compatibility layer. Prepending size is no longer
 // necessary, using lz4 create instead of lz4-flex, but we must remove
 // it in a major version to not unexpectedly break compatibility
@@ -216,7 +619,7 @@ fn compress_lz4(input: Vec<u8>) -> LuaResult<Vec<u8>> {
     Ok(output.into_inner())
 }
 
-fn decompress_lz4(input: Vec<u8>) -> LuaResult<Vec<u8>> {
+fn decompress_lz4(input: Vec<u8>, options: &DecompressOptions) -> LuaResult<Vec<u8>> {
     let mut input = Cursor::new(input);
 
     // Skip size for compatibility with old lz4-flex implementation
@@ -224,13 +627,11 @@ fn decompress_lz4(input: Vec<u8>) -> LuaResult<Vec<u8>> {
     // and a small efficiency gain, maybe we can expose this as some kind
     // of "size hint" parameter instead in the serde library in the future
     let mut size = [0; 4];
-    input.read_exact(&mut size)?;
+    Read::read_exact(&mut input, &mut size)?;
 
     let capacity = u32::from_le_bytes(size) as usize;
-    let mut output = Cursor::new(Vec::with_capacity(capacity));
+    options.check_size(capacity as u64)?;
 
-    let mut decoder = Decoder::new(input)?;
-    copy_std(&mut decoder, &mut output)?;
-
-    Ok(output.into_inner())
+    let decoder = Decoder::new(input)?;
+    read_limited_std(decoder, options, capacity)
 }
@@ -0,0 +1,151 @@
+use std::path::{Path, PathBuf};
+
+use mlua::prelude::*;
+
+use lune_utils::FsRoot;
+
+use super::{
+    encode_decode::{decode, EncodeDecodeConfig, EncodeDecodeFormat},
+    json_value::is_array,
+};
+
+// NOTE: Defaults are copied into the loaded config, and this guards against
+// defaults that contain themselves, which could otherwise never be copied
+const MAX_DEFAULTS_DEPTH: usize = 64;
+
+/**
+    Loads the config file at the given path, decoding it using
+    the format that matches its extension - JSON, YAML, or TOML.
+
+    If a filesystem root has been set for the given Lua state, the path must be inside of it.
+
+    # Errors
+
+    Errors if the file has an unsupported extension, if it could not be read,
+    if it could not be decoded, or if it does not contain a table at the top level.
+*/
+pub async fn load_config<'lua>(
+    lua: &'lua Lua,
+    path: impl AsRef<Path>,
+) -> LuaResult<LuaTable<'lua>> {
+    let path = path.as_ref();
+    let format = config_format(path)?;
+
+    let resolved: PathBuf = match lua.app_data_ref::<FsRoot>() {
+        Some(root) => root.resolve(path)?,
+        None => path.to_path_buf(),
+    };
+    let bytes = tokio::fs::read(&resolved).await.map_err(|e| {
+        LuaError::RuntimeError(format!(
+            "Failed to read config file '{}'\n{e}",
+            path.display()
+        ))
+    })?;
+
+    let value = decode(bytes, lua, EncodeDecodeConfig::from(format)).map_err(|e| {
+        LuaError::RuntimeError(format!(
+            "Failed to parse config file '{}'\n{}",
+            path.display(),
+            e.to_string().trim()
+        ))
+    })?;
+
+    match value {
+        LuaValue::Table(t) => Ok(t),
+        value => Err(LuaError::RuntimeError(format!(
+            "Invalid config file '{}' - expected a table at the top level, got {}",
+            path.display(),
+            value.type_name()
+        ))),
+    }
+}
+
+fn config_format(path: &Path) -> LuaResult<EncodeDecodeFormat> {
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("json") => Ok(EncodeDecodeFormat::Json),
+        Some("yaml" | "yml") => Ok(EncodeDecodeFormat::Yaml),
+        Some("toml") => Ok(EncodeDecodeFormat::Toml),
+        _ => Err(LuaError::RuntimeError(format!(
+            "Unsupported config file '{}' - expected a file with \
+            one of the extensions .json, .yaml, .yml, or .toml",
+            path.display()
+        ))),
+    }
+}
+
+/**
+    Merges the given defaults into a loaded config, for any values that the config does not have.
+
+    Tables that exist in both are merged recursively, except for arrays, which are
+    kept as they are in the config. Tables from the defaults are copied, so changing
+    the returned config never changes the defaults.
+
+    # Errors
+
+    Errors if the defaults are nested too deeply, or contain themselves.
+*/
+pub fn merge_config_defaults<'lua>(
+    lua: &'lua Lua,
+    config: &LuaTable<'lua>,
+    defaults: &LuaTable<'lua>,
+) -> LuaResult<()> {
+    merge_defaults(lua, config, defaults, 0)
+}
+
+fn merge_defaults<'lua>(
+    lua: &'lua Lua,
+    config: &LuaTable<'lua>,
+    defaults: &LuaTable<'lua>,
+    depth: usize,
+) -> LuaResult<()> {
+    if depth >= MAX_DEFAULTS_DEPTH {
+        return Err(too_deep_error());
+    }
+    for pair in defaults.clone().pairs::<LuaValue, LuaValue>() {
+        let (key, default) = pair?;
+        match (config.raw_get::<_, LuaValue>(key.clone())?, default) {
+            (LuaValue::Nil, default) => {
+                config.raw_set(key, copy_value(lua, default, depth + 1)?)?;
+            }
+            (LuaValue::Table(existing), LuaValue::Table(default))
+                if !is_array(lua, &existing)? && !is_array(lua, &default)? =>
+            {
+                merge_defaults(lua, &existing, &default, depth + 1)?;
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn copy_value<'lua>(
+    lua: &'lua Lua,
+    value: LuaValue<'lua>,
+    depth: usize,
+) -> LuaResult<LuaValue<'lua>> {
+    let LuaValue::Table(table) = value else {
+        return Ok(value);
+    };
+    if depth >= MAX_DEFAULTS_DEPTH {
+        return Err(too_deep_error());
+    }
+    let copy = lua.create_table()?;
+    if is_array(lua, &table)? {
+        copy.set_metatable(Some(lua.array_metatable()));
+    }
+    for pair in table.pairs::<LuaValue, LuaValue>() {
+        let (key, value) = pair?;
+        copy.raw_set(key, copy_value(lua, value, depth + 1)?)?;
+    }
+    Ok(LuaValue::Table(copy))
+}
+
+fn too_deep_error() -> LuaError {
+    LuaError::RuntimeError(format!(
+        "Invalid config defaults - tables may not be nested deeper \
+        than {MAX_DEFAULTS_DEPTH} levels, or contain themselves"
+    ))
+}
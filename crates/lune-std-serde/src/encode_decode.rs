@@ -1,8 +1,14 @@
 use mlua::prelude::*;
 
+use rmpv::Value as MsgPackValue;
 use serde_json::Value as JsonValue;
 use serde_yaml::Value as YamlValue;
-use toml::Value as TomlValue;
+
+use lune_utils::buffer;
+
+use super::json_value::encode_json;
+use super::numbers::{normalize_msgpack_numbers, normalize_yaml_numbers};
+use super::toml_value::{decode_toml, encode_toml};
 
 // NOTE: These are options for going from other format -> lua ("serializing" lua values)
 const LUA_SERIALIZE_OPTIONS: LuaSerializeOptions = LuaSerializeOptions::new()
@@ -10,7 +16,8 @@ const LUA_SERIALIZE_OPTIONS: LuaSerializeOptions = LuaSerializeOptions::new()
     .serialize_none_to_null(false)
     .serialize_unit_to_null(false);
 
-// NOTE: These are options for going from lua -> other format ("deserializing" lua values)
+// NOTE: These are options for going from lua -> other format ("deserializing" lua values),
+// keys are sorted by default since table iteration order is not stable between runs
 const LUA_DESERIALIZE_OPTIONS: LuaDeserializeOptions = LuaDeserializeOptions::new()
     .sort_keys(true)
     .deny_recursive_tables(false)
@@ -26,6 +33,7 @@ pub enum EncodeDecodeFormat {
     Json,
     Yaml,
     Toml,
+    MsgPack,
 }
 
 impl<'lua> FromLua<'lua> for EncodeDecodeFormat {
@@ -35,11 +43,12 @@ impl<'lua> FromLua<'lua> for EncodeDecodeFormat {
                 "json" => Ok(Self::Json),
                 "yaml" => Ok(Self::Yaml),
                 "toml" => Ok(Self::Toml),
+                "msgpack" => Ok(Self::MsgPack),
                 kind => Err(LuaError::FromLuaConversionError {
                     from: value.type_name(),
                     to: "EncodeDecodeFormat",
                     message: Some(format!(
-                        "Invalid format '{kind}', valid formats are:  json, yaml, toml"
+                        "Invalid format '{kind}', valid formats are:  json, yaml, toml, msgpack"
                     )),
                 }),
             }
@@ -62,6 +71,8 @@ impl<'lua> FromLua<'lua> for EncodeDecodeFormat {
 pub struct EncodeDecodeConfig {
     pub format: EncodeDecodeFormat,
     pub pretty: bool,
+    pub sort_keys: bool,
+    pub integers: bool,
 }
 
 impl From<EncodeDecodeFormat> for EncodeDecodeConfig {
@@ -69,6 +80,8 @@ impl From<EncodeDecodeFormat> for EncodeDecodeConfig {
         Self {
             format,
             pretty: false,
+            sort_keys: true,
+            integers: true,
         }
     }
 }
@@ -78,6 +91,70 @@ impl From<(EncodeDecodeFormat, bool)> for EncodeDecodeConfig {
         Self {
             format: value.0,
             pretty: value.1,
+            sort_keys: true,
+            integers: true,
+        }
+    }
+}
+
+impl From<(EncodeDecodeFormat, EncodeOptions)> for EncodeDecodeConfig {
+    fn from(value: (EncodeDecodeFormat, EncodeOptions)) -> Self {
+        Self {
+            format: value.0,
+            pretty: value.1.pretty,
+            sort_keys: value.1.sort_keys,
+            integers: value.1.integers,
+        }
+    }
+}
+
+/**
+    Options for encoding values, given either as a table
+    of options or, for backwards compatibility, a boolean
+    that only determines if the output should be pretty.
+*/
+#[derive(Debug, Clone, Copy)]
+pub struct EncodeOptions {
+    pub pretty: bool,
+    pub sort_keys: bool,
+    pub integers: bool,
+}
+
+impl Default for EncodeOptions {
+    fn default() -> Self {
+        Self {
+            pretty: false,
+            sort_keys: true,
+            integers: true,
+        }
+    }
+}
+
+impl<'lua> FromLua<'lua> for EncodeOptions {
+    fn from_lua(value: LuaValue<'lua>, _: &'lua Lua) -> LuaResult<Self> {
+        let defaults = Self::default();
+        match value {
+            LuaValue::Nil => Ok(defaults),
+            LuaValue::Boolean(pretty) => Ok(Self { pretty, ..defaults }),
+            LuaValue::Table(t) => Ok(Self {
+                pretty: t
+                    .get::<_, Option<bool>>("pretty")?
+                    .unwrap_or(defaults.pretty),
+                sort_keys: t
+                    .get::<_, Option<bool>>("sortKeys")?
+                    .unwrap_or(defaults.sort_keys),
+                integers: t
+                    .get::<_, Option<bool>>("integers")?
+                    .unwrap_or(defaults.integers),
+            }),
+            _ => Err(LuaError::FromLuaConversionError {
+                from: value.type_name(),
+                to: "EncodeOptions",
+                message: Some(format!(
+                    "Invalid encode options - expected boolean or table, got {}",
+                    value.type_name()
+                )),
+            }),
         }
     }
 }
@@ -94,9 +171,12 @@ pub fn encode<'lua>(
     lua: &'lua Lua,
     config: EncodeDecodeConfig,
 ) -> LuaResult<LuaString<'lua>> {
+    let options = LUA_DESERIALIZE_OPTIONS.sort_keys(config.sort_keys);
     let bytes = match config.format {
         EncodeDecodeFormat::Json => {
-            let serialized: JsonValue = lua.from_value_with(value, LUA_DESERIALIZE_OPTIONS)?;
+            // NOTE: JSON is encoded without going through serde, so that
+            // mixed and sparse tables can be encoded as objects without losing values
+            let serialized = encode_json(lua, value, config.sort_keys, config.integers)?;
             if config.pretty {
                 serde_json::to_vec_pretty(&serialized).into_lua_err()?
             } else {
@@ -104,19 +184,21 @@ pub fn encode<'lua>(
             }
         }
         EncodeDecodeFormat::Yaml => {
-            let serialized: YamlValue = lua.from_value_with(value, LUA_DESERIALIZE_OPTIONS)?;
+            let mut serialized: YamlValue = lua.from_value_with(value, options)?;
+            normalize_yaml_numbers(&mut serialized, config.integers);
             let mut writer = Vec::with_capacity(128);
             serde_yaml::to_writer(&mut writer, &serialized).into_lua_err()?;
             writer
         }
         EncodeDecodeFormat::Toml => {
-            let serialized: TomlValue = lua.from_value_with(value, LUA_DESERIALIZE_OPTIONS)?;
-            let s = if config.pretty {
-                toml::to_string_pretty(&serialized).into_lua_err()?
-            } else {
-                toml::to_string(&serialized).into_lua_err()?
-            };
-            s.as_bytes().to_vec()
+            // NOTE: TOML is encoded without going through serde, so
+            // that datetimes and the order of keys can be preserved
+            encode_toml(lua, value, config.pretty, config.sort_keys, config.integers)?.into_bytes()
+        }
+        EncodeDecodeFormat::MsgPack => {
+            let mut serialized: MsgPackValue = lua.from_value_with(value, options)?;
+            normalize_msgpack_numbers(&mut serialized, config.integers);
+            rmp_serde::to_vec(&serialized).into_lua_err()?
         }
     };
     lua.create_string(bytes)
@@ -141,18 +223,50 @@ pub fn decode(
             lua.to_value_with(&value, LUA_SERIALIZE_OPTIONS)
         }
         EncodeDecodeFormat::Yaml => {
-            let value: YamlValue = serde_yaml::from_slice(bytes).into_lua_err()?;
+            let mut value: YamlValue = serde_yaml::from_slice(bytes).into_lua_err()?;
+            value.apply_merge().into_lua_err()?;
             lua.to_value_with(&value, LUA_SERIALIZE_OPTIONS)
         }
         EncodeDecodeFormat::Toml => {
-            if let Ok(s) = String::from_utf8(bytes.to_vec()) {
-                let value: TomlValue = toml::from_str(&s).into_lua_err()?;
-                lua.to_value_with(&value, LUA_SERIALIZE_OPTIONS)
+            if let Ok(s) = std::str::from_utf8(bytes) {
+                decode_toml(lua, s)
             } else {
                 Err(LuaError::RuntimeError(
                     "TOML must be valid utf-8".to_string(),
                 ))
             }
         }
+        EncodeDecodeFormat::MsgPack => {
+            let value: MsgPackValue = rmp_serde::from_slice(bytes).into_lua_err()?;
+            msgpack_to_lua(lua, value)
+        }
+    }
+}
+
+/**
+    Converts the given msgpack value into a Lua value, same as going through serde,
+    except for binary data which is converted into buffers instead of strings.
+*/
+fn msgpack_to_lua(lua: &Lua, value: MsgPackValue) -> LuaResult<LuaValue> {
+    match value {
+        MsgPackValue::Binary(bytes) => buffer::to_lua(lua, bytes),
+        MsgPackValue::Array(array) => {
+            let table = lua.create_table_with_capacity(array.len(), 0)?;
+            for (index, value) in array.into_iter().enumerate() {
+                table.raw_set(index + 1, msgpack_to_lua(lua, value)?)?;
+            }
+            Ok(LuaValue::Table(table))
+        }
+        MsgPackValue::Map(map) => {
+            let table = lua.create_table_with_capacity(0, map.len())?;
+            for (key, value) in map {
+                let key = msgpack_to_lua(lua, key)?;
+                if !key.is_nil() {
+                    table.raw_set(key, msgpack_to_lua(lua, value)?)?;
+                }
+            }
+            Ok(LuaValue::Table(table))
+        }
+        value => lua.to_value_with(&value, LUA_SERIALIZE_OPTIONS),
     }
 }
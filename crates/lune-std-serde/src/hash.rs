@@ -0,0 +1,151 @@
+use bstr::BString;
+use md5::Md5;
+use mlua::prelude::*;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+/**
+    A hashing algorithm supported by the `serde` standard library.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashAlgorithm {
+    Md5,
+    Sha1,
+    Sha256,
+    Blake3,
+    Crc32,
+}
+
+impl HashAlgorithm {
+    pub const ALL: [Self; 5] = [
+        Self::Md5,
+        Self::Sha1,
+        Self::Sha256,
+        Self::Blake3,
+        Self::Crc32,
+    ];
+
+    /**
+        Returns the human-friendly name of this hashing algorithm.
+    */
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Md5 => "md5",
+            Self::Sha1 => "sha1",
+            Self::Sha256 => "sha256",
+            Self::Blake3 => "blake3",
+            Self::Crc32 => "crc32",
+        }
+    }
+}
+
+impl<'lua> FromLua<'lua> for HashAlgorithm {
+    fn from_lua(value: LuaValue<'lua>, _: &'lua Lua) -> LuaResult<Self> {
+        if let LuaValue::String(s) = &value {
+            let s = s.to_str()?;
+            match s.trim().to_ascii_lowercase().as_str() {
+                "md5" => Ok(Self::Md5),
+                "sha1" => Ok(Self::Sha1),
+                "sha256" => Ok(Self::Sha256),
+                "blake3" => Ok(Self::Blake3),
+                "crc32" => Ok(Self::Crc32),
+                _ => Err(LuaError::RuntimeError(format!(
+                    "Invalid hash algorithm '{s}', valid algorithms are: {}",
+                    Self::ALL.map(Self::name).join(", ")
+                ))),
+            }
+        } else {
+            Err(LuaError::FromLuaConversionError {
+                from: value.type_name(),
+                to: "HashAlgorithm",
+                message: Some(format!(
+                    "Invalid hash algorithm - expected string, got {}",
+                    value.type_name()
+                )),
+            })
+        }
+    }
+}
+
+/**
+    A streaming hasher, which can be updated with more data
+    any number of times before producing the final digest.
+*/
+#[derive(Debug, Clone)]
+pub enum Hasher {
+    Md5(Md5),
+    Sha1(Sha1),
+    Sha256(Sha256),
+    Blake3(Box<blake3::Hasher>),
+    Crc32(crc32fast::Hasher),
+}
+
+impl Hasher {
+    /**
+        Creates a new hasher for the given algorithm.
+    */
+    #[must_use]
+    pub fn new(algorithm: HashAlgorithm) -> Self {
+        match algorithm {
+            HashAlgorithm::Md5 => Self::Md5(Md5::new()),
+            HashAlgorithm::Sha1 => Self::Sha1(Sha1::new()),
+            HashAlgorithm::Sha256 => Self::Sha256(Sha256::new()),
+            HashAlgorithm::Blake3 => Self::Blake3(Box::default()),
+            HashAlgorithm::Crc32 => Self::Crc32(crc32fast::Hasher::new()),
+        }
+    }
+
+    /**
+        Feeds more data into the hasher.
+    */
+    pub fn update(&mut self, data: impl AsRef<[u8]>) {
+        let data = data.as_ref();
+        match self {
+            Self::Md5(h) => h.update(data),
+            Self::Sha1(h) => h.update(data),
+            Self::Sha256(h) => h.update(data),
+            Self::Blake3(h) => {
+                h.update(data);
+            }
+            Self::Crc32(h) => h.update(data),
+        }
+    }
+
+    /**
+        Returns the digest of all data fed into the hasher so far, as a lowercase hex string.
+
+        The hasher is left as-is, so more data may still be fed into it afterwards.
+    */
+    #[must_use]
+    pub fn hex_digest(&self) -> String {
+        match self.clone() {
+            Self::Md5(h) => hex::encode(h.finalize()),
+            Self::Sha1(h) => hex::encode(h.finalize()),
+            Self::Sha256(h) => hex::encode(h.finalize()),
+            Self::Blake3(h) => h.finalize().to_hex().to_string(),
+            // NOTE: Checksums are conventionally written as big-endian hex
+            Self::Crc32(h) => format!("{:08x}", h.finalize()),
+        }
+    }
+}
+
+impl LuaUserData for Hasher {
+    fn add_methods<'lua, M: LuaUserDataMethods<'lua, Self>>(methods: &mut M) {
+        methods.add_method_mut("update", |_, this, data: BString| {
+            this.update(data);
+            Ok(())
+        });
+        methods.add_method("digest", |_, this, ()| Ok(this.hex_digest()));
+    }
+}
+
+/**
+    Hashes the given data using the specified algorithm, returning the digest as a lowercase hex string.
+*/
+#[must_use]
+pub fn hash(algorithm: HashAlgorithm, data: impl AsRef<[u8]>) -> String {
+    let mut hasher = Hasher::new(algorithm);
+    hasher.update(data);
+    hasher.hex_digest()
+}
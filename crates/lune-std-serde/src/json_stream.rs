@@ -0,0 +1,545 @@
+use std::ops::ControlFlow;
+
+use bstr::BString;
+use mlua::prelude::*;
+
+use lune_utils::TableBuilder;
+
+use super::encode_decode::{decode, EncodeDecodeConfig, EncodeDecodeFormat};
+use super::pointer::{parse_index, JsonPointer};
+
+const DECODE_STREAM_IMPL_LUA: &str = r#"
+local format, reader, callback, options = ...
+if type(reader) ~= "string" and type(reader) ~= "buffer" and type(reader) ~= "function" then
+    error("Expected a string, buffer or function as the reader, got " .. type(reader), 2)
+end
+if type(callback) ~= "function" then
+    error("Expected a function as the callback, got " .. type(callback), 2)
+end
+
+local decoder = create(format, options)
+local count = 0
+local function emit(values, n)
+    for i = 1, n do
+        count += 1
+        callback(values[i], count)
+    end
+end
+
+if type(reader) == "function" then
+    while true do
+        local chunk = reader()
+        if chunk == nil then
+            break
+        end
+        emit(decoder:push(chunk))
+    end
+else
+    emit(decoder:push(reader))
+end
+emit(decoder:finish())
+return count
+"#;
+
+/**
+    Creates the `decodeStream` function.
+*/
+pub fn create_decode_stream(lua: &Lua) -> LuaResult<LuaFunction> {
+    let env = TableBuilder::new(lua)?
+        .with_function(
+            "create",
+            |_, (format, options): (EncodeDecodeFormat, DecodeStreamOptions)| {
+                JsonStreamDecoder::new(format, options)
+            },
+        )?
+        .with_value("type", lua.globals().get::<_, LuaFunction>("type")?)?
+        .with_value("error", lua.globals().get::<_, LuaFunction>("error")?)?
+        .build_readonly()?;
+    lua.load(DECODE_STREAM_IMPL_LUA)
+        .set_name("serde.decodeStream")
+        .set_environment(env)
+        .into_function()
+}
+
+/**
+    Options for decoding values using `serde.decodeStream`.
+*/
+#[derive(Debug, Clone, Default)]
+pub struct DecodeStreamOptions {
+    pointer: Option<(String, JsonPointer)>,
+    lines: bool,
+}
+
+impl<'lua> FromLua<'lua> for DecodeStreamOptions {
+    fn from_lua(value: LuaValue<'lua>, lua: &'lua Lua) -> LuaResult<Self> {
+        let t = match value {
+            LuaValue::Nil => return Ok(Self::default()),
+            LuaValue::Table(t) => t,
+            value => {
+                return Err(LuaError::FromLuaConversionError {
+                    from: value.type_name(),
+                    to: "DecodeStreamOptions",
+                    message: Some(format!(
+                        "Invalid decode stream options - expected table, got {}",
+                        value.type_name()
+                    )),
+                })
+            }
+        };
+
+        let pointer = match t.get::<_, LuaValue>("pointer")? {
+            LuaValue::Nil => None,
+            LuaValue::String(s) => {
+                let name = s.to_str()?.to_string();
+                let pointer = JsonPointer::from_lua(LuaValue::String(s), lua)?;
+                Some((name, pointer))
+            }
+            value => {
+                return Err(LuaError::RuntimeError(format!(
+                    "Invalid option value for 'pointer' in decode stream options \
+                    - expected string, got {}",
+                    value.type_name()
+                )))
+            }
+        };
+        let lines = match t.get::<_, LuaValue>("lines")? {
+            LuaValue::Nil => false,
+            LuaValue::Boolean(b) => b,
+            value => {
+                return Err(LuaError::RuntimeError(format!(
+                    "Invalid option value for 'lines' in decode stream options \
+                    - expected boolean, got {}",
+                    value.type_name()
+                )))
+            }
+        };
+        if lines && pointer.is_some() {
+            return Err(LuaError::RuntimeError(
+                "Invalid decode stream options - 'pointer' and 'lines' can not be used together"
+                    .to_string(),
+            ));
+        }
+
+        Ok(Self { pointer, lines })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Frame {
+    Object,
+    Array(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    // Navigating towards the array at the pointer
+    Value,
+    KeyOrEnd { first: bool },
+    Colon { matches: bool },
+    ElementOrEnd { first: bool },
+    AfterValue,
+    Skip,
+    // Inside of the array at the pointer, or between values for lines
+    ItemOrEnd { first: bool },
+    Capture { start: usize },
+    AfterItem,
+    Done,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum ValueKind {
+    #[default]
+    Unknown,
+    Scalar,
+    Nested,
+}
+
+/**
+    Finds the end of a single value without parsing it, so that it can be
+    skipped over, or decoded on its own once all of its bytes have been read.
+*/
+#[derive(Debug, Clone, Copy, Default)]
+struct ValueScanner {
+    kind: ValueKind,
+    nesting: usize,
+    in_string: bool,
+    escaped: bool,
+}
+
+impl ValueScanner {
+    /**
+        Feeds a single byte to the scanner, returning `Some` once the value has ended,
+        with `true` if the given byte was a part of the value, and `false` otherwise.
+    */
+    fn feed(&mut self, byte: u8) -> Option<bool> {
+        if self.kind == ValueKind::Unknown {
+            self.kind = ValueKind::Nested;
+            match byte {
+                b'{' | b'[' => self.nesting = 1,
+                b'"' => self.in_string = true,
+                _ => self.kind = ValueKind::Scalar,
+            }
+            return None;
+        }
+        if self.kind == ValueKind::Scalar {
+            let is_delimiter =
+                byte.is_ascii_whitespace() || matches!(byte, b',' | b':' | b']' | b'}');
+            return is_delimiter.then_some(false);
+        }
+        if self.in_string {
+            if self.escaped {
+                self.escaped = false;
+            } else if byte == b'\\' {
+                self.escaped = true;
+            } else if byte == b'"' {
+                self.in_string = false;
+                if self.nesting == 0 {
+                    return Some(true);
+                }
+            }
+            return None;
+        }
+        match byte {
+            b'"' => self.in_string = true,
+            b'{' | b'[' => self.nesting += 1,
+            b'}' | b']' => {
+                self.nesting -= 1;
+                if self.nesting == 0 {
+                    return Some(true);
+                }
+            }
+            _ => {}
+        }
+        None
+    }
+}
+
+/**
+    An incremental decoder for JSON, which decodes values one at a time, as soon as they have
+    been fully read, instead of needing the whole input to be read and decoded at once.
+
+    Decodes either the elements of the array at the given pointer, which is the root value
+    if no pointer was given, or - for lines - any number of values separated by whitespace.
+*/
+#[derive(Debug)]
+struct JsonStreamDecoder {
+    pointer_name: String,
+    target: Vec<String>,
+    lines: bool,
+    buffer: Vec<u8>,
+    offset: usize,
+    pos: usize,
+    frames: Vec<Frame>,
+    scanner: ValueScanner,
+    state: State,
+}
+
+impl JsonStreamDecoder {
+    fn new(format: EncodeDecodeFormat, options: DecodeStreamOptions) -> LuaResult<Self> {
+        if !matches!(format, EncodeDecodeFormat::Json) {
+            return Err(LuaError::runtime(
+                "Streaming decoding is only supported for the 'json' format",
+            ));
+        }
+        let (pointer_name, target) = match options.pointer {
+            Some((name, pointer)) => (name, pointer.tokens().to_vec()),
+            None => (String::new(), Vec::new()),
+        };
+        Ok(Self {
+            pointer_name,
+            target,
+            lines: options.lines,
+            buffer: Vec::new(),
+            offset: 0,
+            pos: 0,
+            frames: Vec::new(),
+            scanner: ValueScanner::default(),
+            state: State::Value,
+        })
+    }
+
+    fn push<'lua>(&mut self, lua: &'lua Lua, chunk: &[u8]) -> LuaResult<(LuaTable<'lua>, usize)> {
+        self.buffer.extend_from_slice(chunk);
+        self.process(lua, false)
+    }
+
+    fn finish<'lua>(&mut self, lua: &'lua Lua) -> LuaResult<(LuaTable<'lua>, usize)> {
+        let values = self.process(lua, true)?;
+        let is_complete = if self.lines {
+            self.state == State::Value
+        } else {
+            self.state == State::Done
+        };
+        if !is_complete {
+            return Err(LuaError::runtime(
+                "Failed to decode JSON stream - unexpected end of input",
+            ));
+        }
+        Ok(values)
+    }
+
+    fn process<'lua>(&mut self, lua: &'lua Lua, eof: bool) -> LuaResult<(LuaTable<'lua>, usize)> {
+        let values = lua.create_table()?;
+        let mut count = 0;
+        let mut pos = self.pos;
+
+        loop {
+            let progress = match self.state {
+                State::Skip | State::Capture { .. } => {
+                    self.scan(lua, &values, &mut count, pos, eof)?
+                }
+                State::Done => ControlFlow::Break(self.buffer.len()),
+                _ => self.step(pos)?,
+            };
+            match progress {
+                ControlFlow::Continue(next) => pos = next,
+                ControlFlow::Break(next) => {
+                    pos = next;
+                    break;
+                }
+            }
+        }
+
+        // Drop any bytes that are no longer needed, keeping only
+        // the ones that belong to a value that is still being read
+        let keep_from = match &mut self.state {
+            State::Capture { start } => std::mem::take(start),
+            _ => pos,
+        };
+        self.buffer.drain(..keep_from);
+        self.offset += keep_from;
+        self.pos = pos - keep_from;
+
+        Ok((values, count))
+    }
+
+    /**
+        Scans the value that is currently being skipped or captured, decoding it
+        if it was captured, or pauses if the value has not been fully read yet.
+    */
+    fn scan<'lua>(
+        &mut self,
+        lua: &'lua Lua,
+        values: &LuaTable<'lua>,
+        count: &mut usize,
+        mut pos: usize,
+        eof: bool,
+    ) -> LuaResult<ControlFlow<usize, usize>> {
+        if self.state == State::Skip && self.scanner.kind == ValueKind::Unknown {
+            pos = self.skip_whitespace(pos);
+        }
+        let mut end = None;
+        while pos < self.buffer.len() {
+            if let Some(consumed) = self.scanner.feed(self.buffer[pos]) {
+                end = Some(if consumed { pos + 1 } else { pos });
+                break;
+            }
+            pos += 1;
+        }
+        if end.is_none() && eof && self.scanner.kind == ValueKind::Scalar {
+            end = Some(self.buffer.len());
+        }
+        let Some(end) = end else {
+            return Ok(ControlFlow::Break(pos));
+        };
+
+        if let State::Capture { start } = self.state {
+            let value = decode(
+                &self.buffer[start..end],
+                lua,
+                EncodeDecodeConfig::from(EncodeDecodeFormat::Json),
+            )
+            .map_err(|e| LuaError::runtime(format!("Failed to decode JSON stream - {e}")))?;
+            *count += 1;
+            values.raw_set(*count, value)?;
+            self.state = if self.lines {
+                State::Value
+            } else {
+                State::AfterItem
+            };
+        } else {
+            self.state = State::AfterValue;
+        }
+        Ok(ControlFlow::Continue(end))
+    }
+
+    /**
+        Reads the next structural character, such as a bracket or comma, or the next
+        key of an object, or pauses if there is nothing more to read at the moment.
+    */
+    fn step(&mut self, pos: usize) -> LuaResult<ControlFlow<usize, usize>> {
+        if self.state == State::AfterValue && self.frames.is_empty() {
+            return Err(self.not_found_error());
+        }
+        let pos = self.skip_whitespace(pos);
+        let Some(&byte) = self.buffer.get(pos) else {
+            return Ok(ControlFlow::Break(pos));
+        };
+
+        match self.state {
+            State::Value if self.lines => self.begin(State::Capture { start: pos }),
+            State::Value if self.frames.len() == self.target.len() => {
+                if byte != b'[' {
+                    return Err(LuaError::runtime(format!(
+                        "Failed to decode JSON stream - value at pointer '{}' is not an array",
+                        self.pointer_name
+                    )));
+                }
+                self.state = State::ItemOrEnd { first: true };
+                return Ok(ControlFlow::Continue(pos + 1));
+            }
+            State::ItemOrEnd { first } => {
+                if byte == b']' && first {
+                    self.state = State::Done;
+                    return Ok(ControlFlow::Continue(pos + 1));
+                }
+                self.begin(State::Capture { start: pos });
+            }
+            State::AfterItem => {
+                match byte {
+                    b',' => self.state = State::ItemOrEnd { first: false },
+                    b']' => self.state = State::Done,
+                    _ => return Err(self.unexpected_error(byte, pos)),
+                }
+                return Ok(ControlFlow::Continue(pos + 1));
+            }
+            _ => return self.navigate(byte, pos),
+        }
+        Ok(ControlFlow::Continue(pos))
+    }
+
+    /**
+        Reads the next structural character or key on the way to the array at the pointer,
+        entering values that lead to it and skipping over any other values.
+    */
+    fn navigate(&mut self, byte: u8, pos: usize) -> LuaResult<ControlFlow<usize, usize>> {
+        match self.state {
+            State::Value => match byte {
+                b'{' => {
+                    self.frames.push(Frame::Object);
+                    self.state = State::KeyOrEnd { first: true };
+                }
+                b'[' => {
+                    self.frames.push(Frame::Array(0));
+                    self.state = State::ElementOrEnd { first: true };
+                }
+                _ => return Err(self.not_found_error()),
+            },
+            State::KeyOrEnd { first: true } if byte == b'}' => {
+                self.frames.pop();
+                self.state = State::AfterValue;
+            }
+            State::KeyOrEnd { .. } => {
+                if byte != b'"' {
+                    return Err(self.unexpected_error(byte, pos));
+                }
+                let Some(len) = find_string_end(&self.buffer[pos..]) else {
+                    // NOTE: The key has not been fully read yet, so we
+                    // keep it in the buffer and try again on the next push
+                    return Ok(ControlFlow::Break(pos));
+                };
+                let key: String =
+                    serde_json::from_slice(&self.buffer[pos..pos + len]).map_err(|e| {
+                        LuaError::runtime(format!("Failed to decode JSON stream - {e}"))
+                    })?;
+                let matches = key == self.target[self.frames.len() - 1];
+                self.state = State::Colon { matches };
+                return Ok(ControlFlow::Continue(pos + len));
+            }
+            State::Colon { matches } => {
+                if byte != b':' {
+                    return Err(self.unexpected_error(byte, pos));
+                }
+                if matches {
+                    self.state = State::Value;
+                } else {
+                    self.begin(State::Skip);
+                }
+            }
+            State::ElementOrEnd { first: true } if byte == b']' => {
+                self.frames.pop();
+                self.state = State::AfterValue;
+            }
+            State::ElementOrEnd { .. } => {
+                let token = &self.target[self.frames.len() - 1];
+                let matches = matches!(
+                    self.frames.last(),
+                    Some(Frame::Array(index)) if parse_index(token) == Some(*index)
+                );
+                if matches {
+                    self.state = State::Value;
+                } else {
+                    self.begin(State::Skip);
+                }
+                return Ok(ControlFlow::Continue(pos));
+            }
+            State::AfterValue => match (self.frames.last_mut(), byte) {
+                (Some(Frame::Object), b',') => {
+                    self.state = State::KeyOrEnd { first: false };
+                }
+                (Some(Frame::Array(index)), b',') => {
+                    *index += 1;
+                    self.state = State::ElementOrEnd { first: false };
+                }
+                (Some(Frame::Object), b'}') | (Some(Frame::Array(_)), b']') => {
+                    self.frames.pop();
+                }
+                _ => return Err(self.unexpected_error(byte, pos)),
+            },
+            _ => unreachable!("values and items are not read while navigating"),
+        }
+        Ok(ControlFlow::Continue(pos + 1))
+    }
+
+    fn skip_whitespace(&self, mut pos: usize) -> usize {
+        while pos < self.buffer.len() && self.buffer[pos].is_ascii_whitespace() {
+            pos += 1;
+        }
+        pos
+    }
+
+    fn begin(&mut self, state: State) {
+        self.scanner = ValueScanner::default();
+        self.state = state;
+    }
+
+    fn not_found_error(&self) -> LuaError {
+        LuaError::runtime(format!(
+            "Failed to decode JSON stream - no array found at pointer '{}'",
+            self.pointer_name
+        ))
+    }
+
+    fn unexpected_error(&self, byte: u8, pos: usize) -> LuaError {
+        LuaError::runtime(format!(
+            "Failed to decode JSON stream - unexpected character '{}' at byte {}",
+            char::from(byte).escape_default(),
+            self.offset + pos
+        ))
+    }
+}
+
+impl LuaUserData for JsonStreamDecoder {
+    fn add_methods<'lua, M: LuaUserDataMethods<'lua, Self>>(methods: &mut M) {
+        methods.add_method_mut("push", |lua, this, chunk: BString| this.push(lua, &chunk));
+        methods.add_method_mut("finish", |lua, this, (): ()| this.finish(lua));
+    }
+}
+
+/**
+    Finds the length of the string at the start of the given bytes,
+    including its quotes, or `None` if the string has not ended yet.
+*/
+fn find_string_end(bytes: &[u8]) -> Option<usize> {
+    let mut escaped = false;
+    for (index, &byte) in bytes.iter().enumerate().skip(1) {
+        if escaped {
+            escaped = false;
+        } else if byte == b'\\' {
+            escaped = true;
+        } else if byte == b'"' {
+            return Some(index + 1);
+        }
+    }
+    None
+}
@@ -0,0 +1,168 @@
+use mlua::prelude::*;
+use serde_json::{Map as JsonMap, Number as JsonNumber, Value as JsonValue};
+
+use super::numbers::float_to_integer;
+
+/**
+    Marks the given table as an array, so that it is always encoded as an
+    array - even when it is empty, which would otherwise be encoded as an object.
+
+    # Errors
+
+    Errors when the table already has a metatable.
+*/
+pub fn mark_array<'lua>(lua: &'lua Lua, table: LuaTable<'lua>) -> LuaResult<LuaTable<'lua>> {
+    let array = lua.array_metatable();
+    match table.get_metatable() {
+        None => table.set_metatable(Some(array)),
+        Some(meta) if meta == array => {}
+        Some(_) => {
+            return Err(LuaError::runtime(
+                "Failed to mark table as array - table already has a metatable",
+            ))
+        }
+    }
+    Ok(table)
+}
+
+/**
+    Checks if the given table should be encoded as an array, meaning that it was marked
+    as an array using `mark_array`, or it has a sequence of values and no other keys.
+
+    Other empty tables are encoded as objects / tables.
+*/
+pub fn is_array(lua: &Lua, table: &LuaTable) -> LuaResult<bool> {
+    if table
+        .get_metatable()
+        .is_some_and(|meta| meta == lua.array_metatable())
+    {
+        return Ok(true);
+    }
+    let len = table.raw_len();
+    if len == 0 {
+        return Ok(false);
+    }
+    let mut count = 0;
+    for pair in table.clone().pairs::<LuaValue, LuaValue>() {
+        pair?;
+        count += 1;
+    }
+    Ok(count == len)
+}
+
+/**
+    Converts the given Luau value into a JSON value.
+
+    Tables are converted into arrays only if they were marked as arrays, or if they contain
+    a sequence of values and no other keys - mixed and sparse tables are instead converted
+    into objects, with any number keys converted into strings, so that no values are lost.
+
+    Integral numbers are converted into integers, unless `integers` is false, in which
+    case all numbers are converted into floats, and written with a decimal point.
+
+    # Errors
+
+    Errors when the value contains values that can not be encoded as JSON.
+*/
+pub fn encode_json(
+    lua: &Lua,
+    value: LuaValue,
+    sort_keys: bool,
+    integers: bool,
+) -> LuaResult<JsonValue> {
+    let mut encoder = JsonEncoder {
+        lua,
+        sort_keys,
+        integers,
+        visiting: Vec::new(),
+    };
+    encoder.value(value)
+}
+
+struct JsonEncoder<'lua> {
+    lua: &'lua Lua,
+    sort_keys: bool,
+    integers: bool,
+    visiting: Vec<*const std::ffi::c_void>,
+}
+
+impl<'lua> JsonEncoder<'lua> {
+    fn value(&mut self, value: LuaValue<'lua>) -> LuaResult<JsonValue> {
+        Ok(match value {
+            LuaValue::Nil => JsonValue::Null,
+            LuaValue::LightUserData(ud) if ud.0.is_null() => JsonValue::Null,
+            LuaValue::Boolean(b) => JsonValue::Bool(b),
+            LuaValue::Integer(i) => number_to_json(f64::from(i), self.integers),
+            LuaValue::Number(n) => number_to_json(n, self.integers),
+            LuaValue::String(s) => JsonValue::String(s.to_str()?.to_string()),
+            LuaValue::Table(table) => {
+                self.enter(&table)?;
+                let value = if is_array(self.lua, &table)? {
+                    let array = table
+                        .clone()
+                        .sequence_values::<LuaValue>()
+                        .map(|value| self.value(value?))
+                        .collect::<LuaResult<_>>()?;
+                    JsonValue::Array(array)
+                } else {
+                    JsonValue::Object(self.object(&table)?)
+                };
+                self.visiting.pop();
+                value
+            }
+            value => {
+                return Err(LuaError::runtime(format!(
+                    "Failed to encode JSON - unsupported value of type {}",
+                    value.type_name()
+                )))
+            }
+        })
+    }
+
+    fn object(&mut self, table: &LuaTable<'lua>) -> LuaResult<JsonMap<String, JsonValue>> {
+        let mut pairs = Vec::new();
+        for pair in table.clone().pairs::<LuaValue, LuaValue>() {
+            let (key, value) = pair?;
+            let key = match key {
+                LuaValue::String(s) => s.to_str()?.to_string(),
+                LuaValue::Integer(i) => i.to_string(),
+                LuaValue::Number(n) => number_to_json(n, true).to_string(),
+                key => {
+                    return Err(LuaError::runtime(format!(
+                        "Failed to encode JSON - table keys must be strings or numbers, got {}",
+                        key.type_name()
+                    )))
+                }
+            };
+            pairs.push((key, value));
+        }
+        if self.sort_keys {
+            pairs.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+        }
+
+        let mut object = JsonMap::with_capacity(pairs.len());
+        for (key, value) in pairs {
+            object.insert(key, self.value(value)?);
+        }
+        Ok(object)
+    }
+
+    fn enter(&mut self, table: &LuaTable) -> LuaResult<()> {
+        let ptr = table.to_pointer();
+        if self.visiting.contains(&ptr) {
+            return Err(LuaError::runtime(
+                "Failed to encode JSON - tables can not contain themselves",
+            ));
+        }
+        self.visiting.push(ptr);
+        Ok(())
+    }
+}
+
+fn number_to_json(n: f64, integers: bool) -> JsonValue {
+    match float_to_integer(n).filter(|_| integers) {
+        Some(i) => JsonValue::Number(JsonNumber::from(i)),
+        // NOTE: NaN and infinity can not be represented in JSON, and become null
+        None => JsonNumber::from_f64(n).map_or(JsonValue::Null, JsonValue::Number),
+    }
+}
@@ -5,11 +5,35 @@ use mlua::prelude::*;
 
 use lune_utils::TableBuilder;
 
+mod base64_hex;
+mod buffer;
 mod compress_decompress;
+mod config;
 mod encode_decode;
+mod hash;
+mod json_stream;
+mod json_value;
+mod numbers;
+mod pointer;
+mod radix;
+mod toml_value;
 
-pub use self::compress_decompress::{compress, decompress, CompressDecompressFormat};
-pub use self::encode_decode::{decode, encode, EncodeDecodeConfig, EncodeDecodeFormat};
+pub use self::base64_hex::{base64_decode, base64_encode, hex_decode, hex_encode, Base64Options};
+pub use self::buffer::{BufferReader, BufferWriter};
+pub use self::compress_decompress::{
+    compress, compress_to_writer, decompress, decompress_from_reader, decompressed_size,
+    train_zstd_dictionary, CompressDecompressFormat, CompressOptions, DecompressOptions,
+};
+pub use self::config::{load_config, merge_config_defaults};
+pub use self::encode_decode::{
+    decode, encode, EncodeDecodeConfig, EncodeDecodeFormat, EncodeOptions,
+};
+pub use self::hash::{hash, HashAlgorithm, Hasher};
+pub use self::pointer::{pointer_get, pointer_set, JsonPointer};
+pub use self::radix::{format_int, parse_int, Radix};
+
+use self::json_stream::create_decode_stream;
+use self::json_value::mark_array;
 
 /**
     Creates the `serde` standard library module.
@@ -22,16 +46,33 @@ pub fn module(lua: &Lua) -> LuaResult<LuaTable> {
     TableBuilder::new(lua)?
         .with_function("encode", serde_encode)?
         .with_function("decode", serde_decode)?
+        .with_value("decodeStream", create_decode_stream(lua)?)?
+        .with_async_function("loadConfig", serde_load_config)?
+        .with_function("array", serde_array)?
         .with_async_function("compress", serde_compress)?
         .with_async_function("decompress", serde_decompress)?
+        .with_function("decompressedSize", serde_decompressed_size)?
+        .with_async_function("zstdTrainDictionary", serde_zstd_train_dictionary)?
+        .with_function("base64Encode", serde_base64_encode)?
+        .with_function("base64Decode", serde_base64_decode)?
+        .with_function("hexEncode", serde_hex_encode)?
+        .with_function("hexDecode", serde_hex_decode)?
+        .with_function("parseInt", serde_parse_int)?
+        .with_function("toString", serde_to_string)?
+        .with_function("hash", serde_hash)?
+        .with_function("hasher", serde_hasher)?
+        .with_function("bufferReader", serde_buffer_reader)?
+        .with_function("bufferWriter", serde_buffer_writer)?
+        .with_function("get", serde_get)?
+        .with_function("set", serde_set)?
         .build_readonly()
 }
 
 fn serde_encode<'lua>(
     lua: &'lua Lua,
-    (format, value, pretty): (EncodeDecodeFormat, LuaValue<'lua>, Option<bool>),
+    (format, value, options): (EncodeDecodeFormat, LuaValue<'lua>, EncodeOptions),
 ) -> LuaResult<LuaString<'lua>> {
-    let config = EncodeDecodeConfig::from((format, pretty.unwrap_or_default()));
+    let config = EncodeDecodeConfig::from((format, options));
     encode(value, lua, config)
 }
 
@@ -40,18 +81,105 @@ fn serde_decode(lua: &Lua, (format, bs): (EncodeDecodeFormat, BString)) -> LuaRe
     decode(bs, lua, config)
 }
 
+async fn serde_load_config<'lua>(
+    lua: &'lua Lua,
+    (path, defaults): (String, Option<LuaTable<'lua>>),
+) -> LuaResult<LuaTable<'lua>> {
+    let config = load_config(lua, path).await?;
+    if let Some(defaults) = defaults {
+        merge_config_defaults(lua, &config, &defaults)?;
+    }
+    Ok(config)
+}
+
+fn serde_array<'lua>(lua: &'lua Lua, table: LuaTable<'lua>) -> LuaResult<LuaTable<'lua>> {
+    mark_array(lua, table)
+}
+
 async fn serde_compress(
     lua: &Lua,
-    (format, bs): (CompressDecompressFormat, BString),
+    (format, bs, options): (CompressDecompressFormat, BString, CompressOptions),
 ) -> LuaResult<LuaString> {
-    let bytes = compress(bs, format).await?;
+    let bytes = compress(bs, format, options).await?;
     lua.create_string(bytes)
 }
 
 async fn serde_decompress(
     lua: &Lua,
+    (format, bs, options): (CompressDecompressFormat, BString, DecompressOptions),
+) -> LuaResult<LuaString> {
+    let bytes = decompress(bs, format, options).await?;
+    lua.create_string(bytes)
+}
+
+fn serde_decompressed_size(
+    _: &Lua,
     (format, bs): (CompressDecompressFormat, BString),
+) -> LuaResult<Option<u64>> {
+    Ok(decompressed_size(bs, format))
+}
+
+async fn serde_zstd_train_dictionary(
+    lua: &Lua,
+    (samples, size): (Vec<BString>, usize),
 ) -> LuaResult<LuaString> {
-    let bytes = decompress(bs, format).await?;
+    let samples = samples.into_iter().map(Vec::from).collect();
+    let dictionary = train_zstd_dictionary(samples, size).await?;
+    lua.create_string(dictionary)
+}
+
+fn serde_base64_encode(_: &Lua, (bs, options): (BString, Base64Options)) -> LuaResult<String> {
+    Ok(base64_encode(bs, options))
+}
+
+fn serde_base64_decode(lua: &Lua, (bs, options): (BString, Base64Options)) -> LuaResult<LuaString> {
+    let bytes = base64_decode(bs, options)?;
     lua.create_string(bytes)
 }
+
+fn serde_hex_encode(_: &Lua, bs: BString) -> LuaResult<String> {
+    Ok(hex_encode(bs))
+}
+
+fn serde_hex_decode(lua: &Lua, bs: BString) -> LuaResult<LuaString> {
+    let bytes = hex_decode(bs)?;
+    lua.create_string(bytes)
+}
+
+fn serde_parse_int(_: &Lua, (s, radix): (String, Radix)) -> LuaResult<f64> {
+    parse_int(&s, radix)
+}
+
+fn serde_to_string(_: &Lua, (n, radix): (f64, Radix)) -> LuaResult<String> {
+    format_int(n, radix)
+}
+
+fn serde_hash(_: &Lua, (algorithm, bs): (HashAlgorithm, BString)) -> LuaResult<String> {
+    Ok(hash(algorithm, bs))
+}
+
+fn serde_hasher(_: &Lua, algorithm: HashAlgorithm) -> LuaResult<Hasher> {
+    Ok(Hasher::new(algorithm))
+}
+
+fn serde_buffer_reader(_: &Lua, bs: BString) -> LuaResult<BufferReader> {
+    Ok(BufferReader::new(bs))
+}
+
+fn serde_buffer_writer(_: &Lua, (): ()) -> LuaResult<BufferWriter> {
+    Ok(BufferWriter::new())
+}
+
+fn serde_get<'lua>(
+    lua: &'lua Lua,
+    (value, pointer): (LuaValue<'lua>, JsonPointer),
+) -> LuaResult<LuaValue<'lua>> {
+    pointer_get(lua, value, &pointer)
+}
+
+fn serde_set<'lua>(
+    lua: &'lua Lua,
+    (value, pointer, new_value): (LuaValue<'lua>, JsonPointer, LuaValue<'lua>),
+) -> LuaResult<()> {
+    pointer_set(lua, value, &pointer, new_value)
+}
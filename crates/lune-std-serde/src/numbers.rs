@@ -0,0 +1,69 @@
+use rmpv::Value as MsgPackValue;
+use serde_yaml::{Number as YamlNumber, Value as YamlValue};
+
+/**
+    Converts the given number into an integer, if it has no fractional part
+    and is in the range of a 64-bit integer, so that it can be represented exactly.
+
+    Since Luau only has one number type, this is used to decide which numbers
+    to encode as integers, for formats that have separate integer and float types,
+    or that write integers without a decimal point, such as `1` instead of `1.0`.
+*/
+#[must_use]
+pub fn float_to_integer(n: f64) -> Option<i64> {
+    #[allow(clippy::cast_precision_loss)]
+    let in_range = n >= i64::MIN as f64 && n < i64::MAX as f64;
+    #[allow(clippy::cast_possible_truncation)]
+    (n.fract() == 0.0 && in_range).then_some(n as i64)
+}
+
+/**
+    Makes all numbers in the given YAML value integers if they are integral,
+    or floats if integers should not be used, same as [`float_to_integer`].
+
+    Keys of mappings are left as they are.
+*/
+pub fn normalize_yaml_numbers(value: &mut YamlValue, integers: bool) {
+    let mut stack = vec![value];
+    while let Some(value) = stack.pop() {
+        match value {
+            YamlValue::Number(number) => {
+                let n = number.as_f64().unwrap_or_default();
+                *number = match float_to_integer(n) {
+                    Some(i) if integers => YamlNumber::from(i),
+                    _ if number.is_f64() || !integers => YamlNumber::from(n),
+                    _ => continue,
+                };
+            }
+            YamlValue::Mapping(mapping) => stack.extend(mapping.values_mut()),
+            YamlValue::Sequence(sequence) => stack.extend(sequence),
+            YamlValue::Tagged(tagged) => stack.push(&mut tagged.value),
+            _ => {}
+        }
+    }
+}
+
+/**
+    Makes all numbers in the given msgpack value integers if they are integral,
+    or floats if integers should not be used, same as [`float_to_integer`].
+
+    Keys of maps are left as they are.
+*/
+pub fn normalize_msgpack_numbers(value: &mut MsgPackValue, integers: bool) {
+    let mut stack = vec![value];
+    while let Some(value) = stack.pop() {
+        match value {
+            MsgPackValue::F64(n) => {
+                if let Some(i) = float_to_integer(*n).filter(|_| integers) {
+                    *value = MsgPackValue::from(i);
+                }
+            }
+            MsgPackValue::Integer(i) if !integers => {
+                *value = MsgPackValue::F64(i.as_f64().unwrap_or_default());
+            }
+            MsgPackValue::Map(map) => stack.extend(map.iter_mut().map(|(_, value)| value)),
+            MsgPackValue::Array(array) => stack.extend(array),
+            _ => {}
+        }
+    }
+}
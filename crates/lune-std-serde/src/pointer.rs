@@ -0,0 +1,214 @@
+use mlua::prelude::*;
+
+/**
+    A parsed [RFC 6901](https://www.rfc-editor.org/rfc/rfc6901) JSON Pointer.
+
+    Each token refers to either a string key or, for tables that are arrays,
+    a zero-based index - the same as if the table was encoded as JSON.
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonPointer {
+    tokens: Vec<String>,
+}
+
+impl JsonPointer {
+    /**
+        Parses a JSON Pointer, such as `/a/b/0/c`.
+
+        # Errors
+
+        Errors when the pointer is not empty and does not start with
+        a `/`, or when it contains a `~` not followed by `0` or `1`.
+    */
+    pub fn parse(pointer: &str) -> LuaResult<Self> {
+        if pointer.is_empty() {
+            return Ok(Self { tokens: Vec::new() });
+        }
+        let Some(rest) = pointer.strip_prefix('/') else {
+            return Err(LuaError::runtime(format!(
+                "Invalid pointer '{pointer}' - must be empty or start with '/'"
+            )));
+        };
+        let tokens = rest
+            .split('/')
+            .map(|token| unescape_token(token, pointer))
+            .collect::<LuaResult<_>>()?;
+        Ok(Self { tokens })
+    }
+
+    /**
+        Returns the unescaped tokens of the pointer.
+    */
+    pub(crate) fn tokens(&self) -> &[String] {
+        &self.tokens
+    }
+}
+
+impl<'lua> FromLua<'lua> for JsonPointer {
+    fn from_lua(value: LuaValue<'lua>, _: &'lua Lua) -> LuaResult<Self> {
+        match value {
+            LuaValue::String(s) => Self::parse(s.to_str()?),
+            _ => Err(LuaError::FromLuaConversionError {
+                from: value.type_name(),
+                to: "JsonPointer",
+                message: Some(format!(
+                    "Invalid pointer - expected string, got {}",
+                    value.type_name()
+                )),
+            }),
+        }
+    }
+}
+
+fn unescape_token(token: &str, pointer: &str) -> LuaResult<String> {
+    let mut unescaped = String::with_capacity(token.len());
+    let mut chars = token.chars();
+    while let Some(c) = chars.next() {
+        if c == '~' {
+            match chars.next() {
+                Some('0') => unescaped.push('~'),
+                Some('1') => unescaped.push('/'),
+                _ => {
+                    return Err(LuaError::runtime(format!(
+                        "Invalid pointer '{pointer}' - '~' must be followed by '0' or '1'"
+                    )))
+                }
+            }
+        } else {
+            unescaped.push(c);
+        }
+    }
+    Ok(unescaped)
+}
+
+/**
+    Parses a token as an array index, following the rules for indices in
+    JSON Pointers, meaning that leading zeros and signs are not allowed.
+*/
+pub(crate) fn parse_index(token: &str) -> Option<usize> {
+    let is_valid = !token.is_empty()
+        && token.bytes().all(|b| b.is_ascii_digit())
+        && (token == "0" || !token.starts_with('0'));
+    if is_valid {
+        token.parse().ok()
+    } else {
+        None
+    }
+}
+
+/**
+    Checks if the given table would be encoded as an array, same as the JSON encoder does.
+
+    Empty tables count as arrays, so that indices can be used to add their first element.
+*/
+fn is_array(table: &LuaTable) -> LuaResult<bool> {
+    if table.raw_len() > 0 {
+        return Ok(true);
+    }
+    Ok(table.clone().pairs::<LuaValue, LuaValue>().next().is_none())
+}
+
+/**
+    Resolves the table key for a single pointer token.
+
+    Indices refer to existing elements of arrays, or the position right after the last element
+    if `allow_append` is set, which may also be referred to using the special `-` token.
+    Any other tokens, including indices outside of arrays, are used as string keys.
+*/
+fn resolve_key<'lua>(
+    lua: &'lua Lua,
+    table: &LuaTable<'lua>,
+    token: &str,
+    allow_append: bool,
+) -> LuaResult<LuaValue<'lua>> {
+    if is_array(table)? {
+        let len = table.raw_len();
+        let index = if token == "-" {
+            allow_append.then_some(len)
+        } else {
+            parse_index(token).filter(|index| *index < len || (allow_append && *index == len))
+        };
+        if let Some(index) = index {
+            return Ok(LuaValue::Integer(
+                LuaInteger::try_from(index + 1).into_lua_err()?,
+            ));
+        }
+    }
+    lua.create_string(token).map(LuaValue::String)
+}
+
+/**
+    Gets the value found at the given pointer, or `nil` if there is no such value.
+
+    # Errors
+
+    Errors when indexing one of the tables along the path errors, or when out of memory.
+*/
+pub fn pointer_get<'lua>(
+    lua: &'lua Lua,
+    value: LuaValue<'lua>,
+    pointer: &JsonPointer,
+) -> LuaResult<LuaValue<'lua>> {
+    let mut current = value;
+    for token in &pointer.tokens {
+        let LuaValue::Table(table) = current else {
+            return Ok(LuaValue::Nil);
+        };
+        let key = resolve_key(lua, &table, token, false)?;
+        current = table.get(key)?;
+    }
+    Ok(current)
+}
+
+/**
+    Sets the value found at the given pointer, creating any missing tables along the path.
+
+    # Errors
+
+    Errors when the pointer refers to the whole value, when a value along the
+    path exists but is not a table, or when setting any of the tables errors.
+*/
+pub fn pointer_set<'lua>(
+    lua: &'lua Lua,
+    value: LuaValue<'lua>,
+    pointer: &JsonPointer,
+    new_value: LuaValue<'lua>,
+) -> LuaResult<()> {
+    let Some((last, parents)) = pointer.tokens.split_last() else {
+        return Err(LuaError::runtime(
+            "Invalid pointer '' - can not set the whole value",
+        ));
+    };
+    let LuaValue::Table(mut table) = value else {
+        return Err(LuaError::runtime(format!(
+            "Invalid value - expected table, got {}",
+            value.type_name()
+        )));
+    };
+
+    for (depth, token) in parents.iter().enumerate() {
+        let key = resolve_key(lua, &table, token, true)?;
+        table = match table.get::<_, LuaValue>(key.clone())? {
+            LuaValue::Table(child) => child,
+            LuaValue::Nil => {
+                let child = lua.create_table()?;
+                table.set(key, child.clone())?;
+                child
+            }
+            other => {
+                let path = pointer.tokens[..=depth]
+                    .iter()
+                    .map(|token| token.replace('~', "~0").replace('/', "~1"))
+                    .collect::<Vec<_>>()
+                    .join("/");
+                return Err(LuaError::runtime(format!(
+                    "Failed to set value - expected table at '/{path}', got {}",
+                    other.type_name()
+                )));
+            }
+        };
+    }
+
+    let key = resolve_key(lua, &table, last, true)?;
+    table.set(key, new_value)
+}
@@ -0,0 +1,135 @@
+use mlua::prelude::*;
+
+// NOTE: These are the bounds of integers that fit in 64 bits, either
+// signed or unsigned, all of which are exactly representable as floats
+const MIN_VALUE: f64 = -9_223_372_036_854_775_808.0; // -2^63
+const MAX_VALUE_EXCLUSIVE: f64 = 18_446_744_073_709_551_616.0; // 2^64
+
+const DIGITS: &[u8; 36] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+/**
+    A radix (base) for parsing and formatting integers, between 2 and 36.
+
+    Defaults to 10 when not given.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Radix(u32);
+
+impl Default for Radix {
+    fn default() -> Self {
+        Self(10)
+    }
+}
+
+impl<'lua> FromLua<'lua> for Radix {
+    fn from_lua(value: LuaValue<'lua>, lua: &'lua Lua) -> LuaResult<Self> {
+        if value.is_nil() {
+            return Ok(Self::default());
+        }
+        let radix = f64::from_lua(value, lua)?;
+        if radix.fract() == 0.0 && (2.0..=36.0).contains(&radix) {
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+            Ok(Self(radix as u32))
+        } else {
+            Err(LuaError::FromLuaConversionError {
+                from: "number",
+                to: "Radix",
+                message: Some(format!(
+                    "Invalid radix '{radix}' - must be an integer between 2 and 36"
+                )),
+            })
+        }
+    }
+}
+
+/**
+    Parses a string containing an integer in the given radix, with an optional `+` or `-` sign.
+
+    Digits above 9 use the letters `a` to `z`, in either case. Integers from `-2^63` up
+    to `2^64 - 1` are accepted, but values over `2^53` in magnitude are rounded to
+    the nearest number that can be represented, same as any other Luau number.
+
+    # Errors
+
+    Errors when the string is empty, contains characters that are not
+    digits of the radix, or the integer does not fit in 64 bits.
+*/
+pub fn parse_int(s: &str, radix: Radix) -> LuaResult<f64> {
+    let (negative, digits) = match s.as_bytes().first() {
+        Some(b'-') => (true, &s[1..]),
+        Some(b'+') => (false, &s[1..]),
+        _ => (false, s),
+    };
+
+    // NOTE: The standard library accepts a leading plus sign by
+    // itself, we check the digits first so that "+-1" and "++1" are not
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_alphanumeric()) {
+        return Err(invalid_int_error(s, radix));
+    }
+
+    let magnitude = u64::from_str_radix(digits, radix.0).map_err(|e| match e.kind() {
+        std::num::IntErrorKind::PosOverflow => out_of_range_error(s),
+        _ => invalid_int_error(s, radix),
+    })?;
+
+    if negative && magnitude > i64::MIN.unsigned_abs() {
+        return Err(out_of_range_error(s));
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    let value = magnitude as f64;
+    Ok(if negative { -value } else { value })
+}
+
+/**
+    Formats an integer as a string in the given radix, using lowercase letters for digits above 9.
+
+    # Errors
+
+    Errors when the number is not an integer, or is not between `-2^63` and `2^64 - 1`.
+*/
+pub fn format_int(n: f64, radix: Radix) -> LuaResult<String> {
+    if !n.is_finite() || n.fract() != 0.0 {
+        return Err(LuaError::runtime(format!(
+            "Invalid number '{n}' - must be an integer"
+        )));
+    }
+    if !(MIN_VALUE..MAX_VALUE_EXCLUSIVE).contains(&n) {
+        return Err(LuaError::runtime(format!(
+            "Invalid number '{n}' - must be between -2^63 and 2^64 - 1"
+        )));
+    }
+
+    // NOTE: The range and integer checks above guarantee
+    // that the magnitude of the number is exactly a u64
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let mut magnitude = n.abs() as u64;
+    let mut digits = Vec::new();
+    loop {
+        #[allow(clippy::cast_possible_truncation)]
+        let digit = (magnitude % u64::from(radix.0)) as usize;
+        digits.push(char::from(DIGITS[digit]));
+        magnitude /= u64::from(radix.0);
+        if magnitude == 0 {
+            break;
+        }
+    }
+    if n < 0.0 {
+        digits.push('-');
+    }
+
+    Ok(digits.into_iter().rev().collect())
+}
+
+fn invalid_int_error(s: &str, radix: Radix) -> LuaError {
+    LuaError::runtime(format!(
+        "Invalid integer '{s}' - not a valid base {} integer",
+        radix.0
+    ))
+}
+
+fn out_of_range_error(s: &str) -> LuaError {
+    LuaError::runtime(format!(
+        "Invalid integer '{s}' - must be between -2^63 and 2^64 - 1"
+    ))
+}
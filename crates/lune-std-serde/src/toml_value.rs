@@ -0,0 +1,265 @@
+use std::str::FromStr;
+
+use mlua::prelude::*;
+use toml::{value::Datetime as TomlDatetime, Table as TomlTable, Value as TomlValue};
+
+use super::json_value::is_array;
+use super::numbers::float_to_integer;
+
+const TABLE_INFO_KEY: &str = "SerdeTomlTableInfo";
+
+const DATETIME_KIND: &str = "datetime";
+
+/**
+    Gets the weak table that stores extra information about decoded tables.
+
+    Luau tables do not keep the order that keys were added in, and empty arrays can not
+    be told apart from empty tables, so for every table decoded from TOML we store either
+    the order of its keys, or `true` if it was an array, and use it when encoding again.
+*/
+fn table_info(lua: &Lua) -> LuaResult<LuaTable> {
+    if let Some(info) = lua.named_registry_value::<Option<LuaTable>>(TABLE_INFO_KEY)? {
+        return Ok(info);
+    }
+    let info = lua.create_table()?;
+    let meta = lua.create_table()?;
+    meta.set("__mode", "k")?;
+    info.set_metatable(Some(meta));
+    lua.set_named_registry_value(TABLE_INFO_KEY, info.clone())?;
+    Ok(info)
+}
+
+/**
+    Decodes the given TOML string into a Luau value.
+
+    Datetimes are decoded as tables with a `__kind` of `"datetime"` and their
+    string representation as `value`, and the order of keys in tables is kept.
+
+    # Errors
+
+    Errors when the string is not valid TOML.
+*/
+pub fn decode_toml<'lua>(lua: &'lua Lua, s: &str) -> LuaResult<LuaValue<'lua>> {
+    let table: TomlTable = toml::from_str(s).into_lua_err()?;
+    let info = table_info(lua)?;
+    toml_table_to_lua(lua, &info, table).map(LuaValue::Table)
+}
+
+fn toml_to_lua<'lua>(
+    lua: &'lua Lua,
+    info: &LuaTable<'lua>,
+    value: TomlValue,
+) -> LuaResult<LuaValue<'lua>> {
+    Ok(match value {
+        TomlValue::String(s) => LuaValue::String(lua.create_string(s)?),
+        TomlValue::Integer(i) => i.into_lua(lua)?,
+        TomlValue::Float(f) => LuaValue::Number(f),
+        TomlValue::Boolean(b) => LuaValue::Boolean(b),
+        TomlValue::Datetime(datetime) => {
+            let table = lua.create_table_with_capacity(0, 2)?;
+            table.set("__kind", DATETIME_KIND)?;
+            table.set("value", datetime.to_string())?;
+            LuaValue::Table(table)
+        }
+        TomlValue::Array(array) => {
+            let table = lua.create_table_with_capacity(array.len(), 0)?;
+            for value in array {
+                table.raw_push(toml_to_lua(lua, info, value)?)?;
+            }
+            info.raw_set(table.clone(), true)?;
+            LuaValue::Table(table)
+        }
+        TomlValue::Table(table) => LuaValue::Table(toml_table_to_lua(lua, info, table)?),
+    })
+}
+
+fn toml_table_to_lua<'lua>(
+    lua: &'lua Lua,
+    info: &LuaTable<'lua>,
+    table: TomlTable,
+) -> LuaResult<LuaTable<'lua>> {
+    let keys = lua.create_table_with_capacity(table.len(), 0)?;
+    let lua_table = lua.create_table_with_capacity(0, table.len())?;
+    for (key, value) in table {
+        keys.raw_push(key.as_str())?;
+        lua_table.raw_set(key, toml_to_lua(lua, info, value)?)?;
+    }
+    info.raw_set(lua_table.clone(), keys)?;
+    Ok(lua_table)
+}
+
+/**
+    Encodes the given Luau table into a TOML string.
+
+    Tables that were decoded from TOML keep the order of their keys, and any keys added to
+    them afterwards, or keys of other tables, are sorted if `sort_keys` is set. Datetimes
+    may be given as tables in the same format that [`decode_toml`] returns them in.
+
+    Integral numbers are encoded as integers, unless `integers` is false,
+    in which case all numbers are encoded as floats.
+
+    # Errors
+
+    Errors when the value is not a table, or contains values that can not be encoded as TOML.
+*/
+pub fn encode_toml(
+    lua: &Lua,
+    value: LuaValue,
+    pretty: bool,
+    sort_keys: bool,
+    integers: bool,
+) -> LuaResult<String> {
+    let LuaValue::Table(table) = value else {
+        return Err(LuaError::runtime(format!(
+            "Failed to encode TOML - expected table, got {}",
+            value.type_name()
+        )));
+    };
+    let mut encoder = TomlEncoder {
+        lua,
+        info: table_info(lua)?,
+        sort_keys,
+        integers,
+        visiting: Vec::new(),
+    };
+    let table = encoder.table(&table)?;
+    if pretty {
+        toml::to_string_pretty(&table).into_lua_err()
+    } else {
+        toml::to_string(&table).into_lua_err()
+    }
+}
+
+struct TomlEncoder<'lua> {
+    lua: &'lua Lua,
+    info: LuaTable<'lua>,
+    sort_keys: bool,
+    integers: bool,
+    visiting: Vec<*const std::ffi::c_void>,
+}
+
+impl<'lua> TomlEncoder<'lua> {
+    fn value(&mut self, value: LuaValue<'lua>) -> LuaResult<TomlValue> {
+        Ok(match value {
+            LuaValue::Boolean(b) => TomlValue::Boolean(b),
+            LuaValue::Integer(i) => number_to_toml(f64::from(i), self.integers),
+            LuaValue::Number(n) => number_to_toml(n, self.integers),
+            LuaValue::String(s) => TomlValue::String(s.to_str()?.to_string()),
+            LuaValue::Table(table) => {
+                if let Some(datetime) = table_to_datetime(&table)? {
+                    TomlValue::Datetime(datetime)
+                } else if self.is_array(&table)? {
+                    self.enter(&table)?;
+                    let array = table
+                        .clone()
+                        .sequence_values::<LuaValue>()
+                        .map(|value| self.value(value?))
+                        .collect::<LuaResult<_>>()?;
+                    self.visiting.pop();
+                    TomlValue::Array(array)
+                } else {
+                    TomlValue::Table(self.table(&table)?)
+                }
+            }
+            value => {
+                return Err(LuaError::runtime(format!(
+                    "Failed to encode TOML - unsupported value of type {}",
+                    value.type_name()
+                )))
+            }
+        })
+    }
+
+    fn table(&mut self, table: &LuaTable<'lua>) -> LuaResult<TomlTable> {
+        self.enter(table)?;
+
+        let mut keys = Vec::new();
+        for pair in table.clone().pairs::<LuaValue, LuaValue>() {
+            let (key, _) = pair?;
+            match key {
+                LuaValue::String(s) => keys.push(s.to_str()?.to_string()),
+                key => {
+                    return Err(LuaError::runtime(format!(
+                        "Failed to encode TOML - table keys must be strings, got {}",
+                        key.type_name()
+                    )))
+                }
+            }
+        }
+        if self.sort_keys {
+            keys.sort_unstable();
+        }
+
+        // NOTE: Keys in the stored order come first, in that order, followed
+        // by any other keys - which are usually keys that were added later on
+        if let LuaValue::Table(order) = self.info.raw_get(table.clone())? {
+            let mut ordered = Vec::with_capacity(keys.len());
+            for key in order.sequence_values::<String>() {
+                let key = key?;
+                if let Some(index) = keys.iter().position(|k| *k == key) {
+                    ordered.push(keys.remove(index));
+                }
+            }
+            ordered.append(&mut keys);
+            keys = ordered;
+        }
+
+        let mut toml_table = TomlTable::new();
+        for key in keys {
+            let value = table.raw_get::<_, LuaValue>(key.as_str())?;
+            toml_table.insert(key, self.value(value)?);
+        }
+
+        self.visiting.pop();
+        Ok(toml_table)
+    }
+
+    /**
+        Checks if the given table should be encoded as an array, meaning that it was decoded
+        from an array, marked as an array, or it has a sequence of values and no other keys.
+
+        Other empty tables are encoded as tables.
+    */
+    fn is_array(&self, table: &LuaTable) -> LuaResult<bool> {
+        if let LuaValue::Boolean(true) = self.info.raw_get(table.clone())? {
+            return Ok(true);
+        }
+        is_array(self.lua, table)
+    }
+
+    fn enter(&mut self, table: &LuaTable) -> LuaResult<()> {
+        let ptr = table.to_pointer();
+        if self.visiting.contains(&ptr) {
+            return Err(LuaError::runtime(
+                "Failed to encode TOML - tables can not contain themselves",
+            ));
+        }
+        self.visiting.push(ptr);
+        Ok(())
+    }
+}
+
+fn number_to_toml(n: f64, integers: bool) -> TomlValue {
+    match float_to_integer(n).filter(|_| integers) {
+        Some(i) => TomlValue::Integer(i),
+        None => TomlValue::Float(n),
+    }
+}
+
+fn table_to_datetime(table: &LuaTable) -> LuaResult<Option<TomlDatetime>> {
+    let is_datetime = matches!(
+        table.raw_get::<_, LuaValue>("__kind")?,
+        LuaValue::String(s) if s.as_bytes() == DATETIME_KIND.as_bytes()
+    );
+    if !is_datetime {
+        return Ok(None);
+    }
+    let value = table.raw_get::<_, String>("value").map_err(|_| {
+        LuaError::runtime("Failed to encode TOML - datetimes must have a string value")
+    })?;
+    TomlDatetime::from_str(&value).map(Some).map_err(|e| {
+        LuaError::runtime(format!(
+            "Failed to encode TOML - invalid datetime '{value}'\n{e}"
+        ))
+    })
+}
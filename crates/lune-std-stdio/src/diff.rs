@@ -0,0 +1,50 @@
+use console::style;
+use mlua::prelude::*;
+use similar::{ChangeTag, TextDiff};
+
+use lune_utils::fmt::{indent_output, write_output, OutputStream};
+
+/**
+    Creates a line-based diff between the expected and actual strings.
+
+    Removed lines are prefixed with `-` and styled red, added lines are prefixed
+    with `+` and styled green, and unchanged lines are prefixed with a space.
+    Styling is left out if colors are disabled.
+
+    Returns `None` if the strings are equal.
+*/
+fn diff_lines(expected: &str, actual: &str) -> Option<String> {
+    if expected == actual {
+        return None;
+    }
+
+    let mut output = String::new();
+    for change in TextDiff::from_lines(expected, actual).iter_all_changes() {
+        let line = change.value().trim_end_matches(['\r', '\n']);
+        let styled = match change.tag() {
+            ChangeTag::Delete => style(format!("- {line}")).red().to_string(),
+            ChangeTag::Insert => style(format!("+ {line}")).green().to_string(),
+            ChangeTag::Equal => format!("  {line}"),
+        };
+        output.push_str(&styled);
+        output.push('\n');
+    }
+    Some(output)
+}
+
+/**
+    Writes a line-based diff between the expected and actual strings to stdout.
+
+    Returns `true` if the strings differ, and nothing is written if they are equal.
+*/
+pub fn write_diff(lua: &Lua, expected: &str, actual: &str) -> LuaResult<bool> {
+    let Some(diff) = diff_lines(expected, actual) else {
+        return Ok(false);
+    };
+    write_output(
+        lua,
+        OutputStream::Stdout,
+        indent_output(lua, &diff).as_bytes(),
+    )?;
+    Ok(true)
+}
@@ -0,0 +1,42 @@
+use console::style;
+use mlua::prelude::*;
+
+use lune_utils::fmt::{
+    decrease_output_indent, increase_output_indent, indent_output, pretty_format_multi_value,
+    write_output, OutputStream, ValueFormatConfig,
+};
+
+const FORMAT_CONFIG: ValueFormatConfig = ValueFormatConfig::new()
+    .with_max_depth(4)
+    .with_colors_enabled(false);
+
+/**
+    Creates the output grouping functions for the `stdio` standard library.
+
+    Groups are started using `group` and ended using `groupEnd`, and any
+    output written in between is indented by one more level, including
+    output from the global `print` and `warn` functions.
+*/
+pub fn create_group_functions(lua: &Lua) -> LuaResult<Vec<(&'static str, LuaFunction)>> {
+    let group = lua.create_function(|lua, label: LuaMultiValue| {
+        if !label.is_empty() {
+            let formatted = format!(
+                "{}\n",
+                style(pretty_format_multi_value(&label, &FORMAT_CONFIG)).bold()
+            );
+            let formatted = indent_output(lua, &formatted);
+            write_output(lua, OutputStream::Stdout, formatted.as_bytes())?;
+        }
+        increase_output_indent(lua);
+        Ok(())
+    })?;
+
+    // NOTE: Ending more groups than were started is
+    // harmless, so it is not treated as an error here
+    let group_end = lua.create_function(|lua, (): ()| {
+        decrease_output_indent(lua);
+        Ok(())
+    })?;
+
+    Ok(vec![("group", group), ("groupEnd", group_end)])
+}
@@ -0,0 +1,75 @@
+use mlua::prelude::*;
+
+use lune_utils::fmt::{pretty_format_value, ValueFormatConfig};
+
+const DEFAULT_MAX_DEPTH: usize = 4;
+
+#[derive(Debug, Clone, Copy)]
+pub struct InspectOptions {
+    pub colors: bool,
+    pub max_depth: usize,
+    pub multiline: bool,
+}
+
+impl Default for InspectOptions {
+    fn default() -> Self {
+        Self {
+            colors: true,
+            max_depth: DEFAULT_MAX_DEPTH,
+            multiline: true,
+        }
+    }
+}
+
+impl<'lua> FromLua<'lua> for InspectOptions {
+    fn from_lua(value: LuaValue<'lua>, _: &'lua Lua) -> LuaResult<Self> {
+        match value {
+            LuaValue::Nil => Ok(Self::default()),
+            LuaValue::Table(t) => {
+                let colors: Option<bool> = t.get("color")?;
+                // NOTE: The depth option is kept as an alias for maxDepth
+                let max_depth: Option<usize> = match t.get("maxDepth")? {
+                    Some(max_depth) => Some(max_depth),
+                    None => t.get("depth")?,
+                };
+                let multiline = match t.get::<_, LuaValue>("multiline")? {
+                    LuaValue::Nil => true,
+                    LuaValue::Boolean(b) => b,
+                    value => {
+                        return Err(LuaError::RuntimeError(format!(
+                            "Invalid option value for 'multiline' in inspect options \
+                            - expected boolean, got {}",
+                            value.type_name()
+                        )))
+                    }
+                };
+                Ok(Self {
+                    colors: colors.unwrap_or(true),
+                    max_depth: max_depth.unwrap_or(DEFAULT_MAX_DEPTH),
+                    multiline,
+                })
+            }
+            _ => Err(LuaError::FromLuaConversionError {
+                from: value.type_name(),
+                to: "InspectOptions",
+                message: Some(format!(
+                    "Invalid inspect options - expected table, got {}",
+                    value.type_name()
+                )),
+            }),
+        }
+    }
+}
+
+/**
+    Formats the given value the same way as `print` would, and returns the formatted string.
+
+    Colors are only included if they are enabled both in the options and globally.
+*/
+pub fn inspect(value: &LuaValue, options: InspectOptions) -> String {
+    let config = ValueFormatConfig::new()
+        .with_max_depth(options.max_depth)
+        .with_colors_enabled(options.colors)
+        .with_multiline(options.multiline);
+    pretty_format_value(value, &config)
+}
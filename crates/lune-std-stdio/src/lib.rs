@@ -1,18 +1,38 @@
 #![allow(clippy::cargo_common_metadata)]
 
-use lune_utils::fmt::{pretty_format_multi_value, ValueFormatConfig};
+use console::colors_enabled;
+use lune_utils::fmt::{
+    pretty_format_multi_value, pretty_format_value, set_theme, write_output, OutputStream,
+    ValueFormatConfig,
+};
 use mlua::prelude::*;
 use mlua_luau_scheduler::LuaSpawnExt;
 
-use tokio::io::{stderr, stdin, stdout, AsyncReadExt, AsyncWriteExt};
+use tokio::io::{stdin, AsyncReadExt};
 
 use lune_utils::TableBuilder;
 
+mod diff;
+mod group;
+mod inspect;
+mod log;
+mod output;
 mod prompt;
 mod style_and_color;
-
+mod table;
+mod timer;
+
+use self::diff::write_diff;
+use self::group::create_group_functions;
+use self::inspect::{inspect, InspectOptions};
+use self::log::create_log_functions;
+use self::output::create_output_functions;
 use self::prompt::{prompt, PromptOptions, PromptResult};
-use self::style_and_color::{ColorKind, StyleKind};
+use self::style_and_color::{
+    init_colors_enabled, set_colors_enabled_all, ColorKind, StyleKind, ThemeConfig,
+};
+use self::table::write_table;
+use self::timer::create_timer_functions;
 
 const FORMAT_CONFIG: ValueFormatConfig = ValueFormatConfig::new()
     .with_max_depth(4)
@@ -26,40 +46,80 @@ const FORMAT_CONFIG: ValueFormatConfig = ValueFormatConfig::new()
     Errors when out of memory.
 */
 pub fn module(lua: &Lua) -> LuaResult<LuaTable> {
+    init_colors_enabled();
     TableBuilder::new(lua)?
         .with_function("color", stdio_color)?
         .with_function("style", stdio_style)?
+        .with_function("setColorEnabled", stdio_set_color_enabled)?
+        .with_function("setTheme", stdio_set_theme)?
         .with_function("format", stdio_format)?
-        .with_async_function("write", stdio_write)?
-        .with_async_function("ewrite", stdio_ewrite)?
+        .with_function("inspect", stdio_inspect)?
+        .with_function("diff", stdio_diff)?
+        .with_function("table", stdio_table)?
+        .with_function("write", stdio_write)?
+        .with_function("ewrite", stdio_ewrite)?
         .with_async_function("readToEnd", stdio_read_to_end)?
         .with_async_function("prompt", stdio_prompt)?
+        .with_values(create_log_functions(lua)?)?
+        .with_values(create_group_functions(lua)?)?
+        .with_values(create_timer_functions(lua)?)?
+        .with_values(create_output_functions(lua)?)?
         .build_readonly()
 }
 
 fn stdio_color(lua: &Lua, color: ColorKind) -> LuaResult<LuaValue> {
-    color.ansi_escape_sequence().into_lua(lua)
+    if colors_enabled() {
+        color.ansi_escape_sequence().into_lua(lua)
+    } else {
+        "".into_lua(lua)
+    }
 }
 
 fn stdio_style(lua: &Lua, style: StyleKind) -> LuaResult<LuaValue> {
-    style.ansi_escape_sequence().into_lua(lua)
+    if colors_enabled() {
+        style.ansi_escape_sequence().into_lua(lua)
+    } else {
+        "".into_lua(lua)
+    }
+}
+
+fn stdio_set_color_enabled(_: &Lua, enabled: bool) -> LuaResult<bool> {
+    init_colors_enabled();
+    let previous = colors_enabled();
+    set_colors_enabled_all(enabled);
+    Ok(previous)
+}
+
+fn stdio_set_theme(_: &Lua, theme: ThemeConfig) -> LuaResult<()> {
+    set_theme(theme.0);
+    Ok(())
+}
+
+fn stdio_diff(lua: &Lua, (expected, actual): (LuaValue, LuaValue)) -> LuaResult<bool> {
+    let expected = pretty_format_value(&expected, &FORMAT_CONFIG);
+    let actual = pretty_format_value(&actual, &FORMAT_CONFIG);
+    write_diff(lua, &expected, &actual)
 }
 
 fn stdio_format(_: &Lua, args: LuaMultiValue) -> LuaResult<String> {
     Ok(pretty_format_multi_value(&args, &FORMAT_CONFIG))
 }
 
-async fn stdio_write(_: &Lua, s: LuaString<'_>) -> LuaResult<()> {
-    let mut stdout = stdout();
-    stdout.write_all(s.as_bytes()).await?;
-    stdout.flush().await?;
+fn stdio_inspect(_: &Lua, (value, options): (LuaValue, InspectOptions)) -> LuaResult<String> {
+    Ok(inspect(&value, options))
+}
+
+fn stdio_table(lua: &Lua, data: LuaTable) -> LuaResult<()> {
+    write_table(lua, data)
+}
+
+fn stdio_write(lua: &Lua, s: LuaString) -> LuaResult<()> {
+    write_output(lua, OutputStream::Stdout, s.as_bytes())?;
     Ok(())
 }
 
-async fn stdio_ewrite(_: &Lua, s: LuaString<'_>) -> LuaResult<()> {
-    let mut stderr = stderr();
-    stderr.write_all(s.as_bytes()).await?;
-    stderr.flush().await?;
+fn stdio_ewrite(lua: &Lua, s: LuaString) -> LuaResult<()> {
+    write_output(lua, OutputStream::Stderr, s.as_bytes())?;
     Ok(())
 }
 
@@ -0,0 +1,156 @@
+use std::{cell::Cell, io, rc::Rc, str::FromStr};
+
+use mlua::prelude::*;
+
+use lune_utils::fmt::{
+    indent_output, pretty_format_multi_value, write_output, Label, OutputStream, ValueFormatConfig,
+};
+
+const FORMAT_CONFIG: ValueFormatConfig = ValueFormatConfig::new()
+    .with_max_depth(4)
+    .with_colors_enabled(true);
+
+/**
+    A log level supported by the `stdio` standard library.
+
+    Levels are ordered by severity, from least to most severe.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Log,
+    Info,
+    Warn,
+    Error,
+}
+
+impl LogLevel {
+    pub const ALL: [Self; 4] = [Self::Log, Self::Info, Self::Warn, Self::Error];
+
+    /**
+        Returns the human-friendly name of this log level.
+    */
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Log => "log",
+            Self::Info => "info",
+            Self::Warn => "warn",
+            Self::Error => "error",
+        }
+    }
+
+    fn label(self) -> Label {
+        match self {
+            Self::Log => Label::Log,
+            Self::Info => Label::Info,
+            Self::Warn => Label::Warn,
+            Self::Error => Label::Error,
+        }
+    }
+
+    /**
+        Writes the given values, formatted and prefixed with
+        the label for this log level, to stdout or stderr.
+
+        Warnings and errors are written to stderr, anything else to stdout.
+    */
+    fn write(self, lua: &Lua, values: &LuaMultiValue) -> io::Result<()> {
+        let formatted = format!(
+            "{} {}\n",
+            self.label(),
+            pretty_format_multi_value(values, &FORMAT_CONFIG)
+        );
+        let formatted = indent_output(lua, &formatted);
+        let stream = match self {
+            Self::Log | Self::Info => OutputStream::Stdout,
+            Self::Warn | Self::Error => OutputStream::Stderr,
+        };
+        write_output(lua, stream, formatted.as_bytes())
+    }
+}
+
+impl FromStr for LogLevel {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.trim().to_ascii_lowercase().as_str() {
+            "log" => Self::Log,
+            "info" => Self::Info,
+            "warn" => Self::Warn,
+            "error" => Self::Error,
+            _ => return Err(()),
+        })
+    }
+}
+
+impl FromLua<'_> for LogLevel {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        if let LuaValue::String(s) = value {
+            let s = s.to_str()?;
+            match s.parse() {
+                Ok(level) => Ok(level),
+                Err(()) => Err(LuaError::RuntimeError(format!(
+                    "Invalid log level: '{}'\nValid log levels: {}",
+                    s,
+                    Self::ALL.map(Self::name).join(", ")
+                ))),
+            }
+        } else {
+            Err(LuaError::FromLuaConversionError {
+                from: value.type_name(),
+                to: "LogLevel",
+                message: None,
+            })
+        }
+    }
+}
+
+/**
+    Creates the leveled logging functions for the `stdio` standard library.
+
+    All of the functions share the same minimum log level, which
+    may be changed using the returned `setLevel` function.
+
+    Also creates the `assert` function, which logs at the error level.
+*/
+pub fn create_log_functions(lua: &Lua) -> LuaResult<Vec<(&'static str, LuaFunction)>> {
+    let min_level = Rc::new(Cell::new(LogLevel::Log));
+
+    let mut functions = Vec::new();
+    for level in LogLevel::ALL {
+        let min_level = Rc::clone(&min_level);
+        let function = lua.create_function(move |lua, values: LuaMultiValue| {
+            if level >= min_level.get() {
+                level.write(lua, &values)?;
+            }
+            Ok(())
+        })?;
+        functions.push((level.name(), function));
+    }
+
+    // NOTE: Failed assertions are written at the error level, but unlike
+    // the global assert function they do not throw or stop the script
+    let assert_min_level = Rc::clone(&min_level);
+    let assert = lua.create_function(
+        move |lua, (condition, mut values): (LuaValue, LuaMultiValue)| {
+            let failed = matches!(condition, LuaValue::Nil | LuaValue::Boolean(false));
+            if failed && LogLevel::Error >= assert_min_level.get() {
+                let prefix = if values.is_empty() {
+                    "Assertion failed"
+                } else {
+                    "Assertion failed:"
+                };
+                values.push_front(LuaValue::String(lua.create_string(prefix)?));
+                LogLevel::Error.write(lua, &values)?;
+            }
+            Ok(())
+        },
+    )?;
+    functions.push(("assert", assert));
+
+    let set_level = lua.create_function(move |_, level: LogLevel| {
+        min_level.set(level);
+        Ok(())
+    })?;
+    functions.push(("setLevel", set_level));
+
+    Ok(functions)
+}
@@ -0,0 +1,80 @@
+use std::fs::OpenOptions;
+
+use lune_utils::{
+    fmt::{
+        begin_output_capture, end_output_capture, output_history, set_output_history_size,
+        set_output_target, OutputTarget,
+    },
+    TableBuilder,
+};
+use mlua::prelude::*;
+
+const CAPTURE_IMPL_LUA: &str = r"
+local id = begin()
+local results = pack(pcall(...))
+local output = finish(id)
+if not results[1] then
+    error(results[2], 0)
+end
+return output, unpack(results, 2, results.n)
+";
+
+/**
+    Creates the `setOutput`, `capture`, `getHistory`, and `setHistorySize` functions.
+*/
+pub fn create_output_functions(lua: &Lua) -> LuaResult<Vec<(&'static str, LuaFunction)>> {
+    let capture_env = TableBuilder::new(lua)?
+        .with_function("begin", |lua, (): ()| Ok(begin_output_capture(lua)))?
+        .with_function("finish", |lua, id: usize| {
+            lua.create_string(end_output_capture(lua, id))
+        })?
+        .with_value("pcall", lua.globals().get::<_, LuaFunction>("pcall")?)?
+        .with_value("error", lua.globals().get::<_, LuaFunction>("error")?)?
+        .with_value(
+            "pack",
+            lua.globals()
+                .get::<_, LuaTable>("table")?
+                .get::<_, LuaFunction>("pack")?,
+        )?
+        .with_value("unpack", lua.globals().get::<_, LuaFunction>("unpack")?)?
+        .build_readonly()?;
+    let capture = lua
+        .load(CAPTURE_IMPL_LUA)
+        .set_name("stdio.capture")
+        .set_environment(capture_env)
+        .into_function()?;
+
+    let set_output = lua.create_function(stdio_set_output)?;
+    let get_history = lua.create_function(|lua, (): ()| Ok(output_history(lua)))?;
+    let set_history_size = lua.create_function(|lua, size: usize| {
+        set_output_history_size(lua, size);
+        Ok(())
+    })?;
+
+    Ok(vec![
+        ("setOutput", set_output),
+        ("capture", capture),
+        ("getHistory", get_history),
+        ("setHistorySize", set_history_size),
+    ])
+}
+
+fn stdio_set_output(lua: &Lua, target: Option<String>) -> LuaResult<()> {
+    let target = match target.as_deref() {
+        None => OutputTarget::Default,
+        Some("stdout") => OutputTarget::Stdout,
+        Some("stderr") => OutputTarget::Stderr,
+        Some(path) => {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .map_err(|e| {
+                    LuaError::runtime(format!("Failed to open output file '{path}' - {e}"))
+                })?;
+            OutputTarget::File(file)
+        }
+    };
+    set_output_target(lua, target);
+    Ok(())
+}
@@ -1,9 +1,48 @@
-use std::str::FromStr;
+use std::{
+    env::var_os,
+    io::{stderr, stdout, IsTerminal},
+    str::FromStr,
+    sync::Once,
+};
 
+use console::{set_colors_enabled, set_colors_enabled_stderr};
 use mlua::prelude::*;
 
+use lune_utils::fmt::Theme;
+
 const ESCAPE_SEQ_RESET: &str = "\x1b[0m";
 
+static COLORS_INIT: Once = Once::new();
+
+/**
+    Disables colored output if the `NO_COLOR` environment variable is
+    set, or if the output is not being written to a terminal.
+
+    This only ever disables colors, and only runs once, so that any
+    colors explicitly disabled or enabled are not overwritten later.
+*/
+pub fn init_colors_enabled() {
+    COLORS_INIT.call_once(|| {
+        let no_color = var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+        if no_color || !stdout().is_terminal() {
+            set_colors_enabled(false);
+        }
+        if no_color || !stderr().is_terminal() {
+            set_colors_enabled_stderr(false);
+        }
+    });
+}
+
+/**
+    Enables or disables colored output, for both stdout and stderr.
+*/
+pub fn set_colors_enabled_all(enabled: bool) {
+    // NOTE: Make sure the defaults never overwrite this explicit choice
+    init_colors_enabled();
+    set_colors_enabled(enabled);
+    set_colors_enabled_stderr(enabled);
+}
+
 /**
     A color kind supported by the `stdio` standard library.
 */
@@ -193,3 +232,35 @@ impl FromLua<'_> for StyleKind {
         }
     }
 }
+
+/**
+    A theme given to `stdio.setTheme`, where any colors not given use their defaults.
+*/
+#[derive(Debug, Clone, Copy)]
+pub struct ThemeConfig(pub Theme);
+
+impl FromLua<'_> for ThemeConfig {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        let LuaValue::Table(table) = value else {
+            return Err(LuaError::FromLuaConversionError {
+                from: value.type_name(),
+                to: "Theme",
+                message: None,
+            });
+        };
+        let mut theme = Theme::DEFAULT;
+        for pair in table.pairs::<String, LuaString>() {
+            let (name, color) = pair?;
+            let Some(slot) = theme.get_mut(&name) else {
+                return Err(LuaError::RuntimeError(format!(
+                    "Invalid theme color name '{name}'\nValid names are: {}",
+                    Theme::names().join(", ")
+                )));
+            };
+            *slot = color.to_str()?.parse().map_err(|e| {
+                LuaError::RuntimeError(format!("Invalid theme color '{name}' - {e}"))
+            })?;
+        }
+        Ok(Self(theme))
+    }
+}
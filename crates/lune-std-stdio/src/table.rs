@@ -0,0 +1,196 @@
+use std::{cmp::Ordering, fmt::Write as _};
+
+use console::colors_enabled;
+use mlua::prelude::*;
+
+use lune_utils::fmt::{
+    indent_output, pretty_format_value, write_output, OutputStream, ValueFormatConfig,
+};
+
+use super::style_and_color::StyleKind;
+
+// NOTE: Nested tables are not expanded inside of cells, they would
+// make rows span several lines and the table would become unreadable
+const CELL_FORMAT_CONFIG: ValueFormatConfig = ValueFormatConfig::new()
+    .with_max_depth(0)
+    .with_colors_enabled(false);
+
+const CELL_MAX_WIDTH: usize = 32;
+const CELL_TRUNCATED: &str = "...";
+
+const HEADER_INDEX: &str = "(index)";
+const HEADER_VALUES: &str = "Values";
+
+const EMPTY_TABLE: &str = "(empty table)";
+
+/**
+    A column in a formatted table, either for a key
+    in one or more of the rows, or for non-table rows.
+*/
+#[derive(Debug, Clone, PartialEq)]
+enum TableColumn<'lua> {
+    Key(LuaValue<'lua>),
+    Values,
+}
+
+/**
+    Formats the given table of rows as an aligned ASCII table.
+
+    Columns are derived from the union of keys in all of the rows, and the first
+    column contains the key of each row. Rows that are not tables are written
+    to a separate `Values` column at the end of the table.
+*/
+pub fn format_table(data: LuaTable) -> LuaResult<String> {
+    let mut rows = data
+        .pairs::<LuaValue, LuaValue>()
+        .collect::<LuaResult<Vec<_>>>()?;
+    if rows.is_empty() {
+        return Ok(format!("{EMPTY_TABLE}\n"));
+    }
+    rows.sort_by(|(a, _), (b, _)| compare_keys(a, b));
+
+    let mut keys = Vec::new();
+    let mut has_values = false;
+    for (_, row) in &rows {
+        if let LuaValue::Table(row) = row {
+            for key in row.clone().pairs::<LuaValue, LuaValue>() {
+                let (key, _) = key?;
+                if !keys.contains(&key) {
+                    keys.push(key);
+                }
+            }
+        } else {
+            has_values = true;
+        }
+    }
+    keys.sort_by(compare_keys);
+
+    let mut columns = keys.into_iter().map(TableColumn::Key).collect::<Vec<_>>();
+    if has_values {
+        columns.push(TableColumn::Values);
+    }
+
+    let mut header = vec![HEADER_INDEX.to_string()];
+    header.extend(columns.iter().map(|column| match column {
+        TableColumn::Key(key) => format_cell(key),
+        TableColumn::Values => HEADER_VALUES.to_string(),
+    }));
+
+    let mut cells = Vec::new();
+    for (key, row) in &rows {
+        let mut line = vec![format_cell(key)];
+        for column in &columns {
+            let cell = match (column, row) {
+                (TableColumn::Key(key), LuaValue::Table(row)) => {
+                    match row.raw_get::<_, LuaValue>(key.clone())? {
+                        LuaValue::Nil => String::new(),
+                        value => format_cell(&value),
+                    }
+                }
+                (TableColumn::Values, value) if !matches!(value, LuaValue::Table(_)) => {
+                    format_cell(value)
+                }
+                _ => String::new(),
+            };
+            line.push(cell);
+        }
+        cells.push(line);
+    }
+
+    let widths = header
+        .iter()
+        .enumerate()
+        .map(|(index, name)| {
+            cells
+                .iter()
+                .map(|line| cell_width(&line[index]))
+                .chain([cell_width(name)])
+                .max()
+                .unwrap_or_default()
+        })
+        .collect::<Vec<_>>();
+
+    let separator = format_separator(&widths);
+    let mut output = String::new();
+    output.push_str(&separator);
+    output.push_str(&format_line(&header, &widths, true));
+    output.push_str(&separator);
+    for line in &cells {
+        output.push_str(&format_line(line, &widths, false));
+    }
+    output.push_str(&separator);
+
+    Ok(output)
+}
+
+/**
+    Formats the given table of rows and writes it to stdout.
+*/
+pub fn write_table(lua: &Lua, data: LuaTable) -> LuaResult<()> {
+    let formatted = indent_output(lua, &format_table(data)?);
+    write_output(lua, OutputStream::Stdout, formatted.as_bytes())?;
+    Ok(())
+}
+
+fn compare_keys(a: &LuaValue, b: &LuaValue) -> Ordering {
+    // Numeric keys come first, in numeric order, then
+    // any other keys ordered by their formatted string
+    match (a.as_f64(), b.as_f64()) {
+        (Some(a), Some(b)) => a.total_cmp(&b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => format_cell(a).cmp(&format_cell(b)),
+    }
+}
+
+fn format_cell(value: &LuaValue) -> String {
+    let formatted = match value {
+        LuaValue::String(s) => s.to_string_lossy().to_string(),
+        value => pretty_format_value(value, &CELL_FORMAT_CONFIG),
+    };
+    // NOTE: Cells must stay on a single line to keep the table aligned
+    let formatted = formatted.replace(['\r', '\n', '\t'], " ");
+    if cell_width(&formatted) > CELL_MAX_WIDTH {
+        let truncated = formatted
+            .chars()
+            .take(CELL_MAX_WIDTH - CELL_TRUNCATED.len())
+            .collect::<String>();
+        format!("{truncated}{CELL_TRUNCATED}")
+    } else {
+        formatted
+    }
+}
+
+fn cell_width(cell: &str) -> usize {
+    cell.chars().count()
+}
+
+fn format_separator(widths: &[usize]) -> String {
+    let mut separator = String::from("+");
+    for width in widths {
+        separator.push_str(&"-".repeat(width + 2));
+        separator.push('+');
+    }
+    separator.push('\n');
+    separator
+}
+
+fn format_line(line: &[String], widths: &[usize], is_header: bool) -> String {
+    let mut formatted = String::from("|");
+    for (cell, width) in line.iter().zip(widths) {
+        let padding = " ".repeat(width - cell_width(cell));
+        if is_header && colors_enabled() {
+            write!(
+                formatted,
+                " {}{cell}{}{padding} |",
+                StyleKind::Bold.ansi_escape_sequence(),
+                StyleKind::Reset.ansi_escape_sequence(),
+            )
+        } else {
+            write!(formatted, " {cell}{padding} |")
+        }
+        .expect("writing into strings should never fail");
+    }
+    formatted.push('\n');
+    formatted
+}
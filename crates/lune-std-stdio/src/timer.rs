@@ -0,0 +1,63 @@
+use std::{
+    cell::RefCell,
+    collections::{hash_map::Entry, HashMap},
+    io,
+    rc::Rc,
+    time::Instant,
+};
+
+use mlua::prelude::*;
+
+use lune_utils::fmt::{indent_output, theme, write_output, Label, OutputStream};
+
+const DEFAULT_LABEL: &str = "default";
+
+/**
+    Creates the timer functions for the `stdio` standard library.
+
+    All of the functions share the same set of running timers,
+    which are started using `time` and stopped using `timeEnd`.
+*/
+pub fn create_timer_functions(lua: &Lua) -> LuaResult<Vec<(&'static str, LuaFunction)>> {
+    let timers = Rc::new(RefCell::new(HashMap::<String, Instant>::new()));
+
+    let timers_start = Rc::clone(&timers);
+    let time = lua.create_function(move |lua, label: Option<String>| {
+        let label = label.unwrap_or_else(|| DEFAULT_LABEL.to_string());
+        match timers_start.borrow_mut().entry(label) {
+            Entry::Occupied(entry) => {
+                write_warning(lua, &format!("Timer '{}' already exists", entry.key()))?;
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(Instant::now());
+            }
+        }
+        Ok(())
+    })?;
+
+    let time_end = lua.create_function(move |lua, label: Option<String>| {
+        let label = label.unwrap_or_else(|| DEFAULT_LABEL.to_string());
+        let Some(started) = timers.borrow_mut().remove(&label) else {
+            write_warning(lua, &format!("Timer '{label}' does not exist"))?;
+            return Ok(None);
+        };
+        let elapsed = started.elapsed();
+        let theme = theme();
+        let formatted = format!(
+            "{}{} {:.3}ms\n",
+            theme.label.apply_bold(&label),
+            theme.dim.apply(":"),
+            elapsed.as_secs_f64() * 1000.0
+        );
+        let formatted = indent_output(lua, &formatted);
+        write_output(lua, OutputStream::Stdout, formatted.as_bytes())?;
+        Ok(Some(elapsed.as_secs_f64()))
+    })?;
+
+    Ok(vec![("time", time), ("timeEnd", time_end)])
+}
+
+fn write_warning(lua: &Lua, message: &str) -> io::Result<()> {
+    let formatted = indent_output(lua, &format!("{} {message}\n", Label::Warn));
+    write_output(lua, OutputStream::Stderr, formatted.as_bytes())
+}
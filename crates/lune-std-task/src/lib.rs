@@ -1,14 +1,31 @@
 #![allow(clippy::cargo_common_metadata)]
 
-use std::time::Duration;
+use std::{cell::RefCell, fmt::Write as _, rc::Rc, time::Duration};
 
+use futures_util::StreamExt;
 use mlua::prelude::*;
 use mlua_luau_scheduler::Functions;
+use once_cell::sync::Lazy;
 
-use tokio::time::{sleep, Instant};
+use tokio::{
+    pin, select,
+    sync::oneshot,
+    time::{sleep, Instant},
+};
 
 use lune_utils::TableBuilder;
 
+mod stats;
+mod timer;
+
+use self::stats::{stats, TaskTracker};
+
+// NOTE: Waits use our own timer instead of the one in tokio, which fires on ticks of one
+// millisecond, and its resolution depends on the OS, so it is measured once, when first needed
+static TIMER_RESOLUTION: Lazy<Duration> = Lazy::new(timer::measure_resolution);
+
+const ERROR_HANDLER_KEY: &str = "TaskErrorHandler";
+
 /**
     Creates the `task` standard library module.
 
@@ -19,48 +36,683 @@ use lune_utils::TableBuilder;
 pub fn module(lua: &Lua) -> LuaResult<LuaTable> {
     let fns = Functions::new(lua)?;
 
-    // Create wait & delay functions
-    let task_wait = lua.create_async_function(wait)?;
-    let task_delay_env = TableBuilder::new(lua)?
-        .with_value("select", lua.globals().get::<_, LuaFunction>("select")?)?
+    // Create the tracker for task.stats and task.join, which all of the functions below add tasks to
+    let tracker = Rc::new(TaskTracker::new());
+    let tracker_stats = Rc::clone(&tracker);
+    let task_stats = lua.create_function(move |lua, ()| stats(lua, &tracker_stats))?;
+    let task_set_error_handler = lua.create_function(set_error_handler)?;
+
+    // Create wait function, which tasks waiting on timers are counted by
+    let coroutine = lua.globals().get::<_, LuaTable>("coroutine")?;
+    let tracker_wait = Rc::clone(&tracker);
+    let task_wait = lua.create_async_function(move |lua, secs| {
+        let tracker = Rc::clone(&tracker_wait);
+        async move { wait(lua, &tracker, secs).await }
+    })?;
+
+    // Create spawn, defer & delay functions, which run functions in task threads
+    let runner = create_runner(lua, &tracker, &task_wait)?;
+    let (task_spawn, task_defer, task_delay) = create_spawn_defer(lua, &fns, &tracker, runner)?;
+    let tracker_cancel = Rc::clone(&tracker);
+    let cancel_key = lua.create_registry_value(fns.cancel.clone())?;
+    let task_cancel = lua.create_function(move |lua, thread: LuaThread| {
+        let cancel = lua.registry_value::<LuaFunction>(&cancel_key)?;
+        cancel.call::<_, ()>(&thread)?;
+        let joiners = tracker_cancel.untrack(&thread);
+        let message = lua.create_string("Task was cancelled")?;
+        notify_joiners(lua, joiners, false, LuaValue::String(message))
+    })?;
+
+    // Create join function, which needs to spawn and resume threads
+    let tracker_is_task = Rc::clone(&tracker);
+    let task_is_task =
+        lua.create_function(move |_, thread: LuaThread| Ok(tracker_is_task.is_task(&thread)))?;
+    let tracker_watch = Rc::clone(&tracker);
+    let task_watch =
+        lua.create_function(move |lua, (thread, func): (LuaThread, LuaFunction)| {
+            Ok(tracker_watch.add_joiner(&thread, lua.create_registry_value(func)?))
+        })?;
+    let task_join_env = TableBuilder::new(lua)?
+        .with_value("error", lua.globals().get::<_, LuaFunction>("error")?)?
+        .with_value("pcall", lua.globals().get::<_, LuaFunction>("pcall")?)?
+        .with_value("tostring", lua.globals().get::<_, LuaFunction>("tostring")?)?
+        .with_value("type", lua.globals().get::<_, LuaFunction>("type")?)?
+        .with_value("running", coroutine.get::<_, LuaFunction>("running")?)?
+        .with_value("status", coroutine.get::<_, LuaFunction>("status")?)?
+        .with_value("yield", coroutine.get::<_, LuaFunction>("yield")?)?
         .with_value("spawn", fns.spawn.clone())?
+        .with_value("isTask", task_is_task)?
+        .with_value("watch", task_watch)?
+        .build_readonly()?;
+    let task_join = lua
+        .load(JOIN_IMPL_LUA)
+        .set_name("task.join")
+        .set_environment(task_join_env)
+        .into_function()?;
+
+    // Create yield function, which defers the current thread
+    // and yields it, resuming it again on the next tick
+    let task_yield_env = TableBuilder::new(lua)?
+        .with_value("running", coroutine.get::<_, LuaFunction>("running")?)?
+        .with_value("yield", coroutine.get::<_, LuaFunction>("yield")?)?
         .with_value("defer", fns.defer.clone())?
-        .with_value("wait", task_wait.clone())?
         .build_readonly()?;
-    let task_delay = lua
-        .load(DELAY_IMPL_LUA)
-        .set_name("task.delay")
-        .set_environment(task_delay_env)
+    let task_yield = lua
+        .load(YIELD_IMPL_LUA)
+        .set_name("task.yield")
+        .set_environment(task_yield_env)
         .into_function()?;
 
+    // Create timeout function, which runs the given function in a
+    // separate thread that we drive until it completes or times out
+    let task_timeout = create_timeout(lua, &fns)?;
+
+    // Create semaphore & limit functions, which queue up threads
+    // and resume them once a permit has been released
+    let (task_semaphore, task_limit) = create_semaphore(lua, &fns)?;
+
     // Overwrite resume & wrap functions on the coroutine global
     // with ones that are compatible with our scheduler
-    let co = lua.globals().get::<_, LuaTable>("coroutine")?;
-    co.set("resume", fns.resume.clone())?;
-    co.set("wrap", fns.wrap.clone())?;
+    coroutine.set("resume", fns.resume.clone())?;
+    coroutine.set("wrap", fns.wrap.clone())?;
 
     TableBuilder::new(lua)?
-        .with_value("cancel", fns.cancel)?
-        .with_value("defer", fns.defer)?
+        .with_value("cancel", task_cancel)?
+        .with_value("defer", task_defer)?
         .with_value("delay", task_delay)?
-        .with_value("spawn", fns.spawn)?
+        .with_value("join", task_join)?
+        .with_value("limit", task_limit)?
+        .with_value("resolution", TIMER_RESOLUTION.as_secs_f64())?
+        .with_value("semaphore", task_semaphore)?
+        .with_value("setErrorHandler", task_set_error_handler)?
+        .with_value("spawn", task_spawn)?
+        .with_value("stats", task_stats)?
+        .with_value("timeout", task_timeout)?
         .with_value("wait", task_wait)?
+        .with_value("yield", task_yield)?
         .build_readonly()
 }
 
-const DELAY_IMPL_LUA: &str = r"
-return defer(function(...)
-    wait(select(1, ...))
-    spawn(select(2, ...))
-end, ...)
+fn create_runner<'lua>(
+    lua: &'lua Lua,
+    tracker: &Rc<TaskTracker>,
+    task_wait: &LuaFunction<'lua>,
+) -> LuaResult<LuaFunction<'lua>> {
+    let tracker_finish = Rc::clone(tracker);
+    let task_finish = lua.create_function(move |lua, (ok, value): (bool, LuaValue)| {
+        let handler = if ok {
+            None
+        } else {
+            lua.named_registry_value::<Option<LuaFunction>>(ERROR_HANDLER_KEY)?
+        };
+        let joiners = tracker_finish.untrack(&lua.current_thread());
+        notify_joiners(lua, joiners, ok, value)?;
+        Ok(handler)
+    })?;
+    let task_runner_env = TableBuilder::new(lua)?
+        .with_value("select", lua.globals().get::<_, LuaFunction>("select")?)?
+        .with_value("xpcall", lua.globals().get::<_, LuaFunction>("xpcall")?)?
+        .with_value("error", lua.globals().get::<_, LuaFunction>("error")?)?
+        .with_value(
+            "pack",
+            lua.globals()
+                .get::<_, LuaTable>("table")?
+                .get::<_, LuaFunction>("pack")?,
+        )?
+        .with_value("unpack", lua.globals().get::<_, LuaFunction>("unpack")?)?
+        .with_value("traceback", lua.create_function(error_with_traceback)?)?
+        .with_value("finish", task_finish)?
+        .with_value("wait", task_wait.clone())?
+        .build_readonly()?;
+    lua.load(RUNNER_IMPL_LUA)
+        .set_name("task")
+        .set_environment(task_runner_env)
+        .into_function()
+}
+
+fn create_spawn_defer<'lua>(
+    lua: &'lua Lua,
+    fns: &Functions<'lua>,
+    tracker: &Rc<TaskTracker>,
+    runner: LuaFunction<'lua>,
+) -> LuaResult<(LuaFunction<'lua>, LuaFunction<'lua>, LuaFunction<'lua>)> {
+    let runner_key = Rc::new(lua.create_registry_value(runner)?);
+    let spawn_key = Rc::new(lua.create_registry_value(fns.spawn.clone())?);
+
+    let (runner_spawn, spawn_spawn) = (Rc::clone(&runner_key), Rc::clone(&spawn_key));
+    let tracker_spawn = Rc::clone(tracker);
+    let task_spawn = lua.create_function(move |lua, (tof, args): (LuaValue, LuaMultiValue)| {
+        let spawn = lua.registry_value::<LuaFunction>(&spawn_spawn)?;
+        let (thread, args) = match tof {
+            LuaValue::Function(func) => {
+                let runner = lua.registry_value::<LuaFunction>(&runner_spawn)?;
+                let thread = create_task_thread(lua, &tracker_spawn, runner)?;
+                (thread, task_args(lua, None, func, args)?)
+            }
+            LuaValue::Thread(thread) => {
+                tracker_spawn.set_active(&thread);
+                (thread, args)
+            }
+            value => return Err(expected_thread_or_function(&value)),
+        };
+        tracker_spawn.resuming(lua, || spawn.call::<_, LuaThread>((thread, args)))
+    })?;
+
+    // NOTE: Deferring the function that marks deferred tasks as resumed is done at most once
+    // for each batch of deferred tasks, instead of once for every task, to keep deferring cheap
+    let defer_key = Rc::new(lua.create_registry_value(fns.defer.clone())?);
+    let defer_undefer = Rc::clone(&defer_key);
+    let tracker_undefer = Rc::clone(tracker);
+    let undefer_key: Rc<RefCell<Option<LuaRegistryKey>>> = Rc::new(RefCell::new(None));
+    let undefer_inner = Rc::clone(&undefer_key);
+    let task_undefer = lua.create_function(move |lua, ()| {
+        if tracker_undefer.undefer() {
+            defer_undefer_fn(lua, &defer_undefer, &undefer_inner)?;
+        }
+        Ok(())
+    })?;
+    undefer_key.replace(Some(lua.create_registry_value(task_undefer)?));
+
+    let runner_defer = Rc::clone(&runner_key);
+    let tracker_defer = Rc::clone(tracker);
+    let task_defer = lua.create_function(move |lua, (tof, args): (LuaValue, LuaMultiValue)| {
+        let defer = lua.registry_value::<LuaFunction>(&defer_key)?;
+        let (thread, args) = match tof {
+            LuaValue::Function(func) => {
+                let runner = lua.registry_value::<LuaFunction>(&runner_defer)?;
+                let thread = create_task_thread(lua, &tracker_defer, runner)?;
+                (thread, task_args(lua, None, func, args)?)
+            }
+            LuaValue::Thread(thread) => (thread, args),
+            value => return Err(expected_thread_or_function(&value)),
+        };
+        let thread = defer.call::<_, LuaThread>((thread, args))?;
+        if tracker_defer.set_deferred(&thread) {
+            defer_undefer_fn(lua, &defer_key, &undefer_key)?;
+        }
+        Ok(thread)
+    })?;
+
+    // NOTE: The delaying thread is spawned instead of deferred so that its timer starts
+    // right away, making delayed functions resume in the same order as other waiting
+    // threads do, ordered by when they were scheduled instead of when they were deferred
+    let tracker_delay = Rc::clone(tracker);
+    let task_delay = lua.create_function(
+        move |lua, (secs, tof, args): (Option<f64>, LuaValue, LuaMultiValue)| {
+            let spawn = lua.registry_value::<LuaFunction>(&spawn_key)?;
+            let runner = lua.registry_value::<LuaFunction>(&runner_key)?;
+            let delay = Some(secs.unwrap_or_default());
+            let args = match tof {
+                LuaValue::Function(func) => task_args(lua, delay, func, args)?,
+                LuaValue::Thread(thread) => {
+                    let args = (thread, args).into_lua_multi(lua)?;
+                    task_args(lua, delay, spawn.clone(), args)?
+                }
+                value => return Err(expected_thread_or_function(&value)),
+            };
+            let thread = create_task_thread(lua, &tracker_delay, runner)?;
+            tracker_delay.resuming(lua, || spawn.call::<_, LuaThread>((thread, args)))
+        },
+    )?;
+
+    Ok((task_spawn, task_defer, task_delay))
+}
+
+fn defer_undefer_fn(
+    lua: &Lua,
+    defer_key: &LuaRegistryKey,
+    undefer_key: &RefCell<Option<LuaRegistryKey>>,
+) -> LuaResult<()> {
+    let defer = lua.registry_value::<LuaFunction>(defer_key)?;
+    let undefer = match undefer_key.borrow().as_ref() {
+        Some(key) => lua.registry_value::<LuaFunction>(key)?,
+        None => return Ok(()),
+    };
+    defer.call::<_, ()>(undefer)
+}
+
+fn create_timeout<'lua>(lua: &'lua Lua, fns: &Functions<'lua>) -> LuaResult<LuaFunction<'lua>> {
+    let task_timeout_runner_env = TableBuilder::new(lua)?
+        .with_value("pcall", lua.globals().get::<_, LuaFunction>("pcall")?)?
+        .with_value("select", lua.globals().get::<_, LuaFunction>("select")?)?
+        .with_value(
+            "pack",
+            lua.globals()
+                .get::<_, LuaTable>("table")?
+                .get::<_, LuaFunction>("pack")?,
+        )?
+        .build_readonly()?;
+    let task_timeout_runner = lua
+        .load(TIMEOUT_RUNNER_IMPL_LUA)
+        .set_name("task.timeout")
+        .set_environment(task_timeout_runner_env)
+        .into_function()?;
+    let runner_key = lua.create_registry_value(task_timeout_runner)?;
+    let cancel_key = lua.create_registry_value(fns.cancel.clone())?;
+    let task_timeout_run = lua.create_async_function(move |lua, (secs, func, args)| {
+        let runner = lua.registry_value(&runner_key);
+        let cancel = lua.registry_value(&cancel_key);
+        async move { run_with_timeout(lua, runner?, cancel?, secs, func, args).await }
+    })?;
+    let task_timeout_env = TableBuilder::new(lua)?
+        .with_value("error", lua.globals().get::<_, LuaFunction>("error")?)?
+        .with_value("select", lua.globals().get::<_, LuaFunction>("select")?)?
+        .with_value("type", lua.globals().get::<_, LuaFunction>("type")?)?
+        .with_value("unpack", lua.globals().get::<_, LuaFunction>("unpack")?)?
+        .with_value("run", task_timeout_run)?
+        .build_readonly()?;
+    lua.load(TIMEOUT_IMPL_LUA)
+        .set_name("task.timeout")
+        .set_environment(task_timeout_env)
+        .into_function()
+}
+
+fn create_semaphore<'lua>(
+    lua: &'lua Lua,
+    fns: &Functions<'lua>,
+) -> LuaResult<(LuaFunction<'lua>, LuaFunction<'lua>)> {
+    let coroutine = lua.globals().get::<_, LuaTable>("coroutine")?;
+    let table = lua.globals().get::<_, LuaTable>("table")?;
+    let task_semaphore_env = TableBuilder::new(lua)?
+        .with_value("error", lua.globals().get::<_, LuaFunction>("error")?)?
+        .with_value("tostring", lua.globals().get::<_, LuaFunction>("tostring")?)?
+        .with_value("type", lua.globals().get::<_, LuaFunction>("type")?)?
+        .with_value("insert", table.get::<_, LuaFunction>("insert")?)?
+        .with_value("remove", table.get::<_, LuaFunction>("remove")?)?
+        .with_value("running", coroutine.get::<_, LuaFunction>("running")?)?
+        .with_value("status", coroutine.get::<_, LuaFunction>("status")?)?
+        .with_value("yield", coroutine.get::<_, LuaFunction>("yield")?)?
+        .with_value("spawn", fns.spawn.clone())?
+        .build_readonly()?;
+    let task_semaphore = lua
+        .load(SEMAPHORE_IMPL_LUA)
+        .set_name("task.semaphore")
+        .set_environment(task_semaphore_env)
+        .into_function()?;
+    let task_limit_env = TableBuilder::new(lua)?
+        .with_value("error", lua.globals().get::<_, LuaFunction>("error")?)?
+        .with_value("pcall", lua.globals().get::<_, LuaFunction>("pcall")?)?
+        .with_value("type", lua.globals().get::<_, LuaFunction>("type")?)?
+        .with_value("unpack", lua.globals().get::<_, LuaFunction>("unpack")?)?
+        .with_value("pack", table.get::<_, LuaFunction>("pack")?)?
+        .with_value("semaphore", task_semaphore.clone())?
+        .build_readonly()?;
+    let task_limit = lua
+        .load(LIMIT_IMPL_LUA)
+        .set_name("task.limit")
+        .set_environment(task_limit_env)
+        .into_function()?;
+    Ok((task_semaphore, task_limit))
+}
+
+// NOTE: Functions given to spawn, defer and delay are run by this, so that tasks are
+// untracked once they finish - errors are rethrown with the traceback of where they were
+// thrown, or passed to the error handler, instead of the default error callback, if set
+const RUNNER_IMPL_LUA: &str = r"
+local delay, func = ...
+if delay then
+    wait(delay)
+end
+local results = pack(xpcall(func, traceback, select(3, ...)))
+local handler = finish(results[1], results[2])
+if results[1] then
+    return unpack(results, 2, results.n)
+elseif handler ~= nil then
+    handler(results[2])
+else
+    error(results[2], 0)
+end
+";
+
+const JOIN_IMPL_LUA: &str = r#"
+local tasks = ...
+if type(tasks) ~= "table" then
+    error("Expected a table of functions or threads to join, got " .. type(tasks), 2)
+end
+local remaining = 0
+for index, value in tasks do
+    if type(value) == "thread" then
+        if status(value) ~= "dead" and not isTask(value) then
+            error("Expected a thread created using task.spawn, task.defer or task.delay to join at index " .. tostring(index), 2)
+        end
+    elseif type(value) ~= "function" then
+        error("Expected a function or thread to join at index " .. tostring(index) .. ", got " .. type(value), 2)
+    end
+    remaining += 1
+end
+
+local current = running()
+local results, errors = {}, {}
+local waiting = false
+
+local function settle(index, success, result)
+    if success then
+        results[index] = result
+    else
+        errors[index] = result
+    end
+    remaining -= 1
+    if remaining == 0 and waiting then
+        spawn(current)
+    end
+end
+
+-- NOTE: Threads that are not tasks that are still running have already finished,
+-- and since their results have already been given to whoever resumed them,
+-- they are settled right away without any result
+for index, value in tasks do
+    if type(value) == "thread" then
+        if not watch(value, function(success, result)
+            settle(index, success, result)
+        end) then
+            settle(index, true, nil)
+        end
+    else
+        spawn(function()
+            settle(index, pcall(value))
+        end)
+    end
+end
+
+if remaining > 0 then
+    waiting = true
+    yield()
+end
+
+-- NOTE: Array indices are checked in order first, so that the first function
+-- that errored is the one mentioned, and then any other keys the table had
+for index = 1, #tasks do
+    if errors[index] ~= nil then
+        error("Joined task #" .. tostring(index) .. " errored: " .. tostring(errors[index]), 2)
+    end
+end
+for index, message in errors do
+    error("Joined task '" .. tostring(index) .. "' errored: " .. tostring(message), 2)
+end
+
+return results
+"#;
+
+const LIMIT_IMPL_LUA: &str = r#"
+local permits, func = ...
+if type(func) ~= "function" then
+    error("Expected a function to limit, got " .. type(func), 2)
+end
+
+local limiter = semaphore(permits)
+return function(...)
+    limiter:acquire()
+    local results = pack(pcall(func, ...))
+    limiter:release()
+    if not results[1] then
+        error(results[2], 0)
+    end
+    return unpack(results, 2, results.n)
+end
+"#;
+
+// NOTE: Permits are handed over directly to the next waiting thread when released,
+// so that threads calling acquire can not take a permit from a thread that has been
+// waiting for longer - threads that were cancelled while waiting are skipped over
+const SEMAPHORE_IMPL_LUA: &str = r#"
+local permits = ...
+if type(permits) ~= "number" or permits < 1 or permits % 1 ~= 0 then
+    error("Expected a positive integer as the number of permits, got " .. tostring(permits), 2)
+end
+
+local available = permits
+local waiting = {}
+
+local semaphore = {}
+
+function semaphore.acquire()
+    if available > 0 then
+        available -= 1
+        return
+    end
+    insert(waiting, running())
+    yield()
+end
+
+function semaphore.release()
+    while #waiting > 0 do
+        local thread = remove(waiting, 1)
+        if status(thread) == "suspended" then
+            spawn(thread)
+            return
+        end
+    end
+    if available >= permits then
+        error("Semaphore was released more times than it was acquired", 2)
+    end
+    available += 1
+end
+
+function semaphore.available()
+    return available
+end
+
+return semaphore
+"#;
+
+const TIMEOUT_IMPL_LUA: &str = r#"
+local duration, func = ...
+if type(duration) ~= "number" then
+    error("Expected a number of seconds as the timeout duration, got " .. type(duration), 2)
+end
+if type(func) ~= "function" then
+    error("Expected a function to run with a timeout, got " .. type(func), 2)
+end
+
+local results = run(duration, func, select(3, ...))
+if results == nil then
+    return false
+elseif not results[1] then
+    error(results[2], 0)
+end
+return true, unpack(results, 2, results.n)
+"#;
+
+const TIMEOUT_RUNNER_IMPL_LUA: &str = r"
+local finish, func = ...
+finish(pack(pcall(func, select(3, ...))))
 ";
 
-async fn wait(_: &Lua, secs: Option<f64>) -> LuaResult<f64> {
-    let duration = Duration::from_secs_f64(secs.unwrap_or_default());
+const YIELD_IMPL_LUA: &str = r"
+defer(running())
+yield()
+";
+
+fn set_error_handler(lua: &Lua, handler: Option<LuaFunction>) -> LuaResult<()> {
+    lua.set_named_registry_value(ERROR_HANDLER_KEY, handler)
+}
+
+/**
+    Creates the thread for a new task, and starts tracking it for `task.stats`.
+*/
+fn create_task_thread<'lua>(
+    lua: &'lua Lua,
+    tracker: &TaskTracker,
+    runner: LuaFunction<'lua>,
+) -> LuaResult<LuaThread<'lua>> {
+    let thread = lua.create_thread(runner)?;
+    tracker.track(&thread);
+    Ok(thread)
+}
+
+/**
+    Creates the arguments for the task runner, which are the
+    duration to delay for, the function to run, and its arguments.
+
+    Deferred threads get their arguments from a sequence, which can
+    not start with `nil`, so `false` is used when not delaying instead.
+*/
+fn task_args<'lua>(
+    lua: &'lua Lua,
+    delay: Option<f64>,
+    func: LuaFunction<'lua>,
+    args: LuaMultiValue<'lua>,
+) -> LuaResult<LuaMultiValue<'lua>> {
+    let delay = delay.map_or(LuaValue::Boolean(false), LuaValue::Number);
+    (delay, func, args).into_lua_multi(lua)
+}
+
+fn expected_thread_or_function(value: &LuaValue) -> LuaError {
+    LuaError::FromLuaConversionError {
+        from: value.type_name(),
+        to: "LuaThreadOrFunction",
+        message: Some("Expected thread or function".to_string()),
+    }
+}
+
+/**
+    Calls the functions that were waiting for a task to finish, with its first result or error.
+*/
+fn notify_joiners<'lua>(
+    lua: &'lua Lua,
+    joiners: Vec<LuaRegistryKey>,
+    ok: bool,
+    value: LuaValue<'lua>,
+) -> LuaResult<()> {
+    // NOTE: Errors rethrown by the task runner have their traceback added to them,
+    // which is left out here so that the error is the same as the one that was thrown
+    let value = match value {
+        LuaValue::Error(LuaError::RuntimeError(message)) => {
+            let message = message
+                .split("\nstack traceback:")
+                .next()
+                .unwrap_or_default();
+            LuaValue::String(lua.create_string(message)?)
+        }
+        value => value,
+    };
+    for joiner in joiners {
+        let joiner = lua.registry_value::<LuaFunction>(&joiner)?;
+        joiner.call::<_, ()>((ok, value.clone()))?;
+    }
+    Ok(())
+}
+
+/**
+    Adds the traceback of where an error was thrown to it, for errors that are
+    rethrown by the task runner, unless they will be passed to the error handler.
+
+    This is called before the stack is unwound, and the two outermost levels,
+    which are the task runner and the `xpcall` it uses, are left out.
+*/
+fn error_with_traceback<'lua>(lua: &'lua Lua, err: LuaValue<'lua>) -> LuaResult<LuaValue<'lua>> {
+    let LuaValue::String(message) = &err else {
+        return Ok(err);
+    };
+    if lua
+        .named_registry_value::<Option<LuaFunction>>(ERROR_HANDLER_KEY)?
+        .is_some()
+    {
+        return Ok(err);
+    }
+
+    let levels = (1..)
+        .map_while(|level| lua.inspect_stack(level))
+        .collect::<Vec<_>>();
+    let mut message = message.to_string_lossy().to_string();
+    message.push_str("\nstack traceback:");
+    for level in &levels[..levels.len().saturating_sub(2)] {
+        let source = level.source();
+        let short_src = source.short_src.as_deref().unwrap_or("?");
+        let line = match level.curr_line() {
+            line if line > 0 => format!("{line}:"),
+            _ => String::new(),
+        };
+        let name = match level.names().name {
+            Some(name) => format!("function '{name}'"),
+            None => "?".to_string(),
+        };
+        write!(message, "\n\t{short_src}:{line} in {name}")
+            .expect("writing into strings should never fail");
+    }
+
+    Ok(LuaValue::Error(LuaError::RuntimeError(message)))
+}
+
+async fn wait(lua: &Lua, tracker: &Rc<TaskTracker>, secs: Option<f64>) -> LuaResult<f64> {
+    // NOTE: Negative and NaN durations wait for as short as possible, same as
+    // no duration, and durations that are too large to represent wait forever
+    let secs = secs.unwrap_or_default().max(0.0);
+    let duration = Duration::try_from_secs_f64(secs).unwrap_or(Duration::MAX);
+
+    // NOTE: The current thread must not be held on to while waiting, since this
+    // future may get dropped while Lua is collecting garbage, where refs can't be released
+    let delayed = tracker.set_delayed(&lua.current_thread());
 
     let before = Instant::now();
-    sleep(duration).await;
+    match before.checked_add(duration) {
+        Some(deadline) if !duration.is_zero() => timer::sleep_until(deadline.into_std()).await,
+        _ => sleep(duration).await,
+    }
     let after = Instant::now();
 
+    drop(delayed);
+
     Ok((after - before).as_secs_f64())
 }
+
+async fn run_with_timeout<'lua>(
+    lua: &'lua Lua,
+    runner: LuaFunction<'lua>,
+    cancel: LuaFunction<'lua>,
+    secs: f64,
+    func: LuaFunction<'lua>,
+    args: LuaMultiValue<'lua>,
+) -> LuaResult<Option<LuaTable<'lua>>> {
+    let secs = secs.max(0.0);
+    let duration = Duration::try_from_secs_f64(secs).unwrap_or(Duration::MAX);
+
+    // The runner passes its packed results back using this finish function,
+    // which also works if the thread ends up being resumed by the scheduler
+    let (tx, mut rx) = oneshot::channel();
+    let tx = RefCell::new(Some(tx));
+    let finish = lua.create_function(move |lua, results: LuaTable| {
+        if let Some(tx) = tx.borrow_mut().take() {
+            tx.send(lua.create_registry_value(results)?).ok();
+        }
+        Ok(())
+    })?;
+
+    let thread = lua.create_thread(runner)?;
+    let mut stream = thread.clone().into_async::<_, ()>((finish, func, args));
+    let mut driving = true;
+
+    let deadline = sleep(duration);
+    pin!(deadline);
+
+    loop {
+        select! {
+            biased;
+            res = &mut rx => {
+                let key = res.into_lua_err()?;
+                let results = lua.registry_value(&key)?;
+                lua.remove_registry_value(key)?;
+                return Ok(Some(results));
+            }
+            res = stream.next(), if driving => match res {
+                Some(Err(e)) => return Err(e),
+                // NOTE: If the thread yielded manually it will be resumed by the
+                // scheduler and not by us, and we must no longer drive it here
+                Some(Ok(())) if thread.status() == LuaThreadStatus::Resumable => driving = false,
+                _ => {}
+            },
+            () = &mut deadline => break,
+        }
+    }
+
+    // NOTE: Cancelling the thread does not drop any future that it was waiting on,
+    // such as a request, until it has been garbage collected - we collect right
+    // away to make sure that any such operation actually gets interrupted
+    drop(stream);
+    cancel.call::<_, ()>(thread)?;
+    if driving {
+        lua.gc_collect()?;
+    }
+
+    Ok(None)
+}
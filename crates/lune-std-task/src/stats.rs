@@ -0,0 +1,261 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    rc::Rc,
+};
+
+use mlua::prelude::*;
+
+use lune_utils::TableBuilder;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TaskState {
+    Active,
+    Deferred,
+    Delayed,
+}
+
+struct Task {
+    id: u64,
+    state: TaskState,
+    joiners: Vec<LuaRegistryKey>,
+}
+
+/**
+    Keeps track of tasks created using the `task` library, for `task.stats` and `task.join`.
+
+    Tasks are kept by the address of their thread, which is never held on to, and they are
+    removed by the task runner once they finish, or when they are cancelled. Counts for each
+    state are updated along with the tasks themselves, so that reading them is always cheap.
+*/
+pub(crate) struct TaskTracker {
+    tasks: RefCell<HashMap<usize, Task>>,
+    deferred: Cell<usize>,
+    delayed: Cell<usize>,
+    resuming: Cell<usize>,
+    spawned: Cell<u64>,
+    // NOTE: Deferred threads are resumed in the order they were deferred, so deferring a
+    // function right after a batch of threads lets us know when they have all been resumed
+    undeferred: RefCell<Vec<(usize, u64)>>,
+    undefer_covers: Cell<Option<usize>>,
+}
+
+impl TaskTracker {
+    pub fn new() -> Self {
+        Self {
+            tasks: RefCell::new(HashMap::new()),
+            deferred: Cell::new(0),
+            delayed: Cell::new(0),
+            resuming: Cell::new(0),
+            spawned: Cell::new(0),
+            undeferred: RefCell::new(Vec::new()),
+            undefer_covers: Cell::new(None),
+        }
+    }
+
+    /**
+        Starts tracking the given thread as a new task.
+    */
+    pub fn track(&self, thread: &LuaThread) {
+        let id = self.spawned.get() + 1;
+        self.spawned.set(id);
+        let task = Task {
+            id,
+            state: TaskState::Active,
+            joiners: Vec::new(),
+        };
+        // NOTE: A thread that was collected without finishing may have had the same address
+        if let Some(previous) = self.tasks.borrow_mut().insert(key(thread), task) {
+            self.count(previous.state, false);
+        }
+    }
+
+    /**
+        Stops tracking the given thread, returning the functions waiting for it to finish.
+    */
+    pub fn untrack(&self, thread: &LuaThread) -> Vec<LuaRegistryKey> {
+        match self.tasks.borrow_mut().remove(&key(thread)) {
+            Some(task) => {
+                self.count(task.state, false);
+                task.joiners
+            }
+            None => Vec::new(),
+        }
+    }
+
+    pub fn is_task(&self, thread: &LuaThread) -> bool {
+        self.tasks.borrow().contains_key(&key(thread))
+    }
+
+    /**
+        Adds a function that will be called once the given task finishes, returning
+        `false` and not adding it if the given thread is not a task that is running.
+    */
+    pub fn add_joiner(&self, thread: &LuaThread, joiner: LuaRegistryKey) -> bool {
+        match self.tasks.borrow_mut().get_mut(&key(thread)) {
+            Some(task) => {
+                task.joiners.push(joiner);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /**
+        Marks the given task as deferred, returning `true` if the function that marks
+        deferred tasks as resumed must be deferred after it, using [`Self::undefer`].
+    */
+    pub fn set_deferred(&self, thread: &LuaThread) -> bool {
+        let Some(id) = self.set_state(key(thread), None, TaskState::Deferred) else {
+            return false;
+        };
+        let mut undeferred = self.undeferred.borrow_mut();
+        undeferred.push((key(thread), id));
+        if self.undefer_covers.get().is_none() {
+            self.undefer_covers.set(Some(undeferred.len()));
+            true
+        } else {
+            false
+        }
+    }
+
+    /**
+        Marks the tasks that were deferred before this was last deferred as resumed,
+        returning `true` if this must be deferred again for tasks deferred after that.
+    */
+    pub fn undefer(&self) -> bool {
+        let covered = self.undefer_covers.take().unwrap_or_default();
+        let mut undeferred = self.undeferred.borrow_mut();
+        let covered = covered.min(undeferred.len());
+        for (key, id) in undeferred.drain(..covered) {
+            self.set_state(key, Some((id, TaskState::Deferred)), TaskState::Active);
+        }
+        if undeferred.is_empty() {
+            false
+        } else {
+            self.undefer_covers.set(Some(undeferred.len()));
+            true
+        }
+    }
+
+    /**
+        Marks the given task as waiting on a timer, returning a guard that
+        marks it as active again once dropped, if it was a task at all.
+    */
+    pub fn set_delayed(self: &Rc<Self>, thread: &LuaThread) -> Option<DelayedGuard> {
+        let key = key(thread);
+        let id = self.set_state(key, None, TaskState::Delayed)?;
+        Some(DelayedGuard {
+            tracker: Rc::clone(self),
+            key,
+            id,
+        })
+    }
+
+    pub fn set_active(&self, thread: &LuaThread) {
+        self.set_state(key(thread), None, TaskState::Active);
+    }
+
+    /**
+        Runs the given function, counting the current task as running while it does, which
+        is used for functions that resume other tasks right away, such as `task.spawn`.
+    */
+    pub fn resuming<R>(&self, lua: &Lua, f: impl FnOnce() -> R) -> R {
+        let counted = self.is_task(&lua.current_thread());
+        if counted {
+            self.resuming.set(self.resuming.get() + 1);
+        }
+        let result = f();
+        if counted {
+            self.resuming.set(self.resuming.get() - 1);
+        }
+        result
+    }
+
+    fn set_state(
+        &self,
+        key: usize,
+        expected: Option<(u64, TaskState)>,
+        state: TaskState,
+    ) -> Option<u64> {
+        let mut tasks = self.tasks.borrow_mut();
+        let task = tasks.get_mut(&key)?;
+        if expected.is_some_and(|expected| expected != (task.id, task.state)) {
+            return None;
+        }
+        self.count(task.state, false);
+        self.count(state, true);
+        task.state = state;
+        Some(task.id)
+    }
+
+    fn count(&self, state: TaskState, added: bool) {
+        let counter = match state {
+            TaskState::Active => return,
+            TaskState::Deferred => &self.deferred,
+            TaskState::Delayed => &self.delayed,
+        };
+        if added {
+            counter.set(counter.get() + 1);
+        } else {
+            counter.set(counter.get() - 1);
+        }
+    }
+}
+
+/**
+    Marks a task as active again once it has stopped waiting on a timer.
+
+    The task is identified by its id as well, since it could have been cancelled and
+    collected while waiting, with its address then being used by another task.
+*/
+pub(crate) struct DelayedGuard {
+    tracker: Rc<TaskTracker>,
+    key: usize,
+    id: u64,
+}
+
+impl Drop for DelayedGuard {
+    fn drop(&mut self) {
+        self.tracker.set_state(
+            self.key,
+            Some((self.id, TaskState::Delayed)),
+            TaskState::Active,
+        );
+    }
+}
+
+fn key(thread: &LuaThread) -> usize {
+    thread.to_pointer() as usize
+}
+
+/**
+    Counts the tasks that have not yet finished, by what they are currently doing.
+*/
+pub(crate) fn stats<'lua>(lua: &'lua Lua, tracker: &TaskTracker) -> LuaResult<LuaTable<'lua>> {
+    // NOTE: The calling task may have been deferred and resumed before
+    // the tasks deferred along with it were marked as resumed
+    let current = lua.current_thread();
+    let running = if tracker.is_task(&current) {
+        tracker.set_active(&current);
+        tracker.resuming.get() + 1
+    } else {
+        tracker.resuming.get()
+    };
+
+    let deferred = tracker.deferred.get();
+    let delayed = tracker.delayed.get();
+    let pending = tracker
+        .tasks
+        .borrow()
+        .len()
+        .saturating_sub(running + deferred + delayed);
+
+    TableBuilder::new(lua)?
+        .with_value("running", running)?
+        .with_value("pending", pending)?
+        .with_value("deferred", deferred)?
+        .with_value("delayed", delayed)?
+        .with_value("spawned", tracker.spawned.get())?
+        .build()
+}
@@ -0,0 +1,133 @@
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::BinaryHeap,
+    sync::{Condvar, Mutex, PoisonError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use once_cell::sync::Lazy;
+use tokio::sync::oneshot;
+
+static TIMER: Lazy<Timer> = Lazy::new(Timer::start);
+
+/**
+    A timer with better precision than the one in tokio, which fires on ticks of one
+    millisecond, making short waits overshoot by up to a full tick and drift over time.
+
+    Deadlines are waited for on a single background thread, which sleeps until the
+    closest deadline using the timeouts of the OS, and then wakes up the waiting task.
+*/
+struct Timer {
+    deadlines: Mutex<BinaryHeap<Reverse<TimerEntry>>>,
+    changed: Condvar,
+}
+
+struct TimerEntry {
+    deadline: Instant,
+    tx: oneshot::Sender<()>,
+}
+
+impl PartialEq for TimerEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.deadline == other.deadline
+    }
+}
+
+impl Eq for TimerEntry {}
+
+impl PartialOrd for TimerEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for TimerEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.deadline.cmp(&other.deadline)
+    }
+}
+
+impl Timer {
+    fn start() -> Self {
+        thread::Builder::new()
+            .name("lune-task-timer".to_string())
+            .spawn(|| TIMER.run())
+            .expect("Failed to spawn timer thread");
+        Self {
+            deadlines: Mutex::new(BinaryHeap::new()),
+            changed: Condvar::new(),
+        }
+    }
+
+    fn run(&self) {
+        let mut deadlines = self
+            .deadlines
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        loop {
+            let now = Instant::now();
+            deadlines = match deadlines.peek() {
+                None => self
+                    .changed
+                    .wait(deadlines)
+                    .unwrap_or_else(PoisonError::into_inner),
+                Some(Reverse(entry)) if entry.deadline <= now => {
+                    if let Some(Reverse(entry)) = deadlines.pop() {
+                        // NOTE: The task may have stopped waiting, which is fine
+                        entry.tx.send(()).ok();
+                    }
+                    deadlines
+                }
+                Some(Reverse(entry)) => {
+                    let timeout = entry.deadline - now;
+                    self.changed
+                        .wait_timeout(deadlines, timeout)
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .0
+                }
+            };
+        }
+    }
+
+    fn add(&self, deadline: Instant) -> oneshot::Receiver<()> {
+        let (tx, rx) = oneshot::channel();
+        self.deadlines
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(Reverse(TimerEntry { deadline, tx }));
+        self.changed.notify_one();
+        rx
+    }
+}
+
+/**
+    Waits until the given deadline, using the high precision timer.
+*/
+pub(crate) async fn sleep_until(deadline: Instant) {
+    if deadline > Instant::now() {
+        TIMER.add(deadline).await.ok();
+    }
+}
+
+/**
+    Measures the resolution of the timer, which is the shortest amount of
+    time that the timer thread can be woken up after by the OS.
+
+    The shortest of a few measurements is used, since any one of
+    them may have been delayed by other threads running at the time.
+*/
+pub(crate) fn measure_resolution() -> Duration {
+    let lock = Mutex::new(());
+    let condvar = Condvar::new();
+    (0..5)
+        .map(|_| {
+            let guard = lock.lock().unwrap_or_else(PoisonError::into_inner);
+            let before = Instant::now();
+            drop(condvar.wait_timeout(guard, Duration::from_micros(1)));
+            before.elapsed()
+        })
+        .min()
+        .unwrap_or_default()
+        .max(Duration::from_micros(1))
+}
@@ -1,6 +1,6 @@
-use std::io::Write;
-
-use lune_utils::fmt::{pretty_format_multi_value, ValueFormatConfig};
+use lune_utils::fmt::{
+    indent_output, pretty_format_multi_value, write_output, OutputStream, ValueFormatConfig,
+};
 use mlua::prelude::*;
 
 const FORMAT_CONFIG: ValueFormatConfig = ValueFormatConfig::new()
@@ -8,11 +8,10 @@ const FORMAT_CONFIG: ValueFormatConfig = ValueFormatConfig::new()
     .with_colors_enabled(true);
 
 pub fn create(lua: &Lua) -> LuaResult<LuaValue> {
-    let f = lua.create_function(|_, args: LuaMultiValue| {
+    let f = lua.create_function(|lua, args: LuaMultiValue| {
         let formatted = format!("{}\n", pretty_format_multi_value(&args, &FORMAT_CONFIG));
-        let mut stdout = std::io::stdout();
-        stdout.write_all(formatted.as_bytes())?;
-        stdout.flush()?;
+        let formatted = indent_output(lua, &formatted);
+        write_output(lua, OutputStream::Stdout, formatted.as_bytes())?;
         Ok(())
     })?;
     f.into_lua(lua)
@@ -1,3 +1,5 @@
+use std::path::PathBuf;
+
 use mlua::prelude::*;
 
 use lune_utils::path::{clean_path_and_make_absolute, diff_path, get_current_dir};
@@ -16,6 +18,19 @@ pub(super) async fn require<'lua, 'ctx>(
 where
     'lua: 'ctx,
 {
+    let (abs_path, rel_path) = resolve_paths(source, alias, path).await?;
+    super::path::require_abs_rel(lua, ctx, source, abs_path, rel_path).await
+}
+
+/**
+    Resolves the given aliased path into an absolute path, as well as
+    a path relative to the current working directory for displaying.
+*/
+pub(super) async fn resolve_paths(
+    source: &str,
+    alias: &str,
+    path: &str,
+) -> LuaResult<(PathBuf, PathBuf)> {
     let alias = alias.to_ascii_lowercase();
 
     let parent = clean_path_and_make_absolute(source)
@@ -69,5 +84,5 @@ where
         LuaError::runtime(format!("failed to find relative path for alias '{alias}'"))
     })?;
 
-    super::path::require_abs_rel(lua, ctx, abs_path, rel_path).await
+    Ok((abs_path, rel_path))
 }
@@ -0,0 +1,85 @@
+use std::path::{Path, PathBuf};
+
+use lune_utils::path::{clean_path_and_make_absolute, get_current_dir};
+
+/**
+    Configuration for how `require` resolves the paths of modules.
+
+    By default, modules are resolved relative to the file that requires them, and
+    script names that are relative paths are relative to the current working directory.
+
+    - A root directory replaces the current working directory for script
+      names that are relative paths, such as the name given when running a
+      script using a runtime, making any modules they require relative to it.
+
+    - Search paths are directories that are searched, in order, for modules that
+      could not be found relative to the requiring file. Only paths that do not
+      explicitly start with `./` or `../` are searched for. Relative search paths
+      are relative to the root directory, if one is set.
+*/
+#[derive(Debug, Clone, Default)]
+pub struct RequireConfig {
+    root: Option<PathBuf>,
+    search_paths: Vec<PathBuf>,
+}
+
+impl RequireConfig {
+    /**
+        Creates a new config that resolves modules the same way as the default one.
+    */
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /**
+        Sets the root directory that script names that are relative paths are relative to.
+    */
+    #[must_use]
+    pub fn root(mut self, root: impl AsRef<Path>) -> Self {
+        self.root = Some(clean_path_and_make_absolute(root));
+        self
+    }
+
+    /**
+        Adds a directory to search for modules that could not be found relative to
+        the requiring file, after any search paths that were added before it.
+    */
+    #[must_use]
+    pub fn search_path(mut self, path: impl AsRef<Path>) -> Self {
+        self.search_paths.push(path.as_ref().to_path_buf());
+        self
+    }
+
+    /**
+        Resolves the given source, the name of a requiring chunk, against the root directory.
+    */
+    pub(super) fn resolve_source(&self, source: &str) -> String {
+        match &self.root {
+            Some(root) if Path::new(source).is_relative() => {
+                root.join(source).to_string_lossy().to_string()
+            }
+            _ => source.to_string(),
+        }
+    }
+
+    /**
+        Returns the directory that relative script names and search paths are relative to.
+    */
+    pub(super) fn base_dir(&self) -> PathBuf {
+        self.root
+            .clone()
+            .unwrap_or_else(|| get_current_dir().to_path_buf())
+    }
+
+    /**
+        Returns the absolute paths of all search paths, in the order they should be searched.
+    */
+    pub(super) fn search_paths(&self) -> Vec<PathBuf> {
+        let base = self.base_dir();
+        self.search_paths
+            .iter()
+            .map(|path| clean_path_and_make_absolute(base.join(path)))
+            .collect()
+    }
+}
@@ -1,5 +1,5 @@
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     path::{Path, PathBuf},
     sync::Arc,
 };
@@ -19,6 +19,8 @@ use lune_utils::path::{clean_path, clean_path_and_make_absolute};
 
 use crate::library::LuneStandardLibrary;
 
+use super::reload::ReloadWatcher;
+
 /**
     Context containing cached results for all `require` operations.
 
@@ -30,6 +32,8 @@ pub(super) struct RequireContext {
     libraries: Arc<AsyncMutex<HashMap<LuneStandardLibrary, LuaResult<LuaRegistryKey>>>>,
     results: Arc<AsyncMutex<HashMap<PathBuf, LuaResult<LuaRegistryKey>>>>,
     pending: Arc<AsyncMutex<HashMap<PathBuf, Sender<()>>>>,
+    dependencies: Arc<AsyncMutex<HashMap<PathBuf, HashMap<PathBuf, usize>>>>,
+    watcher: Arc<AsyncMutex<Option<ReloadWatcher>>>,
 }
 
 impl RequireContext {
@@ -45,6 +49,8 @@ impl RequireContext {
             libraries: Arc::new(AsyncMutex::new(HashMap::new())),
             results: Arc::new(AsyncMutex::new(HashMap::new())),
             pending: Arc::new(AsyncMutex::new(HashMap::new())),
+            dependencies: Arc::new(AsyncMutex::new(HashMap::new())),
+            watcher: Arc::new(AsyncMutex::new(None)),
         }
     }
 
@@ -83,6 +89,95 @@ impl RequireContext {
         Ok(is_cached)
     }
 
+    /**
+        Checks if the given path has a cached require result that should be required again,
+        meaning that automatic reloading is enabled and the file has changed since it was cached.
+    */
+    pub fn is_stale(&self, abs_path: impl AsRef<Path>) -> bool {
+        self.watcher
+            .try_lock()
+            .expect("RequireContext may not be used from multiple threads")
+            .as_ref()
+            .is_some_and(|watcher| watcher.forget(abs_path.as_ref()))
+    }
+
+    /**
+        Removes the cached require result for the given path, if any,
+        so that the next `require` of the path runs the file again.
+
+        Returns `true` if there was a cached result to remove.
+    */
+    pub fn remove_from_cache(&self, abs_path: impl AsRef<Path>) -> bool {
+        let abs_path = abs_path.as_ref();
+        if let Some(watcher) = self
+            .watcher
+            .try_lock()
+            .expect("RequireContext may not be used from multiple threads")
+            .as_ref()
+        {
+            watcher.forget(abs_path);
+        }
+        self.results
+            .try_lock()
+            .expect("RequireContext may not be used from multiple threads")
+            .remove(abs_path)
+            .is_some()
+    }
+
+    /**
+        Removes all cached require results for files, returning the number of removed results.
+
+        Built-in libraries are not affected, since requiring them again would give the same result.
+    */
+    pub fn clear_cache(&self) -> usize {
+        let paths = self
+            .results
+            .try_lock()
+            .expect("RequireContext may not be used from multiple threads")
+            .drain()
+            .map(|(path, _)| path)
+            .collect::<Vec<_>>();
+        if let Some(watcher) = self
+            .watcher
+            .try_lock()
+            .expect("RequireContext may not be used from multiple threads")
+            .as_ref()
+        {
+            for path in &paths {
+                watcher.forget(path);
+            }
+        }
+        paths.len()
+    }
+
+    /**
+        Sets whether cached require results should be discarded when their files change.
+
+        Enabling this starts watching the files of all currently cached results, as well as
+        any files required afterwards, and disabling it stops watching all of them.
+    */
+    pub fn set_auto_reload(&self, enabled: bool) -> LuaResult<()> {
+        let mut watcher = self
+            .watcher
+            .try_lock()
+            .expect("RequireContext may not be used from multiple threads");
+        if !enabled {
+            *watcher = None;
+        } else if watcher.is_none() {
+            let mut new_watcher = ReloadWatcher::new()?;
+            for path in self
+                .results
+                .try_lock()
+                .expect("RequireContext may not be used from multiple threads")
+                .keys()
+            {
+                new_watcher.watch(path);
+            }
+            *watcher = Some(new_watcher);
+        }
+        Ok(())
+    }
+
     /**
         Checks if the given path is currently being used in `require`.
     */
@@ -95,6 +190,96 @@ impl RequireContext {
         Ok(is_pending)
     }
 
+    /**
+        Marks the given path as currently requiring the other given path,
+        until the returned guard is dropped.
+
+        The guard makes sure that the dependency is also removed when the
+        require errors, or when the thread that is requiring gets cancelled.
+    */
+    pub fn add_dependency(
+        &self,
+        abs_source: impl AsRef<Path>,
+        abs_path: impl AsRef<Path>,
+    ) -> DependencyGuard<'_> {
+        let abs_source = abs_source.as_ref().to_path_buf();
+        let abs_path = abs_path.as_ref().to_path_buf();
+        *self
+            .dependencies
+            .try_lock()
+            .expect("RequireContext may not be used from multiple threads")
+            .entry(abs_source.clone())
+            .or_default()
+            .entry(abs_path.clone())
+            .or_default() += 1;
+        DependencyGuard {
+            ctx: self,
+            abs_source,
+            abs_path,
+        }
+    }
+
+    /**
+        Marks the given path as no longer requiring the other given path.
+    */
+    fn remove_dependency(&self, abs_source: &Path, abs_path: &Path) {
+        let mut dependencies = self
+            .dependencies
+            .try_lock()
+            .expect("RequireContext may not be used from multiple threads");
+        if let Some(paths) = dependencies.get_mut(abs_source) {
+            if let Some(count) = paths.get_mut(abs_path) {
+                *count -= 1;
+                if *count == 0 {
+                    paths.remove(abs_path);
+                }
+            }
+            if paths.is_empty() {
+                dependencies.remove(abs_source);
+            }
+        }
+    }
+
+    /**
+        Finds a chain of currently pending requires that would lead from the given
+        path back to the given source, meaning that requiring it would never finish.
+
+        The returned chain starts with the source and ends with the source.
+    */
+    pub fn find_circular(
+        &self,
+        abs_source: impl AsRef<Path>,
+        abs_path: impl AsRef<Path>,
+    ) -> Option<Vec<PathBuf>> {
+        let dependencies = self
+            .dependencies
+            .try_lock()
+            .expect("RequireContext may not be used from multiple threads");
+
+        let abs_source = abs_source.as_ref();
+        let mut visited = HashSet::new();
+        let mut stack = vec![vec![
+            abs_source.to_path_buf(),
+            abs_path.as_ref().to_path_buf(),
+        ]];
+        while let Some(chain) = stack.pop() {
+            let last = chain.last().expect("chain is never empty");
+            if last == abs_source {
+                return Some(chain);
+            }
+            if !visited.insert(last.clone()) {
+                continue;
+            }
+            for next in dependencies.get(last).into_iter().flat_map(HashMap::keys) {
+                let mut next_chain = chain.clone();
+                next_chain.push(next.clone());
+                stack.push(next_chain);
+            }
+        }
+
+        None
+    }
+
     /**
         Gets the resulting value from the require cache.
 
@@ -161,6 +346,11 @@ impl RequireContext {
 
         // Read the file at the given path, try to parse and
         // load it into a new lua thread that we can schedule
+        // NOTE: The file is watched first, so that changes made while
+        // reading are picked up the next time that the file is required
+        if let Some(watcher) = self.watcher.lock().await.as_mut() {
+            watcher.watch(abs_path);
+        }
         let file_contents = read(&abs_path).await?;
         let file_thread = lua
             .load(file_contents)
@@ -287,3 +477,18 @@ impl RequireContext {
         result
     }
 }
+
+/**
+    Removes a dependency added using [`RequireContext::add_dependency`] once dropped.
+*/
+pub(super) struct DependencyGuard<'ctx> {
+    ctx: &'ctx RequireContext,
+    abs_source: PathBuf,
+    abs_path: PathBuf,
+}
+
+impl Drop for DependencyGuard<'_> {
+    fn drop(&mut self) {
+        self.ctx.remove_dependency(&self.abs_source, &self.abs_path);
+    }
+}
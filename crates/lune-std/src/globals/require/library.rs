@@ -1,6 +1,6 @@
 use mlua::prelude::*;
 
-use super::context::*;
+use super::{context::*, RequireLibrary};
 
 pub(super) fn require<'lua, 'ctx>(
     lua: &'lua Lua,
@@ -10,5 +10,14 @@ pub(super) fn require<'lua, 'ctx>(
 where
     'lua: 'ctx,
 {
+    // NOTE: The require library is not a separate standard library
+    // crate, since it needs access to the context of the require global
+    if name == "require" {
+        let library = lua
+            .app_data_ref::<RequireLibrary>()
+            .expect("Failed to get RequireLibrary from app data");
+        let table = lua.registry_value::<LuaTable>(&library.0)?;
+        return Ok(LuaMultiValue::from_vec(vec![LuaValue::Table(table)]));
+    }
     ctx.load_library(lua, name)
 }
@@ -5,14 +5,44 @@ use lune_utils::TableBuilder;
 mod context;
 use context::RequireContext;
 
+pub use config::RequireConfig;
+
 mod alias;
+mod config;
 mod library;
 mod path;
+mod reload;
 
 const REQUIRE_IMPL: &str = r"
 return require(source(), ...)
 ";
 
+const RELOAD_IMPL: &str = r"
+return reload(source(), ...)
+";
+
+/**
+    The `@lune/require` library, which contains functions for working with the require cache.
+
+    It is created together with the `require` global, since it needs the same require context.
+*/
+struct RequireLibrary(LuaRegistryKey);
+
+/**
+    Creates the `require` global, as well as the `@lune/require` library:
+
+    - `reload(path?)` removes the cached result for the given path, or for all files
+      if no path is given, returning `true` if anything was removed. The next `require`
+      of the path then runs the file again, and returns its new result.
+
+    - `setAutoReload(enabled)` starts watching the files of cached results for changes,
+      and makes `require` run changed files again instead of returning their results.
+
+    Reloading never changes values that were already returned by `require`, any references
+    to them, such as a local variable holding the old module, keep pointing to the old values.
+    Only files that call `require` again get the new values, and any state stored in the old
+    module, such as connections or running threads, is kept around until it is no longer used.
+*/
 pub fn create(lua: &Lua) -> LuaResult<LuaValue> {
     lua.set_app_data(RequireContext::new());
 
@@ -48,6 +78,22 @@ pub fn create(lua: &Lua) -> LuaResult<LuaValue> {
         },
     })?;
 
+    let reload_env = TableBuilder::new(lua)?
+        .with_value("source", get_source_fn.clone())?
+        .with_async_function("reload", require_reload)?
+        .build_readonly()?;
+    let reload_fn = lua
+        .load(RELOAD_IMPL)
+        .set_name("reload")
+        .set_environment(reload_env)
+        .into_function()?;
+
+    let library = TableBuilder::new(lua)?
+        .with_value("reload", reload_fn)?
+        .with_function("setAutoReload", require_set_auto_reload)?
+        .build_readonly()?;
+    lua.set_app_data(RequireLibrary(lua.create_registry_value(library)?));
+
     let require_env = TableBuilder::new(lua)?
         .with_value("source", get_source_fn)?
         .with_value("require", require_fn)?
@@ -76,6 +122,11 @@ async fn require<'lua>(
         .context("Failed to parse require path as string")?
         .to_string();
 
+    let source = match lua.app_data_ref::<RequireConfig>() {
+        Some(config) => config.resolve_source(&source),
+        None => source,
+    };
+
     let context = lua
         .app_data_ref()
         .expect("Failed to get RequireContext from app data");
@@ -91,3 +142,39 @@ async fn require<'lua>(
         path::require(lua, &context, &source, &path).await
     }
 }
+
+async fn require_reload<'lua>(
+    lua: &'lua Lua,
+    (source, path): (LuaString<'lua>, Option<LuaString<'lua>>),
+) -> LuaResult<bool> {
+    let source = source
+        .to_str()
+        .into_lua_err()
+        .context("Failed to parse require source as string")?
+        .to_string();
+
+    let path = path
+        .map(|path| path.to_str().map(str::to_string))
+        .transpose()
+        .into_lua_err()
+        .context("Failed to parse reload path as string")?;
+
+    let context = lua
+        .app_data_ref::<RequireContext>()
+        .expect("Failed to get RequireContext from app data")
+        .clone();
+
+    reload::reload(&context, &source, path.as_deref()).await
+}
+
+fn require_set_auto_reload(lua: &Lua, enabled: LuaValue) -> LuaResult<()> {
+    let LuaValue::Boolean(enabled) = enabled else {
+        return Err(LuaError::runtime(format!(
+            "Expected a boolean for automatic reloading, got {}",
+            enabled.type_name()
+        )));
+    };
+    lua.app_data_ref::<RequireContext>()
+        .expect("Failed to get RequireContext from app data")
+        .set_auto_reload(enabled)
+}
@@ -3,7 +3,9 @@ use std::path::{Path, PathBuf};
 use mlua::prelude::*;
 use mlua::Error::ExternalError;
 
-use super::context::*;
+use lune_utils::path::{clean_path_and_make_absolute, diff_path, get_current_dir};
+
+use super::{config::RequireConfig, context::*};
 
 pub(super) async fn require<'lua, 'ctx>(
     lua: &'lua Lua,
@@ -15,21 +17,64 @@ where
     'lua: 'ctx,
 {
     let (abs_path, rel_path) = RequireContext::resolve_paths(source, path)?;
-    require_abs_rel(lua, ctx, abs_path, rel_path).await
+    if let Some(res) = try_require_abs_rel(lua, ctx, source, &abs_path, &rel_path).await? {
+        return Ok(res);
+    }
+
+    // Paths that are not explicitly relative may also be found in any of the search paths
+    if !is_explicitly_relative(path) {
+        // NOTE: Relative paths are used as chunk names, which are sources for any
+        // further requires, so they must be relative to the root directory, if set
+        let (base_dir, search_paths) = lua
+            .app_data_ref::<RequireConfig>()
+            .map(|config| (config.base_dir(), config.search_paths()))
+            .unwrap_or_default();
+        for search_path in search_paths {
+            let abs_path = clean_path_and_make_absolute(search_path.join(path));
+            let rel_path = diff_path(&abs_path, &base_dir).unwrap_or(abs_path.clone());
+            if let Some(res) = try_require_abs_rel(lua, ctx, source, &abs_path, &rel_path).await? {
+                return Ok(res);
+            }
+        }
+    }
+
+    Err(not_found_error(&rel_path))
 }
 
 pub(super) async fn require_abs_rel<'lua, 'ctx>(
     lua: &'lua Lua,
     ctx: &'ctx RequireContext,
+    source: &str,
     abs_path: PathBuf, // Absolute to filesystem
     rel_path: PathBuf, // Relative to CWD (for displaying)
 ) -> LuaResult<LuaMultiValue<'lua>>
 where
     'lua: 'ctx,
 {
+    match try_require_abs_rel(lua, ctx, source, &abs_path, &rel_path).await? {
+        Some(res) => Ok(res),
+        None => Err(not_found_error(&rel_path)),
+    }
+}
+
+/**
+    Requires the given path, trying all of the paths that it may resolve to,
+    returning `None` if no file exists at any of them.
+*/
+async fn try_require_abs_rel<'lua, 'ctx>(
+    lua: &'lua Lua,
+    ctx: &'ctx RequireContext,
+    source: &str,
+    abs_path: &Path, // Absolute to filesystem
+    rel_path: &Path, // Relative to CWD (for displaying)
+) -> LuaResult<Option<LuaMultiValue<'lua>>>
+where
+    'lua: 'ctx,
+{
+    let abs_source = clean_path_and_make_absolute(source);
     // 1. Try to require the exact path
-    match require_inner(lua, ctx, &abs_path, &rel_path).await {
-        Ok(res) => return Ok(res),
+    match require_inner(lua, ctx, &abs_source, abs_path, rel_path).await {
+        Ok(res) => return Ok(Some(res)),
         Err(err) => {
             if !is_file_not_found_error(&err) {
                 return Err(err);
@@ -43,12 +88,13 @@ where
         match require_inner(
             lua,
             ctx,
-            &append_extension(&abs_path, extension),
-            &append_extension(&rel_path, extension),
+            &abs_source,
+            &append_extension(abs_path, extension),
+            &append_extension(rel_path, extension),
         )
         .await
         {
-            Ok(res) => return Ok(res),
+            Ok(res) => return Ok(Some(res)),
             Err(err) => {
                 if !is_file_not_found_error(&err) {
                     return Err(err);
@@ -68,12 +114,13 @@ where
         match require_inner(
             lua,
             ctx,
+            &abs_source,
             &append_extension(&abs_init, extension),
             &append_extension(&rel_init, extension),
         )
         .await
         {
-            Ok(res) => return Ok(res),
+            Ok(res) => return Ok(Some(res)),
             Err(err) => {
                 if !is_file_not_found_error(&err) {
                     return Err(err);
@@ -82,34 +129,74 @@ where
         }
     }
 
-    // Nothing left to try, throw an error
-    Err(LuaError::runtime(format!(
-        "No file exists at the path '{}'",
-        rel_path.display()
-    )))
+    // Nothing left to try
+    Ok(None)
 }
 
 async fn require_inner<'lua, 'ctx>(
     lua: &'lua Lua,
     ctx: &'ctx RequireContext,
+    abs_source: impl AsRef<Path>,
     abs_path: impl AsRef<Path>,
     rel_path: impl AsRef<Path>,
 ) -> LuaResult<LuaMultiValue<'lua>>
 where
     'lua: 'ctx,
 {
+    let abs_source = abs_source.as_ref();
     let abs_path = abs_path.as_ref();
     let rel_path = rel_path.as_ref();
 
     if ctx.is_cached(abs_path)? {
-        ctx.get_from_cache(lua, abs_path)
-    } else if ctx.is_pending(abs_path)? {
+        if !ctx.is_stale(abs_path) {
+            return ctx.get_from_cache(lua, abs_path);
+        }
+        ctx.remove_from_cache(abs_path);
+    }
+
+    // NOTE: Waiting for a pending require that is itself waiting for
+    // the requiring file would never finish, so we error out instead
+    if let Some(chain) = ctx.find_circular(abs_source, abs_path) {
+        return Err(LuaError::runtime(format!(
+            "Detected a circular require - {}",
+            chain
+                .iter()
+                .map(|path| display_path(path))
+                .collect::<Vec<_>>()
+                .join(" -> ")
+        )));
+    }
+
+    let _dependency = ctx.add_dependency(abs_source, abs_path);
+    if ctx.is_pending(abs_path)? {
         ctx.wait_for_cache(lua, &abs_path).await
     } else {
         ctx.load_with_caching(lua, &abs_path, &rel_path).await
     }
 }
 
+/**
+    Returns all of the paths that requiring the given path may resolve to,
+    in the same order that `require` tries them in.
+*/
+pub(super) fn candidate_paths(abs_path: &Path) -> Vec<PathBuf> {
+    let abs_init = abs_path.join("init");
+    vec![
+        abs_path.to_path_buf(),
+        append_extension(abs_path, "luau"),
+        append_extension(abs_path, "lua"),
+        append_extension(&abs_init, "luau"),
+        append_extension(&abs_init, "lua"),
+    ]
+}
+
+fn display_path(abs_path: &Path) -> String {
+    diff_path(abs_path, get_current_dir())
+        .unwrap_or_else(|| abs_path.to_path_buf())
+        .display()
+        .to_string()
+}
+
 fn append_extension(path: impl Into<PathBuf>, ext: &'static str) -> PathBuf {
     let mut new = path.into();
     match new.extension() {
@@ -120,6 +207,19 @@ fn append_extension(path: impl Into<PathBuf>, ext: &'static str) -> PathBuf {
     new
 }
 
+fn not_found_error(rel_path: &Path) -> LuaError {
+    LuaError::runtime(format!(
+        "No file exists at the path '{}'",
+        rel_path.display()
+    ))
+}
+
+fn is_explicitly_relative(path: &str) -> bool {
+    ["./", "../", ".\\", "..\\"]
+        .iter()
+        .any(|prefix| path.starts_with(prefix))
+}
+
 fn is_file_not_found_error(err: &LuaError) -> bool {
     if let ExternalError(err) = err {
         err.as_ref().downcast_ref::<std::io::Error>().is_some()
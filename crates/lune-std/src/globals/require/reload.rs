@@ -0,0 +1,123 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use mlua::prelude::*;
+use notify::{
+    recommended_watcher, Event as NotifyEvent, EventKind as NotifyEventKind, RecommendedWatcher,
+    RecursiveMode, Result as NotifyResult, Watcher,
+};
+
+use super::context::*;
+
+/**
+    Watches the directories of required files for changes, so that cached results for
+    changed files can be discarded without checking the files every time they are required.
+
+    Directories are watched instead of the files themselves, since editors
+    often save files by replacing them, which would stop a file watcher.
+*/
+#[derive(Debug)]
+pub(super) struct ReloadWatcher {
+    watcher: RecommendedWatcher,
+    directories: HashSet<PathBuf>,
+    changed: Arc<Mutex<HashSet<PathBuf>>>,
+}
+
+impl ReloadWatcher {
+    pub fn new() -> LuaResult<Self> {
+        let changed = Arc::new(Mutex::new(HashSet::new()));
+        let changed_inner = Arc::clone(&changed);
+        let watcher = recommended_watcher(move |res: NotifyResult<NotifyEvent>| {
+            if let Ok(event) = res {
+                if matches!(
+                    event.kind,
+                    NotifyEventKind::Create(_)
+                        | NotifyEventKind::Modify(_)
+                        | NotifyEventKind::Remove(_)
+                ) {
+                    changed_inner
+                        .lock()
+                        .expect("Reload watcher lock was poisoned")
+                        .extend(event.paths);
+                }
+            }
+        })
+        .map_err(|e| LuaError::runtime(format!("Failed to watch required files\n{e}")))?;
+        Ok(Self {
+            watcher,
+            directories: HashSet::new(),
+            changed,
+        })
+    }
+
+    /**
+        Starts watching the given file for changes, forgetting about any previous changes.
+    */
+    pub fn watch(&mut self, abs_path: &Path) {
+        self.forget(abs_path);
+        if let Some(dir) = abs_path.parent() {
+            // NOTE: Directories that can not be watched are not an error, files in
+            // them will not be reloaded automatically, same as if this was disabled
+            if self.directories.insert(dir.to_path_buf()) {
+                let _ = self.watcher.watch(dir, RecursiveMode::NonRecursive);
+            }
+        }
+    }
+
+    /**
+        Forgets about any changes to the given file, returning `true` if it had changed.
+    */
+    pub fn forget(&self, abs_path: &Path) -> bool {
+        self.changed
+            .lock()
+            .expect("Reload watcher lock was poisoned")
+            .remove(abs_path)
+    }
+}
+
+/**
+    Removes the cached results for the given required path, or all files if no path was given,
+    so that requiring them again runs the files again instead of returning the cached results.
+
+    Returns `true` if any cached results were removed.
+*/
+pub(super) async fn reload(
+    ctx: &RequireContext,
+    source: &str,
+    path: Option<&str>,
+) -> LuaResult<bool> {
+    let Some(path) = path else {
+        return Ok(ctx.clear_cache() > 0);
+    };
+
+    let abs_path = if path.starts_with("@lune/") {
+        return Err(LuaError::runtime(format!(
+            "Failed to reload '{path}' - built-in libraries can not be reloaded"
+        )));
+    } else if let Some(aliased_path) = path.strip_prefix('@') {
+        let (alias, path) = aliased_path.split_once('/').ok_or(LuaError::runtime(
+            "Require with custom alias must contain '/' delimiter",
+        ))?;
+        super::alias::resolve_paths(source, alias, path).await?.0
+    } else {
+        RequireContext::resolve_paths(source, path)?.0
+    };
+
+    let candidates = super::path::candidate_paths(&abs_path);
+    for candidate in &candidates {
+        if ctx.is_pending(candidate)? {
+            return Err(LuaError::runtime(format!(
+                "Failed to reload '{path}' - it is currently being required"
+            )));
+        }
+    }
+
+    let mut removed = false;
+    for candidate in &candidates {
+        removed |= ctx.remove_from_cache(candidate);
+    }
+    Ok(removed)
+}
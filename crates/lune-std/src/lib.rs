@@ -8,9 +8,16 @@ mod library;
 mod luaurc;
 
 pub use self::global::LuneStandardGlobal;
+pub use self::globals::require::RequireConfig;
 pub use self::globals::version::set_global_version;
 pub use self::library::LuneStandardLibrary;
 
+#[cfg(feature = "fs")]
+pub use lune_std_fs::FsRoot;
+
+#[cfg(feature = "net")]
+pub use lune_std_net::NetPolicy;
+
 /**
     Injects all standard globals into the given Lua state / VM.
 
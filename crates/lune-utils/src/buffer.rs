@@ -0,0 +1,41 @@
+use bstr::BString;
+use mlua::prelude::*;
+
+/**
+    Creates a new Luau `buffer` containing the given bytes.
+
+    This should be preferred over creating strings for any binary data,
+    since Luau strings are expected to contain valid UTF-8 in most places.
+
+    # Errors
+
+    Errors if the buffer could not be created.
+*/
+pub fn to_lua(lua: &Lua, bytes: impl AsRef<[u8]>) -> LuaResult<LuaValue> {
+    lua.create_buffer(bytes).map(LuaValue::UserData)
+}
+
+/**
+    Reads the bytes contained in a Luau `buffer` or `string` value.
+
+    # Errors
+
+    Errors if the given value is neither a buffer nor a string.
+*/
+pub fn from_lua<'lua>(lua: &'lua Lua, value: LuaValue<'lua>) -> LuaResult<Vec<u8>> {
+    match value {
+        LuaValue::String(s) => Ok(s.as_bytes().to_vec()),
+        LuaValue::UserData(_) if value.type_name() == "buffer" => {
+            let bytes = BString::from_lua(value, lua)?;
+            Ok(bytes.into())
+        }
+        value => Err(LuaError::FromLuaConversionError {
+            from: value.type_name(),
+            to: "buffer",
+            message: Some(format!(
+                "expected 'string' or 'buffer', got '{}'",
+                value.type_name()
+            )),
+        }),
+    }
+}
@@ -0,0 +1,9 @@
+/**
+    The exit code that a script asked to exit with using `process.exit`.
+
+    This is stored in Lua app data when the script exits, so that the runtime
+    can report the exact exit code back to its host, since the scheduler only
+    keeps an opaque [`std::process::ExitCode`] that can not be read back.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScriptExitCode(pub u8);
@@ -6,7 +6,7 @@ use console::style;
 use mlua::prelude::*;
 use once_cell::sync::Lazy;
 
-use super::StackTrace;
+use super::{stack_trace::parse_path, StackTrace};
 
 static STYLED_STACK_BEGIN: Lazy<String> = Lazy::new(|| {
     format!(
@@ -98,12 +98,22 @@ impl From<LuaError> for ErrorComponents {
     fn from(error: LuaError) -> Self {
         fn lua_error_message(e: &LuaError) -> String {
             if let LuaError::RuntimeError(s) = e {
-                s.to_string()
+                clean_message(s)
             } else {
                 e.to_string()
             }
         }
 
+        fn clean_message(message: &str) -> String {
+            // Errors thrown from lua are prefixed with their chunk name and line,
+            // we turn the '[string "path"]:line: message' format into a nicer
+            // 'path:line: message' format for consistency with stack traces
+            match parse_path(message) {
+                Some((path, after)) => format!("{path}:{after}"),
+                None => message.to_string(),
+            }
+        }
+
         fn lua_stack_trace(source: &str) -> Option<StackTrace> {
             // FUTURE: Preserve a parsing error here somehow?
             // Maybe we can emit parsing errors using tracing?
@@ -136,10 +146,10 @@ impl From<LuaError> for ErrorComponents {
             // joined with error messages, so we need to split them out
             if let Some(pos) = s.find("stack traceback:") {
                 let (message, traceback) = s.split_at(pos);
-                messages.push(message.trim().to_string());
+                messages.push(clean_message(message.trim()));
                 lua_stack_trace(traceback)
             } else {
-                messages.push(s.to_string());
+                messages.push(clean_message(s));
                 None
             }
         } else {
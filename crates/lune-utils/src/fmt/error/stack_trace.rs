@@ -1,7 +1,11 @@
 use std::fmt;
 use std::str::FromStr;
 
-fn parse_path(s: &str) -> Option<(&str, &str)> {
+// NOTE: This chunk is created internally by mlua when polling async
+// functions, it is not something that users can do anything about
+const MLUA_ASYNC_POLL_CHUNK: &str = "__mlua_async_poll";
+
+pub(super) fn parse_path(s: &str) -> Option<(&str, &str)> {
     let path = s.strip_prefix("[string \"")?;
     let (path, after) = path.split_once("\"]:")?;
 
@@ -16,7 +20,8 @@ fn parse_path(s: &str) -> Option<(&str, &str)> {
 }
 
 fn parse_function_name(s: &str) -> Option<&str> {
-    s.strip_prefix("in function '")
+    s.trim_start()
+        .strip_prefix("in function '")
         .and_then(|s| s.strip_suffix('\''))
 }
 
@@ -164,7 +169,10 @@ impl FromStr for StackTrace {
                     Some(line.parse())
                 }
             })
-            .collect::<Result<Vec<_>, _>>()?;
+            .collect::<Result<Vec<StackTraceLine>, _>>()?
+            .into_iter()
+            .filter(|line| line.path() != Some(MLUA_ASYNC_POLL_CHUNK))
+            .collect();
         Ok(StackTrace { lines })
     }
 }
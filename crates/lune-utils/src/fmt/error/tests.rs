@@ -1,6 +1,6 @@
 use mlua::prelude::*;
 
-use crate::fmt::ErrorComponents;
+use crate::fmt::{ErrorComponents, StackTrace};
 
 fn new_lua_result() -> LuaResult<()> {
     let lua = Lua::new();
@@ -83,3 +83,61 @@ mod error_components {
         assert_eq!(line_2, "Script 'chunk_name', Line 1");
     }
 }
+
+// Tests for chunk names and line numbers in error messages and stack traces
+mod source_locations {
+    use super::*;
+
+    fn new_lua_error_result() -> LuaResult<()> {
+        let lua = Lua::new();
+        lua.load("local _ = 1\nerror('oh no, a lua error')")
+            .set_name("chunk_name")
+            .eval()
+    }
+
+    #[test]
+    fn message_location() {
+        let lua_error = new_lua_error_result().unwrap_err();
+        let components = ErrorComponents::from(lua_error);
+
+        assert_eq!(components.messages()[0], "chunk_name:2: oh no, a lua error");
+    }
+
+    #[test]
+    fn stack_line_location() {
+        let lua_error = new_lua_error_result().unwrap_err();
+        let components = ErrorComponents::from(lua_error);
+
+        let lines = components.trace().unwrap().lines();
+        let last = lines.last().unwrap();
+        assert_eq!(last.path(), Some("chunk_name"));
+        assert_eq!(last.line_number(), Some(2));
+    }
+
+    #[test]
+    fn stack_skips_internal_chunks() {
+        let trace: StackTrace = concat!(
+            "stack traceback:\n",
+            "[C]: in ?\n",
+            "[string \"__mlua_async_poll\"]:4: in function <[string \"__mlua_async_poll\"]:1>\n",
+            "[string \"module\"]:3: in function 'f'\n",
+            "[string \"main\"]:1:\n",
+        )
+        .parse()
+        .unwrap();
+
+        let lines = trace
+            .lines()
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            &[
+                "Script '[C]'",
+                "Script 'module', Line 3 - function 'f'",
+                "Script 'main', Line 1",
+            ]
+        );
+    }
+}
@@ -0,0 +1,89 @@
+use mlua::prelude::*;
+
+const INDENT: &str = "  ";
+
+/**
+    The current indentation level of console output, stored in Lua app data.
+
+    Indentation is increased and decreased when starting and ending output
+    groups, and applied to all console output written while inside of a group.
+*/
+#[derive(Debug, Default, Clone, Copy)]
+struct OutputIndent(usize);
+
+/**
+    Returns the current indentation level of console output.
+*/
+#[must_use]
+pub fn output_indent_level(lua: &Lua) -> usize {
+    lua.app_data_ref::<OutputIndent>()
+        .map(|indent| indent.0)
+        .unwrap_or_default()
+}
+
+/**
+    Increases the indentation level of all subsequent console output by one.
+*/
+pub fn increase_output_indent(lua: &Lua) {
+    let level = output_indent_level(lua);
+    lua.set_app_data(OutputIndent(level + 1));
+}
+
+/**
+    Decreases the indentation level of all subsequent console output by one.
+
+    Does nothing if the output is not currently indented.
+*/
+pub fn decrease_output_indent(lua: &Lua) {
+    let level = output_indent_level(lua);
+    lua.set_app_data(OutputIndent(level.saturating_sub(1)));
+}
+
+/**
+    Indents every line of the given output using the current indentation level.
+
+    Empty lines are left as-is, to not leave trailing whitespace in the output.
+*/
+#[must_use]
+pub fn indent_output(lua: &Lua, output: &str) -> String {
+    let level = output_indent_level(lua);
+    if level == 0 {
+        return output.to_string();
+    }
+
+    let prefix = INDENT.repeat(level);
+    let mut indented = String::with_capacity(output.len() + prefix.len());
+    for line in output.split_inclusive('\n') {
+        if line != "\n" && line != "\r\n" {
+            indented.push_str(&prefix);
+        }
+        indented.push_str(line);
+    }
+    indented
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn indents_every_line() {
+        let lua = Lua::new();
+        assert_eq!(indent_output(&lua, "a\nb\n"), "a\nb\n");
+
+        increase_output_indent(&lua);
+        assert_eq!(indent_output(&lua, "a\nb\n"), "  a\n  b\n");
+
+        increase_output_indent(&lua);
+        assert_eq!(indent_output(&lua, "a\n\nb"), "    a\n\n    b");
+    }
+
+    #[test]
+    fn decreases_to_zero() {
+        let lua = Lua::new();
+        increase_output_indent(&lua);
+        decrease_output_indent(&lua);
+        decrease_output_indent(&lua);
+        assert_eq!(output_indent_level(&lua), 0);
+    }
+}
@@ -1,6 +1,6 @@
 use std::fmt;
 
-use console::{style, Color};
+use super::theme::{theme, ThemeColor};
 
 /**
     Label enum used for consistent output formatting throughout Lune.
@@ -22,6 +22,7 @@ use console::{style, Color};
 */
 #[derive(Debug, Clone, Copy)]
 pub enum Label {
+    Log,
     Info,
     Warn,
     Error,
@@ -34,6 +35,7 @@ impl Label {
     #[must_use]
     pub fn name(&self) -> &str {
         match self {
+            Self::Log => "LOG",
             Self::Info => "INFO",
             Self::Warn => "WARN",
             Self::Error => "ERROR",
@@ -41,26 +43,29 @@ impl Label {
     }
 
     /**
-        Returns the color of the label.
+        Returns the color of the label, from the current theme.
     */
     #[must_use]
-    pub fn color(&self) -> Color {
+    pub fn color(&self) -> ThemeColor {
+        let theme = theme();
         match self {
-            Self::Info => Color::Blue,
-            Self::Warn => Color::Yellow,
-            Self::Error => Color::Red,
+            Self::Log => theme.log,
+            Self::Info => theme.info,
+            Self::Warn => theme.warn,
+            Self::Error => theme.error,
         }
     }
 }
 
 impl fmt::Display for Label {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let dim = theme().dim;
         write!(
             f,
             "{}{}{}",
-            style("[").dim(),
-            style(self.name()).fg(self.color()),
-            style("]").dim()
+            dim.apply("["),
+            self.color().apply(self.name()),
+            dim.apply("]")
         )
     }
 }
@@ -1,7 +1,18 @@
 mod error;
+mod indent;
 mod label;
+mod output;
+mod theme;
 mod value;
 
 pub use self::error::{ErrorComponents, StackTrace, StackTraceLine, StackTraceSource};
+pub use self::indent::{
+    decrease_output_indent, increase_output_indent, indent_output, output_indent_level,
+};
 pub use self::label::Label;
+pub use self::output::{
+    begin_output_capture, end_output_capture, output_history, set_output_history_size,
+    set_output_target, write_output, OutputStream, OutputTarget,
+};
+pub use self::theme::{set_theme, theme, Theme, ThemeColor};
 pub use self::value::{pretty_format_multi_value, pretty_format_value, ValueFormatConfig};
@@ -0,0 +1,243 @@
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{self, Write},
+};
+
+use mlua::prelude::*;
+
+/**
+    A standard stream that console output is normally written to.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+/**
+    Where console output should be written to, instead of its usual stream.
+*/
+#[derive(Debug, Default)]
+pub enum OutputTarget {
+    /// Output is written to the stream it would normally be written to
+    #[default]
+    Default,
+    /// All output is written to stdout
+    Stdout,
+    /// All output is written to stderr
+    Stderr,
+    /// All output is written to the given file
+    File(File),
+}
+
+/**
+    The current output target, any active captures, and the output history, stored in Lua app data.
+*/
+#[derive(Debug, Default)]
+struct OutputState {
+    target: OutputTarget,
+    captures: Vec<(usize, Vec<u8>)>,
+    next_capture_id: usize,
+    history: OutputHistory,
+}
+
+/**
+    A bounded history of the most recent lines of console output.
+
+    Output is split into lines as it is written, and the last line is kept
+    separately until it is terminated, since output may be written in parts.
+*/
+#[derive(Debug, Default)]
+struct OutputHistory {
+    size: usize,
+    lines: VecDeque<String>,
+    pending: Vec<u8>,
+}
+
+impl OutputHistory {
+    fn resize(&mut self, size: usize) {
+        self.size = size;
+        if size == 0 {
+            self.lines.clear();
+            self.pending.clear();
+        }
+        self.truncate();
+    }
+
+    fn record(&mut self, output: &[u8]) {
+        if self.size == 0 {
+            return;
+        }
+        let mut parts = output.split(|b| *b == b'\n');
+        let last = parts.next_back().unwrap_or_default();
+        for part in parts {
+            self.pending.extend_from_slice(part);
+            let line = String::from_utf8_lossy(&self.pending).into_owned();
+            self.pending.clear();
+            self.lines.push_back(line);
+        }
+        self.pending.extend_from_slice(last);
+        self.truncate();
+    }
+
+    fn truncate(&mut self) {
+        // NOTE: An unterminated last line counts towards the size, same as any other line
+        let pending = usize::from(!self.pending.is_empty());
+        while !self.lines.is_empty() && self.lines.len() + pending > self.size {
+            self.lines.pop_front();
+        }
+    }
+
+    fn lines(&self) -> Vec<String> {
+        let mut lines = self.lines.iter().cloned().collect::<Vec<_>>();
+        if !self.pending.is_empty() {
+            lines.push(String::from_utf8_lossy(&self.pending).into_owned());
+        }
+        lines
+    }
+}
+
+/**
+    Sets the target that all subsequent console output is written to.
+*/
+pub fn set_output_target(lua: &Lua, target: OutputTarget) {
+    let mut state = lua.remove_app_data::<OutputState>().unwrap_or_default();
+    state.target = target;
+    lua.set_app_data(state);
+}
+
+/**
+    Sets the number of lines of console output to keep in the output history.
+
+    The history is disabled by default, and setting the size to zero disables it
+    again, clearing it. Making the history smaller discards the oldest lines.
+*/
+pub fn set_output_history_size(lua: &Lua, size: usize) {
+    let mut state = lua.remove_app_data::<OutputState>().unwrap_or_default();
+    state.history.resize(size);
+    lua.set_app_data(state);
+}
+
+/**
+    Returns the lines of console output in the output history, from oldest to newest.
+
+    Output that was captured is not part of the history, since it was never written.
+*/
+#[must_use]
+pub fn output_history(lua: &Lua) -> Vec<String> {
+    lua.app_data_ref::<OutputState>()
+        .map(|state| state.history.lines())
+        .unwrap_or_default()
+}
+
+/**
+    Starts capturing console output, returning an id for the capture.
+
+    While any capture is active, all console output is written to the most
+    recently started capture, and is not written to the output target at all.
+*/
+#[must_use]
+pub fn begin_output_capture(lua: &Lua) -> usize {
+    let mut state = lua.remove_app_data::<OutputState>().unwrap_or_default();
+    let id = state.next_capture_id;
+    state.next_capture_id += 1;
+    state.captures.push((id, Vec::new()));
+    lua.set_app_data(state);
+    id
+}
+
+/**
+    Stops the capture with the given id, returning all output that it captured.
+
+    Returns an empty output if there is no capture with the given id.
+*/
+#[must_use]
+pub fn end_output_capture(lua: &Lua, id: usize) -> Vec<u8> {
+    let Some(mut state) = lua.app_data_mut::<OutputState>() else {
+        return Vec::new();
+    };
+    match state.captures.iter().position(|(cid, _)| *cid == id) {
+        Some(index) => state.captures.remove(index).1,
+        None => Vec::new(),
+    }
+}
+
+/**
+    Writes console output that would normally go to the given stream
+    to the current output target, or to the active capture, if any.
+
+    # Errors
+
+    Errors if writing to, or flushing, the underlying stream or file fails.
+*/
+pub fn write_output(lua: &Lua, stream: OutputStream, output: &[u8]) -> io::Result<()> {
+    let Some(mut state) = lua.app_data_mut::<OutputState>() else {
+        return write_stream(stream, output);
+    };
+    if let Some((_, capture)) = state.captures.last_mut() {
+        capture.extend_from_slice(output);
+        return Ok(());
+    }
+    state.history.record(output);
+    match &mut state.target {
+        OutputTarget::Default => write_stream(stream, output),
+        OutputTarget::Stdout => write_stream(OutputStream::Stdout, output),
+        OutputTarget::Stderr => write_stream(OutputStream::Stderr, output),
+        OutputTarget::File(file) => {
+            file.write_all(output)?;
+            file.flush()
+        }
+    }
+}
+
+fn write_stream(stream: OutputStream, output: &[u8]) -> io::Result<()> {
+    match stream {
+        OutputStream::Stdout => {
+            let mut stdout = io::stdout();
+            stdout.write_all(output)?;
+            stdout.flush()
+        }
+        OutputStream::Stderr => {
+            let mut stderr = io::stderr();
+            stderr.write_all(output)?;
+            stderr.flush()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn captures_output() {
+        let lua = Lua::new();
+        let outer = begin_output_capture(&lua);
+        write_output(&lua, OutputStream::Stdout, b"a").unwrap();
+        let inner = begin_output_capture(&lua);
+        write_output(&lua, OutputStream::Stderr, b"b").unwrap();
+        assert_eq!(end_output_capture(&lua, inner), b"b");
+        write_output(&lua, OutputStream::Stdout, b"c").unwrap();
+        assert_eq!(end_output_capture(&lua, outer), b"ac");
+        assert_eq!(end_output_capture(&lua, outer), b"");
+    }
+
+    #[test]
+    fn keeps_output_history() {
+        let mut history = OutputHistory::default();
+        history.record(b"ignored\n");
+        assert!(history.lines().is_empty());
+
+        history.resize(2);
+        history.record(b"a\nb");
+        history.record(b"c\n");
+        assert_eq!(history.lines(), ["a", "bc"]);
+        history.record(b"d\ne");
+        assert_eq!(history.lines(), ["d", "e"]);
+        history.resize(1);
+        assert_eq!(history.lines(), ["e"]);
+        history.resize(0);
+        assert!(history.lines().is_empty());
+    }
+}
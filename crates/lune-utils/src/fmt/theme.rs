@@ -0,0 +1,217 @@
+use std::{fmt, str::FromStr, sync::RwLock};
+
+use console::colors_enabled;
+
+const ESCAPE_SEQ_RESET: &str = "\x1b[0m";
+
+const BASE_COLOR_NAMES: [&str; 8] = [
+    "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+];
+
+static THEME: RwLock<Theme> = RwLock::new(Theme::DEFAULT);
+
+/**
+    A color used by a [`Theme`].
+
+    This may be one of the 16 base terminal colors, a truecolor (24-bit RGB)
+    color, or the dim style, which uses the default color but fainter.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThemeColor {
+    Base(u8),
+    Bright(u8),
+    Rgb(u8, u8, u8),
+    Dim,
+}
+
+impl ThemeColor {
+    /**
+        Returns the ANSI escape sequence for the color.
+    */
+    #[must_use]
+    pub fn ansi_escape_sequence(self) -> String {
+        match self {
+            Self::Base(index) => format!("\x1b[{}m", 30 + index),
+            Self::Bright(index) => format!("\x1b[{}m", 90 + index),
+            Self::Rgb(r, g, b) => format!("\x1b[38;2;{r};{g};{b}m"),
+            Self::Dim => "\x1b[2m".to_string(),
+        }
+    }
+
+    /**
+        Applies the color to the given text, if colored output is enabled.
+    */
+    #[must_use]
+    pub fn apply(self, text: impl fmt::Display) -> String {
+        self.apply_with(text, false)
+    }
+
+    /**
+        Same as [`ThemeColor::apply`], but also makes the text bold.
+    */
+    #[must_use]
+    pub fn apply_bold(self, text: impl fmt::Display) -> String {
+        self.apply_with(text, true)
+    }
+
+    fn apply_with(self, text: impl fmt::Display, bold: bool) -> String {
+        if !colors_enabled() {
+            return text.to_string();
+        }
+        let bold = if bold { "\x1b[1m" } else { "" };
+        format!(
+            "{}{bold}{text}{ESCAPE_SEQ_RESET}",
+            self.ansi_escape_sequence()
+        )
+    }
+}
+
+impl FromStr for ThemeColor {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().to_ascii_lowercase();
+        if let Some(hex) = name.strip_prefix('#') {
+            let channel = |i: usize| {
+                hex.get(i..i + 2)
+                    .and_then(|h| u8::from_str_radix(h, 16).ok())
+            };
+            return match (hex.len(), channel(0), channel(2), channel(4)) {
+                (6, Some(r), Some(g), Some(b)) => Ok(Self::Rgb(r, g, b)),
+                _ => Err(format!(
+                    "Invalid color '{s}' - hex colors must be in the form '#rrggbb'"
+                )),
+            };
+        }
+        let (bright, base) = match name.strip_prefix("bright") {
+            Some(base) => (true, base.trim_start_matches(['-', '_', ' '])),
+            None => (false, name.as_str()),
+        };
+        // NOTE: Same as for stdio.color, "purple" is kept for backwards compatibility
+        let base = if base == "purple" { "magenta" } else { base };
+        let index = BASE_COLOR_NAMES.iter().position(|n| *n == base);
+        match (name.as_str(), index) {
+            ("dim", _) => Ok(Self::Dim),
+            ("gray" | "grey", _) => Ok(Self::Bright(0)),
+            (_, Some(index)) => {
+                #[allow(clippy::cast_possible_truncation)]
+                let index = index as u8;
+                Ok(if bright {
+                    Self::Bright(index)
+                } else {
+                    Self::Base(index)
+                })
+            }
+            _ => Err(format!(
+                "Invalid color '{s}'\nValid colors are: {}, their bright variants \
+                such as 'brightred', gray, dim, or hex colors such as '#ff8800'",
+                BASE_COLOR_NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+/**
+    The colors used for console output throughout Lune, such as for labels and log levels.
+
+    The theme is shared by all Lua states, same as the setting for colored output
+    being enabled, and may be changed using [`set_theme`] at any time.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    pub log: ThemeColor,
+    pub info: ThemeColor,
+    pub warn: ThemeColor,
+    pub error: ThemeColor,
+    pub label: ThemeColor,
+    pub dim: ThemeColor,
+}
+
+impl Theme {
+    pub const DEFAULT: Self = Self {
+        log: ThemeColor::Base(7),
+        info: ThemeColor::Base(4),
+        warn: ThemeColor::Base(3),
+        error: ThemeColor::Base(1),
+        label: ThemeColor::Base(6),
+        dim: ThemeColor::Dim,
+    };
+
+    /**
+        Returns the names of all colors in a theme.
+    */
+    #[must_use]
+    pub const fn names() -> [&'static str; 6] {
+        ["log", "info", "warn", "error", "label", "dim"]
+    }
+
+    /**
+        Gets a mutable reference to the color with the given name, if it exists.
+    */
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut ThemeColor> {
+        match name {
+            "log" => Some(&mut self.log),
+            "info" => Some(&mut self.info),
+            "warn" => Some(&mut self.warn),
+            "error" => Some(&mut self.error),
+            "label" => Some(&mut self.label),
+            "dim" => Some(&mut self.dim),
+            _ => None,
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/**
+    Returns the current theme used for console output.
+*/
+#[must_use]
+pub fn theme() -> Theme {
+    *THEME
+        .read()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+}
+
+/**
+    Sets the theme used for all subsequent console output.
+*/
+pub fn set_theme(theme: Theme) {
+    *THEME
+        .write()
+        .unwrap_or_else(std::sync::PoisonError::into_inner) = theme;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_colors() {
+        assert_eq!("red".parse(), Ok(ThemeColor::Base(1)));
+        assert_eq!(" Cyan ".parse(), Ok(ThemeColor::Base(6)));
+        assert_eq!("brightred".parse(), Ok(ThemeColor::Bright(1)));
+        assert_eq!("bright-white".parse(), Ok(ThemeColor::Bright(7)));
+        assert_eq!("gray".parse(), Ok(ThemeColor::Bright(0)));
+        assert_eq!("dim".parse(), Ok(ThemeColor::Dim));
+        assert_eq!("#FF8800".parse(), Ok(ThemeColor::Rgb(255, 136, 0)));
+
+        for invalid in ["", "purple?", "bright", "#ff88", "#gg0000", "#ff880000"] {
+            assert!(invalid.parse::<ThemeColor>().is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn creates_escape_sequences() {
+        assert_eq!(ThemeColor::Base(1).ansi_escape_sequence(), "\x1b[31m");
+        assert_eq!(ThemeColor::Bright(0).ansi_escape_sequence(), "\x1b[90m");
+        assert_eq!(
+            ThemeColor::Rgb(1, 2, 3).ansi_escape_sequence(),
+            "\x1b[38;2;1;2;3m"
+        );
+        assert_eq!(ThemeColor::Dim.ansi_escape_sequence(), "\x1b[2m");
+    }
+}
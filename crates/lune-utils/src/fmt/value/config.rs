@@ -5,6 +5,7 @@
 pub struct ValueFormatConfig {
     pub(super) max_depth: usize,
     pub(super) colors_enabled: bool,
+    pub(super) multiline: bool,
 }
 
 impl ValueFormatConfig {
@@ -16,6 +17,7 @@ impl ValueFormatConfig {
         Self {
             max_depth: 3,
             colors_enabled: false,
+            multiline: true,
         }
     }
 
@@ -39,6 +41,16 @@ impl ValueFormatConfig {
             ..self
         }
     }
+
+    /**
+        Sets whether tables should be formatted over multiple lines, with one line per entry.
+
+        When disabled, tables are formatted on a single line instead. Enabled by default.
+    */
+    #[must_use]
+    pub const fn with_multiline(self, multiline: bool) -> Self {
+        Self { multiline, ..self }
+    }
 }
 
 impl Default for ValueFormatConfig {
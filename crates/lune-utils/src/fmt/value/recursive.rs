@@ -6,7 +6,7 @@ use mlua::prelude::*;
 use super::{
     basic::{format_value_styled, lua_value_as_plain_string_key},
     config::ValueFormatConfig,
-    style::STYLE_DIM,
+    style::dim,
 };
 
 const INDENT: &str = "    ";
@@ -33,6 +33,9 @@ impl From<&LuaTable<'_>> for LuaValueId {
     Formats the given value, recursively formatting tables
     up to the maximum depth specified in the config.
 
+    Tables that contain themselves, directly or through some other
+    nested table, are formatted as `<cycle>` instead of recursing forever.
+
     NOTE: We return a result here but it's really just to make handling
     of the `write!` calls easier. Writing into a string should never fail.
 */
@@ -46,39 +49,51 @@ pub(crate) fn format_value_recursive(
 
     if let LuaValue::Table(ref t) = value {
         if depth >= config.max_depth {
-            write!(buffer, "{}", STYLE_DIM.apply_to("{ ... }"))?;
+            write!(buffer, "{}", dim("{ ... }"))?;
+        } else if t.clone().pairs::<LuaValue, LuaValue>().next().is_none() {
+            write!(buffer, "{}", dim("{}"))?;
         } else if !visited.insert(LuaValueId::from(t)) {
-            write!(buffer, "{}", STYLE_DIM.apply_to("{ recursive }"))?;
+            write!(buffer, "{}", dim("<cycle>"))?;
         } else {
-            writeln!(buffer, "{}", STYLE_DIM.apply_to("{"))?;
-
+            let mut entries = Vec::new();
             for res in t.clone().pairs::<LuaValue, LuaValue>() {
                 let (key, value) = res.expect("conversion to LuaValue should never fail");
                 let formatted = if let Some(plain_key) = lua_value_as_plain_string_key(&key) {
                     format!(
-                        "{}{plain_key} {} {}{}",
-                        INDENT.repeat(1 + depth),
-                        STYLE_DIM.apply_to("="),
+                        "{plain_key} {} {}",
+                        dim("="),
                         format_value_recursive(&value, config, visited, depth + 1)?,
-                        STYLE_DIM.apply_to(","),
                     )
                 } else {
                     format!(
-                        "{}{}{}{} {} {}{}",
-                        INDENT.repeat(1 + depth),
-                        STYLE_DIM.apply_to("["),
+                        "{}{}{} {} {}",
+                        dim("["),
                         format_value_recursive(&key, config, visited, depth + 1)?,
-                        STYLE_DIM.apply_to("]"),
-                        STYLE_DIM.apply_to("="),
+                        dim("]"),
+                        dim("="),
                         format_value_recursive(&value, config, visited, depth + 1)?,
-                        STYLE_DIM.apply_to(","),
                     )
                 };
-                buffer.push_str(&formatted);
+                entries.push(formatted);
             }
-
             visited.remove(&LuaValueId::from(t));
-            write!(buffer, "\n{}", STYLE_DIM.apply_to("}"))?;
+
+            if config.multiline {
+                writeln!(buffer, "{}", dim("{"))?;
+                for entry in entries {
+                    writeln!(buffer, "{}{entry}{}", INDENT.repeat(1 + depth), dim(","))?;
+                }
+                write!(buffer, "{}{}", INDENT.repeat(depth), dim("}"))?;
+            } else {
+                let separator = format!("{} ", dim(","));
+                write!(
+                    buffer,
+                    "{} {} {}",
+                    dim("{"),
+                    entries.join(&separator),
+                    dim("}")
+                )?;
+            }
         }
     } else {
         let prefer_plain = depth == 0;
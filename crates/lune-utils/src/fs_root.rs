@@ -0,0 +1,192 @@
+use std::{
+    io::ErrorKind as IoErrorKind,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use mlua::prelude::*;
+
+use crate::path::clean_path;
+
+// NOTE: This matches the limit that most operating systems have
+// for following symlinks, which also guards against symlink loops
+const MAX_SYMLINK_DEPTH: usize = 40;
+
+/**
+    A root directory that all access using the `fs` library is confined to,
+    set as app data for a Lua state, so that other libraries can also respect it.
+
+    Relative paths are relative to the root instead of the current working directory,
+    and absolute paths must be inside of the root. Any path that would end up outside of
+    the root, either using `..` components or by following symlinks, is rejected.
+*/
+#[derive(Debug, Clone)]
+pub struct FsRoot(Arc<Path>);
+
+impl FsRoot {
+    /**
+        Creates a new root from the given directory.
+
+        # Errors
+
+        Errors if the directory does not exist, or if it is not a directory.
+    */
+    pub fn new(dir: impl AsRef<Path>) -> LuaResult<Self> {
+        let dir = dir.as_ref();
+        let canonical = dunce::canonicalize(dir).map_err(|e| {
+            LuaError::RuntimeError(format!(
+                "Failed to set filesystem root to '{}'\n{e}",
+                dir.display()
+            ))
+        })?;
+        if !canonical.is_dir() {
+            return Err(LuaError::RuntimeError(format!(
+                "Failed to set filesystem root to '{}' - not a directory",
+                dir.display()
+            )));
+        }
+        Ok(Self(canonical.into()))
+    }
+
+    /**
+        Returns the canonicalized path of the root directory.
+    */
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+
+    /**
+        Resolves the given path inside of the root, erroring if it is outside of it.
+
+        Symlinks in any of the parent directories are resolved, and the returned
+        path only contains real directories that are inside of the root. The last
+        component is kept as-is, but if it is a symlink, the path that it points
+        to must also be inside of the root.
+
+        # Errors
+
+        Errors if the path is outside of the root, or if it could not be resolved.
+    */
+    pub fn resolve(&self, path: impl AsRef<Path>) -> LuaResult<PathBuf> {
+        self.resolve_with(path.as_ref(), true)
+    }
+
+    /**
+        Same as [`FsRoot::resolve`], but for operations on symlinks themselves, such as
+        removing them, where it does not matter where the symlink in the last component
+        points to - only its parent directories must be inside of the root.
+
+        # Errors
+
+        Errors if the path is outside of the root, or if it could not be resolved.
+    */
+    pub fn resolve_no_follow(&self, path: impl AsRef<Path>) -> LuaResult<PathBuf> {
+        self.resolve_with(path.as_ref(), false)
+    }
+
+    fn resolve_with(&self, path: &Path, follow: bool) -> LuaResult<PathBuf> {
+        self.resolve_inner(path, follow, 0)
+            .ok_or_else(|| outside_error(path))?
+            .map_err(|e| {
+                LuaError::RuntimeError(format!("Failed to resolve path '{}'\n{e}", path.display()))
+            })
+    }
+
+    /**
+        Checks that an existing path, after following all symlinks, is inside of the root.
+
+        # Errors
+
+        Errors if the path is outside of the root, or if it could not be canonicalized.
+    */
+    pub fn check(&self, path: impl AsRef<Path>) -> LuaResult<()> {
+        let path = path.as_ref();
+        match dunce::canonicalize(path) {
+            Ok(canonical) if canonical.starts_with(&self.0) => Ok(()),
+            Ok(_) => Err(outside_error(path)),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn resolve_inner(
+        &self,
+        path: &Path,
+        follow: bool,
+        depth: usize,
+    ) -> Option<std::io::Result<PathBuf>> {
+        // NOTE: Joining an absolute path replaces the root entirely, and those
+        // must then be inside of the root, which the check below makes sure of
+        let joined = clean_path(self.0.join(path));
+        if joined == *self.0 {
+            return Some(Ok(joined));
+        }
+        let (Some(parent), Some(name)) = (joined.parent(), joined.file_name()) else {
+            return None;
+        };
+        let resolved = match canonicalize_existing(parent) {
+            Ok(parent) => parent.join(name),
+            Err(e) => return Some(Err(e)),
+        };
+        if !resolved.starts_with(&self.0) {
+            return None;
+        }
+
+        // NOTE: Links that point to other links are followed one at a time,
+        // so that dangling links can never point outside of the root either
+        if !follow {
+            return Some(Ok(resolved));
+        }
+        match std::fs::symlink_metadata(&resolved) {
+            Ok(meta) if meta.is_symlink() => {
+                if depth >= MAX_SYMLINK_DEPTH {
+                    return None;
+                }
+                let target = match std::fs::read_link(&resolved) {
+                    Ok(target) => target,
+                    Err(e) => return Some(Err(e)),
+                };
+                let target = resolved.parent()?.join(target);
+                match self.resolve_inner(&target, true, depth + 1)? {
+                    Ok(_) => Some(Ok(resolved)),
+                    Err(e) => Some(Err(e)),
+                }
+            }
+            Ok(_) => Some(Ok(resolved)),
+            Err(e) if e.kind() == IoErrorKind::NotFound => Some(Ok(resolved)),
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+/**
+    Canonicalizes the longest part of the given path that exists, and then
+    appends the remaining parts, which can not be symlinks since they do not exist.
+*/
+fn canonicalize_existing(path: &Path) -> std::io::Result<PathBuf> {
+    let mut existing = path;
+    let mut missing = Vec::new();
+    loop {
+        match dunce::canonicalize(existing) {
+            Ok(mut canonical) => {
+                canonical.extend(missing.into_iter().rev());
+                return Ok(canonical);
+            }
+            Err(e) if e.kind() == IoErrorKind::NotFound => {
+                let (Some(parent), Some(name)) = (existing.parent(), existing.file_name()) else {
+                    return Err(e);
+                };
+                missing.push(name);
+                existing = parent;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+fn outside_error(path: &Path) -> LuaError {
+    LuaError::RuntimeError(format!(
+        "Access to the path '{}' is not allowed - it is outside of the filesystem root",
+        path.display()
+    ))
+}
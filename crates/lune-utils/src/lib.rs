@@ -1,10 +1,15 @@
 #![allow(clippy::cargo_common_metadata)]
 
+mod exit_code;
+mod fs_root;
 mod table_builder;
 mod version_string;
 
+pub mod buffer;
 pub mod fmt;
 pub mod path;
 
+pub use self::exit_code::ScriptExitCode;
+pub use self::fs_root::FsRoot;
 pub use self::table_builder::TableBuilder;
 pub use self::version_string::get_version_string;
@@ -9,4 +9,6 @@ pub use lune_roblox as roblox;
 #[cfg(test)]
 mod tests;
 
-pub use crate::rt::{Runtime, RuntimeError, RuntimeResult};
+pub use crate::rt::{Runtime, RuntimeChunk, RuntimeError, RuntimeHandle, RuntimeResult};
+
+pub use lune_utils::fmt::{ErrorComponents, StackTrace, StackTraceLine, StackTraceSource};
@@ -0,0 +1,67 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use tokio::sync::Notify;
+
+#[derive(Debug, Default)]
+struct RuntimeHandleInner {
+    interrupted: AtomicBool,
+    notify: Notify,
+}
+
+/**
+    A handle to a [`Runtime`](crate::Runtime), created using [`Runtime::handle`](crate::Runtime::handle).
+
+    Handles are cheap to clone, and may be sent to and used from any other thread.
+*/
+#[derive(Debug, Clone, Default)]
+pub struct RuntimeHandle {
+    inner: Arc<RuntimeHandleInner>,
+}
+
+impl RuntimeHandle {
+    /**
+        Interrupts the script that is currently running in the runtime.
+
+        Any running Luau code will be stopped at the next instruction, and any threads
+        waiting for scheduled tasks to complete will no longer be resumed. The script
+        will then return an error, which can be checked for using
+        [`RuntimeError::is_interrupted`](crate::RuntimeError::is_interrupted).
+
+        If no script is currently running, the next script
+        to run will be interrupted as soon as it starts.
+    */
+    pub fn interrupt(&self) {
+        self.inner.interrupted.store(true, Ordering::SeqCst);
+        self.inner.notify.notify_one();
+    }
+
+    /**
+        Returns `true` if the runtime has been interrupted, and
+        the interruption has not yet been handled by a script.
+    */
+    #[must_use]
+    pub fn is_interrupted(&self) -> bool {
+        self.inner.interrupted.load(Ordering::SeqCst)
+    }
+
+    /**
+        Returns `true` and resets the interrupted state, if the runtime was interrupted.
+    */
+    pub(crate) fn take_interrupted(&self) -> bool {
+        self.inner.interrupted.swap(false, Ordering::SeqCst)
+    }
+
+    /**
+        Waits until the runtime is interrupted.
+    */
+    pub(crate) async fn interrupted(&self) {
+        // NOTE: The notify may hold on to a permit from an interruption that has already
+        // been handled, so we need to check the flag again every time we get notified
+        while !self.is_interrupted() {
+            self.inner.notify.notified().await;
+        }
+    }
+}
@@ -1,5 +1,8 @@
+mod handle;
 mod result;
 mod runtime;
 
+pub use self::handle::RuntimeHandle;
 pub use self::result::{RuntimeError, RuntimeResult};
-pub use self::runtime::Runtime;
+pub(crate) use self::result::{RuntimeErrorReporter, RuntimeInterruptedError, RuntimeTimeoutError};
+pub use self::runtime::{Runtime, RuntimeChunk};
@@ -1,6 +1,8 @@
 use std::{
     error::Error,
     fmt::{Debug, Display, Formatter, Result as FmtResult},
+    sync::Arc,
+    time::Duration,
 };
 
 use mlua::prelude::*;
@@ -19,6 +21,20 @@ pub struct RuntimeError {
 }
 
 impl RuntimeError {
+    /**
+        Creates a new error for a script that did not complete before the given timeout.
+    */
+    pub(crate) fn timed_out(timeout: Duration) -> Self {
+        Self::from(LuaError::external(RuntimeTimeoutError(timeout)))
+    }
+
+    /**
+        Creates a new error for a script that was interrupted using a [`RuntimeHandle`](crate::RuntimeHandle).
+    */
+    pub(crate) fn interrupted() -> Self {
+        Self::from(LuaError::external(RuntimeInterruptedError))
+    }
+
     /**
         Enables colorization of the error message when formatted using the [`Display`] trait.
 
@@ -43,6 +59,52 @@ impl RuntimeError {
         self
     }
 
+    /**
+        Returns the components of the error, meaning its messages and its stack trace,
+        which can be used to present the error differently than its [`Display`] format.
+
+        The source location of the error can be found in the first
+        line of the stack trace that came from a Luau function.
+    */
+    #[must_use]
+    pub fn components(&self) -> ErrorComponents {
+        ErrorComponents::from(self.error.clone())
+    }
+
+    /**
+        Returns the underlying Lua error.
+    */
+    #[must_use]
+    pub fn as_lua_error(&self) -> &LuaError {
+        &self.error
+    }
+
+    /**
+        Returns `true` if the script did not complete before its timeout.
+
+        See [`Runtime::run_with_timeout`](crate::Runtime::run_with_timeout) for more information.
+    */
+    #[must_use]
+    pub fn is_timeout(&self) -> bool {
+        matches!(
+            &self.error,
+            LuaError::ExternalError(e) if e.downcast_ref::<RuntimeTimeoutError>().is_some()
+        )
+    }
+
+    /**
+        Returns `true` if the script was interrupted.
+
+        See [`RuntimeHandle::interrupt`](crate::RuntimeHandle::interrupt) for more information.
+    */
+    #[must_use]
+    pub fn is_interrupted(&self) -> bool {
+        matches!(
+            &self.error,
+            LuaError::ExternalError(e) if e.downcast_ref::<RuntimeInterruptedError>().is_some()
+        )
+    }
+
     /**
         Returns `true` if the error can likely be fixed by appending more input to the source code.
 
@@ -89,3 +151,56 @@ impl Error for RuntimeError {
         Some(&self.error)
     }
 }
+
+/**
+    A function that is given all errors thrown by scripts and their tasks that
+    were not caught, set using [`Runtime::with_error_reporter`](crate::Runtime::with_error_reporter).
+*/
+#[derive(Clone)]
+pub(crate) struct RuntimeErrorReporter(pub Arc<dyn Fn(&RuntimeError) + Send + Sync>);
+
+impl RuntimeErrorReporter {
+    pub fn report(&self, error: &RuntimeError) {
+        (self.0)(error);
+    }
+}
+
+impl Default for RuntimeErrorReporter {
+    fn default() -> Self {
+        Self(Arc::new(|error| eprintln!("{error}")))
+    }
+}
+
+impl Debug for RuntimeErrorReporter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "RuntimeErrorReporter")
+    }
+}
+
+/**
+    The error used for scripts that did not complete before their timeout.
+*/
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct RuntimeTimeoutError(pub Duration);
+
+impl Display for RuntimeTimeoutError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "Script timed out after {:?}", self.0)
+    }
+}
+
+impl Error for RuntimeTimeoutError {}
+
+/**
+    The error used for scripts that were interrupted using a runtime handle.
+*/
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct RuntimeInterruptedError;
+
+impl Display for RuntimeInterruptedError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "Script was interrupted")
+    }
+}
+
+impl Error for RuntimeInterruptedError {}
@@ -1,23 +1,50 @@
 #![allow(clippy::missing_panics_doc)]
 
 use std::{
+    cell::Cell,
+    future::pending,
     process::ExitCode,
     rc::Rc,
     sync::{
         atomic::{AtomicBool, Ordering},
         Arc,
     },
+    time::Duration,
 };
 
-use mlua::Lua;
+use lune_utils::ScriptExitCode;
+use mlua::prelude::*;
 use mlua_luau_scheduler::Scheduler;
+use tokio::{
+    pin, select,
+    task::unconstrained,
+    time::{sleep, sleep_until, Instant},
+};
+
+use super::{
+    RuntimeError, RuntimeErrorReporter, RuntimeHandle, RuntimeInterruptedError, RuntimeResult,
+    RuntimeTimeoutError,
+};
 
-use super::{RuntimeError, RuntimeResult};
+/**
+    A compiled Lune script, created using [`Runtime::load`].
+
+    The same chunk may be run any number of times using [`Runtime::run_chunk`]
+    and [`Runtime::eval_chunk`], without its source being compiled again.
+*/
+#[derive(Debug)]
+pub struct RuntimeChunk {
+    function: LuaRegistryKey,
+}
 
 #[derive(Debug)]
 pub struct Runtime {
     lua: Rc<Lua>,
     args: Vec<String>,
+    handle: RuntimeHandle,
+    drain_timeout: Option<Duration>,
+    error_reporter: RuntimeErrorReporter,
+    last_exit_code: Cell<Option<u8>>,
 }
 
 impl Runtime {
@@ -53,9 +80,40 @@ impl Runtime {
         Self {
             lua,
             args: Vec::new(),
+            handle: RuntimeHandle::default(),
+            drain_timeout: None,
+            error_reporter: RuntimeErrorReporter::default(),
+            last_exit_code: Cell::new(None),
         }
     }
 
+    /**
+        Returns a handle to the runtime, which can be used to interrupt
+        running scripts from other threads, such as when handling Ctrl-C.
+
+        All handles returned by this function refer to the same runtime.
+    */
+    #[must_use]
+    pub fn handle(&self) -> RuntimeHandle {
+        self.handle.clone()
+    }
+
+    /**
+        Returns the exit code of the script that was most recently run in this runtime,
+        as a number, or `None` if no script has been run yet.
+
+        This is the same exit code as the one returned from running the script - the code
+        given to `process.exit` if the script exited using it, `1` if the script or any of its
+        tasks threw an error, was interrupted, or timed out, and `0` if it ran successfully.
+
+        Unlike the [`ExitCode`] returned from running, this can be passed on to
+        [`std::process::exit`], or be reported to the user of a host application.
+    */
+    #[must_use]
+    pub fn last_exit_code(&self) -> Option<u8> {
+        self.last_exit_code.get()
+    }
+
     /**
         Sets arguments to give in `process.args` for Lune scripts.
     */
@@ -69,9 +127,152 @@ impl Runtime {
         self
     }
 
+    /**
+        Sets the maximum amount of time to wait for scheduled tasks to complete,
+        such as ones spawned using `task.delay` or `task.defer`, after the
+        main chunk of a script has finished running.
+
+        Any tasks that have not completed once the drain timeout has elapsed will no
+        longer be resumed, and the script will return as if all tasks had completed.
+
+        By default, there is no drain timeout, and scripts run until all tasks complete.
+    */
+    #[must_use]
+    pub fn with_drain_timeout(mut self, timeout: Duration) -> Self {
+        self.drain_timeout = Some(timeout);
+        self
+    }
+
+    /**
+        Sets a function that is given all errors thrown by scripts and their tasks that were not
+        caught, such as errors thrown by the main chunk of a script or by a spawned task, replacing
+        the default behavior of printing them to stderr.
+
+        This can be used to present errors in some other way, or to collect them, using
+        [`RuntimeError::components`] to get their messages and stack traces. The function
+        may be called from any thread, and more than once per script, since every task
+        that throws an error is reported separately.
+
+        Errors that are returned from running a script, such as when it fails
+        to compile or times out, are not reported, since they are already returned.
+    */
+    #[must_use]
+    pub fn with_error_reporter<F>(mut self, reporter: F) -> Self
+    where
+        F: Fn(&RuntimeError) + Send + Sync + 'static,
+    {
+        self.error_reporter = RuntimeErrorReporter(Arc::new(reporter));
+        self
+    }
+
+    /**
+        Confines all access using the `fs` standard library to the given root directory.
+
+        Relative paths given to the `fs` library are then relative to the root instead
+        of the current working directory, and any access to a path that is outside of
+        the root, either directly, using `..` components, or by following symlinks,
+        is rejected with an error.
+
+        Note that this only applies to the `fs` library and to config files loaded using
+        `serde.loadConfig`, other libraries such as `process` and `net`, as well as
+        `require`, may still access any path.
+
+        # Errors
+
+        This function will return an error if the given root directory does not exist.
+    */
+    #[cfg(feature = "std-fs")]
+    pub fn with_fs_root(self, root: impl AsRef<std::path::Path>) -> RuntimeResult<Self> {
+        self.lua.set_app_data(lune_std::FsRoot::new(root)?);
+        Ok(self)
+    }
+
+    /**
+        Restricts which hosts the `net` standard library may send requests and connect to.
+
+        Host names are checked before any request is sent, including for redirects, and
+        the addresses that they resolve to are checked right before connecting, so that
+        allowed host names can not be used to reach denied or private addresses.
+        Requests that are rejected by the policy fail with a `"policy"` net error.
+
+        Proxies given to clients are checked the same way as hosts of requests, and proxies
+        from the environment are never used, since those may have been set by the script.
+        Note that when a proxy is used, it resolves the hosts of requests instead, so only
+        their host names can be checked, and not the addresses that they resolve to.
+    */
+    #[cfg(feature = "std-net")]
+    #[must_use]
+    pub fn with_net_policy(self, policy: lune_std::NetPolicy) -> Self {
+        self.lua.set_app_data(policy);
+        self
+    }
+
+    /**
+        Sets how `require` resolves the paths of modules, such as a root directory that
+        script names are relative to, and directories to search for modules in.
+
+        See [`lune_std::RequireConfig`] for more information.
+    */
+    #[cfg(any(
+        feature = "std-datetime",
+        feature = "std-fs",
+        feature = "std-luau",
+        feature = "std-net",
+        feature = "std-process",
+        feature = "std-regex",
+        feature = "std-roblox",
+        feature = "std-serde",
+        feature = "std-stdio",
+        feature = "std-task",
+    ))]
+    #[must_use]
+    pub fn with_require_config(self, config: lune_std::RequireConfig) -> Self {
+        self.lua.set_app_data(config);
+        self
+    }
+
+    /**
+        Sets a global value that will be available to all Lune scripts.
+
+        This can be used to add custom globals, or to override any of the standard globals.
+
+        # Errors
+
+        This function will return an error if the value could not be converted to a Lua value.
+    */
+    pub fn with_global<V>(self, name: impl AsRef<str>, value: V) -> RuntimeResult<Self>
+    where
+        V: for<'lua> IntoLua<'lua>,
+    {
+        self.lua.globals().raw_set(name.as_ref(), value)?;
+        Ok(self)
+    }
+
+    /**
+        Sets global values that will be available to all Lune scripts using the given callback.
+
+        The callback receives the Lua VM and its globals table, which is useful for
+        globals that need to be created using the Lua VM, such as functions and tables.
+
+        # Errors
+
+        This function will return an error if the callback returns an error.
+    */
+    pub fn with_globals<F>(self, f: F) -> RuntimeResult<Self>
+    where
+        F: for<'lua> FnOnce(&'lua Lua, LuaTable<'lua>) -> LuaResult<()>,
+    {
+        f(&self.lua, self.lua.globals())?;
+        Ok(self)
+    }
+
     /**
         Runs a Lune script inside of the current runtime.
 
+        After the main chunk of the script has finished running, the runtime will keep
+        running until all scheduled tasks have completed, or until the drain timeout set
+        using [`Runtime::with_drain_timeout`] has elapsed, and only then return.
+
         This will preserve any modifications to global values / context.
 
         # Errors
@@ -83,34 +284,308 @@ impl Runtime {
         script_name: impl AsRef<str>,
         script_contents: impl AsRef<[u8]>,
     ) -> RuntimeResult<ExitCode> {
-        // Create a new scheduler for this run
+        let main = self.compile(script_name, script_contents)?;
+        let (exit_code, _) = self.execute::<()>(main, (), None, true).await?;
+        Ok(exit_code)
+    }
+
+    /**
+        Runs a Lune script inside of the current runtime, passing the given values to it.
+
+        The values are given to the main chunk of the script as its varargs, which can be read
+        using `...`, and may be any values that can be converted into Luau values, such as tables
+        created from vectors and maps - these are separate from the `process.args` strings, which
+        can be set using [`Runtime::with_args`].
+
+        Otherwise, this behaves the same as [`Runtime::run`].
+
+        # Errors
+
+        This function will return an error if the values could not
+        be converted into Luau values, or if the script fails to run.
+    */
+    pub async fn run_with_args_values<A>(
+        &mut self,
+        script_name: impl AsRef<str>,
+        script_contents: impl AsRef<[u8]>,
+        args: A,
+    ) -> RuntimeResult<ExitCode>
+    where
+        A: for<'lua> IntoLuaMulti<'lua>,
+    {
+        let main = self.compile(script_name, script_contents)?;
+        let (exit_code, _) = self.execute::<()>(main, args, None, true).await?;
+        Ok(exit_code)
+    }
+
+    /**
+        Runs a Lune script inside of the current runtime, returning as
+        soon as the main chunk of the script has finished running.
+
+        Any scheduled tasks that are still waiting to be resumed by then,
+        such as ones spawned using `task.delay`, will never be resumed.
+
+        This will preserve any modifications to global values / context.
+
+        # Errors
+
+        This function will return an error if the script fails to run.
+    */
+    pub async fn run_no_drain(
+        &mut self,
+        script_name: impl AsRef<str>,
+        script_contents: impl AsRef<[u8]>,
+    ) -> RuntimeResult<ExitCode> {
+        let main = self.compile(script_name, script_contents)?;
+        let (exit_code, _) = self.execute::<()>(main, (), None, false).await?;
+        Ok(exit_code)
+    }
+
+    /**
+        Runs a Lune script inside of the current runtime, stopping it if
+        it has not completed before the given timeout has elapsed.
+
+        The timeout covers both time spent running Luau code, such as an infinite
+        loop, and time spent waiting for any scheduled threads or tasks to complete.
+
+        This will preserve any modifications to global values / context.
+
+        # Errors
+
+        This function will return an error if the script fails to run, or if the
+        script times out. Timeouts can be checked for using [`RuntimeError::is_timeout`].
+    */
+    pub async fn run_with_timeout(
+        &mut self,
+        script_name: impl AsRef<str>,
+        script_contents: impl AsRef<[u8]>,
+        timeout: Duration,
+    ) -> RuntimeResult<ExitCode> {
+        let main = self.compile(script_name, script_contents)?;
+        let (exit_code, _) = self.execute::<()>(main, (), Some(timeout), true).await?;
+        Ok(exit_code)
+    }
+
+    /**
+        Runs a Lune script inside of the current runtime, and returns the values it returned.
+
+        This will preserve any modifications to global values / context.
+
+        If the script exits using `process.exit` before returning, it
+        will be treated as if it returned no values at all.
+
+        # Errors
+
+        This function will return an error if the script fails to run, if the script
+        throws an error, or if the returned values could not be converted to `T`.
+    */
+    pub async fn eval<T>(
+        &mut self,
+        script_name: impl AsRef<str>,
+        script_contents: impl AsRef<[u8]>,
+    ) -> RuntimeResult<T>
+    where
+        T: for<'lua> FromLuaMulti<'lua>,
+    {
+        let main = self.compile(script_name, script_contents)?;
+        let (_, result) = self.execute(main, (), None, true).await?;
+        Ok(result?)
+    }
+
+    /**
+        Compiles a Lune script into a chunk that can be run
+        multiple times, without having to compile it again.
+
+        # Errors
+
+        This function will return an error if the script fails to compile.
+    */
+    pub fn load(
+        &self,
+        script_name: impl AsRef<str>,
+        script_contents: impl AsRef<[u8]>,
+    ) -> RuntimeResult<RuntimeChunk> {
+        let main = self.compile(script_name, script_contents)?;
+        let function = self.lua.create_registry_value(main)?;
+        Ok(RuntimeChunk { function })
+    }
+
+    /**
+        Runs a chunk created using [`Runtime::load`] inside of the current runtime.
+
+        This will preserve any modifications to global values / context.
+
+        # Errors
+
+        This function will return an error if the chunk was created by a
+        different runtime, or if it fails to run.
+    */
+    pub async fn run_chunk(&mut self, chunk: &RuntimeChunk) -> RuntimeResult<ExitCode> {
+        let main = self.lua.registry_value(&chunk.function)?;
+        let (exit_code, _) = self.execute::<()>(main, (), None, true).await?;
+        Ok(exit_code)
+    }
+
+    /**
+        Runs a chunk created using [`Runtime::load`] inside of the
+        current runtime, and returns the values it returned.
+
+        This behaves the same as [`Runtime::eval`], but without compiling the script again.
+
+        # Errors
+
+        This function will return an error if the chunk was created by a different runtime, if it
+        fails to run, if it throws an error, or if the returned values could not be converted to `T`.
+    */
+    pub async fn eval_chunk<T>(&mut self, chunk: &RuntimeChunk) -> RuntimeResult<T>
+    where
+        T: for<'lua> FromLuaMulti<'lua>,
+    {
+        let main = self.lua.registry_value(&chunk.function)?;
+        let (_, result) = self.execute(main, (), None, true).await?;
+        Ok(result?)
+    }
+
+    fn compile(
+        &self,
+        script_name: impl AsRef<str>,
+        script_contents: impl AsRef<[u8]>,
+    ) -> LuaResult<LuaFunction<'_>> {
+        self.lua
+            .load(script_contents.as_ref())
+            .set_name(script_name.as_ref())
+            .into_function()
+    }
+
+    async fn execute<'a, T>(
+        &'a self,
+        main: LuaFunction<'a>,
+        args: impl IntoLuaMulti<'a>,
+        timeout: Option<Duration>,
+        drain: bool,
+    ) -> RuntimeResult<(ExitCode, LuaResult<T>)>
+    where
+        T: for<'lua> FromLuaMulti<'lua>,
+    {
+        // Create a new scheduler for this run, and forget any exit code from a previous run
         let sched = Scheduler::new(&self.lua);
+        self.lua.remove_app_data::<ScriptExitCode>();
+        self.last_exit_code.set(Some(1));
 
-        // Add error callback to format errors nicely + store status
+        // Add error callback to report errors + store status
         let got_any_error = Arc::new(AtomicBool::new(false));
         let got_any_inner = Arc::clone(&got_any_error);
+        let reporter = self.error_reporter.clone();
         sched.set_error_callback(move |e| {
             got_any_inner.store(true, Ordering::SeqCst);
-            eprintln!("{}", RuntimeError::from(e));
+            reporter.report(&RuntimeError::from(e));
         });
 
-        // Load our "main" thread
-        let main = self
-            .lua
-            .load(script_contents.as_ref())
-            .set_name(script_name.as_ref());
+        // Run our "main" thread on our scheduler until it and any other spawned threads complete,
+        // interrupting any running Luau code if we have a timeout and it elapses, or if we get
+        // interrupted using a runtime handle
+        let main_id = sched.push_thread_back(main, args)?;
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let timed_out = Arc::new(AtomicBool::new(false));
+        let timed_out_inner = Arc::clone(&timed_out);
+        let handle = self.handle.clone();
+        self.lua.set_interrupt(move |_| {
+            if handle.is_interrupted() {
+                return Err(LuaError::external(RuntimeInterruptedError));
+            }
+            match (timeout, deadline) {
+                (Some(timeout), Some(deadline)) if Instant::now() >= deadline => {
+                    timed_out_inner.store(true, Ordering::SeqCst);
+                    Err(LuaError::external(RuntimeTimeoutError(timeout)))
+                }
+                _ => Ok(LuaVmState::Continue),
+            }
+        });
+
+        // NOTE: The scheduler must always run until completion, so instead of
+        // cancelling it when threads are waiting past our deadline, when we
+        // get interrupted, or when we should stop draining remaining tasks,
+        // we make it exit by setting an exit code, same as process.exit would do
+        // NOTE: The scheduler resumes all of its threads from within this one future,
+        // so it must opt out of the cooperative budget used by tokio, otherwise any
+        // thread making more than a handful of async calls in a row without yielding
+        // would exhaust the budget and spin forever waiting for it to be refilled
+        let run = unconstrained(sched.run());
+        pin!(run);
+        let elapsed = async {
+            match deadline {
+                Some(deadline) => sleep_until(deadline).await,
+                None => pending().await,
+            }
+        };
+        let drain_timeout = if drain {
+            self.drain_timeout
+        } else {
+            Some(Duration::ZERO)
+        };
+        let drained = async {
+            match drain_timeout {
+                Some(drain_timeout) => {
+                    sched.wait_for_thread(main_id).await;
+                    sleep(drain_timeout).await;
+                }
+                None => pending().await,
+            }
+        };
+        select! {
+            () = &mut run => {},
+            () = drained => {
+                if sched.get_exit_code().is_none() {
+                    sched.set_exit_code(if got_any_error.load(Ordering::SeqCst) {
+                        ExitCode::FAILURE
+                    } else {
+                        ExitCode::SUCCESS
+                    });
+                }
+                run.await;
+            }
+            () = elapsed => {
+                timed_out.store(true, Ordering::SeqCst);
+                sched.set_exit_code(ExitCode::FAILURE);
+                run.await;
+            }
+            () = self.handle.interrupted() => {
+                sched.set_exit_code(ExitCode::FAILURE);
+                run.await;
+            }
+        }
+        self.lua.remove_interrupt();
 
-        // Run it on our scheduler until it and any other spawned threads complete
-        sched.push_thread_back(main, ())?;
-        sched.run().await;
+        if self.handle.take_interrupted() {
+            return Err(RuntimeError::interrupted());
+        }
+        if let Some(timeout) = timeout.filter(|_| timed_out.load(Ordering::SeqCst)) {
+            return Err(RuntimeError::timed_out(timeout));
+        }
+
+        // Grab the values returned by the main thread, if it ran until completion
+        let main_result = sched
+            .get_thread_result(main_id)
+            .unwrap_or_else(|| Ok(LuaMultiValue::new()))
+            .and_then(|values| T::from_lua_multi(values, &self.lua));
 
         // Return the exit code - default to FAILURE if we got any errors
-        Ok(sched.get_exit_code().unwrap_or({
+        let exit_code = sched.get_exit_code().unwrap_or({
             if got_any_error.load(Ordering::SeqCst) {
                 ExitCode::FAILURE
             } else {
                 ExitCode::SUCCESS
             }
-        }))
+        });
+
+        // NOTE: The exit code can not be read back from an ExitCode, so process.exit
+        // records the exit code it was given, and we otherwise know it ourselves
+        let script_exit_code = self.lua.remove_app_data::<ScriptExitCode>();
+        self.last_exit_code.set(Some(match script_exit_code {
+            Some(ScriptExitCode(code)) if sched.get_exit_code().is_some() => code,
+            _ => u8::from(exit_code != ExitCode::SUCCESS),
+        }));
+
+        Ok((exit_code, main_result))
     }
 }
@@ -1,6 +1,9 @@
+use std::collections::HashMap;
 use std::env::set_current_dir;
 use std::path::PathBuf;
 use std::process::ExitCode;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use anyhow::Result;
 use console::set_colors_enabled;
@@ -9,7 +12,7 @@ use tokio::fs::read_to_string;
 
 use lune_utils::path::clean_path_and_make_absolute;
 
-use crate::Runtime;
+use crate::{Runtime, StackTraceLine};
 
 const ARGS: &[&str] = &["Foo", "Bar"];
 
@@ -67,11 +70,13 @@ create_tests! {
     require_async_sequential: "require/tests/async_sequential",
     require_builtins: "require/tests/builtins",
     require_children: "require/tests/children",
+    require_circular: "require/tests/circular",
     require_init: "require/tests/init",
     require_invalid: "require/tests/invalid",
     require_multi_ext: "require/tests/multi_ext",
     require_nested: "require/tests/nested",
     require_parents: "require/tests/parents",
+    require_reload: "require/tests/reload",
     require_siblings: "require/tests/siblings",
     require_state: "require/tests/state",
 
@@ -102,10 +107,25 @@ create_tests! {
 #[cfg(feature = "std-fs")]
 create_tests! {
     fs_files: "fs/files",
+    fs_compression: "fs/compression",
     fs_copy: "fs/copy",
     fs_dirs: "fs/dirs",
+    fs_encoding: "fs/encoding",
+    fs_glob: "fs/glob",
+    fs_lines: "fs/lines",
+    fs_lock: "fs/lock",
     fs_metadata: "fs/metadata",
     fs_move: "fs/move",
+    fs_paths: "fs/paths",
+    fs_permissions: "fs/permissions",
+    fs_range: "fs/range",
+    fs_rename: "fs/rename",
+    fs_size: "fs/size",
+    fs_symlinks: "fs/symlinks",
+    fs_temp: "fs/temp",
+    fs_walk: "fs/walk",
+    fs_watch: "fs/watch",
+    fs_writer: "fs/writer",
 }
 
 #[cfg(feature = "std-luau")]
@@ -117,14 +137,39 @@ create_tests! {
 
 #[cfg(feature = "std-net")]
 create_tests! {
+    net_request_body: "net/request/body",
+    net_request_cache: "net/request/cache",
+    net_request_client: "net/request/client",
     net_request_codes: "net/request/codes",
     net_request_compression: "net/request/compression",
+    net_request_download: "net/request/download",
+    net_request_errors: "net/request/errors",
+    net_request_form: "net/request/form",
+    net_request_headers: "net/request/headers",
+    net_request_json: "net/request/json",
+    net_request_max_body_size: "net/request/max_body_size",
     net_request_methods: "net/request/methods",
+    net_request_methods_custom: "net/request/methods_custom",
+    net_request_multipart: "net/request/multipart",
+    net_request_proxy: "net/request/proxy",
     net_request_query: "net/request/query",
+    net_request_query_table: "net/request/query_table",
     net_request_redirect: "net/request/redirect",
+    net_request_redirects: "net/request/redirects",
+    net_request_retry: "net/request/retry",
+    net_request_stream: "net/request/stream",
+    net_request_text: "net/request/text",
+    net_request_timeout: "net/request/timeout",
+    net_request_timing: "net/request/timing",
+    net_request_tls: "net/request/tls",
+    net_request_upload: "net/request/upload",
+    net_request_version: "net/request/version",
     net_url_encode: "net/url/encode",
     net_url_decode: "net/url/decode",
+    net_url_parse: "net/url/parse",
     net_serve_requests: "net/serve/requests",
+    net_serve_shutdown: "net/serve/shutdown",
+    net_serve_upgrade: "net/serve/upgrade",
     net_serve_websockets: "net/serve/websockets",
     net_socket_basic: "net/socket/basic",
     net_socket_wss: "net/socket/wss",
@@ -135,14 +180,21 @@ create_tests! {
 create_tests! {
     process_args: "process/args",
     process_cwd: "process/cwd",
+    process_dotenv: "process/dotenv",
     process_env: "process/env",
     process_exit: "process/exit",
+    process_interrupt: "process/interrupt",
+    process_os: "process/os",
     process_spawn_async: "process/spawn/async",
     process_spawn_basic: "process/spawn/basic",
     process_spawn_cwd: "process/spawn/cwd",
+    process_spawn_env: "process/spawn/env",
+    process_spawn_handle: "process/spawn/handle",
     process_spawn_shell: "process/spawn/shell",
     process_spawn_stdin: "process/spawn/stdin",
     process_spawn_stdio: "process/spawn/stdio",
+    process_spawn_stream: "process/spawn/stream",
+    process_spawn_timeout: "process/spawn/timeout",
 }
 
 #[cfg(feature = "std-regex")]
@@ -167,6 +219,7 @@ create_tests! {
     roblox_datatype_number_sequence: "roblox/datatypes/NumberSequence",
     roblox_datatype_number_sequence_keypoint: "roblox/datatypes/NumberSequenceKeypoint",
     roblox_datatype_physical_properties: "roblox/datatypes/PhysicalProperties",
+    roblox_datatype_random: "roblox/datatypes/Random",
     roblox_datatype_ray: "roblox/datatypes/Ray",
     roblox_datatype_rect: "roblox/datatypes/Rect",
     roblox_datatype_udim: "roblox/datatypes/UDim",
@@ -180,8 +233,10 @@ create_tests! {
 
     roblox_files_deserialize_model: "roblox/files/deserializeModel",
     roblox_files_deserialize_place: "roblox/files/deserializePlace",
+    roblox_files_deserialize_xml: "roblox/files/deserializeXml",
     roblox_files_serialize_model: "roblox/files/serializeModel",
     roblox_files_serialize_place: "roblox/files/serializePlace",
+    roblox_files_serialize_xml: "roblox/files/serializeXml",
 
     roblox_instance_attributes: "roblox/instance/attributes",
     roblox_instance_new: "roblox/instance/new",
@@ -205,6 +260,7 @@ create_tests! {
     roblox_instance_methods_find_first_child: "roblox/instance/methods/FindFirstChild",
     roblox_instance_methods_find_first_child_of_class: "roblox/instance/methods/FindFirstChildOfClass",
     roblox_instance_methods_find_first_child_which_is_a: "roblox/instance/methods/FindFirstChildWhichIsA",
+    roblox_instance_methods_find_first_descendant: "roblox/instance/methods/FindFirstDescendant",
     roblox_instance_methods_get_children: "roblox/instance/methods/GetChildren",
     roblox_instance_methods_get_debug_id: "roblox/instance/methods/GetDebugId",
     roblox_instance_methods_get_descendants: "roblox/instance/methods/GetDescendants",
@@ -212,7 +268,9 @@ create_tests! {
     roblox_instance_methods_is_a: "roblox/instance/methods/IsA",
     roblox_instance_methods_is_ancestor_of: "roblox/instance/methods/IsAncestorOf",
     roblox_instance_methods_is_descendant_of: "roblox/instance/methods/IsDescendantOf",
+    roblox_instance_methods_wait_for_child: "roblox/instance/methods/WaitForChild",
 
+    roblox_misc_diff: "roblox/misc/diff",
     roblox_misc_typeof: "roblox/misc/typeof",
 
     roblox_reflection_class: "roblox/reflection/class",
@@ -223,19 +281,43 @@ create_tests! {
 
 #[cfg(feature = "std-serde")]
 create_tests! {
+    serde_base64: "serde/base64",
+    serde_buffer: "serde/buffer",
+    serde_compression_dictionary: "serde/compression/dictionary",
     serde_compression_files: "serde/compression/files",
+    serde_compression_options: "serde/compression/options",
     serde_compression_roundtrip: "serde/compression/roundtrip",
+    serde_config: "serde/config",
+    serde_hash: "serde/hash",
+    serde_hex: "serde/hex",
     serde_json_decode: "serde/json/decode",
     serde_json_encode: "serde/json/encode",
+    serde_json_stream: "serde/json/stream",
+    serde_msgpack: "serde/msgpack",
+    serde_numbers: "serde/numbers",
+    serde_pointer: "serde/pointer",
+    serde_radix: "serde/radix",
     serde_toml_decode: "serde/toml/decode",
     serde_toml_encode: "serde/toml/encode",
+    serde_toml_roundtrip: "serde/toml/roundtrip",
+    serde_yaml_merge: "serde/yaml/merge",
+    serde_yaml_roundtrip: "serde/yaml/roundtrip",
 }
 
 #[cfg(feature = "std-stdio")]
 create_tests! {
+    stdio_capture: "stdio/capture",
+    stdio_diff: "stdio/diff",
     stdio_format: "stdio/format",
+    stdio_group: "stdio/group",
+    stdio_history: "stdio/history",
+    stdio_inspect: "stdio/inspect",
+    stdio_log: "stdio/log",
     stdio_color: "stdio/color",
     stdio_style: "stdio/style",
+    stdio_table: "stdio/table",
+    stdio_theme: "stdio/theme",
+    stdio_time: "stdio/time",
     stdio_write: "stdio/write",
     stdio_ewrite: "stdio/ewrite",
 }
@@ -245,6 +327,703 @@ create_tests! {
     task_cancel: "task/cancel",
     task_defer: "task/defer",
     task_delay: "task/delay",
+    task_error_handler: "task/error_handler",
+    task_join: "task/join",
+    task_ordering: "task/ordering",
+    task_semaphore: "task/semaphore",
     task_spawn: "task/spawn",
+    task_stats: "task/stats",
+    task_timeout: "task/timeout",
     task_wait: "task/wait",
+    task_wait_drift: "task/wait_drift",
+    task_yield: "task/yield",
+}
+
+#[cfg(feature = "std-process")]
+#[tokio::test(flavor = "multi_thread")]
+async fn process_exit_returns_to_host() -> Result<()> {
+    // Exiting from a script must only stop the script and give the exit code
+    // back to the caller of run, the host process should keep running after it
+    let mut lune = Runtime::new();
+    let script = "require('@lune/process').exit(3)\nerror('Script did not exit')";
+    let exit_code = lune.run("exit_code", script).await?;
+    assert_eq!(exit_code, ExitCode::from(3));
+
+    // The same runtime should also be able to run another script afterwards
+    let exit_code = lune.run("exit_code_again", "local _ = 1").await?;
+    assert_eq!(exit_code, ExitCode::SUCCESS);
+
+    Ok(())
+}
+
+#[cfg(all(feature = "std-process", feature = "std-task"))]
+#[tokio::test(flavor = "multi_thread")]
+async fn runtime_last_exit_code() -> Result<()> {
+    let mut lune = Runtime::new();
+    assert_eq!(lune.last_exit_code(), None);
+
+    lune.run("exit_code", "require('@lune/process').exit(42)")
+        .await?;
+    assert_eq!(lune.last_exit_code(), Some(42));
+
+    // Exiting from a task should also be reported, and errors should report a failure
+    let script = "require('@lune/task').delay(0, require('@lune/process').exit, 7)";
+    lune.run("exit_code_task", script).await?;
+    assert_eq!(lune.last_exit_code(), Some(7));
+
+    lune.run("exit_code_error", "error('Oh no')").await?;
+    assert_eq!(lune.last_exit_code(), Some(1));
+
+    // Exit codes from previous runs should never leak into the next run
+    lune.run("exit_code_success", "local _ = 1").await?;
+    assert_eq!(lune.last_exit_code(), Some(0));
+
+    lune.run_with_timeout(
+        "exit_code_timeout",
+        "while true do end",
+        Duration::from_millis(50),
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(lune.last_exit_code(), Some(1));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn runtime_custom_globals() -> Result<()> {
+    let mut lune = Runtime::new()
+        .with_global("customValue", 42)?
+        .with_global("print", "overridden")?
+        .with_globals(|lua, globals| {
+            globals.set(
+                "customFunction",
+                lua.create_function(|_, value: i32| Ok(value * 2))?,
+            )
+        })?;
+
+    let script = "
+        assert(customValue == 42, 'Custom value was not set')
+        assert(print == 'overridden', 'Default global was not overridden')
+        assert(customFunction(21) == 42, 'Custom function was not set')
+    ";
+    let exit_code = lune.run("custom_globals", script).await?;
+    assert_eq!(exit_code, ExitCode::SUCCESS);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn runtime_eval_returns_values() -> Result<()> {
+    let mut lune = Runtime::new();
+
+    let (number, text): (i32, String) = lune.eval("eval_values", "return 1 + 2, 'three'").await?;
+    assert_eq!(number, 3);
+    assert_eq!(text, "three");
+
+    // Values should be returned even if the script yields before returning
+    let script = "require('@lune/task').wait()\nreturn 4";
+    let value: i32 = lune.eval("eval_yield", script).await?;
+    assert_eq!(value, 4);
+
+    // Scripts that return nothing should be convertible to an empty result
+    let nothing: Option<String> = lune.eval("eval_nothing", "local _ = 1").await?;
+    assert_eq!(nothing, None);
+
+    // Errors thrown by the script should be returned as errors
+    let result = lune.eval::<()>("eval_error", "error('Oh no')").await;
+    assert!(result.is_err());
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn runtime_run_with_args_values() -> Result<()> {
+    let mut lune = Runtime::new();
+
+    // Values should be given to the script as varargs
+    let script = "
+        local number, list, map, missing = ...
+        assert(select('#', ...) == 3, 'Script should get exactly three values')
+        assert(number == 42, 'First value should be a number')
+        assert(list[1] == 'a' and list[2] == 'b', 'Second value should be a list')
+        assert(map.key == true, 'Third value should be a map')
+        assert(missing == nil, 'Values that were not given should be nil')
+    ";
+    let values = (
+        42,
+        vec!["a", "b"],
+        HashMap::from([("key".to_string(), true)]),
+    );
+    let exit_code = lune
+        .run_with_args_values("args_values", script, values)
+        .await?;
+    assert_eq!(exit_code, ExitCode::SUCCESS);
+    assert_eq!(lune.last_exit_code(), Some(0));
+
+    // Regular runs should not get any values
+    let script = "assert(select('#', ...) == 0, 'Script should not get any values')";
+    lune.run("args_values_none", script).await?;
+    assert_eq!(lune.last_exit_code(), Some(0));
+
+    Ok(())
+}
+
+#[cfg(feature = "std-task")]
+#[tokio::test(flavor = "multi_thread")]
+async fn runtime_error_reporter() -> Result<()> {
+    let reported = Arc::new(Mutex::new(Vec::new()));
+    let reported_inner = Arc::clone(&reported);
+    let mut lune = Runtime::new().with_error_reporter(move |error| {
+        let components = error.components();
+        let location = components
+            .trace()
+            .and_then(|trace| trace.lines().iter().find_map(StackTraceLine::line_number));
+        reported_inner
+            .lock()
+            .unwrap()
+            .push((components.messages().join("\n"), location));
+    });
+
+    // Errors from the main chunk and from tasks should all be reported, with their locations
+    let script = "
+        require('@lune/task').spawn(function()
+            error('Task error')
+        end)
+        error('Main error')
+    ";
+    lune.run("error_reporter", script).await?;
+    assert_eq!(lune.last_exit_code(), Some(1));
+
+    let reported = reported.lock().unwrap().clone();
+    assert_eq!(reported.len(), 2);
+    assert!(reported[0].0.contains("Task error"));
+    assert_eq!(reported[0].1, Some(3));
+    assert!(reported[1].0.contains("Main error"));
+    assert_eq!(reported[1].1, Some(5));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn runtime_load_chunk() -> Result<()> {
+    let mut lune = Runtime::new();
+
+    // Chunks should be reusable, and share globals with the runtime
+    let chunk = lune.load(
+        "chunk_counter",
+        "counter = (counter or 0) + 1\nreturn counter",
+    )?;
+    for expected in 1..=3 {
+        let value: i32 = lune.eval_chunk(&chunk).await?;
+        assert_eq!(value, expected);
+    }
+    let exit_code = lune.run_chunk(&chunk).await?;
+    assert_eq!(exit_code, ExitCode::SUCCESS);
+    let value: i32 = lune.eval("chunk_check", "return counter").await?;
+    assert_eq!(value, 4);
+
+    // Scripts that fail to compile should error when loaded, not when run
+    assert!(lune.load("chunk_invalid", "local = 1").is_err());
+
+    // Chunks from other runtimes should not be usable
+    let mut other = Runtime::new();
+    assert!(other.run_chunk(&chunk).await.is_err());
+
+    Ok(())
+}
+
+#[cfg(feature = "std-task")]
+#[tokio::test(flavor = "multi_thread")]
+async fn runtime_run_with_timeout() -> Result<()> {
+    let mut lune = Runtime::new();
+    let timeout = Duration::from_millis(100);
+
+    // Scripts that complete in time should run as normal
+    let exit_code = lune
+        .run_with_timeout("timeout_ok", "local _ = 1", timeout)
+        .await?;
+    assert_eq!(exit_code, ExitCode::SUCCESS);
+
+    // Infinite loops should be interrupted
+    let result = lune
+        .run_with_timeout("timeout_loop", "while true do end", timeout)
+        .await;
+    assert!(result.is_err_and(|e| e.is_timeout()));
+
+    // Waiting on scheduled tasks should also count towards the timeout
+    let script = "require('@lune/task').wait(5)";
+    let result = lune.run_with_timeout("timeout_wait", script, timeout).await;
+    assert!(result.is_err_and(|e| e.is_timeout()));
+
+    // The runtime should still be usable without a timeout afterwards
+    let exit_code = lune.run("timeout_after", "for _ = 1, 1000 do end").await?;
+    assert_eq!(exit_code, ExitCode::SUCCESS);
+
+    Ok(())
+}
+
+#[cfg(feature = "std-task")]
+#[tokio::test(flavor = "multi_thread")]
+async fn runtime_interrupt() -> Result<()> {
+    let mut lune = Runtime::new();
+    let handle = lune.handle();
+    let interrupt_after = |delay: Duration| {
+        let handle = handle.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(delay);
+            handle.interrupt();
+        });
+    };
+
+    // Infinite loops should be interrupted from other threads
+    interrupt_after(Duration::from_millis(100));
+    let result = lune.run("interrupt_loop", "while true do end").await;
+    assert!(result.is_err_and(|e| e.is_interrupted() && !e.is_timeout()));
+
+    // Waiting on scheduled tasks should also be interrupted
+    interrupt_after(Duration::from_millis(100));
+    let script = "require('@lune/task').wait(5)";
+    let result = lune.run("interrupt_wait", script).await;
+    assert!(result.is_err_and(|e| e.is_interrupted()));
+
+    // Interrupting before running should interrupt the next script
+    lune.handle().interrupt();
+    let result = lune.run("interrupt_early", "local _ = 1").await;
+    assert!(result.is_err_and(|e| e.is_interrupted()));
+
+    // The runtime should still be usable after being interrupted
+    let exit_code = lune
+        .run("interrupt_after", "for _ = 1, 1000 do end")
+        .await?;
+    assert_eq!(exit_code, ExitCode::SUCCESS);
+    assert!(!lune.handle().is_interrupted());
+
+    Ok(())
+}
+
+#[cfg(feature = "std-task")]
+#[tokio::test(flavor = "multi_thread")]
+async fn runtime_drain() -> Result<()> {
+    let script = "
+        local task = require('@lune/task')
+        _G.completed = {}
+        task.defer(function()
+            table.insert(_G.completed, 'defer')
+        end)
+        task.delay(0.1, function()
+            table.insert(_G.completed, 'delay')
+        end)
+        task.delay(5, function()
+            table.insert(_G.completed, 'delay_long')
+        end)
+    ";
+    let completed = "return table.concat(_G.completed, ' ')";
+
+    // Scheduled tasks should run to completion by default
+    let mut lune = Runtime::new();
+    let exit_code = lune.run("drain_full", script.replace("5,", "0.2,")).await?;
+    assert_eq!(exit_code, ExitCode::SUCCESS);
+    let result = lune.eval::<String>("drain_full_result", completed).await?;
+    assert_eq!(result, "defer delay delay_long");
+
+    // Tasks still waiting once the drain timeout elapses should not run
+    let mut lune = Runtime::new().with_drain_timeout(Duration::from_millis(500));
+    let exit_code = lune.run("drain_timeout", script).await?;
+    assert_eq!(exit_code, ExitCode::SUCCESS);
+    let result = lune
+        .eval::<String>("drain_timeout_result", completed)
+        .await?;
+    assert_eq!(result, "defer delay");
+
+    // Waiting tasks should not run at all when not draining
+    let mut lune = Runtime::new();
+    let exit_code = lune.run_no_drain("drain_none", script).await?;
+    assert_eq!(exit_code, ExitCode::SUCCESS);
+    let result = lune.eval::<String>("drain_none_result", completed).await?;
+    assert!(!result.contains("delay"));
+
+    Ok(())
+}
+
+#[cfg(feature = "std-fs")]
+#[tokio::test(flavor = "multi_thread")]
+async fn runtime_fs_root() -> Result<()> {
+    let temp_dir = std::env::temp_dir().join(format!("lune-fs-root-{}", std::process::id()));
+    let root = temp_dir.join("root");
+    std::fs::create_dir_all(root.join("dir"))?;
+    std::fs::write(root.join("inside.txt"), "inside")?;
+    std::fs::write(temp_dir.join("outside.txt"), "outside")?;
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink(temp_dir.join("outside.txt"), root.join("link.txt"))?;
+        std::os::unix::fs::symlink(&temp_dir, root.join("link-dir"))?;
+        std::os::unix::fs::symlink("inside.txt", root.join("inside-link.txt"))?;
+    }
+
+    // Paths should be relative to the root, and nothing outside of it should be accessible
+    let script = r#"
+        local fs = require("@lune/fs")
+
+        assert(fs.readFile("inside.txt") == "inside", "Relative paths should be inside the root")
+        assert(fs.readFile("dir/../inside.txt") == "inside", "Paths inside the root should work")
+        fs.writeFile("dir/created.txt", "created")
+        assert(fs.isFile("dir/created.txt"), "Files should be created inside the root")
+        assert(fs.readFile(fs.absolute("inside.txt")) == "inside", "Absolute paths should work")
+
+        for _, path in { "../outside.txt", OUTSIDE, "dir/../../outside.txt", ".." } do
+            assert(not pcall(fs.readFile, path), `Reading '{path}' should not be allowed`)
+            assert(not pcall(fs.readDir, path), `Reading dir '{path}' should not be allowed`)
+            assert(not pcall(fs.writeFile, path, ""), `Writing '{path}' should not be allowed`)
+        end
+        assert(not pcall(fs.copy, "../outside.txt", "copied.txt"), "Copying should not escape")
+        assert(not pcall(fs.move, "inside.txt", "../moved.txt"), "Moving should not escape")
+
+        if IS_UNIX then
+            assert(not pcall(fs.readFile, "link.txt"), "Symlinks should not escape the root")
+            assert(not pcall(fs.writeFile, "link-dir/evil.txt", ""), "Symlinked dirs should not escape")
+            assert(not pcall(fs.symlink, "../outside.txt", "made.txt"), "Creating links should not escape")
+            assert(not pcall(fs.copy, ".", "../copied"), "Copying the root should not escape")
+            assert(not pcall(fs.copy, "link-dir", "copied"), "Copying linked dirs should not escape")
+            assert(fs.readFile("inside-link.txt") == "inside", "Symlinks inside the root should work")
+            fs.removeFile("link.txt")
+        end
+
+        local globbed = fs.glob("*.txt")
+        table.sort(globbed)
+        assert(table.find(globbed, "inside.txt"), "Globbing should be relative to the root")
+        assert(#fs.glob("../*.txt") == 0, "Globbing should not escape the root")
+
+        local temp = fs.createTempFile()
+        assert(fs.isFile(temp), "Temporary files should be created inside the root")
+    "#;
+    let mut lune = Runtime::new()
+        .with_fs_root(&root)?
+        .with_global(
+            "OUTSIDE",
+            temp_dir.join("outside.txt").to_string_lossy().to_string(),
+        )?
+        .with_global("IS_UNIX", cfg!(unix))?;
+    let result = lune.run("fs_root", script).await;
+    let last_exit_code = lune.last_exit_code();
+
+    let outside = std::fs::read_to_string(temp_dir.join("outside.txt"));
+    let created = std::fs::read_to_string(root.join("dir").join("created.txt"));
+    std::fs::remove_dir_all(&temp_dir)?;
+
+    result?;
+    assert_eq!(last_exit_code, Some(0));
+    assert_eq!(outside?, "outside");
+    assert_eq!(created?, "created");
+
+    // Roots must be existing directories
+    assert!(Runtime::new()
+        .with_fs_root(temp_dir.join("missing"))
+        .is_err());
+
+    Ok(())
+}
+
+#[cfg(feature = "std-task")]
+#[tokio::test(flavor = "multi_thread")]
+async fn runtime_require_config() -> Result<()> {
+    use lune_std::RequireConfig;
+
+    let temp_dir = std::env::temp_dir().join(format!("lune-require-{}", std::process::id()));
+    let root = temp_dir.join("root");
+    std::fs::create_dir_all(root.join("lib"))?;
+    std::fs::create_dir_all(root.join("modules"))?;
+    std::fs::create_dir_all(temp_dir.join("other"))?;
+    std::fs::write(root.join("lib").join("util.luau"), "return 'util'")?;
+    std::fs::write(
+        root.join("modules").join("shared.luau"),
+        "return 'shared ' .. require('./helper')",
+    )?;
+    std::fs::write(root.join("modules").join("helper.luau"), "return 'helper'")?;
+    std::fs::write(
+        temp_dir.join("other").join("external.luau"),
+        "return 'external'",
+    )?;
+    std::fs::write(
+        temp_dir.join("other").join("shared.luau"),
+        "return 'shadowed'",
+    )?;
+
+    // Relative script names should be relative to the root, and search paths
+    // should be searched in order, only for paths that are not explicitly relative
+    let script = r#"
+        assert(require("./lib/util") == "util", "Requires should be relative to the root")
+        assert(require("lib/util") == "util", "Requires should be relative to the root")
+        assert(require("shared") == "shared helper", "Search paths should be searched in order")
+        assert(require("external") == "external", "Absolute search paths should be searched")
+        assert(not pcall(require, "./external"), "Explicitly relative paths should not be searched")
+        assert(not pcall(require, "missing"), "Missing modules should still error")
+    "#;
+    let mut lune = Runtime::new().with_require_config(
+        RequireConfig::new()
+            .root(&root)
+            .search_path("modules")
+            .search_path(temp_dir.join("other")),
+    );
+    let result = lune.run("main", script).await;
+    let last_exit_code = lune.last_exit_code();
+    std::fs::remove_dir_all(&temp_dir)?;
+
+    result?;
+    assert_eq!(last_exit_code, Some(0));
+
+    Ok(())
+}
+
+#[cfg(all(feature = "std-fs", feature = "std-serde"))]
+#[tokio::test(flavor = "multi_thread")]
+async fn runtime_fs_root_load_config() -> Result<()> {
+    let temp_dir = std::env::temp_dir().join(format!("lune-config-root-{}", std::process::id()));
+    let root = temp_dir.join("root");
+    std::fs::create_dir_all(&root)?;
+    std::fs::write(root.join("inside.json"), r#"{ "inside": true }"#)?;
+    std::fs::write(temp_dir.join("outside.json"), r#"{ "inside": false }"#)?;
+
+    // Config files should be loaded relative to the root, and must be inside of it
+    let script = r#"
+        local serde = require("@lune/serde")
+
+        assert(serde.loadConfig("inside.json").inside, "Config paths should be relative to the root")
+        assert(not pcall(serde.loadConfig, "../outside.json"), "Config files should not escape the root")
+    "#;
+    let mut lune = Runtime::new().with_fs_root(&root)?;
+    let result = lune.run("fs_root_load_config", script).await;
+    let last_exit_code = lune.last_exit_code();
+    std::fs::remove_dir_all(&temp_dir)?;
+
+    result?;
+    assert_eq!(last_exit_code, Some(0));
+
+    Ok(())
+}
+
+#[cfg(feature = "std-net")]
+#[tokio::test(flavor = "multi_thread")]
+async fn runtime_net_policy() -> Result<()> {
+    use lune_std::NetPolicy;
+
+    // NOTE: The server is not affected by the policy, only outgoing requests are
+    let run = |policy: NetPolicy, script: &'static str| async move {
+        let script = format!(
+            r#"
+            local net = require("@lune/net")
+
+            local handle = net.serve(8108, function(request)
+                if request.path == "/redirect" then
+                    return {{ status = 302, headers = {{ Location = "http://127.0.0.1:8108/" }} }}
+                end
+                return "Hello, policy!"
+            end)
+
+            local function rejected(f, ...)
+                local success, err = pcall(f, ...)
+                return not success and err.kind == "policy"
+            end
+
+            local function rejectedProxy(proxy)
+                local success, err = pcall(net.createClient, {{ proxy = proxy }})
+                return not success and string.find(tostring(err), "network policy", 1, true) ~= nil
+            end
+
+            -- NOTE: The server must always be stopped, or a failing check would never finish
+            local success, err = pcall(function()
+                {script}
+            end)
+            handle.stop()
+            assert(success, err)
+            "#
+        );
+        let mut lune = Runtime::new().with_net_policy(policy);
+        lune.run("net_policy", script).await?;
+        assert_eq!(lune.last_exit_code(), Some(0));
+        Ok::<_, anyhow::Error>(())
+    };
+
+    run(
+        NetPolicy::new().deny("127.0.0.1"),
+        r#"
+        assert(rejected(net.request, "http://127.0.0.1:8108"), "Denied hosts should be rejected")
+        assert(rejected(net.socket, "ws://127.0.0.1:8108"), "Denied sockets should be rejected")
+        local client = net.createClient()
+        assert(rejected(client.request, client, "http://127.0.0.1:8108"), "Clients should be rejected")
+        "#,
+    )
+    .await?;
+
+    run(
+        NetPolicy::new().allow("localhost"),
+        r#"
+        assert(net.request("http://localhost:8108").ok, "Allowed hosts should not be rejected")
+        assert(rejected(net.request, "http://127.0.0.1:8108"), "Hosts not allowed should be rejected")
+        assert(rejected(net.request, "http://localhost:8108/redirect"),
+            "Redirects to hosts that are not allowed should be rejected")
+        "#,
+    )
+    .await?;
+
+    run(
+        NetPolicy::new().allow("*").block_private_ips(true),
+        r#"
+        assert(rejected(net.request, "http://localhost:8108"),
+            "Hosts resolving to private addresses should be rejected")
+        assert(rejected(net.request, "http://[::ffff:127.0.0.1]:8108"),
+            "Mapped private addresses should be rejected")
+        assert(rejectedProxy("http://127.0.0.1:8108"),
+            "Proxies at private addresses should be rejected")
+        assert(rejectedProxy("127.0.0.1:8108"),
+            "Proxies without a scheme should be checked too")
+        "#,
+    )
+    .await?;
+
+    run(
+        NetPolicy::new().deny("10.0.0.0/8").allow("*.example.com"),
+        r#"
+        assert(rejected(net.request, "http://10.1.2.3"), "Denied networks should be rejected")
+        assert(rejected(net.request, "http://localhost:8108"), "Wildcards should not match others")
+        assert(rejected(net.request, "http://example.com"), "Wildcards should only match subdomains")
+        assert(rejectedProxy("http://10.1.2.3:8080"),
+            "Proxies at denied addresses should be rejected")
+        "#,
+    )
+    .await?;
+
+    Ok(())
+}
+
+/*
+    NOTE: Signals are sent to a child process running a script, since signaling the process
+    running the tests would interrupt all of them - the child re-runs this test binary with
+    only the ignored test below, which runs the script given using an environment variable.
+*/
+#[cfg(all(unix, feature = "std-process", feature = "std-task"))]
+const INTERRUPT_SCRIPT_VAR: &str = "LUNE_TEST_INTERRUPT_SCRIPT";
+
+#[cfg(all(unix, feature = "std-process", feature = "std-task"))]
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "only runs as a child process of runtime_process_interrupt"]
+async fn runtime_process_interrupt_child() -> Result<()> {
+    if let Ok(script) = std::env::var(INTERRUPT_SCRIPT_VAR) {
+        let mut lune = Runtime::new();
+        lune.run("interrupt_child", script).await?;
+        std::process::exit(i32::from(lune.last_exit_code().unwrap_or(1)));
+    }
+    Ok(())
+}
+
+#[cfg(all(unix, feature = "std-process", feature = "std-task"))]
+#[tokio::test(flavor = "multi_thread")]
+async fn runtime_process_interrupt() -> Result<()> {
+    use std::io::{BufRead, BufReader};
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::{Command, Stdio};
+
+    // Runs the script in a child process, interrupting it once it writes that it is ready
+    // NOTE: The test harness writes the name of the test right before, on the same line
+    let run_with = |script: &'static str, ignore_interrupts: bool| {
+        tokio::task::spawn_blocking(move || {
+            // NOTE: Ignored signals stay ignored in the executed program, same
+            // as if whatever Lune is embedded in had set that action itself
+            let mut command = Command::new("sh");
+            command.arg("-c").arg(if ignore_interrupts {
+                "trap '' INT; exec \"$0\" \"$@\""
+            } else {
+                "exec \"$0\" \"$@\""
+            });
+            let mut child = command
+                .arg(std::env::current_exe()?)
+                .args(["--exact", "tests::runtime_process_interrupt_child"])
+                .args(["--ignored", "--nocapture", "--test-threads=1"])
+                .env(INTERRUPT_SCRIPT_VAR, script)
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .spawn()?;
+            let stdout = BufReader::new(child.stdout.take().expect("Missing stdout"));
+            let mut lines = Vec::new();
+            for line in stdout.lines() {
+                let line = line?;
+                if line.ends_with("ready") {
+                    Command::new("kill")
+                        .args(["-INT", &child.id().to_string()])
+                        .status()?;
+                } else {
+                    lines.push(line);
+                }
+            }
+            let status = child.wait()?;
+            Ok::<_, anyhow::Error>((status, lines))
+        })
+    };
+    let run = |script: &'static str| run_with(script, false);
+
+    // Handlers should be called, and the script should then exit with the signal exit code
+    let (status, lines) = run(r#"
+        local process = require("@lune/process")
+        local task = require("@lune/task")
+        task.delay(5, process.exit, 0)
+        process.onInterrupt(function()
+            task.wait(0.05)
+            print("handled")
+        end)
+        print("ready")
+        "#)
+    .await??;
+    assert!(lines.iter().any(|line| line == "handled"), "{lines:?}");
+    assert_eq!(status.code(), Some(130));
+
+    // Once all handlers are removed, signals should stop the process as usual
+    let (status, lines) = run(r#"
+        local process = require("@lune/process")
+        local task = require("@lune/task")
+        local remove = process.onInterrupt(function()
+            print("handled")
+        end)
+        remove()
+        task.wait(0.05)
+        print("ready")
+        task.wait(5)
+        print("not interrupted")
+        "#)
+    .await??;
+    assert!(!lines.iter().any(|line| line == "handled"), "{lines:?}");
+    assert!(
+        !lines.iter().any(|line| line == "not interrupted"),
+        "{lines:?}"
+    );
+    assert_eq!(
+        status.signal(),
+        Some(2),
+        "Process was not stopped by SIGINT"
+    );
+
+    // Once all handlers are removed, the actions from before they were added should be restored
+    let (status, lines) = run_with(
+        r#"
+        local process = require("@lune/process")
+        local task = require("@lune/task")
+        local remove = process.onInterrupt(function()
+            print("handled")
+        end)
+        remove()
+        task.wait(0.05)
+        print("ready")
+        task.wait(0.5)
+        print("not interrupted")
+        "#,
+        true,
+    )
+    .await??;
+    assert!(!lines.iter().any(|line| line == "handled"), "{lines:?}");
+    assert!(
+        lines.iter().any(|line| line == "not interrupted"),
+        "Ignored signal should have stayed ignored - {lines:?}"
+    );
+    assert_eq!(status.code(), Some(0));
+
+    Ok(())
 }